@@ -0,0 +1,22 @@
+use katana::core::server::sse::Event;
+use katana::Katana;
+use std::thread;
+use std::time::Duration;
+
+// cargo run --example event_stream, then open http://127.0.0.1:8080/events
+// or listen with `curl -N http://127.0.0.1:8080/events`
+fn main() {
+    let mut katana = Katana::new();
+    let builds = katana.event_stream("/events");
+    builds.set_retry(Duration::from_secs(3));
+
+    // a fake build, reported to every dashboard
+    thread::spawn(move || {
+        for step in 1.. {
+            builds.publish(Event::new(&format!("step {} done", step)).with_event("build"));
+            thread::sleep(Duration::from_secs(2));
+        }
+    });
+
+    katana.start();
+}
@@ -0,0 +1,18 @@
+use katana::core::server::websocket::{Message, WebSocketHandler, WebSocketSender};
+use katana::Katana;
+
+/// Sends every message back to its sender.
+struct Echo;
+
+impl WebSocketHandler for Echo {
+    fn on_message(&self, sender: &WebSocketSender, message: Message) {
+        let _ = sender.send(message);
+    }
+}
+
+// cargo run --example websocket_echo, then connect to ws://127.0.0.1:8080/echo
+fn main() {
+    let mut katana = Katana::new();
+    katana.websocket("/echo", Echo);
+    katana.start();
+}
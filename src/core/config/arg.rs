@@ -1,64 +1,194 @@
 use std::env::args;
 use std::path::PathBuf;
-use crate::core::utils::logger::LogLevel;
-use super::config::Config;
+use crate::core::server::server::Server;
+use super::config::{Config, ConfigIssue, ConfigLayer, ConfigSource};
 
-pub fn load_args() -> Config {
-    let env_args: Vec<String> = args().collect();
-    parse_args(env_args)
+/// A command-line option, `--help` is generated from `Cli::OPTIONS`.
+pub struct CliOption {
+    pub long: &'static str,
+    pub short: Option<char>,
+    /// placeholder of the value, `None` for flags
+    pub value: Option<&'static str>,
+    pub help: &'static str,
 }
 
-pub fn parse_args(args: Vec<String>) -> Config {
-    let mut host = None;
-    let mut port = None;
-    let mut document_root = None;
-    let mut worker = None;
-    let mut log_level = None;
-
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--port" => {
-                if i + 1 < args.len() {
-                    port = args[i + 1].parse().ok();
-                    i += 1;
-                }
-            }
-            "--document-root" => {
-                if i + 1 < args.len() {
-                    document_root = Some(PathBuf::from(&args[i + 1]));
-                    i += 1;
-                }
-            }
-            "--host" => {
-                if i + 1 < args.len() {
-                    host = Some(args[i + 1].clone());
-                    i += 1;
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    Serve,
+    Help,
+    Version,
+    /// `katana check-config [FILE]`
+    CheckConfig(Option<PathBuf>),
+    /// `katana config` waiting for its subcommand
+    Config,
+    /// `katana config dump`
+    ConfigDump,
+}
+
+/// The parsed command line: what to do, and the configuration layer of its options.
+#[derive(Debug, Clone)]
+pub struct Cli {
+    pub command: CliCommand,
+    /// `--config`, `.katana` when left out
+    pub config_file: Option<PathBuf>,
+    pub config: ConfigLayer,
+}
+
+impl Cli {
+    pub const OPTIONS: [CliOption; 17] = [
+        CliOption { long: "host", short: Some('H'), value: Some("ADDRESS"), help: "Address to bind, 127.0.0.1 for local access only" },
+        CliOption { long: "port", short: Some('p'), value: Some("NUMBER"), help: "Port to listen on [default: 8080]" },
+        CliOption { long: "document-root", short: Some('d'), value: Some("PATH"), help: "Folder to serve [default: public]" },
+        CliOption { long: "worker", short: Some('w'), value: Some("NUMBER"), help: "Worker threads [default: 4]" },
+        CliOption { long: "log-level", short: Some('l'), value: Some("LEVEL"), help: "DEBUG, INFO, WARN or ERROR [default: INFO]" },
+        CliOption { long: "max-connections", short: None, value: Some("NUMBER"), help: "Connections open at the same time [default: 1024]" },
+        CliOption { long: "max-connections-per-ip", short: None, value: Some("NUMBER"), help: "Connections open at the same time by one client [default: 32]" },
+        CliOption { long: "webdav", short: None, value: Some("MODE"), help: "WebDAV: off, read-only or read-write [default: off]" },
+        CliOption { long: "watch", short: None, value: None, help: "Reload open pages when files change" },
+        CliOption { long: "clean-urls", short: None, value: None, help: "Serve /about from about.html" },
+        CliOption { long: "spa", short: None, value: Some("FILE"), help: "Serve FILE for the routes of a single-page application" },
+        CliOption { long: "mime-sniff", short: None, value: None, help: "Type files without a known extension from their content" },
+        CliOption { long: "mime-charset", short: None, value: Some("CHARSET"), help: "Charset of text types, false for none [default: utf-8]" },
+        CliOption { long: "mime-default", short: None, value: Some("TYPE"), help: "Type of unknown extensions [default: application/octet-stream]" },
+        CliOption { long: "config", short: Some('c'), value: Some("FILE"), help: "Configuration file [default: .katana]" },
+        CliOption { long: "help", short: Some('h'), value: None, help: "Print this help" },
+        CliOption { long: "version", short: Some('V'), value: None, help: "Print the version" },
+    ];
+
+    /// Parses `args`, the program name first. Invalid input is reported as issues of `config`.
+    pub fn parse(args: Vec<String>) -> Self {
+        let mut cli = Self {
+            command: CliCommand::Serve,
+            config_file: None,
+            config: ConfigLayer::new(ConfigSource::Args),
+        };
+
+        let mut i = 1;
+        while i < args.len() {
+            let arg = &args[i];
+            i += 1;
+
+            // --port 3000, --port=3000 or -p 3000
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if arg.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let option = match name.strip_prefix("--") {
+                Some(long) => Self::OPTIONS.iter().find(|option| option.long == long),
+                None => match name.strip_prefix('-').filter(|short| short.chars().count() == 1) {
+                    Some(short) => Self::OPTIONS.iter().find(|option| option.short == short.chars().next()),
+                    None if !name.starts_with('-') => {
+                        cli.positional(arg);
+                        continue;
+                    }
+                    None => None,
+                },
+            };
+            let Some(option) = option else {
+                cli.report(name, "unknown option");
+                continue;
+            };
+
+            let value = match (option.value, inline) {
+                (None, Some(_)) => {
+                    cli.report(name, "takes no value");
+                    continue;
                 }
+                (None, None) => None,
+                (Some(_), Some(value)) => Some(value),
+                // a following option is not a value, a negative number is
+                (Some(placeholder), None) => match args.get(i).filter(|next| !next.starts_with('-') || next.parse::<i64>().is_ok()) {
+                    Some(value) => {
+                        i += 1;
+                        Some(value.clone())
+                    }
+                    None => {
+                        cli.report(name, &format!("expected a value, {} <{}>", name, placeholder));
+                        continue;
+                    }
+                },
+            };
+            cli.apply(option, name, value);
+        }
+
+        if cli.command == CliCommand::Config {
+            cli.report("config", "expected a subcommand, `katana config dump`");
+        }
+        cli
+    }
+
+    fn positional(&mut self, arg: &str) {
+        match &self.command {
+            CliCommand::Serve if arg == "check-config" => self.command = CliCommand::CheckConfig(None),
+            CliCommand::Serve if arg == "config" => self.command = CliCommand::Config,
+            CliCommand::Config if arg == "dump" => self.command = CliCommand::ConfigDump,
+            CliCommand::CheckConfig(None) => self.command = CliCommand::CheckConfig(Some(PathBuf::from(arg))),
+            _ => self.report(arg, "unexpected argument"),
+        }
+    }
+
+    fn apply(&mut self, option: &CliOption, name: &str, value: Option<String>) {
+        // --document-root sets `document_root`, a flag sets `true`
+        let key = Config::KEYS.iter().find(|key| key.name == option.long.replace('-', "_"));
+        let value = value.unwrap_or_else(|| "true".to_string());
+        let valid = match (option.long, key) {
+            (_, Some(key)) => (key.read)(&mut self.config, &value),
+            ("config", None) => {
+                self.config_file = Some(PathBuf::from(&value)).filter(|path| !path.as_os_str().is_empty());
+                self.config_file.is_some()
             }
-            "--worker" => {
-                if i + 1 < args.len() {
-                    worker = args[i + 1].parse().ok();
-                    i += 1;
-                }
+            ("help", None) => {
+                self.command = CliCommand::Help;
+                true
             }
-            "--log-level" => {
-                if i + 1 < args.len() {
-                    log_level = LogLevel::from_str(&args[i + 1].to_uppercase());
-                    i += 1;
+            ("version", None) => {
+                // --help wins, whatever the order
+                if self.command != CliCommand::Help {
+                    self.command = CliCommand::Version;
                 }
+                true
             }
-            _ => {}
+            _ => false,
+        };
+
+        if !valid {
+            let expected = key.map_or("a value", |key| key.expected);
+            self.report(name, &format!("expected {}, got {:?}", expected, value));
         }
-        i += 1;
     }
 
-    Config {
-        _source: crate::core::config::config::ConfigSource::Args,
-        host: host.unwrap_or_default(),
-        port: port.unwrap_or_default(),
-        document_root: document_root.unwrap_or_default(),
-        worker: worker.unwrap_or_default(),
-        log_level: log_level.unwrap_or(LogLevel::INFO),
+    fn report(&mut self, location: &str, message: &str) {
+        self.config.issues.push(ConfigIssue::new(ConfigSource::Args, location, message));
     }
+
+    /// `katana --help`, from `OPTIONS`.
+    pub fn help() -> String {
+        let names: Vec<String> = Self::OPTIONS
+            .iter()
+            .map(|option| {
+                let short = option.short.map_or("    ".to_string(), |short| format!("-{}, ", short));
+                let value = option.value.map_or(String::new(), |value| format!(" <{}>", value));
+                format!("{}--{}{}", short, option.long, value)
+            })
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0);
+
+        let mut help = format!(
+            "{}, a lightweight web server\n\nUsage: katana [OPTIONS]\n       katana check-config [FILE]\n       katana config dump [OPTIONS]\n\nOptions:\n",
+            Server::version()
+        );
+        for (name, option) in names.iter().zip(Self::OPTIONS.iter()) {
+            help.push_str(&format!("  {:width$}  {}\n", name, option.help, width = width));
+        }
+        help
+    }
+}
+
+pub fn load_args() -> ConfigLayer {
+    let env_args: Vec<String> = args().collect();
+    parse_args(env_args)
+}
+
+pub fn parse_args(args: Vec<String>) -> ConfigLayer {
+    Cli::parse(args).config
 }
@@ -1,7 +1,26 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use crate::core::server::access::AccessRule;
+use crate::core::server::auth::AuthRule;
+use crate::core::server::cache::CacheRule;
+use crate::core::server::cgi::CgiRule;
+use crate::core::server::fastcgi::FastCgiRule;
+use crate::core::server::filetype::{MimeRegistry, MimeSettings};
+use crate::core::server::headers::HeaderRule;
+use crate::core::server::location::LocationRule;
+use crate::core::server::proxy::ProxyRule;
+use crate::core::server::ratelimit::RateLimitRule;
+use crate::core::server::spa::SpaRule;
+use crate::core::server::upload::UploadRule;
+use crate::core::server::webdav::WebDavMode;
 use crate::core::utils::logger::{Logger, LogLevel};
+use crate::core::utils::utils::Utils;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ConfigSource {
     Default,
     File,
@@ -9,59 +28,561 @@ pub enum ConfigSource {
     Args,
 }
 
+/// A configuration value that cannot be used, the server refuses to start with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigIssue {
+    pub source: ConfigSource,
+    /// where the value was set, `.katana:3:1`, `KATANA_PORT` or `--port`
+    pub location: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    pub fn new(source: ConfigSource, location: &str, message: &str) -> Self {
+        Self {
+            source,
+            location: location.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Where the value of a setting comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigOrigin {
+    pub source: ConfigSource,
+    /// `default`, `.katana:3:1`, `KATANA_PORT` or `--port`
+    pub location: String,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.location)
+    }
+}
+
+/// A setting read from text, by the environment as `KATANA_<NAME>` and the command line
+/// as `--<name>`. Options of a section are named after it: `mime_sniff` is `sniff` in
+/// `[mime]`.
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub expected: &'static str,
+    /// sets the value on the layer, `false` when `value` cannot be read
+    pub read: fn(&mut ConfigLayer, &str) -> bool,
+}
+
+/// The values one source sets, `None` for those left to the sources below it.
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub source: ConfigSource,
+    /// where the source sets a key, when more precise than the source itself
+    pub locations: HashMap<&'static str, String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub document_root: Option<PathBuf>,
+    pub worker: Option<i32>,
+    pub log_level: Option<LogLevel>,
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub webdav: Option<WebDavMode>,
+    pub watch: Option<bool>,
+    pub clean_urls: Option<bool>,
+    pub auth: Option<Vec<AuthRule>>,
+    pub access: Option<Vec<AccessRule>>,
+    pub rate_limit: Option<Vec<RateLimitRule>>,
+    pub upload: Option<Vec<UploadRule>>,
+    pub proxy: Option<Vec<ProxyRule>>,
+    pub cgi: Option<Vec<CgiRule>>,
+    pub fastcgi: Option<Vec<FastCgiRule>>,
+    pub spa: Option<Vec<SpaRule>>,
+    pub location: Option<Vec<LocationRule>>,
+    pub headers: Option<Vec<HeaderRule>>,
+    pub cache: Option<Vec<CacheRule>>,
+    pub mime: Option<MimeSettings>,
+    pub mime_sniff: Option<bool>,
+    /// `Some(None)` sends text types without charset
+    pub mime_charset: Option<Option<String>>,
+    pub mime_default: Option<String>,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigLayer {
+    pub fn new(source: ConfigSource) -> Self {
+        Self {
+            source,
+            locations: HashMap::new(),
+            host: None,
+            port: None,
+            document_root: None,
+            worker: None,
+            log_level: None,
+            max_connections: None,
+            max_connections_per_ip: None,
+            webdav: None,
+            watch: None,
+            clean_urls: None,
+            auth: None,
+            access: None,
+            rate_limit: None,
+            upload: None,
+            proxy: None,
+            cgi: None,
+            fastcgi: None,
+            spa: None,
+            location: None,
+            headers: None,
+            cache: None,
+            mime: None,
+            mime_sniff: None,
+            mime_charset: None,
+            mime_default: None,
+            issues: Vec::new(),
+        }
+    }
+
+    /// How this source spells `key`.
+    pub fn origin(&self, key: &str) -> ConfigOrigin {
+        let location = match (self.locations.get(key), self.source) {
+            (Some(location), _) => location.clone(),
+            (None, ConfigSource::Default) => "default".to_string(),
+            (None, ConfigSource::File) => ".katana".to_string(),
+            (None, ConfigSource::Env) => format!("KATANA_{}", key.to_uppercase()),
+            (None, ConfigSource::Args) => format!("--{}", key.replace('_', "-")),
+        };
+        ConfigOrigin { source: self.source, location }
+    }
+}
+
+/// A setting that differs between two configurations, see `Config::diff`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigChange {
+    pub key: &'static str,
+    pub old: String,
+    pub new: String,
+    /// the running server keeps the old value until it is restarted
+    pub restart: bool,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.key, self.old, self.new)?;
+        if self.restart {
+            write!(f, " (requires restart)")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub _source: ConfigSource,
     pub host: String,
     pub port: u16,
     pub document_root: PathBuf,
     pub worker: i32,
     pub log_level: LogLevel,
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    pub webdav: WebDavMode,
+    pub watch: bool,
+    pub clean_urls: bool,
+    pub auth: Vec<AuthRule>,
+    pub access: Vec<AccessRule>,
+    pub rate_limit: Vec<RateLimitRule>,
+    pub upload: Vec<UploadRule>,
+    pub proxy: Vec<ProxyRule>,
+    pub cgi: Vec<CgiRule>,
+    pub fastcgi: Vec<FastCgiRule>,
+    pub spa: Vec<SpaRule>,
+    /// `[[location]]` blocks in file order, see `Location::resolve`
+    pub location: Vec<LocationRule>,
+    /// `[headers]` rules in the order they apply, see `ResponseHeaders::apply`
+    pub headers: Vec<HeaderRule>,
+    /// `[cache]` rules from the least to the most specific, see `Cache::policy_for`
+    pub cache: Vec<CacheRule>,
+    /// where media types come from, see `MimeSettings::build`
+    pub mime: MimeSettings,
+    /// the table built from `mime` when the configuration becomes active, see `ConfigReload`
+    pub mime_types: Arc<MimeRegistry>,
+    /// the layer that set each value, by key, the ones left out are defaults
+    pub origins: HashMap<&'static str, ConfigOrigin>,
+    pub issues: Vec<ConfigIssue>,
 }
 
 impl Config {
+    /// Settings read once when the server starts: the listening socket, the connection
+    /// limits and the file watcher.
+    pub const RESTART_KEYS: [&'static str; 6] = ["host", "port", "worker", "max_connections", "max_connections_per_ip", "watch"];
+
+    /// Settings of the environment and the command line.
+    pub const KEYS: [ConfigKey; 14] = [
+        ConfigKey {
+            name: "host",
+            expected: "an IP address or a host name",
+            read: |layer, value| Self::set(&mut layer.host, Some(value.to_string()).filter(|host| !host.is_empty())),
+        },
+        ConfigKey {
+            name: "port",
+            expected: "a port between 1 and 65535",
+            read: |layer, value| Self::set(&mut layer.port, value.trim().parse::<u16>().ok().filter(|p| *p > 0)),
+        },
+        ConfigKey {
+            name: "document_root",
+            expected: "a folder",
+            read: |layer, value| Self::set(&mut layer.document_root, Some(PathBuf::from(value)).filter(|root| !root.as_os_str().is_empty())),
+        },
+        ConfigKey {
+            name: "worker",
+            expected: "a positive number",
+            read: |layer, value| Self::set(&mut layer.worker, positive(value)),
+        },
+        ConfigKey {
+            name: "log_level",
+            expected: "DEBUG, INFO, WARN or ERROR",
+            read: |layer, value| Self::set(&mut layer.log_level, LogLevel::from_str(&value.trim().to_uppercase())),
+        },
+        ConfigKey {
+            name: "max_connections",
+            expected: "a positive number",
+            read: |layer, value| Self::set(&mut layer.max_connections, positive(value)),
+        },
+        ConfigKey {
+            name: "max_connections_per_ip",
+            expected: "a positive number",
+            read: |layer, value| Self::set(&mut layer.max_connections_per_ip, positive(value)),
+        },
+        ConfigKey {
+            name: "webdav",
+            expected: "off, read-only or read-write",
+            read: |layer, value| Self::set(&mut layer.webdav, WebDavMode::from_name(value.trim())),
+        },
+        ConfigKey {
+            name: "watch",
+            expected: "a boolean like true or false",
+            read: |layer, value| Self::set(&mut layer.watch, boolean(value)),
+        },
+        ConfigKey {
+            name: "clean_urls",
+            expected: "a boolean like true or false",
+            read: |layer, value| Self::set(&mut layer.clean_urls, boolean(value)),
+        },
+        ConfigKey {
+            name: "spa",
+            expected: "an HTML file inside the document root",
+            read: |layer, value| Self::set(&mut layer.spa, SpaRule::new("/", value).map(|rule| vec![rule])),
+        },
+        ConfigKey {
+            name: "mime_sniff",
+            expected: "a boolean like true or false",
+            read: |layer, value| Self::set(&mut layer.mime_sniff, boolean(value)),
+        },
+        ConfigKey {
+            name: "mime_charset",
+            expected: "a charset like utf-8, or false",
+            read: |layer, value| {
+                let charset = match boolean(value) {
+                    Some(false) => Some(None),
+                    _ => Some(Some(value.trim().to_string())).filter(|_| Utils::is_header_name(value.trim())),
+                };
+                Self::set(&mut layer.mime_charset, charset)
+            },
+        },
+        ConfigKey {
+            name: "mime_default",
+            expected: "a media type like application/octet-stream",
+            read: |layer, value| {
+                let media = Some(value.trim().to_string()).filter(|media| MimeRegistry::is_media_type(media));
+                Self::set(&mut layer.mime_default, media)
+            },
+        },
+    ];
+
+    /// Every key `.katana` accepts: the `[katana]` values, the sections and their options,
+    /// dotted as `mime.sniff`.
+    pub fn file_keys() -> Vec<&'static str> {
+        super::file::KATANA_KEYS.into_iter().chain(Self::section_keys().into_iter().map(|(key, _)| key)).collect()
+    }
+
+    /// Keys of `.katana` without an environment variable or option, and why: a section is a
+    /// table keyed by path, extension or media type that does not fit in one value, and its
+    /// options are read with its rules. Every section key `KEYS` does not map, a new section
+    /// is file only until it is mapped; `[katana]` keys are always mapped.
+    pub fn file_only_keys() -> Vec<(&'static str, &'static str)> {
+        Self::section_keys()
+            .into_iter()
+            .filter(|(key, _)| !Self::KEYS.iter().any(|config_key| config_key.name == key.replace('.', "_")))
+            .collect()
+    }
+
+    // the sections and their options, with the reason they would be file only
+    fn section_keys() -> Vec<(&'static str, &'static str)> {
+        let sections = super::file::SECTIONS.into_iter().filter(|section| *section != "katana");
+        sections
+            .map(|section| (section, "a table of rules keyed by path, extension or media type"))
+            .chain(super::file::SECTION_OPTIONS.into_iter().map(|option| (option, "an option read with the rules of its section")))
+            .collect()
+    }
+
     pub fn load() -> Self {
+        let cli = super::arg::Cli::parse(std::env::args().collect());
+        let file = match &cli.config_file {
+            Some(path) if !path.is_file() => {
+                let mut layer = ConfigLayer::new(ConfigSource::Args);
+                layer.issues.push(ConfigIssue::new(ConfigSource::Args, "--config", &format!("file {:?} not found", path)));
+                layer
+            }
+            Some(path) => super::file::load_path(path),
+            None => Self::load_file(), // load .katana file, nothing is set when it does not exist
+        };
+
         // config sources in priority order
-        let configs = vec![
-            Self::load_file(), // load .katana file, but if file not exist, return default config
+        let layers = [
+            file,
             Self::load_env(),
-            Self::load_args(),
+            cli.config,
         ];
 
-        let config = configs.into_iter().fold(Self::default(), |acc, curr| {
-            Config {
-                _source: curr._source,
-                host: if curr.host.is_empty() { acc.host } else { curr.host },
-                port: if curr.port == 0 { acc.port } else { curr.port },
-                document_root: if curr.document_root.as_os_str().is_empty() { acc.document_root } else { curr.document_root },
-                worker: if curr.worker <= 0 { acc.worker } else { curr.worker },
-                log_level: curr.log_level,
-            }
-        });
+        let mut config = Self::default();
+        for layer in &layers {
+            config.overlay(layer);
+        }
+        let issues = config.validate();
+        config.issues.extend(issues);
 
         Logger::debug(
             format!(
-                "[Config] Configuration from {:?}: host={:?}, port={:?}, root_dir={:?}, worker={:?}, log_level={:?}",
-                config._source, config.host, config.port, config.document_root, config.worker, config.log_level
+                "[Config] Configuration: host={:?} ({}), port={:?} ({}), root_dir={:?} ({}), worker={:?} ({}), log_level={:?} ({})",
+                config.host, config.origin("host"),
+                config.port, config.origin("port"),
+                config.document_root, config.origin("document_root"),
+                config.worker, config.origin("worker"),
+                config.log_level, config.origin("log_level"),
             ).as_str(),
         );
 
         config
     }
 
+    /// Overrides the values `layer` sets, recording it as their origin.
+    pub fn overlay(&mut self, layer: &ConfigLayer) {
+        let origins = &mut self.origins;
+        Self::take(&mut self.host, &layer.host, origins, layer, "host");
+        Self::take(&mut self.port, &layer.port, origins, layer, "port");
+        Self::take(&mut self.document_root, &layer.document_root, origins, layer, "document_root");
+        Self::take(&mut self.worker, &layer.worker, origins, layer, "worker");
+        Self::take(&mut self.log_level, &layer.log_level, origins, layer, "log_level");
+        Self::take(&mut self.max_connections, &layer.max_connections, origins, layer, "max_connections");
+        Self::take(&mut self.max_connections_per_ip, &layer.max_connections_per_ip, origins, layer, "max_connections_per_ip");
+        Self::take(&mut self.webdav, &layer.webdav, origins, layer, "webdav");
+        Self::take(&mut self.watch, &layer.watch, origins, layer, "watch");
+        Self::take(&mut self.clean_urls, &layer.clean_urls, origins, layer, "clean_urls");
+        Self::take(&mut self.auth, &layer.auth, origins, layer, "auth");
+        Self::take(&mut self.access, &layer.access, origins, layer, "access");
+        Self::take(&mut self.rate_limit, &layer.rate_limit, origins, layer, "rate_limit");
+        Self::take(&mut self.upload, &layer.upload, origins, layer, "upload");
+        Self::take(&mut self.proxy, &layer.proxy, origins, layer, "proxy");
+        Self::take(&mut self.cgi, &layer.cgi, origins, layer, "cgi");
+        Self::take(&mut self.fastcgi, &layer.fastcgi, origins, layer, "fastcgi");
+        Self::take(&mut self.spa, &layer.spa, origins, layer, "spa");
+        Self::take(&mut self.location, &layer.location, origins, layer, "location");
+        Self::take(&mut self.headers, &layer.headers, origins, layer, "headers");
+        Self::take(&mut self.cache, &layer.cache, origins, layer, "cache");
+        Self::take(&mut self.mime, &layer.mime, origins, layer, "mime");
+        Self::take(&mut self.mime.sniff, &layer.mime_sniff, origins, layer, "mime_sniff");
+        Self::take(&mut self.mime.charset, &layer.mime_charset, origins, layer, "mime_charset");
+        Self::take(&mut self.mime.default_type, &layer.mime_default, origins, layer, "mime_default");
+        self.issues.extend(layer.issues.iter().cloned());
+    }
+
+    fn take<T: Clone>(
+        field: &mut T,
+        value: &Option<T>,
+        origins: &mut HashMap<&'static str, ConfigOrigin>,
+        layer: &ConfigLayer,
+        key: &'static str,
+    ) {
+        if let Some(value) = value {
+            *field = value.clone();
+            origins.insert(key, layer.origin(key));
+        }
+    }
+
+    // whether there was a value to set
+    fn set<T>(field: &mut Option<T>, value: Option<T>) -> bool {
+        let read = value.is_some();
+        if read {
+            *field = value;
+        }
+        read
+    }
+
+    /// The layer that set `key`, the default value when none did.
+    pub fn origin(&self, key: &str) -> ConfigOrigin {
+        match self.origins.get(key) {
+            Some(origin) => origin.clone(),
+            None => ConfigLayer::new(ConfigSource::Default).origin(key),
+        }
+    }
+
+    /// The settings `other` changes, in declaration order.
+    pub fn diff(&self, other: &Config) -> Vec<ConfigChange> {
+        let value = |value: &dyn fmt::Debug| format!("{:?}", value);
+        let settings: [(&'static str, String, String); 22] = [
+            ("host", value(&self.host), value(&other.host)),
+            ("port", value(&self.port), value(&other.port)),
+            ("document_root", value(&self.document_root), value(&other.document_root)),
+            ("worker", value(&self.worker), value(&other.worker)),
+            ("log_level", value(&self.log_level), value(&other.log_level)),
+            ("max_connections", value(&self.max_connections), value(&other.max_connections)),
+            ("max_connections_per_ip", value(&self.max_connections_per_ip), value(&other.max_connections_per_ip)),
+            ("webdav", value(&self.webdav), value(&other.webdav)),
+            ("watch", value(&self.watch), value(&other.watch)),
+            ("clean_urls", value(&self.clean_urls), value(&other.clean_urls)),
+            ("auth", value(&self.auth), value(&other.auth)),
+            ("access", value(&self.access), value(&other.access)),
+            ("rate_limit", value(&self.rate_limit), value(&other.rate_limit)),
+            ("upload", value(&self.upload), value(&other.upload)),
+            ("proxy", value(&self.proxy), value(&other.proxy)),
+            ("cgi", value(&self.cgi), value(&other.cgi)),
+            ("fastcgi", value(&self.fastcgi), value(&other.fastcgi)),
+            ("spa", value(&self.spa), value(&other.spa)),
+            ("location", value(&self.location), value(&other.location)),
+            ("headers", value(&self.headers), value(&other.headers)),
+            ("cache", value(&self.cache), value(&other.cache)),
+            ("mime", value(&self.mime), value(&other.mime)),
+        ];
+
+        settings
+            .into_iter()
+            .filter(|(_, old, new)| old != new)
+            .map(|(key, old, new)| ConfigChange { key, old, new, restart: Self::RESTART_KEYS.contains(&key) })
+            .collect()
+    }
+
+    /// Loads the `.katana` file at `path` alone, on top of the default values, as
+    /// `katana check-config` does.
+    pub fn from_file(path: &Path) -> Self {
+        let mut config = Self::default();
+        config.overlay(&super::file::load_path(path));
+        let issues = config.validate();
+        config.issues.extend(issues);
+        config
+    }
+
+    /// The effective configuration in the `.katana` format, every value followed by the
+    /// layer that set it. Rule sections are summed up.
+    pub fn dump(&self) -> String {
+        let mut lines = vec![
+            ("host", format!("host = {:?}", self.host)),
+            ("port", format!("port = {}", self.port)),
+            ("document_root", format!("document_root = {:?}", self.document_root.to_string_lossy())),
+            ("worker", format!("worker = {}", self.worker)),
+            ("log_level", format!("log_level = {:?}", format!("{:?}", self.log_level))),
+            ("max_connections", format!("max_connections = {}", self.max_connections)),
+            ("max_connections_per_ip", format!("max_connections_per_ip = {}", self.max_connections_per_ip)),
+            ("webdav", format!("webdav = {:?}", self.webdav.as_str())),
+            ("watch", format!("watch = {}", self.watch)),
+            ("clean_urls", format!("clean_urls = {}", self.clean_urls)),
+        ];
+        let sections = [
+            ("auth", self.auth.len()),
+            ("access", self.access.len()),
+            ("rate_limit", self.rate_limit.len()),
+            ("upload", self.upload.len()),
+            ("proxy", self.proxy.len()),
+            ("cgi", self.cgi.len()),
+            ("fastcgi", self.fastcgi.len()),
+            ("spa", self.spa.len()),
+            ("location", self.location.len()),
+            ("headers", self.headers.len()),
+            ("cache", self.cache.len()),
+            ("mime", self.mime.types.len()),
+        ];
+        for (key, rules) in sections {
+            let line = match key {
+                "location" => format!("# [[location]] {} block(s)", rules),
+                "mime" => format!("# [mime] {} type(s), {} file(s)", rules, self.mime.files.len()),
+                _ => format!("# [{}] {} rule(s)", key, rules),
+            };
+            lines.push((key, line));
+        }
+        let charset = self.mime.charset.as_ref().map_or("false".to_string(), |charset| format!("{:?}", charset));
+        lines.push(("mime_sniff", format!("#   sniff = {}", self.mime.sniff)));
+        lines.push(("mime_charset", format!("#   charset = {}", charset)));
+        lines.push(("mime_default", format!("#   default = {:?}", self.mime.default_type)));
+
+        let width = lines.iter().map(|(_, line)| line.len()).max().unwrap_or(0);
+        let mut dump = String::from("[katana]\n");
+        for (key, line) in &lines {
+            // the rule sections after the [katana] values
+            if *key == "auth" {
+                dump.push('\n');
+            }
+            dump.push_str(&format!("{:width$}  # {}\n", line, self.origin(key), width = width));
+        }
+        dump
+    }
+
+    // checks of the merged values, located at the layer that set them
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        // the file reports its own document root, at the line setting it
+        let origin = self.origin("document_root");
+        if let Some(message) = Self::check_document_root(&self.document_root).filter(|_| origin.source != ConfigSource::File) {
+            issues.push(ConfigIssue::new(origin.source, &origin.location, &message));
+        }
+
+        let hostname = |host: &str| {
+            host.split('.')
+                .all(|label| !label.is_empty() && !label.starts_with('-') && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        };
+        if self.host.parse::<IpAddr>().is_err() && !hostname(&self.host) {
+            let origin = self.origin("host");
+            issues.push(ConfigIssue::new(origin.source, &origin.location, &format!("host {:?} is neither an IP address nor a host name", self.host)));
+        }
+
+        issues
+    }
+
+    pub(crate) fn check_document_root(document_root: &Path) -> Option<String> {
+        match (document_root.is_dir(), document_root.exists()) {
+            (true, _) => None,
+            (false, true) => Some(format!("document root {:?} is not a folder", document_root)),
+            (false, false) => Some(format!("document root {:?} does not exist", document_root)),
+        }
+    }
+
     fn default() -> Self {
         super::default::load_default()
     }
 
-    pub fn load_args() -> Self {
+    pub fn load_args() -> ConfigLayer {
         super::arg::load_args()
     }
-    
-    pub fn load_env() -> Self {
+
+    pub fn load_env() -> ConfigLayer {
         super::env::load_env()
     }
 
-    pub fn load_file() -> Self {
+    pub fn load_file() -> ConfigLayer {
         super::file::load_file()
     }
 }
+
+fn positive<T: FromStr + Default + PartialOrd>(value: &str) -> Option<T> {
+    value.trim().parse().ok().filter(|value| *value > T::default())
+}
+
+fn boolean(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
@@ -1,6 +1,9 @@
-use std::path::PathBuf;
-use crate::core::utils::logger::LogLevel;
 use super::config::Config;
+use crate::core::server::filetype::{MimeRegistry, MimeSettings};
+use crate::core::server::webdav::WebDavMode;
+use crate::core::utils::logger::LogLevel;
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 pub struct DefaultConfig;
 
@@ -11,10 +14,14 @@ impl DefaultConfig {
     pub const DOCUMENT_ROOT: &'static str = "public";
     pub const WORKER: i32 = 4;
     pub const LOG_LEVEL: LogLevel = LogLevel::INFO;
+    pub const MAX_CONNECTIONS: usize = 1024;
+    pub const MAX_CONNECTIONS_PER_IP: usize = 32;
+    pub const WEBDAV: WebDavMode = WebDavMode::Off;
+    pub const WATCH: bool = false;
+    pub const CLEAN_URLS: bool = false;
 
     pub fn as_config() -> Config {
         Config {
-            _source: crate::core::config::config::ConfigSource::Default,
             host: None::<String>.unwrap_or_else(|| {
                 if cfg!(target_family = "windows") {
                     Self::HOST_WINDOWS.to_string()
@@ -26,6 +33,26 @@ impl DefaultConfig {
             document_root: None::<PathBuf>.unwrap_or_else(|| PathBuf::from(Self::DOCUMENT_ROOT)),
             worker: None::<i32>.unwrap_or(Self::WORKER),
             log_level: None::<LogLevel>.unwrap_or(Self::LOG_LEVEL),
+            max_connections: Self::MAX_CONNECTIONS,
+            max_connections_per_ip: Self::MAX_CONNECTIONS_PER_IP,
+            webdav: Self::WEBDAV,
+            watch: Self::WATCH,
+            clean_urls: Self::CLEAN_URLS,
+            auth: Vec::new(),
+            access: Vec::new(),
+            rate_limit: Vec::new(),
+            upload: Vec::new(),
+            proxy: Vec::new(),
+            cgi: Vec::new(),
+            fastcgi: Vec::new(),
+            spa: Vec::new(),
+            location: Vec::new(),
+            headers: Vec::new(),
+            cache: Vec::new(),
+            mime: MimeSettings::default(),
+            mime_types: MimeRegistry::builtin(),
+            origins: HashMap::new(),
+            issues: Vec::new(),
         }
     }
 }
@@ -1,70 +1,1149 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use crate::core::config::config::Config;
-use crate::core::config::default::load_default;
+use std::path::{Path, PathBuf};
+use crate::core::config::config::{Config, ConfigIssue, ConfigLayer, ConfigSource};
+use crate::core::server::access::{AccessDirective, AccessRule};
+use crate::core::server::auth::{AuthRule, BasicAuth};
+use crate::core::server::cache::{Cache, CacheMatch, CachePolicy, CacheRule};
+use crate::core::server::cgi::CgiRule;
+use crate::core::server::fastcgi::{FastCgiAddress, FastCgiRule};
+use crate::core::server::filetype::{MimeRegistry, MimeSettings};
+use crate::core::server::headers::{HeaderAction, HeaderMatch, HeaderRule};
+use crate::core::server::http::HttpMethod;
+use crate::core::server::location::{LocationAuth, LocationMatch, LocationRule};
+use crate::core::server::proxy::{ProxyRule, Upstream};
+use crate::core::server::ratelimit::RateLimitRule;
+use crate::core::server::spa::SpaRule;
+use crate::core::server::upload::{OverwritePolicy, UploadRule};
+use crate::core::server::webdav::WebDavMode;
 use crate::core::utils::logger::{Logger, LogLevel};
+use crate::core::utils::regex::Regex;
 use crate::core::utils::toml::{TomlParser, TomlValue};
+use crate::core::utils::utils::Utils;
 
-pub fn load_file() -> Config {
-    // Use the root directory of the project
-    let document_root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    let katana_file_path = document_root.join(".katana");
+pub(super) const SECTIONS: [&str; 13] = [
+    "katana",
+    "auth",
+    "access",
+    "rate_limit",
+    "upload",
+    "proxy",
+    "cgi",
+    "fastcgi",
+    "spa",
+    "location",
+    "headers",
+    "cache",
+    "mime",
+];
 
+const LOCATION_KEYS: [&str; 9] = [
+    "path",
+    "headers",
+    "cache_control",
+    "directory_listing",
+    "index",
+    "methods",
+    "auth",
+    "realm",
+    "error_pages",
+];
+
+pub(super) const KATANA_KEYS: [&str; 10] = [
+    "host",
+    "port",
+    "document_root",
+    "worker",
+    "log_level",
+    "max_connections",
+    "max_connections_per_ip",
+    "webdav",
+    "watch",
+    "clean_urls",
+];
+
+/// Options of the sections, next to their rules.
+pub(super) const SECTION_OPTIONS: [&str; 19] = [
+    "auth.realm",
+    "rate_limit.rate",
+    "rate_limit.burst",
+    "upload.max_size",
+    "upload.quota",
+    "upload.overwrite",
+    "upload.form",
+    "proxy.timeout",
+    "cgi.timeout",
+    "fastcgi.timeout",
+    "headers.security",
+    "headers.server",
+    "cache.expires",
+    "cache.immutable",
+    "cache.fingerprint",
+    "mime.files",
+    "mime.default",
+    "mime.charset",
+    "mime.sniff",
+];
+
+// the options of `section`, without the section name
+fn options(section: &str) -> Vec<&'static str> {
+    SECTION_OPTIONS
+        .iter()
+        .filter_map(|option| option.strip_prefix(section)?.strip_prefix('.'))
+        .collect()
+}
+
+pub fn load_file() -> ConfigLayer {
+    // relative to the working directory, the root directory of the project
+    load_path(Path::new(".katana"))
+}
+
+pub fn load_path(katana_file_path: &Path) -> ConfigLayer {
     let mut parser = TomlParser::new();
 
-    let default_config = load_default();
+    let mut layer = ConfigLayer::new(ConfigSource::File);
 
     if !katana_file_path.exists() {
         Logger::debug(
             format!("[Config:File] .katana file not found at {:?}.", katana_file_path).as_str(),
         );
-        return default_config;
+        return layer;
+    }
+
+    let file = katana_file_path.display().to_string();
+    let toml_string = match fs::read_to_string(katana_file_path) {
+        Ok(toml_string) => toml_string,
+        Err(error) => {
+            let issue = ConfigIssue::new(ConfigSource::File, &file, &format!("cannot read the file, {}", error));
+            layer.issues.push(issue);
+            return layer;
+        }
+    };
+
+    if let Err(error) = parser.parse(&toml_string) {
+        let location = format!("{}:{}:{}", file, error.line, error.column);
+        layer.issues.push(ConfigIssue::new(ConfigSource::File, &location, &error.message));
+        return layer;
     }
 
-    let toml_string = fs::read_to_string(&katana_file_path).unwrap_or_else(|_| {
-        Logger::error("[Config:File] Failed to read .katana file");
-        String::new()
-    });
+    let mut issues = Issues::new(&file, &parser);
 
-    parser.parse(&toml_string);
+    for (name, value) in &parser.data {
+        if !SECTIONS.contains(&name.as_str()) {
+            issues.report(name, &format!("unknown section [{}]", name));
+        } else if name == "location" {
+            if !matches!(value, TomlValue::Array(_)) {
+                issues.report(name, "`location` must be blocks, [[location]]");
+            }
+        } else if !matches!(value, TomlValue::Table(_)) {
+            issues.report(name, &format!("`{}` must be a section, [{}]", name, name));
+        }
+    }
 
     // Extracting configuration values from the parser
+    let empty = HashMap::new();
     let katana = match parser.get_value("katana") {
         Some(TomlValue::Table(t)) => t,
-        _ => &std::collections::HashMap::new(), // use keyval later
+        _ => &empty,
+    };
+    unknown_keys("katana", katana, &KATANA_KEYS, &[], &mut issues);
+
+    // keys left out keep the value of the sources below
+    layer.host = match katana.get("host") {
+        Some(TomlValue::String(h)) if !h.is_empty() => Some(h.clone()),
+        Some(other) => {
+            issues.expected("katana.host", "an IP address or a host name", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.port = match katana.get("port") {
+        Some(TomlValue::Integer(p)) if (1..=65535).contains(p) => Some(*p as u16),
+        Some(other) => {
+            issues.expected("katana.port", "a port between 1 and 65535", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.document_root = match katana.get("document_root") {
+        Some(TomlValue::String(dir)) if !dir.is_empty() => {
+            if let Some(message) = Config::check_document_root(Path::new(dir)) {
+                issues.report("katana.document_root", &message);
+            }
+            Some(PathBuf::from(dir))
+        }
+        Some(other) => {
+            issues.expected("katana.document_root", "a folder", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.worker = match katana.get("worker") {
+        Some(TomlValue::Integer(w)) if (1..=i32::MAX as i64).contains(w) => Some(*w as i32),
+        Some(other) => {
+            issues.expected("katana.worker", "a positive number", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.log_level = match katana.get("log_level") {
+        Some(TomlValue::String(level)) if LogLevel::from_str(&level.to_uppercase()).is_some() => {
+            LogLevel::from_str(&level.to_uppercase())
+        }
+        Some(other) => {
+            issues.expected("katana.log_level", "DEBUG, INFO, WARN or ERROR", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.max_connections = match katana.get("max_connections") {
+        Some(TomlValue::Integer(m)) if *m > 0 => Some(*m as usize),
+        Some(other) => {
+            issues.expected("katana.max_connections", "a positive number", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.max_connections_per_ip = match katana.get("max_connections_per_ip") {
+        Some(TomlValue::Integer(m)) if *m > 0 => Some(*m as usize),
+        Some(other) => {
+            issues.expected("katana.max_connections_per_ip", "a positive number", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.webdav = match katana.get("webdav") {
+        Some(TomlValue::String(mode)) if WebDavMode::from_name(mode).is_some() => WebDavMode::from_name(mode),
+        Some(TomlValue::Boolean(true)) => Some(WebDavMode::ReadWrite),
+        Some(TomlValue::Boolean(false)) => Some(WebDavMode::Off),
+        Some(other) => {
+            issues.expected("katana.webdav", "off, read-only or read-write", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.watch = match katana.get("watch") {
+        Some(TomlValue::Boolean(watch)) => Some(*watch),
+        Some(other) => {
+            issues.expected("katana.watch", "true or false", other);
+            None
+        }
+        None => None,
+    };
+
+    layer.clean_urls = match katana.get("clean_urls") {
+        Some(TomlValue::Boolean(clean_urls)) => Some(*clean_urls),
+        Some(other) => {
+            issues.expected("katana.clean_urls", "true or false", other);
+            None
+        }
+        None => None,
+    };
+
+    let section = |name: &str| match parser.get_value(name) {
+        Some(TomlValue::Table(t)) => Some(t),
+        _ => None,
+    };
+    layer.auth = section("auth").map(|t| load_auth(t, &mut issues));
+    layer.access = section("access").map(|t| load_access(t, &mut issues));
+    layer.rate_limit = section("rate_limit").map(|t| load_rate_limit(t, &mut issues));
+    layer.upload = section("upload").map(|t| load_upload(t, &mut issues));
+    layer.proxy = section("proxy").map(|t| load_proxy(t, &mut issues));
+    layer.cgi = section("cgi").map(|t| load_cgi(t, &mut issues));
+    layer.fastcgi = section("fastcgi").map(|t| load_fastcgi(t, &mut issues));
+    layer.spa = section("spa").map(|t| load_spa(t, &mut issues));
+    layer.headers = section("headers").map(|t| load_headers(t, &mut issues));
+    layer.cache = section("cache").map(|t| load_cache(t, &mut issues));
+    layer.mime = section("mime").map(|t| load_mime(t, &mut issues));
+    // the options of [mime] layer on their own, the environment and the command line set them too
+    if let (Some(mime), Some(table)) = (&layer.mime, section("mime")) {
+        layer.mime_sniff = table.contains_key("sniff").then_some(mime.sniff);
+        layer.mime_charset = table.contains_key("charset").then(|| mime.charset.clone());
+        layer.mime_default = table.contains_key("default").then(|| mime.default_type.clone());
+    }
+    let blocks = match parser.get_value("location") {
+        Some(TomlValue::Array(blocks)) => Some(blocks),
+        _ => None,
+    };
+    layer.location = blocks.map(|blocks| load_location(blocks, &mut issues));
+
+    // where the values come from, `config dump` shows them
+    for key in KATANA_KEYS {
+        if katana.contains_key(key) {
+            layer.locations.insert(key, issues.location(&format!("katana.{}", key)));
+        }
+    }
+    for key in SECTIONS.into_iter().filter(|key| *key != "katana") {
+        if section(key).is_some() {
+            layer.locations.insert(key, issues.location(key));
+        }
+    }
+    if blocks.is_some() {
+        layer.locations.insert("location", issues.location("location.0"));
+    }
+    for (key, option) in [("mime_sniff", "sniff"), ("mime_charset", "charset"), ("mime_default", "default")] {
+        if section("mime").is_some_and(|table| table.contains_key(option)) {
+            layer.locations.insert(key, issues.location(&format!("mime.{}", option)));
+        }
+    }
+
+    layer.issues = issues.into_list();
+    layer
+}
+
+// problems found in the file, located by line and column
+struct Issues<'p> {
+    file: String,
+    parser: &'p TomlParser,
+    list: Vec<(Option<(usize, usize)>, ConfigIssue)>,
+}
+
+impl<'p> Issues<'p> {
+    fn new(file: &str, parser: &'p TomlParser) -> Self {
+        Self {
+            file: file.to_string(),
+            parser,
+            list: Vec::new(),
+        }
+    }
+
+    // `path` is the section and the key, `katana.port`; keys of an inline table are
+    // located at the key holding the table
+    fn position(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(position) = self.parser.position(path) {
+                return Some(position);
+            }
+            path = path.rsplit_once('.')?.0;
+        }
+    }
+
+    fn location(&self, path: &str) -> String {
+        match self.position(path) {
+            Some((line, column)) => format!("{}:{}:{}", self.file, line, column),
+            None => self.file.clone(),
+        }
+    }
+
+    fn report(&mut self, path: &str, message: &str) {
+        let position = self.position(path);
+        let location = self.location(path);
+        self.list.push((position, ConfigIssue::new(ConfigSource::File, &location, message)));
+    }
+
+    fn expected(&mut self, path: &str, expected: &str, value: &TomlValue) {
+        // `location.1.methods` is `methods`
+        let key = path.split_once('.').map_or(path, |(_, key)| key);
+        let key = key.split_once('.').filter(|(index, _)| index.parse::<usize>().is_ok()).map_or(key, |(_, key)| key);
+        self.report(path, &format!("`{}` must be {}, got {}", key, expected, describe(value)));
+    }
+
+    // in the order of the file
+    fn into_list(mut self) -> Vec<ConfigIssue> {
+        self.list.sort_by_key(|(position, _)| *position);
+        self.list.into_iter().map(|(_, issue)| issue).collect()
+    }
+}
+
+fn describe(value: &TomlValue) -> String {
+    match value {
+        TomlValue::String(value) => format!("{:?}", value),
+        TomlValue::Integer(value) => value.to_string(),
+        TomlValue::Float(value) => value.to_string(),
+        TomlValue::Boolean(value) => value.to_string(),
+        TomlValue::Datetime(value) => value.to_string(),
+        TomlValue::Array(_) => "an array".to_string(),
+        TomlValue::Table(_) => "a table".to_string(),
+    }
+}
+
+// keys that are neither an option of the section nor one of its path patterns, likely typos
+fn unknown_keys(name: &str, section: &HashMap<String, TomlValue>, options: &[&str], patterns: &[char], issues: &mut Issues) {
+    for key in section.keys() {
+        if !options.contains(&key.as_str()) && !key.starts_with(patterns) {
+            issues.report(&format!("{}.{}", name, key), &format!("unknown key `{}` in [{}]", key, name));
+        }
+    }
+}
+
+// [auth]
+// realm = "Restricted"
+// "/private" = ".htpasswd"
+fn load_auth(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<AuthRule> {
+    unknown_keys("auth", section, &options("auth"), &['/'], issues);
+
+    let realm = match section.get("realm") {
+        Some(TomlValue::String(realm)) => realm.clone(),
+        Some(other) => {
+            issues.expected("auth.realm", "a string", other);
+            BasicAuth::DEFAULT_REALM.to_string()
+        }
+        None => BasicAuth::DEFAULT_REALM.to_string(),
+    };
+
+    let mut rules: Vec<AuthRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/'))
+        .filter_map(|(prefix, value)| match value {
+            TomlValue::String(htpasswd) => {
+                if !Path::new(htpasswd).is_file() {
+                    issues.report(&format!("auth.{}", prefix), &format!("htpasswd file {:?} not found", htpasswd));
+                }
+                Some(AuthRule::new(prefix, PathBuf::from(htpasswd), &realm))
+            }
+            other => {
+                issues.expected(&format!("auth.{}", prefix), "the path of an htpasswd file", other);
+                None
+            }
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    rules
+}
+
+// [access]
+// "/internal" = ["allow 10.0.0.0/8", "allow ::1", "deny all"]
+fn load_access(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<AccessRule> {
+    unknown_keys("access", section, &[], &['/'], issues);
+
+    let mut rules: Vec<AccessRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/'))
+        .map(|(prefix, value)| {
+            let values = match value {
+                TomlValue::Array(values) => values.clone(),
+                other => vec![other.clone()],
+            };
+
+            let directives: Result<Vec<AccessDirective>, TomlValue> = values
+                .iter()
+                .filter(|value| !matches!(value, TomlValue::String(s) if s.is_empty()))
+                .map(|value| match value {
+                    TomlValue::String(directive) => AccessDirective::parse(directive).ok_or_else(|| value.clone()),
+                    _ => Err(value.clone()),
+                })
+                .collect();
+
+            match directives {
+                Ok(directives) => AccessRule::new(prefix, directives),
+                Err(invalid) => {
+                    let path = format!("access.{}", prefix);
+                    issues.expected(&path, "directives like \"allow 10.0.0.0/8\" or \"deny all\"", &invalid);
+                    // a typo must not open the folder, refuse everyone instead
+                    AccessRule::new(prefix, vec![AccessDirective::parse("deny all").unwrap()])
+                }
+            }
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    rules
+}
+
+// [rate_limit]
+// rate = 10          # requests per second for every path
+// burst = 20
+// "/api" = [2, 5]    # rate and burst for this prefix
+fn load_rate_limit(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<RateLimitRule> {
+    unknown_keys("rate_limit", section, &options("rate_limit"), &['/'], issues);
+
+    let mut rules = Vec::new();
+
+    if let Some(rate) = section.get("rate") {
+        let burst = section.get("burst").map(|burst| ("rate_limit.burst", burst));
+        rules.extend(rate_limit_rule("/", ("rate_limit.rate", rate), burst, issues));
+    }
+
+    for (prefix, value) in section.iter().filter(|(key, _)| key.starts_with('/')) {
+        let path = format!("rate_limit.{}", prefix);
+        match value {
+            TomlValue::Array(values) if values.len() == 2 => {
+                rules.extend(rate_limit_rule(prefix, (&path, &values[0]), Some((&path, &values[1])), issues));
+            }
+            other => issues.expected(&path, "[rate, burst]", other),
+        }
+    }
+
+    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    rules
+}
+
+fn rate_limit_rule(
+    prefix: &str,
+    (rate_path, rate): (&str, &TomlValue),
+    burst: Option<(&str, &TomlValue)>,
+    issues: &mut Issues,
+) -> Option<RateLimitRule> {
+    let rate = match rate {
+        TomlValue::Integer(rate) if *rate > 0 => *rate as f64,
+        TomlValue::Float(rate) if *rate > 0.0 => *rate,
+        other => {
+            issues.expected(rate_path, "a positive rate of requests per second", other);
+            return None;
+        }
     };
 
-    let host = match katana.get("host") {
-        Some(TomlValue::String(h)) => h.clone(),
-        _ => default_config.host.clone(),
+    // without an explicit burst, allow one second worth of requests at once
+    let burst = match burst {
+        Some((_, TomlValue::Integer(burst))) if *burst > 0 => (*burst).min(u32::MAX as i64) as u32,
+        None | Some((_, TomlValue::Integer(0))) => rate.ceil() as u32,
+        Some((burst_path, other)) => {
+            issues.expected(burst_path, "a positive burst of requests", other);
+            return None;
+        }
     };
 
-    let port = match katana.get("port") {
-        Some(TomlValue::Integer(p)) => *p as u16,
-        _ => default_config.port,
+    Some(RateLimitRule::new(prefix, rate, burst))
+}
+
+// [upload]
+// max_size = "100M"     # per file, 0 for no limit
+// quota = "10G"         # all files under a prefix, 0 for no limit
+// overwrite = "deny"    # allow, deny or rename
+// form = true
+// "/incoming" = true
+// "/builds" = ["max_size 2G", "overwrite allow"]
+fn load_upload(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<UploadRule> {
+    unknown_keys("upload", section, &options("upload"), &['/'], issues);
+
+    let mut defaults = UploadRule::new("/");
+
+    for (key, size) in [("max_size", &mut defaults.max_size), ("quota", &mut defaults.quota)] {
+        if let Some(value) = section.get(key) {
+            match size_value(value) {
+                Some(value) => *size = value,
+                None => issues.expected(&format!("upload.{}", key), "a size like \"100M\"", value),
+            }
+        }
+    }
+
+    match section.get("overwrite") {
+        Some(TomlValue::String(policy)) if OverwritePolicy::from_name(policy).is_some() => {
+            defaults.overwrite = OverwritePolicy::from_name(policy).unwrap_or(defaults.overwrite);
+        }
+        Some(TomlValue::Boolean(allow)) => defaults.overwrite = if *allow { OverwritePolicy::Allow } else { OverwritePolicy::Deny },
+        Some(other) => issues.expected("upload.overwrite", "allow, deny or rename", other),
+        None => {}
+    }
+
+    match section.get("form") {
+        Some(TomlValue::Boolean(form)) => defaults.form = *form,
+        Some(other) => issues.expected("upload.form", "true or false", other),
+        None => {}
+    }
+
+    let mut rules: Vec<UploadRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/'))
+        .filter_map(|(prefix, value)| {
+            let options = match value {
+                TomlValue::Boolean(false) => return None,
+                TomlValue::Boolean(true) => Vec::new(),
+                TomlValue::Array(values) => values.clone(),
+                other => vec![other.clone()],
+            };
+
+            let mut rule = UploadRule {
+                prefix: prefix.clone(),
+                ..defaults.clone()
+            };
+            for option in options.iter().filter(|value| !matches!(value, TomlValue::String(s) if s.is_empty())) {
+                let applied = match option {
+                    TomlValue::String(option) => rule.set(option),
+                    _ => false,
+                };
+                if !applied {
+                    // refuse the prefix rather than accept uploads with unexpected limits
+                    issues.expected(&format!("upload.{}", prefix), "options like \"max_size 2G\" or \"overwrite allow\"", option);
+                    return None;
+                }
+            }
+            Some(rule)
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    rules
+}
+
+// [proxy]
+// timeout = 30
+// "/api" = "http://127.0.0.1:3000"
+// "/app" = ["unix:/run/app.sock", "try_files on", "strip_prefix on"]
+fn load_proxy(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<ProxyRule> {
+    unknown_keys("proxy", section, &options("proxy"), &['/'], issues);
+
+    let timeout = match section.get("timeout") {
+        Some(TomlValue::Integer(seconds)) if *seconds > 0 => Some(*seconds),
+        Some(other) => {
+            issues.expected("proxy.timeout", "a positive number of seconds", other);
+            None
+        }
+        None => None,
+    };
+
+    let mut rules: Vec<ProxyRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/'))
+        .filter_map(|(prefix, value)| {
+            let path = format!("proxy.{}", prefix);
+            let values = match value {
+                TomlValue::Array(values) => values.clone(),
+                other => vec![other.clone()],
+            };
+            let mut values = values.into_iter().filter_map(|value| match value {
+                TomlValue::String(value) if !value.is_empty() => Some(value),
+                _ => None,
+            });
+
+            let upstream = match values.next() {
+                Some(upstream) => match Upstream::parse(&upstream) {
+                    Some(upstream) => upstream,
+                    None => {
+                        issues.expected(&path, "an upstream like \"http://host:port\" or \"unix:/path\"", &TomlValue::String(upstream));
+                        return None;
+                    }
+                },
+                None => {
+                    issues.expected(&path, "an upstream like \"http://host:port\" or \"unix:/path\"", value);
+                    return None;
+                }
+            };
+            let mut rule = ProxyRule::new(prefix, upstream);
+            if let Some(seconds) = timeout {
+                rule.timeout = std::time::Duration::from_secs(seconds as u64);
+            }
+            for option in values {
+                if !rule.set(&option) {
+                    issues.expected(&path, "options like \"try_files on\" or \"strip_prefix on\"", &TomlValue::String(option));
+                    return None;
+                }
+            }
+            Some(rule)
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    rules
+}
+
+// [cgi]
+// timeout = 30
+// "/cgi-bin" = true
+// ".pl" = "/usr/bin/perl"
+fn load_cgi(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<CgiRule> {
+    unknown_keys("cgi", section, &options("cgi"), &['/', '.'], issues);
+
+    let timeout = match section.get("timeout") {
+        Some(TomlValue::Integer(seconds)) if *seconds > 0 => std::time::Duration::from_secs(*seconds as u64),
+        Some(other) => {
+            issues.expected("cgi.timeout", "a positive number of seconds", other);
+            CgiRule::DEFAULT_TIMEOUT
+        }
+        None => CgiRule::DEFAULT_TIMEOUT,
+    };
+
+    let mut rules: Vec<CgiRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/') || key.starts_with('.'))
+        .filter_map(|(pattern, value)| {
+            let interpreter = match value {
+                TomlValue::Boolean(false) => return None,
+                TomlValue::Boolean(true) => None,
+                TomlValue::String(interpreter) if !interpreter.is_empty() => Some(PathBuf::from(interpreter)),
+                other => {
+                    issues.expected(&format!("cgi.{}", pattern), "true or an interpreter path", other);
+                    return None;
+                }
+            };
+            Some(CgiRule {
+                timeout,
+                ..CgiRule::new(pattern, interpreter)
+            })
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+    rules
+}
+
+// [fastcgi]
+// timeout = 30
+// ".php" = ["unix:/run/php/php-fpm.sock", "index index.php"]
+// "/app" = "127.0.0.1:9000"
+fn load_fastcgi(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<FastCgiRule> {
+    unknown_keys("fastcgi", section, &options("fastcgi"), &['/', '.'], issues);
+
+    let timeout = match section.get("timeout") {
+        Some(TomlValue::Integer(seconds)) if *seconds > 0 => Some(*seconds),
+        Some(other) => {
+            issues.expected("fastcgi.timeout", "a positive number of seconds", other);
+            None
+        }
+        None => None,
+    };
+
+    let mut rules: Vec<FastCgiRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/') || key.starts_with('.'))
+        .filter_map(|(pattern, value)| {
+            let path = format!("fastcgi.{}", pattern);
+            let values = match value {
+                TomlValue::Array(values) => values.clone(),
+                other => vec![other.clone()],
+            };
+            let mut values = values.into_iter().filter_map(|value| match value {
+                TomlValue::String(value) if !value.is_empty() => Some(value),
+                _ => None,
+            });
+
+            let address = match values.next() {
+                Some(address) => match FastCgiAddress::parse(&address) {
+                    Some(address) => address,
+                    None => {
+                        issues.expected(&path, "a backend like \"host:port\" or \"unix:/path\"", &TomlValue::String(address));
+                        return None;
+                    }
+                },
+                None => {
+                    issues.expected(&path, "a backend like \"host:port\" or \"unix:/path\"", value);
+                    return None;
+                }
+            };
+            let mut rule = FastCgiRule::new(pattern, address);
+            if let Some(seconds) = timeout {
+                rule.timeout = std::time::Duration::from_secs(seconds as u64);
+            }
+            for option in values {
+                if !rule.set(&option) {
+                    issues.expected(&path, "options like \"index index.php\" or \"timeout 30\"", &TomlValue::String(option));
+                    return None;
+                }
+            }
+            Some(rule)
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+    rules
+}
+
+// [spa]
+// "/" = "index.html"
+// "/admin" = "admin/index.html"
+fn load_spa(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<SpaRule> {
+    unknown_keys("spa", section, &[], &['/'], issues);
+
+    let mut rules: Vec<SpaRule> = section
+        .iter()
+        .filter(|(key, _)| key.starts_with('/'))
+        .filter_map(|(prefix, value)| {
+            let rule = match value {
+                TomlValue::String(fallback) => SpaRule::new(prefix, fallback),
+                _ => None,
+            };
+            if rule.is_none() {
+                issues.expected(&format!("spa.{}", prefix), "an HTML file inside the document root", value);
+            }
+            rule
+        })
+        .collect();
+
+    rules.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    rules
+}
+
+// [headers]
+// security = true       # HSTS, CSP, X-Frame-Options... on every answer
+// server = false        # hide the Server header, or a name to send instead
+// "/api" = ["set Cache-Control no-store", "remove X-Powered-By"]
+// "*.woff2" = "add Access-Control-Allow-Origin *"
+// "text/html" = ["set X-Robots-Tag noindex"]
+fn load_headers(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<HeaderRule> {
+    let mut presets = Vec::new();
+
+    match section.get("security") {
+        Some(TomlValue::Boolean(true)) => presets.push(HeaderRule::security()),
+        Some(TomlValue::Boolean(false)) | None => {}
+        Some(other) => issues.expected("headers.security", "true or false", other),
+    }
+
+    match section.get("server") {
+        Some(TomlValue::Boolean(true)) | None => {}
+        Some(TomlValue::Boolean(false)) => presets.push(HeaderRule::server(None)),
+        Some(TomlValue::String(name)) if !name.is_empty() && !name.contains(['\r', '\n']) => {
+            presets.push(HeaderRule::server(Some(name)))
+        }
+        Some(other) => issues.expected("headers.server", "true, false or a server name", other),
+    }
+
+    let mut keys: Vec<&String> = section.keys().filter(|key| *key != "security" && *key != "server").collect();
+    keys.sort();
+
+    let mut rules = Vec::new();
+    for key in keys {
+        let path = format!("headers.{}", key);
+        let rule_match = HeaderMatch::new(key);
+        if matches!(&rule_match, HeaderMatch::Path(LocationMatch::Prefix(prefix)) if !prefix.starts_with('/')) {
+            issues.report(&path, &format!("unknown key `{}` in [headers]", key));
+            continue;
+        }
+
+        let values = match &section[key] {
+            TomlValue::Array(values) => values.clone(),
+            other => vec![other.clone()],
+        };
+        let mut actions = Vec::new();
+        for value in values.iter().filter(|value| !matches!(value, TomlValue::String(s) if s.is_empty())) {
+            match value {
+                TomlValue::String(directive) => match HeaderAction::parse(directive) {
+                    Some(action) if HeaderAction::RESERVED.iter().any(|name| name.eq_ignore_ascii_case(action.name())) => {
+                        issues.report(&path, &format!("`{}` is set by the server, it cannot be changed", action.name()));
+                    }
+                    Some(action) => actions.push(action),
+                    None => issues.expected(&path, "directives like \"set X-Frame-Options DENY\" or \"remove X-Powered-By\"", value),
+                },
+                other => issues.expected(&path, "directives like \"set X-Frame-Options DENY\" or \"remove X-Powered-By\"", other),
+            }
+        }
+        rules.push(HeaderRule { matcher: rule_match, actions });
+    }
+
+    // the presets apply first, the rules for "/" may then change them
+    presets.extend(rules);
+    HeaderRule::sort(&mut presets);
+    presets
+}
+
+// [cache]
+// expires = true                          # Expires along Cache-Control
+// immutable = true                        # a year for fingerprinted names
+// fingerprint = '\.[0-9a-f]{4,}\.[^.]+$'  # what a fingerprinted name looks like
+// "/" = "no-cache"
+// ".css" = "public, max-age=1d"
+// "image/*" = "max-age=1w"
+// "/downloads" = false                    # no caching headers
+fn load_cache(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> Vec<CacheRule> {
+    let expires = match section.get("expires") {
+        Some(TomlValue::Boolean(expires)) => *expires,
+        Some(other) => {
+            issues.expected("cache.expires", "true or false", other);
+            true
+        }
+        None => true,
     };
 
-    let document_root = match katana.get("document_root") {
-        Some(TomlValue::String(dir)) => PathBuf::from(dir),
-        _ => default_config.document_root.clone(),
+    let immutable = match section.get("immutable") {
+        Some(TomlValue::Boolean(immutable)) => *immutable,
+        Some(other) => {
+            issues.expected("cache.immutable", "true or false", other);
+            false
+        }
+        None => false,
     };
 
-    let worker = match katana.get("worker") {
-        Some(TomlValue::Integer(w)) => *w as i32,
-        _ => default_config.worker,
+    let fingerprint = match section.get("fingerprint") {
+        Some(TomlValue::String(pattern)) => match Regex::new(pattern) {
+            Ok(regex) => Some(regex),
+            Err(message) => {
+                issues.report("cache.fingerprint", &format!("`fingerprint` is not a valid pattern: {}", message));
+                None
+            }
+        },
+        Some(other) => {
+            issues.expected("cache.fingerprint", "a pattern matching file names", other);
+            None
+        }
+        None => Regex::new(Cache::DEFAULT_FINGERPRINT).ok(),
     };
 
-    let log_level = match katana.get("log_level") {
-        Some(TomlValue::String(level)) => LogLevel::from_str(&level.to_uppercase()).unwrap_or(default_config.log_level),
-        _ => default_config.log_level,
+    let options = options("cache");
+    let mut keys: Vec<&String> = section.keys().filter(|key| !options.contains(&key.as_str())).collect();
+    keys.sort();
+
+    let mut rules = Vec::new();
+    for key in keys {
+        let path = format!("cache.{}", key);
+        let rule_match = CacheMatch::new(key);
+        if matches!(&rule_match, CacheMatch::File(HeaderMatch::Path(LocationMatch::Prefix(prefix))) if !prefix.starts_with('/')) {
+            issues.report(&path, &format!("unknown key `{}` in [cache]", key));
+            continue;
+        }
+
+        let policy = match &section[key] {
+            TomlValue::Boolean(false) => Some(CachePolicy::none()),
+            TomlValue::String(value) => CachePolicy::parse(value),
+            _ => None,
+        };
+        match policy {
+            Some(policy) => rules.push(CacheRule {
+                matcher: rule_match,
+                policy: CachePolicy { expires, ..policy },
+            }),
+            None => issues.expected(&path, "Cache-Control directives like \"public, max-age=1d\", or false", &section[key]),
+        }
+    }
+
+    if let Some(fingerprint) = fingerprint.filter(|_| immutable) {
+        let mut rule = CacheRule::immutable(fingerprint);
+        rule.policy.expires = expires;
+        rules.push(rule);
+    }
+
+    CacheRule::sort(&mut rules);
+    rules
+}
+
+// [mime]
+// files = ["/etc/mime.types"]         # read over the built-in table
+// default = "application/octet-stream" # unknown extensions
+// charset = "utf-8"                   # added to text types, false for none
+// sniff = true                        # types files without a known extension by content
+// md = "text/plain"                   # an extension and its type
+fn load_mime(section: &HashMap<String, TomlValue>, issues: &mut Issues) -> MimeSettings {
+    let mut settings = MimeSettings::default();
+    // a type, parameters allowed: `text/plain; charset=iso-8859-1`
+    let media_type = |value: &TomlValue| match value {
+        TomlValue::String(media) if MimeRegistry::is_media_type(media.split(';').next().unwrap_or("").trim()) && !media.contains(['\r', '\n']) => {
+            Some(media.trim().to_string())
+        }
+        _ => None,
     };
 
-    Config {
-        _source: crate::core::config::config::ConfigSource::File,
-        host,
-        port,
-        document_root,
-        worker,
-        log_level,
+    match section.get("files") {
+        Some(TomlValue::String(file)) if !file.is_empty() => settings.files.push(PathBuf::from(file)),
+        Some(TomlValue::Array(files)) => {
+            for file in files {
+                match file {
+                    TomlValue::String(file) if !file.is_empty() => settings.files.push(PathBuf::from(file)),
+                    other => issues.expected("mime.files", "paths of mime.types files", other),
+                }
+            }
+        }
+        Some(other) => issues.expected("mime.files", "the path of a mime.types file, or a list of them", other),
+        None => {}
+    }
+    for file in settings.files.iter().filter(|file| !file.is_file()) {
+        issues.report("mime.files", &format!("mime.types file {:?} not found", file));
+    }
+
+    if let Some(value) = section.get("default") {
+        match media_type(value) {
+            Some(media) => settings.default_type = media,
+            None => issues.expected("mime.default", "a media type like \"application/octet-stream\"", value),
+        }
+    }
+
+    match section.get("charset") {
+        Some(TomlValue::String(charset)) if Utils::is_header_name(charset) => settings.charset = Some(charset.clone()),
+        Some(TomlValue::Boolean(false)) => settings.charset = None,
+        Some(other) => issues.expected("mime.charset", "a charset like \"utf-8\", or false", other),
+        None => {}
+    }
+
+    match section.get("sniff") {
+        Some(TomlValue::Boolean(sniff)) => settings.sniff = *sniff,
+        Some(other) => issues.expected("mime.sniff", "true or false", other),
+        None => {}
+    }
+
+    let options = options("mime");
+    let mut extensions: Vec<&String> = section.keys().filter(|key| !options.contains(&key.as_str())).collect();
+    extensions.sort();
+    for extension in extensions {
+        let path = format!("mime.{}", extension);
+        let name = extension.strip_prefix('.').unwrap_or(extension);
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || "-_+~".contains(c)) {
+            issues.report(&path, &format!("`{}` is not a file extension", extension));
+            continue;
+        }
+        match media_type(&section[extension]) {
+            Some(media) => settings.types.push((name.to_lowercase(), media)),
+            None => issues.expected(&path, "a media type like \"text/plain\"", &section[extension]),
+        }
+    }
+
+    settings
+}
+
+// [[location]]
+// path = "/docs"                      # a prefix, or a glob like "*.pdf" or "/docs/**/*.md"
+// index = ["index.html", "README.html"]
+// directory_listing = false
+// methods = ["GET", "POST"]
+// auth = "docs.htpasswd"              # or false to lift an [auth] rule
+// cache_control = "max-age=3600"
+// headers = { "X-Robots-Tag" = "noindex" }
+// error_pages = { 404 = "/docs/404.html" }
+fn load_location(blocks: &[TomlValue], issues: &mut Issues) -> Vec<LocationRule> {
+    let mut rules = Vec::new();
+
+    for (index, block) in blocks.iter().enumerate() {
+        let name = format!("location.{}", index);
+        let block = match block {
+            TomlValue::Table(block) => block,
+            other => {
+                issues.expected(&name, "a block, [[location]]", other);
+                continue;
+            }
+        };
+        for key in block.keys().filter(|key| !LOCATION_KEYS.contains(&key.as_str())) {
+            issues.report(&format!("{}.{}", name, key), &format!("unknown key `{}` in [[location]]", key));
+        }
+        let key = |key: &str| format!("{}.{}", name, key);
+
+        let mut rule = match block.get("path") {
+            Some(TomlValue::String(path)) if !path.is_empty() => LocationRule::new(path),
+            Some(other) => {
+                issues.expected(&key("path"), "a path prefix or a glob", other);
+                continue;
+            }
+            None => {
+                issues.report(&name, "[[location]] without a `path`");
+                continue;
+            }
+        };
+
+        match block.get("headers") {
+            Some(TomlValue::Table(headers)) => {
+                for (header, value) in headers {
+                    let path = format!("{}.{}", key("headers"), header);
+                    match value {
+                        _ if !Utils::is_header_name(header) => issues.report(&path, &format!("`{}` is not a header name", header)),
+                        TomlValue::String(value) if !value.contains(['\r', '\n']) => {
+                            rule.headers.push((header.clone(), value.clone()));
+                        }
+                        other => issues.expected(&path, "a header value on one line", other),
+                    }
+                }
+                rule.headers.sort();
+            }
+            Some(other) => issues.expected(&key("headers"), "a table of headers", other),
+            None => {}
+        }
+
+        match block.get("cache_control") {
+            Some(TomlValue::String(cache_control)) => rule.cache_control = Some(cache_control.clone()),
+            Some(other) => issues.expected(&key("cache_control"), "a Cache-Control value", other),
+            None => {}
+        }
+
+        match block.get("directory_listing") {
+            Some(TomlValue::Boolean(listing)) => rule.directory_listing = Some(*listing),
+            Some(other) => issues.expected(&key("directory_listing"), "true or false", other),
+            None => {}
+        }
+
+        match block.get("index") {
+            Some(TomlValue::String(file)) if is_file_name(file) => rule.index = Some(vec![file.clone()]),
+            Some(TomlValue::Array(files)) => {
+                let names: Vec<String> = files
+                    .iter()
+                    .filter_map(|file| match file {
+                        TomlValue::String(file) if is_file_name(file) => Some(file.clone()),
+                        other => {
+                            issues.expected(&key("index"), "file names", other);
+                            None
+                        }
+                    })
+                    .collect();
+                rule.index = Some(names);
+            }
+            Some(other) => issues.expected(&key("index"), "a file name or a list of them", other),
+            None => {}
+        }
+
+        match block.get("methods") {
+            Some(TomlValue::Array(methods)) => {
+                let methods: Vec<HttpMethod> = methods
+                    .iter()
+                    .filter_map(|method| match method {
+                        TomlValue::String(name) if HttpMethod::from_str(&name.to_uppercase()).is_some() => {
+                            HttpMethod::from_str(&name.to_uppercase())
+                        }
+                        other => {
+                            issues.expected(&key("methods"), "HTTP methods like \"GET\"", other);
+                            None
+                        }
+                    })
+                    .collect();
+                rule.methods = Some(methods);
+            }
+            Some(other) => issues.expected(&key("methods"), "a list of HTTP methods", other),
+            None => {}
+        }
+
+        let realm = match block.get("realm") {
+            Some(TomlValue::String(realm)) => realm.clone(),
+            Some(other) => {
+                issues.expected(&key("realm"), "a string", other);
+                BasicAuth::DEFAULT_REALM.to_string()
+            }
+            None => BasicAuth::DEFAULT_REALM.to_string(),
+        };
+        match block.get("auth") {
+            Some(TomlValue::Boolean(false)) => rule.auth = Some(LocationAuth::Off),
+            Some(TomlValue::String(htpasswd)) if !htpasswd.is_empty() => {
+                if !Path::new(htpasswd).is_file() {
+                    issues.report(&key("auth"), &format!("htpasswd file {:?} not found", htpasswd));
+                }
+                let rule_auth = AuthRule::new(rule.matcher.as_str(), PathBuf::from(htpasswd), &realm);
+                rule.auth = Some(LocationAuth::Required(rule_auth));
+            }
+            Some(other) => issues.expected(&key("auth"), "the path of an htpasswd file, or false", other),
+            None => {}
+        }
+
+        match block.get("error_pages") {
+            Some(TomlValue::Table(pages)) => {
+                for (code, page) in pages {
+                    let path = format!("{}.{}", key("error_pages"), code);
+                    let code = match code.parse::<u16>() {
+                        Ok(code) if (400..=599).contains(&code) => code,
+                        _ => {
+                            issues.report(&path, &format!("`{}` must be an error status between 400 and 599", code));
+                            continue;
+                        }
+                    };
+                    match page {
+                        TomlValue::String(page) if Utils::request_path(page).is_some() => {
+                            rule.error_pages.push((code, Utils::request_path(page).unwrap_or_default()));
+                        }
+                        other => issues.expected(&path, "a page inside the document root", other),
+                    }
+                }
+                rule.error_pages.sort();
+            }
+            Some(other) => issues.expected(&key("error_pages"), "a table of status codes and pages", other),
+            None => {}
+        }
+
+        rules.push(rule);
+    }
+
+    rules
+}
+
+// a name without folders, `index.htm`
+fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != ".."
+}
+
+// 1048576, "1024K", "1M"
+fn size_value(value: &TomlValue) -> Option<u64> {
+    match value {
+        TomlValue::Integer(size) if *size >= 0 => Some(*size as u64),
+        TomlValue::String(size) => Utils::parse_size(size),
+        _ => None,
     }
 }
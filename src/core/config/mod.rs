@@ -1,5 +1,5 @@
 pub mod config;
 mod default;
-mod arg;
+pub mod arg;
 mod env;
 mod file;
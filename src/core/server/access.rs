@@ -0,0 +1,144 @@
+use crate::core::utils::utils::Utils;
+use std::net::IpAddr;
+
+/// An IPv4 or IPv6 address range in CIDR notation, a bare address is a /32 or /128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// Parses `10.0.0.0/8`, `fd00::/8`, `192.168.1.10` or `all`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("all") {
+            // "all" matches both families, see `contains`
+            return Some(Self {
+                addr: IpAddr::from([0u8; 16]),
+                prefix_len: 0,
+            });
+        }
+
+        let (addr, prefix_len) = match value.split_once('/') {
+            Some((addr, len)) => (addr, Some(len.parse::<u8>().ok()?)),
+            None => (value, None),
+        };
+        let addr = Self::canonical(addr.parse::<IpAddr>().ok()?);
+        let max_len = Self::max_len(&addr);
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        if prefix_len > max_len {
+            return None;
+        }
+
+        Some(Self { addr, prefix_len })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        if self.prefix_len == 0 {
+            return true;
+        }
+
+        match (self.addr, Self::canonical(*ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX << (32 - self.prefix_len as u32);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX << (128 - self.prefix_len as u32);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+
+    // dual-stack sockets report IPv4 clients as "::ffff:a.b.c.d"
+    fn canonical(ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => IpAddr::V4(v4),
+                None => ip,
+            },
+            v4 => v4,
+        }
+    }
+
+    fn max_len(ip: &IpAddr) -> u8 {
+        match ip {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessAction {
+    Allow,
+    Deny,
+}
+
+/// One `allow <network>` or `deny <network>` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessDirective {
+    pub action: AccessAction,
+    pub network: IpNetwork,
+}
+
+impl AccessDirective {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (action, network) = value.trim().split_once(char::is_whitespace)?;
+        let action = match action.to_lowercase().as_str() {
+            "allow" => AccessAction::Allow,
+            "deny" => AccessAction::Deny,
+            _ => return None,
+        };
+
+        Some(Self {
+            action,
+            network: IpNetwork::parse(network)?,
+        })
+    }
+}
+
+/// Ordered allow/deny directives guarding every request path under `prefix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessRule {
+    pub prefix: String,
+    pub directives: Vec<AccessDirective>,
+}
+
+impl AccessRule {
+    pub fn new(prefix: &str, directives: Vec<AccessDirective>) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            directives,
+        }
+    }
+
+    /// The first directive matching `ip` decides, a client matching none is allowed.
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        self.directives
+            .iter()
+            .find(|directive| directive.network.contains(ip))
+            .map(|directive| directive.action == AccessAction::Allow)
+            .unwrap_or(true)
+    }
+}
+
+pub struct AccessControl;
+
+impl AccessControl {
+    /// Returns the rule with the longest prefix covering `path`, if any.
+    pub fn rule_for<'a>(rules: &'a [AccessRule], path: &str) -> Option<&'a AccessRule> {
+        Utils::longest_prefix(rules, path, |rule| &rule.prefix)
+    }
+
+    /// Whether a client may access `path`, an unknown address only passes unguarded paths.
+    pub fn is_allowed(rules: &[AccessRule], path: &str, ip: Option<&IpAddr>) -> bool {
+        match (Self::rule_for(rules, path), ip) {
+            (None, _) => true,
+            (Some(rule), Some(ip)) => rule.allows(ip),
+            (Some(_), None) => false,
+        }
+    }
+}
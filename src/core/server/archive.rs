@@ -0,0 +1,520 @@
+use crate::core::utils::crc32::Crc32;
+use crate::core::utils::gzip::GzipWriter;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Result, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_name(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "zip" => Some(Self::Zip),
+            "tar" => Some(Self::Tar),
+            "tar.gz" | "tgz" => Some(Self::TarGz),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Zip => "application/zip",
+            Self::Tar => "application/x-tar",
+            Self::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// Streams a directory tree as an archive, one file at a time.
+///
+/// Nothing is buffered besides the zip central directory (names and offsets),
+/// file contents go straight from disk to the writer.
+pub struct Archive;
+
+impl Archive {
+    pub fn write<W: Write>(format: ArchiveFormat, dir: &Path, name: &str, writer: W) -> Result<W> {
+        Self::write_excluding(format, dir, name, &[], writer)
+    }
+
+    /// Same as `write`, leaving out everything under the `exclude` paths (e.g. protected folders).
+    pub fn write_excluding<W: Write>(
+        format: ArchiveFormat,
+        dir: &Path,
+        name: &str,
+        exclude: &[PathBuf],
+        writer: W,
+    ) -> Result<W> {
+        Logger::debug(
+            format!(
+                "[Archive] Streaming {} as {}",
+                Utils::path_prettifier(dir.to_path_buf()),
+                format.extension()
+            )
+            .as_str(),
+        );
+
+        let exclude: Vec<PathBuf> = exclude
+            .iter()
+            .map(|path| Utils::normalize_path(path.clone()))
+            .collect();
+
+        match format {
+            ArchiveFormat::Zip => {
+                let mut builder = ZipBuilder::new(writer);
+                Self::walk(&mut builder, dir, name, &exclude)?;
+                builder.finish()
+            }
+            ArchiveFormat::Tar => {
+                let mut builder = TarBuilder::new(writer);
+                Self::walk(&mut builder, dir, name, &exclude)?;
+                builder.finish()
+            }
+            ArchiveFormat::TarGz => {
+                let mut builder = TarBuilder::new(GzipWriter::new(writer)?);
+                Self::walk(&mut builder, dir, name, &exclude)?;
+                builder.finish()?.finish()
+            }
+        }
+    }
+
+    fn walk<B: ArchiveBuilder>(builder: &mut B, dir: &Path, prefix: &str, exclude: &[PathBuf]) -> Result<()> {
+        builder.append_dir(&format!("{}/", prefix), &fs::metadata(dir)?)?;
+
+        let mut entries = Utils::collect_entries(fs::read_dir(dir)?);
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let file_name = entry.file_name();
+            let name = match file_name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // same rules as the directory listing
+            if !Utils::is_valid_entry(name) {
+                continue;
+            }
+
+            let path = entry.path();
+            let entry_name = format!("{}/{}", prefix, name);
+
+            let normalized = Utils::normalize_path(path.clone());
+            if exclude.iter().any(|excluded| normalized.starts_with(excluded)) {
+                Logger::debug(format!("[Archive] Skipping excluded path: {}", entry_name).as_str());
+                continue;
+            }
+
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue, // dangling symlink
+            };
+
+            if metadata.is_dir() {
+                // following directory links could loop forever or escape the document root
+                if entry.file_type().map(|t| t.is_symlink()).unwrap_or(true) {
+                    Logger::debug(format!("[Archive] Skipping linked directory: {}", entry_name).as_str());
+                    continue;
+                }
+                Self::walk(builder, &path, &entry_name, exclude)?;
+            } else if metadata.is_file() {
+                match File::open(&path) {
+                    Ok(file) => builder.append_file(&entry_name, file, &metadata)?,
+                    Err(e) => {
+                        Logger::warn(format!("[Archive] Skipping {}: {}", entry_name, e).as_str());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mtime(metadata: &Metadata) -> u64 {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+
+    fn mode(metadata: &Metadata) -> u32 {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o7777
+        };
+
+        #[cfg(not(unix))]
+        let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+
+        mode
+    }
+}
+
+trait ArchiveBuilder {
+    fn append_dir(&mut self, name: &str, metadata: &Metadata) -> Result<()>;
+    fn append_file(&mut self, name: &str, file: File, metadata: &Metadata) -> Result<()>;
+}
+
+/// Copies exactly `size` bytes, the archive headers were already written with that size.
+fn copy_exact<R: Read, W: Write>(reader: R, writer: &mut W, size: u64) -> Result<()> {
+    let copied = io::copy(&mut reader.take(size), writer)?;
+    if copied != size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "file changed while being archived",
+        ));
+    }
+    Ok(())
+}
+
+const TAR_BLOCK: usize = 512;
+
+// @see: https://www.gnu.org/software/tar/manual/html_node/Standard.html
+struct TarBuilder<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> TarBuilder<W> {
+    fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    fn finish(mut self) -> Result<W> {
+        // end of archive is marked by two empty blocks
+        self.inner.write_all(&[0; TAR_BLOCK * 2])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_header(&mut self, name: &str, size: u64, mode: u32, mtime: u64, type_flag: u8) -> Result<()> {
+        let (prefix, short_name) = match Self::split_name(name) {
+            Some(split) => split,
+            None => {
+                // GNU extension: the real name travels in a pseudo entry before the header
+                let mut long_name = name.as_bytes().to_vec();
+                long_name.push(0);
+                let header = Self::header("././@LongLink", "", long_name.len() as u64, 0o644, 0, b'L');
+                self.inner.write_all(&header)?;
+                self.inner.write_all(&long_name)?;
+                self.pad(long_name.len() as u64)?;
+                ("", Self::truncate(name, 100))
+            }
+        };
+
+        let header = Self::header(short_name, prefix, size, mode, mtime, type_flag);
+        self.inner.write_all(&header)
+    }
+
+    fn header(name: &str, prefix: &str, size: u64, mode: u32, mtime: u64, type_flag: u8) -> [u8; 512] {
+        let mut header = [0u8; 512];
+
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        Self::octal(&mut header[100..108], mode as u64);
+        Self::octal(&mut header[108..116], 0); // uid
+        Self::octal(&mut header[116..124], 0); // gid
+        if size < 0o77777777777 {
+            Self::octal(&mut header[124..136], size);
+        } else {
+            // base-256 for files larger than 8 GiB
+            header[124] = 0x80;
+            header[128..136].copy_from_slice(&size.to_be_bytes());
+        }
+        Self::octal(&mut header[136..148], mtime);
+        header[156] = type_flag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        // checksum is computed with its own field filled with spaces
+        header[148..156].copy_from_slice(b"        ");
+        let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+        let checksum = format!("{:06o}\0 ", checksum);
+        header[148..156].copy_from_slice(checksum.as_bytes());
+
+        header
+    }
+
+    fn octal(field: &mut [u8], value: u64) {
+        let digits = field.len() - 1;
+        let formatted = format!("{:0width$o}", value, width = digits);
+        field[..digits].copy_from_slice(&formatted.as_bytes()[formatted.len() - digits..]);
+        field[digits] = 0;
+    }
+
+    fn split_name(name: &str) -> Option<(&str, &str)> {
+        if name.len() <= 100 {
+            return Some(("", name));
+        }
+
+        // ustar can store up to 155 bytes of leading directories separately
+        name.char_indices()
+            .filter(|(_, c)| *c == '/')
+            .map(|(index, _)| (&name[..index], &name[index + 1..]))
+            .find(|(prefix, rest)| prefix.len() <= 155 && rest.len() <= 100 && !rest.is_empty())
+    }
+
+    fn truncate(name: &str, max: usize) -> &str {
+        let mut end = max.min(name.len());
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        &name[..end]
+    }
+
+    fn pad(&mut self, size: u64) -> Result<()> {
+        let remainder = (size % TAR_BLOCK as u64) as usize;
+        if remainder > 0 {
+            self.inner.write_all(&vec![0; TAR_BLOCK - remainder])?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ArchiveBuilder for TarBuilder<W> {
+    fn append_dir(&mut self, name: &str, metadata: &Metadata) -> Result<()> {
+        self.write_header(name, 0, Archive::mode(metadata), Archive::mtime(metadata), b'5')
+    }
+
+    fn append_file(&mut self, name: &str, file: File, metadata: &Metadata) -> Result<()> {
+        let size = metadata.len();
+        self.write_header(name, size, Archive::mode(metadata), Archive::mtime(metadata), b'0')?;
+        copy_exact(file, &mut self.inner, size)?;
+        self.pad(size)
+    }
+}
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u64,
+    offset: u64,
+    mode: u32,
+    is_dir: bool,
+    time: u16,
+    date: u16,
+}
+
+// @see: https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+struct ZipBuilder<W: Write> {
+    inner: W,
+    offset: u64,
+    entries: Vec<ZipEntry>,
+}
+
+impl<W: Write> ZipBuilder<W> {
+    const LIMIT: u64 = 0xFFFF_FFFF;
+    const FLAG_UTF8: u16 = 1 << 11;
+    const VERSION: u16 = 20;
+    const VERSION_ZIP64: u16 = 45;
+    const MADE_BY_UNIX: u16 = 3 << 8;
+
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn dos_datetime(timestamp: u64) -> (u16, u16) {
+        let (year, month, day, hours, minutes, seconds) = Utils::date_components(timestamp);
+        if year < 1980 {
+            return (0, (1 << 5) | 1); // 1980-01-01 00:00:00
+        }
+        let time = (hours << 11) | (minutes << 5) | (seconds / 2);
+        let date = ((year.min(2107) - 1980) as u32) << 9 | (month << 5) | day;
+        (time as u16, date as u16)
+    }
+
+    fn add_entry(&mut self, name: &str, crc: u32, size: u64, metadata: &Metadata) -> Result<()> {
+        let (time, date) = Self::dos_datetime(Archive::mtime(metadata));
+        let entry = ZipEntry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset: self.offset,
+            mode: Archive::mode(metadata),
+            is_dir: metadata.is_dir(),
+            time,
+            date,
+        };
+
+        let zip64 = entry.size >= Self::LIMIT;
+        let mut header = Vec::with_capacity(30 + name.len() + 20);
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&(if zip64 { Self::VERSION_ZIP64 } else { Self::VERSION }).to_le_bytes());
+        header.extend_from_slice(&Self::FLAG_UTF8.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // stored, no compression
+        header.extend_from_slice(&entry.time.to_le_bytes());
+        header.extend_from_slice(&entry.date.to_le_bytes());
+        header.extend_from_slice(&entry.crc.to_le_bytes());
+        if zip64 {
+            header.extend_from_slice(&(Self::LIMIT as u32).to_le_bytes());
+            header.extend_from_slice(&(Self::LIMIT as u32).to_le_bytes());
+        } else {
+            header.extend_from_slice(&(entry.size as u32).to_le_bytes());
+            header.extend_from_slice(&(entry.size as u32).to_le_bytes());
+        }
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(if zip64 { 20u16 } else { 0 }).to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        if zip64 {
+            header.extend_from_slice(&0x0001u16.to_le_bytes());
+            header.extend_from_slice(&16u16.to_le_bytes());
+            header.extend_from_slice(&entry.size.to_le_bytes());
+            header.extend_from_slice(&entry.size.to_le_bytes());
+        }
+
+        self.write(&header)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W> {
+        let directory_offset = self.offset;
+
+        for index in 0..self.entries.len() {
+            let header = Self::central_header(&self.entries[index]);
+            self.write(&header)?;
+        }
+
+        let directory_size = self.offset - directory_offset;
+        let count = self.entries.len() as u64;
+        let zip64 = count >= 0xFFFF || directory_offset >= Self::LIMIT || directory_size >= Self::LIMIT;
+
+        let mut end = Vec::new();
+        if zip64 {
+            let record_offset = self.offset;
+            end.extend_from_slice(&0x0606_4b50u32.to_le_bytes());
+            end.extend_from_slice(&44u64.to_le_bytes());
+            end.extend_from_slice(&(Self::MADE_BY_UNIX | Self::VERSION_ZIP64).to_le_bytes());
+            end.extend_from_slice(&Self::VERSION_ZIP64.to_le_bytes());
+            end.extend_from_slice(&0u32.to_le_bytes());
+            end.extend_from_slice(&0u32.to_le_bytes());
+            end.extend_from_slice(&count.to_le_bytes());
+            end.extend_from_slice(&count.to_le_bytes());
+            end.extend_from_slice(&directory_size.to_le_bytes());
+            end.extend_from_slice(&directory_offset.to_le_bytes());
+
+            end.extend_from_slice(&0x0706_4b50u32.to_le_bytes());
+            end.extend_from_slice(&0u32.to_le_bytes());
+            end.extend_from_slice(&record_offset.to_le_bytes());
+            end.extend_from_slice(&1u32.to_le_bytes());
+        }
+
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&(count.min(0xFFFF) as u16).to_le_bytes());
+        end.extend_from_slice(&(count.min(0xFFFF) as u16).to_le_bytes());
+        end.extend_from_slice(&(directory_size.min(Self::LIMIT) as u32).to_le_bytes());
+        end.extend_from_slice(&(directory_offset.min(Self::LIMIT) as u32).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+        self.write(&end)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn central_header(entry: &ZipEntry) -> Vec<u8> {
+        // zip64 extra field only carries the values that overflowed, in this order
+        let mut extra = Vec::new();
+        if entry.size >= Self::LIMIT {
+            extra.extend_from_slice(&entry.size.to_le_bytes());
+            extra.extend_from_slice(&entry.size.to_le_bytes());
+        }
+        if entry.offset >= Self::LIMIT {
+            extra.extend_from_slice(&entry.offset.to_le_bytes());
+        }
+        let zip64 = !extra.is_empty();
+        if zip64 {
+            let mut field = Vec::with_capacity(extra.len() + 4);
+            field.extend_from_slice(&0x0001u16.to_le_bytes());
+            field.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            field.extend_from_slice(&extra);
+            extra = field;
+        }
+
+        let version = if zip64 { Self::VERSION_ZIP64 } else { Self::VERSION };
+        let file_type = if entry.is_dir { 0o040000 } else { 0o100000 };
+        let attributes = ((file_type | entry.mode) << 16) | if entry.is_dir { 0x10 } else { 0 };
+
+        let mut header = Vec::with_capacity(46 + entry.name.len() + extra.len());
+        header.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        header.extend_from_slice(&(Self::MADE_BY_UNIX | version).to_le_bytes());
+        header.extend_from_slice(&version.to_le_bytes());
+        header.extend_from_slice(&Self::FLAG_UTF8.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&entry.time.to_le_bytes());
+        header.extend_from_slice(&entry.date.to_le_bytes());
+        header.extend_from_slice(&entry.crc.to_le_bytes());
+        header.extend_from_slice(&(entry.size.min(Self::LIMIT) as u32).to_le_bytes());
+        header.extend_from_slice(&(entry.size.min(Self::LIMIT) as u32).to_le_bytes());
+        header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        header.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        header.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        header.extend_from_slice(&attributes.to_le_bytes());
+        header.extend_from_slice(&(entry.offset.min(Self::LIMIT) as u32).to_le_bytes());
+        header.extend_from_slice(entry.name.as_bytes());
+        header.extend_from_slice(&extra);
+
+        header
+    }
+}
+
+impl<W: Write> ArchiveBuilder for ZipBuilder<W> {
+    fn append_dir(&mut self, name: &str, metadata: &Metadata) -> Result<()> {
+        self.add_entry(name, 0, 0, metadata)
+    }
+
+    fn append_file(&mut self, name: &str, mut file: File, metadata: &Metadata) -> Result<()> {
+        // entries are stored uncompressed, so a first pass for the checksum lets us write
+        // a plain local header instead of relying on trailing data descriptors
+        let size = metadata.len();
+        let mut crc = Crc32::new();
+        let mut buffer = vec![0; 64 * 1024];
+        let mut remaining = size;
+        while remaining > 0 {
+            let to_read = remaining.min(buffer.len() as u64) as usize;
+            let bytes_read = file.read(&mut buffer[..to_read])?;
+            if bytes_read == 0 {
+                break;
+            }
+            crc.update(&buffer[..bytes_read]);
+            remaining -= bytes_read as u64;
+        }
+
+        self.add_entry(name, crc.finalize(), size, metadata)?;
+
+        file.rewind()?;
+        copy_exact(file, &mut self.inner, size)?;
+        self.offset += size;
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use crate::core::server::request::Request;
+use crate::core::utils::base64::Base64;
+use crate::core::utils::crypt::{Crypt, CryptScheme};
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+// the password files read by `Htpasswd::load_cached`, with the modification time and size
+// they were read at
+type LoadedHtpasswd = (Option<SystemTime>, u64, Arc<Htpasswd>);
+static LOADED: OnceLock<Mutex<HashMap<PathBuf, LoadedHtpasswd>>> = OnceLock::new();
+
+/// Protects every request path under `prefix` with the users of an htpasswd file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthRule {
+    pub prefix: String,
+    pub htpasswd: PathBuf,
+    pub realm: String,
+}
+
+impl AuthRule {
+    pub fn new(prefix: &str, htpasswd: PathBuf, realm: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            htpasswd,
+            realm: realm.to_string(),
+        }
+    }
+
+    // @see: https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Headers/WWW-Authenticate
+    pub fn challenge(&self) -> String {
+        format!(
+            "Basic realm=\"{}\", charset=\"UTF-8\"",
+            self.realm.replace('"', "'")
+        )
+    }
+}
+
+/// Apache-style password file, one `user:hash` entry per line.
+#[derive(Debug, Clone)]
+pub struct Htpasswd {
+    entries: Vec<(String, String)>,
+}
+
+impl Htpasswd {
+    pub fn load(path: &PathBuf) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        Ok(Self::parse(&content))
+    }
+
+    /// `load`, kept by file until its modification time or size change: the file is not
+    /// read and parsed again for every request.
+    pub fn load_cached(path: &PathBuf) -> Result<Arc<Self>, Error> {
+        let metadata = fs::metadata(path)?;
+        let (modified, size) = (metadata.modified().ok(), metadata.len());
+        let cache = LOADED.get_or_init(|| Mutex::new(HashMap::new()));
+        if let Some((cached_modified, cached_size, htpasswd)) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(path) {
+            if *cached_modified == modified && *cached_size == size {
+                return Ok(htpasswd.clone());
+            }
+        }
+
+        let htpasswd = Arc::new(Self::load(path)?);
+        Logger::debug(format!("[Auth] Loaded {} user(s) from {}", htpasswd.len(), path.display()).as_str());
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.insert(path.clone(), (modified, size, htpasswd.clone()));
+        Ok(htpasswd)
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut entries = Vec::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((user, hash)) = line.split_once(':') {
+                if CryptScheme::detect(hash) == CryptScheme::Unsupported {
+                    Logger::warn(
+                        format!("[Auth] Unsupported password hash format for user '{}'", user).as_str(),
+                    );
+                }
+                entries.push((user.to_string(), hash.to_string()));
+            }
+        }
+
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn verify(&self, user: &str, password: &str) -> bool {
+        // look at every entry so the lookup time does not depend on where the user is
+        let mut hash = None;
+        for (name, entry_hash) in &self.entries {
+            if Utils::constant_time_eq(name.as_bytes(), user.as_bytes()) && hash.is_none() {
+                hash = Some(entry_hash);
+            }
+        }
+
+        match hash {
+            Some(hash) => Crypt::verify(password, hash),
+            None => false,
+        }
+    }
+}
+
+pub struct BasicAuth;
+
+impl BasicAuth {
+    pub const DEFAULT_REALM: &'static str = "Katana";
+
+    /// Returns the rule with the longest prefix covering `path`, if any.
+    pub fn rule_for<'a>(rules: &'a [AuthRule], path: &str) -> Option<&'a AuthRule> {
+        Utils::longest_prefix(rules, path, |rule| &rule.prefix)
+    }
+
+    /// Extracts user and password from an `Authorization: Basic` header.
+    // @see: https://datatracker.ietf.org/doc/html/rfc7617
+    pub fn credentials(request: &Request) -> Option<(String, String)> {
+        let header = request.header("Authorization")?;
+        let (scheme, token) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+
+        let decoded = Base64::decode(token.trim())?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (user, password) = decoded.split_once(':')?;
+
+        Some((user.to_string(), password.to_string()))
+    }
+
+    /// Checks the request against `rule` and returns the authenticated user name.
+    pub fn authenticate(rule: &AuthRule, request: &Request) -> Option<String> {
+        let (user, password) = Self::credentials(request)?;
+
+        let htpasswd = match Htpasswd::load_cached(&rule.htpasswd) {
+            Ok(htpasswd) => htpasswd,
+            Err(e) => {
+                Logger::error(
+                    format!(
+                        "[Auth] Unable to read {}: {}",
+                        Utils::path_prettifier(rule.htpasswd.clone()),
+                        e
+                    )
+                    .as_str(),
+                );
+                return None;
+            }
+        };
+
+        if htpasswd.verify(&user, &password) {
+            Some(user)
+        } else {
+            Logger::warn(format!("[Auth] Invalid credentials for user '{}'", user).as_str());
+            None
+        }
+    }
+}
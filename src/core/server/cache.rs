@@ -0,0 +1,195 @@
+use crate::core::server::headers::HeaderMatch;
+use crate::core::utils::keyval::KeyVal;
+use crate::core::utils::regex::Regex;
+use crate::core::utils::utils::Utils;
+use std::path::PathBuf;
+
+/// `Cache-Control` directives of a file, and the `Expires` date they imply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachePolicy {
+    /// lowercase directives, durations in seconds: `public`, `max-age=3600`
+    pub directives: Vec<String>,
+    pub max_age: Option<u64>,
+    /// sends `Expires` along, for HTTP/1.0 caches
+    pub expires: bool,
+}
+
+impl CachePolicy {
+    /// Directives taking a number of seconds, given as `3600` or `1h`.
+    pub const DURATIONS: [&'static str; 4] = ["max-age", "s-maxage", "stale-while-revalidate", "stale-if-error"];
+    /// A year, the longest caches keep a file.
+    pub const IMMUTABLE_MAX_AGE: u64 = 365 * 86400;
+
+    /// Parses `"public, max-age=1y"`, `None` for anything but comma separated directives.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut policy = Self::none();
+        for directive in value.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name.trim().to_lowercase(), Some(argument.trim())),
+                None => (directive.to_lowercase(), None),
+            };
+            if !Utils::is_header_name(&name) {
+                return None;
+            }
+            let directive = match argument {
+                Some(argument) if Self::DURATIONS.contains(&name.as_str()) => {
+                    let seconds = Utils::parse_duration(argument)?;
+                    if name == "max-age" {
+                        policy.max_age = Some(seconds);
+                    }
+                    format!("{}={}", name, seconds)
+                }
+                None if Self::DURATIONS.contains(&name.as_str()) => return None,
+                // a token, or a quoted string as `no-cache="Set-Cookie"`
+                Some(argument) if Utils::is_header_name(argument) => format!("{}={}", name, argument),
+                Some(argument) if argument.len() > 1 && argument.starts_with('"') && argument.ends_with('"') && !argument[1..argument.len() - 1].contains(['"', '\r', '\n']) => {
+                    format!("{}={}", name, argument)
+                }
+                Some(_) => return None,
+                None => name,
+            };
+            policy.directives.push(directive);
+        }
+        (!policy.directives.is_empty()).then_some(policy)
+    }
+
+    /// No caching header at all, the file is left to the heuristics of caches.
+    pub fn none() -> Self {
+        Self {
+            directives: Vec::new(),
+            max_age: None,
+            expires: true,
+        }
+    }
+
+    /// For fingerprinted files: a new version gets a new name, the old one never changes.
+    pub fn immutable() -> Self {
+        Self {
+            directives: vec![
+                "public".to_string(),
+                format!("max-age={}", Self::IMMUTABLE_MAX_AGE),
+                "immutable".to_string(),
+            ],
+            max_age: Some(Self::IMMUTABLE_MAX_AGE),
+            expires: true,
+        }
+    }
+
+    pub fn cache_control(&self) -> Option<String> {
+        (!self.directives.is_empty()).then(|| self.directives.join(", "))
+    }
+
+    /// The `Expires` date for an answer sent at `now`, already past when caches must
+    /// revalidate.
+    pub fn expires_at(&self, now: u64) -> Option<String> {
+        let revalidate = self.directives.iter().any(|directive| directive == "no-cache" || directive == "no-store");
+        match (self.expires, self.max_age) {
+            (false, _) => None,
+            (true, _) if revalidate => Some(Utils::http_date(0)),
+            (true, Some(0)) => Some(Utils::http_date(0)),
+            (true, Some(max_age)) => Some(Utils::http_date(now.saturating_add(max_age))),
+            (true, None) => None,
+        }
+    }
+
+    pub fn add_headers(&self, headers: &mut KeyVal, now: u64) {
+        if let Some(cache_control) = self.cache_control() {
+            headers.add("Cache-Control".to_string(), cache_control);
+        }
+        if let Some(expires) = self.expires_at(now) {
+            headers.add("Expires".to_string(), expires);
+        }
+    }
+}
+
+/// What a `[cache]` rule applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheMatch {
+    /// a path prefix, a glob or a media type, as `[headers]` rules
+    File(HeaderMatch),
+    /// file names with a content hash, `app.3f9a.js`
+    Fingerprint(Regex),
+}
+
+impl CacheMatch {
+    /// `.css` stands for `*.css`, other keys are read as `[headers]` keys.
+    pub fn new(key: &str) -> Self {
+        match key.strip_prefix('.') {
+            Some(extension) if !extension.is_empty() && !extension.contains(['/', '.', '*', '?', '[']) => {
+                Self::File(HeaderMatch::new(&format!("*.{}", extension)))
+            }
+            _ => Self::File(HeaderMatch::new(key)),
+        }
+    }
+
+    pub fn matches(&self, path: &str, content_type: Option<&str>) -> bool {
+        match self {
+            Self::File(matcher) => matcher.matches(path, content_type),
+            Self::Fingerprint(regex) => regex.is_match(path.rsplit('/').next().unwrap_or("")),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::File(matcher) => matcher.as_str(),
+            Self::Fingerprint(regex) => regex.as_str(),
+        }
+    }
+
+    // the order of `[headers]` rules, fingerprints last
+    fn precedence(&self) -> (u8, usize) {
+        match self {
+            Self::File(matcher) => matcher.precedence(),
+            Self::Fingerprint(_) => (u8::MAX, 0),
+        }
+    }
+}
+
+/// A key of the `[cache]` section and its policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheRule {
+    pub matcher: CacheMatch,
+    pub policy: CachePolicy,
+}
+
+impl CacheRule {
+    pub fn new(key: &str, policy: CachePolicy) -> Self {
+        Self {
+            matcher: CacheMatch::new(key),
+            policy,
+        }
+    }
+
+    /// The `immutable = true` rule, for file names matching `fingerprint`.
+    pub fn immutable(fingerprint: Regex) -> Self {
+        Self {
+            matcher: CacheMatch::Fingerprint(fingerprint),
+            policy: CachePolicy::immutable(),
+        }
+    }
+
+    /// Orders `rules` from the least to the most specific, see `Cache::policy_for`.
+    pub fn sort(rules: &mut [CacheRule]) {
+        rules.sort_by_key(|rule| rule.matcher.precedence());
+    }
+}
+
+pub struct Cache;
+
+impl Cache {
+    /// Hashed names as bundlers write them, `app.3f9a.js` or `vendor.0d3c51e8.css`.
+    pub const DEFAULT_FINGERPRINT: &'static str = "\\.[0-9a-f]{4,}\\.[^.]+$";
+
+    /// The policy of the most specific rule matching the file at `path`, of type
+    /// `content_type`: fingerprinted names, then media types, globs and the longest prefix.
+    pub fn policy_for<'r>(rules: &'r [CacheRule], path: &str, content_type: Option<&str>) -> Option<&'r CachePolicy> {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        let normalized = normalized.to_string_lossy();
+
+        rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matcher.matches(&normalized, content_type))
+            .map(|rule| &rule.policy)
+    }
+}
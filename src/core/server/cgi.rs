@@ -0,0 +1,541 @@
+use crate::core::server::chunked::ChunkedWriter;
+use crate::core::server::fastcgi::{FastCgi, FastCgiRule};
+use crate::core::server::headers::ResponseHeaders;
+use crate::core::server::http::{HttpMethod, HttpStatus, HttpVersion};
+use crate::core::server::request::Request;
+use crate::core::server::response::Response;
+use crate::core::server::server::Server;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// @see: https://www.rfc-editor.org/rfc/rfc3875
+
+/// Scripts run as CGI, every file under a folder prefix (`/cgi-bin`) or every file with an
+/// extension (`.pl`), optionally through an interpreter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgiRule {
+    pub pattern: String,
+    /// runs `interpreter script`, the script itself must be executable otherwise
+    pub interpreter: Option<PathBuf>,
+    pub timeout: Duration,
+}
+
+impl CgiRule {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(pattern: &str, interpreter: Option<PathBuf>) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            interpreter,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn is_extension(&self) -> bool {
+        self.pattern.starts_with('.')
+    }
+}
+
+/// A request path resolved to a script of the document root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgiScript {
+    pub rule: CgiRule,
+    pub file: PathBuf,
+    /// URL path of the script
+    pub script_name: String,
+    /// rest of the request path after the script, may be empty
+    pub path_info: String,
+}
+
+/// The CGI and FastCGI rules of the server. Clients never write a file one of them would
+/// run, uploads and WebDAV refuse such targets with 403 Forbidden.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScriptRules<'a> {
+    pub cgi: &'a [CgiRule],
+    pub fastcgi: &'a [FastCgiRule],
+}
+
+impl ScriptRules<'_> {
+    /// Whether a file at the normalized URL `path` would run as a script.
+    pub fn run(&self, path: &str) -> bool {
+        Cgi::runs(self.cgi, path) || FastCgi::runs(self.fastcgi, path)
+    }
+}
+
+/// Status and headers of a script answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgiHead {
+    pub code: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+}
+
+pub struct Cgi<'a> {
+    root: &'a Path,
+    script: &'a CgiScript,
+    port: u16,
+}
+
+impl<'a> Cgi<'a> {
+    // bodies without length are buffered, CONTENT_LENGTH is mandatory
+    const MAX_CHUNKED_BODY: u64 = 16 * 1024 * 1024;
+    const MAX_HEAD: usize = 64 * 1024;
+    const READ_SIZE: usize = 8 * 1024;
+
+    pub fn new(root: &'a Path, script: &'a CgiScript, port: u16) -> Self {
+        Self { root, script, port }
+    }
+
+    /// Finds the script named by `path`: the first existing file along the path, covered by a
+    /// folder rule, or else by an extension rule. Hidden paths never run.
+    pub fn script_for(rules: &[CgiRule], root: &Path, path: &str) -> Option<CgiScript> {
+        if rules.is_empty() {
+            return None;
+        }
+        let path = Utils::request_path(path)?;
+
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        let mut file = root.to_path_buf();
+        for (index, segment) in segments.iter().enumerate() {
+            file.push(segment);
+            if file.is_dir() {
+                continue;
+            }
+            if !file.is_file() {
+                return None;
+            }
+
+            let script_name = format!("/{}", segments[..=index].join("/"));
+            let path_info = segments[index + 1..]
+                .iter()
+                .map(|segment| format!("/{}", segment))
+                .collect::<String>();
+            let rule = Utils::longest_prefix(rules.iter().filter(|rule| !rule.is_extension()), &script_name, |rule| &rule.pattern)
+                .or_else(|| {
+                    rules
+                        .iter()
+                        .find(|rule| rule.is_extension() && segment.to_lowercase().ends_with(&rule.pattern.to_lowercase()))
+                })?;
+
+            // the script runs from its own folder, a relative root would no longer resolve
+            return Some(CgiScript {
+                rule: rule.clone(),
+                file: fs::canonicalize(&file).unwrap_or(file),
+                script_name,
+                path_info,
+            });
+        }
+        None
+    }
+
+    /// Whether a file at `path` would run: a folder rule covers it or an extension rule
+    /// names it.
+    pub fn runs(rules: &[CgiRule], path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+        rules.iter().any(|rule| match rule.is_extension() {
+            true => name.ends_with(&rule.pattern.to_lowercase()),
+            false => Utils::path_matches_prefix(path, &rule.pattern),
+        })
+    }
+
+    /// Meta-variables passed to the script, the environment of Katana is not inherited.
+    pub fn variables(&self, request: &Request, content_length: Option<u64>) -> Vec<(String, String)> {
+        let script = self.script;
+        let root = fs::canonicalize(self.root).unwrap_or_else(|_| self.root.to_path_buf());
+        let server_name = request
+            .domain
+            .rsplit_once(':')
+            .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+            .map(|(host, _)| host)
+            .unwrap_or(&request.domain);
+        let request_uri = match request.query_string.is_empty() {
+            true => Utils::encode_path(&request.path),
+            false => format!("{}?{}", Utils::encode_path(&request.path), request.query_string),
+        };
+
+        let mut variables = vec![
+            ("GATEWAY_INTERFACE", "CGI/1.1".to_string()),
+            ("SERVER_SOFTWARE", Server::version()),
+            ("SERVER_NAME", server_name.to_string()),
+            ("SERVER_PORT", self.port.to_string()),
+            ("SERVER_PROTOCOL", request.version.as_str().to_string()),
+            ("REQUEST_METHOD", request.method.as_str().to_string()),
+            ("REQUEST_URI", request_uri),
+            ("QUERY_STRING", request.query_string.clone()),
+            ("SCRIPT_NAME", script.script_name.clone()),
+            ("SCRIPT_FILENAME", script.file.to_string_lossy().to_string()),
+            ("PATH_INFO", script.path_info.clone()),
+            ("DOCUMENT_ROOT", root.to_string_lossy().to_string()),
+            (
+                "REMOTE_ADDR",
+                request.remote_addr.map(|ip| ip.to_string()).unwrap_or_default(),
+            ),
+            ("PATH", std::env::var("PATH").unwrap_or_else(|_| "/usr/local/bin:/usr/bin:/bin".to_string())),
+        ];
+        if !script.path_info.is_empty() {
+            let translated = root.join(script.path_info.trim_start_matches('/'));
+            variables.push(("PATH_TRANSLATED", translated.to_string_lossy().to_string()));
+        }
+        if let Some(user) = &request.remote_user {
+            variables.push(("AUTH_TYPE", "Basic".to_string()));
+            variables.push(("REMOTE_USER", user.clone()));
+        }
+        if let Some(length) = content_length {
+            variables.push(("CONTENT_LENGTH", length.to_string()));
+        }
+        if let Some(content_type) = request.header("Content-Type") {
+            variables.push(("CONTENT_TYPE", content_type.clone()));
+        }
+
+        let mut variables: Vec<(String, String)> = variables
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        for (key, value) in request.headers.iter() {
+            let name = key.trim().to_uppercase().replace('-', "_");
+            // credentials stay with the server, HTTP_PROXY would be taken as a proxy setting
+            // @see: https://httpoxy.org
+            if matches!(name.as_str(), "AUTHORIZATION" | "PROXY" | "CONTENT_TYPE" | "CONTENT_LENGTH")
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                continue;
+            }
+            variables.push((format!("HTTP_{}", name), value.trim().to_string()));
+        }
+        variables
+    }
+
+    /// Runs the script and streams its output to the client.
+    ///
+    /// Returns the status code and the body size once the answer was sent, or leaves an error
+    /// in `response` when the script could not start, failed or timed out before its headers.
+    pub fn handle(&self, response: &mut Response, stream: &TcpStream) -> Option<(u16, usize)> {
+        let request = &response.request;
+        let body = match Self::body(request, stream) {
+            Ok(body) => body,
+            Err(e) => {
+                Logger::warn(format!("[CGI] Refusing body for {}: {}", self.script.script_name, e).as_str());
+                response.serve_error_response(Self::body_failure(&e));
+                return None;
+            }
+        };
+        let content_length = body.as_ref().map(Body::len);
+
+        let mut child = match self.spawn(request, content_length) {
+            Ok(child) => child,
+            Err(e) => {
+                Logger::error(format!("[CGI] Unable to run {}: {}", self.script.file.display(), e).as_str());
+                request.discard_body(stream);
+                response.serve_error_response(match e.kind() {
+                    ErrorKind::PermissionDenied => HttpStatus::Forbidden,
+                    _ => HttpStatus::InternalServerError,
+                });
+                return None;
+            }
+        };
+
+        self.log_errors(&mut child);
+        let stdin = child.stdin.take();
+        let output = Self::read_output(child.stdout.take());
+        let deadline = Instant::now() + self.script.rule.timeout;
+        let request = response.request.clone();
+
+        thread::scope(|scope| {
+            // a script may answer before reading its input, feed it on the side
+            scope.spawn(|| {
+                let (Some(mut stdin), Some(body)) = (stdin, body) else {
+                    return;
+                };
+                let written = match body {
+                    Body::Buffered(data) => stdin.write_all(&data),
+                    Body::Streamed(length) => io::copy(&mut request.body_reader(stream).take(length), &mut stdin).map(|_| ()),
+                };
+                if let Err(e) = written {
+                    Logger::debug(format!("[CGI] Script input closed: {}", e).as_str());
+                }
+            });
+
+            let result = Self::respond(response, stream, "CGI", &self.script.script_name, self.script.rule.timeout, || {
+                Self::next(&output, deadline)
+            });
+            // also unblocks the input thread when the script stopped reading
+            if result.is_none() || Instant::now() >= deadline {
+                let _ = child.kill();
+            }
+            let _ = child.wait();
+            result
+        })
+    }
+
+    fn spawn(&self, request: &Request, content_length: Option<u64>) -> io::Result<Child> {
+        let script = &self.script.file;
+        let mut command = match &self.script.rule.interpreter {
+            Some(interpreter) => {
+                let mut command = Command::new(interpreter);
+                command.arg(script);
+                command
+            }
+            None => Command::new(script),
+        };
+        if let Some(folder) = script.parent() {
+            command.current_dir(folder);
+        }
+        command
+            .env_clear()
+            .envs(self.variables(request, content_length))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+
+    // the body must be known in full before the script starts, CONTENT_LENGTH comes first
+    pub(crate) fn body(request: &Request, stream: &TcpStream) -> io::Result<Option<Body>> {
+        if !request.body.is_empty() {
+            return Ok(Some(Body::Buffered(request.body.clone().into_bytes())));
+        }
+        if request.is_chunked() {
+            let mut data = Vec::new();
+            request.body_reader(stream).take(Self::MAX_CHUNKED_BODY + 1).read_to_end(&mut data)?;
+            if data.len() as u64 > Self::MAX_CHUNKED_BODY {
+                return Err(Error::new(ErrorKind::FileTooLarge, "chunked body too large"));
+            }
+            return Ok(Some(Body::Buffered(data)));
+        }
+        Ok(request.content_length().filter(|length| *length > 0).map(Body::Streamed))
+    }
+
+    pub(crate) fn body_failure(error: &Error) -> HttpStatus {
+        match error.kind() {
+            ErrorKind::FileTooLarge => HttpStatus::PayloadTooLarge,
+            _ => HttpStatus::BadRequest,
+        }
+    }
+
+    // every stderr line goes to the log
+    fn log_errors(&self, child: &mut Child) {
+        if let Some(stderr) = child.stderr.take() {
+            let name = self.script.script_name.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    Logger::warn(format!("[CGI] {}: {}", name, line).as_str());
+                }
+            });
+        }
+    }
+
+    // stdout is read on its own thread so the timeout can interrupt a silent script
+    fn read_output(stdout: Option<ChildStdout>) -> Receiver<io::Result<Vec<u8>>> {
+        let (sender, receiver) = mpsc::channel();
+        if let Some(mut stdout) = stdout {
+            thread::spawn(move || loop {
+                let mut buffer = vec![0; Self::READ_SIZE];
+                match stdout.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(size) => {
+                        buffer.truncate(size);
+                        if sender.send(Ok(buffer)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        break;
+                    }
+                }
+            });
+        }
+        receiver
+    }
+
+    fn next(output: &Receiver<io::Result<Vec<u8>>>, deadline: Instant) -> io::Result<Option<Vec<u8>>> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match output.recv_timeout(remaining) {
+            Ok(Ok(data)) => Ok(Some(data)),
+            Ok(Err(e)) => Err(e),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+            Err(RecvTimeoutError::Timeout) => Err(Error::new(ErrorKind::TimedOut, "script timed out")),
+        }
+    }
+
+    /// Sends the answer of a script read from `next` to the client, shared with FastCGI.
+    ///
+    /// `next` gives the output piece by piece, `None` at its end and a `TimedOut` error when
+    /// the script is too slow.
+    pub(crate) fn respond(
+        response: &mut Response,
+        mut stream: &TcpStream,
+        tag: &str,
+        name: &str,
+        timeout: Duration,
+        mut next: impl FnMut() -> io::Result<Option<Vec<u8>>>,
+    ) -> Option<(u16, usize)> {
+        let mut buffer = Vec::new();
+        let head_end = loop {
+            if let Some(end) = Self::head_end(&buffer) {
+                break end;
+            }
+            let failure = match next() {
+                Ok(Some(data)) if buffer.len() < Self::MAX_HEAD => {
+                    buffer.extend(data);
+                    continue;
+                }
+                Ok(Some(_)) => Some("headers too large"),
+                Ok(None) => Some("premature end of script headers"),
+                Err(e) if e.kind() == ErrorKind::TimedOut => None,
+                Err(_) => Some("unable to read the script output"),
+            };
+            match failure {
+                Some(reason) => {
+                    Logger::error(format!("[{}] {}: {}", tag, name, reason).as_str());
+                    response.serve_error_response(HttpStatus::InternalServerError);
+                }
+                None => {
+                    Logger::error(format!("[{}] {} timed out after {}s", tag, name, timeout.as_secs()).as_str());
+                    response.serve_error_response(HttpStatus::GatewayTimeout);
+                }
+            }
+            return None;
+        };
+
+        let (head, separator) = head_end;
+        let head = String::from_utf8_lossy(&buffer[..head]).to_string();
+        let rest = buffer[head_end.0 + separator..].to_vec();
+        let CgiHead { code, reason, mut headers } = match Self::parse_head(&head) {
+            Some(parsed) => parsed,
+            None => {
+                Logger::error(format!("[{}] {}: malformed headers", tag, name).as_str());
+                response.serve_error_response(HttpStatus::InternalServerError);
+                return None;
+            }
+        };
+
+        let request = &response.request;
+        let no_body = request.method == HttpMethod::HEAD || code == 204 || code == 304;
+        let length = headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|(_, value)| value.trim().parse::<u64>().ok());
+        let chunked = !no_body && length.is_none() && request.version == HttpVersion::Http11;
+
+        headers.push(("Server".to_string(), Server::version()));
+        ResponseHeaders::apply_list(&response.header_rules, &request.path, &mut headers);
+
+        let mut head = format!("{} {} {}\r\n", request.version.as_str(), code, reason);
+        for (key, value) in &headers {
+            head.push_str(&format!("{}: {}\r\n", key, value));
+        }
+        if chunked {
+            head.push_str("Transfer-Encoding: chunked\r\n");
+        }
+        head.push_str("Connection: close\r\n\r\n");
+
+        let result = (|| -> io::Result<usize> {
+            stream.write_all(head.as_bytes())?;
+            if no_body {
+                return Ok(0);
+            }
+            let size = if chunked {
+                let mut writer = ChunkedWriter::new(stream);
+                let size = Self::pump(&rest, &mut next, &mut writer)?;
+                writer.finish()?;
+                size
+            } else {
+                Self::pump(&rest, &mut next, &mut stream)?
+            };
+            stream.flush()?;
+            Ok(size)
+        })();
+
+        match result {
+            Ok(size) => Some((code, size)),
+            Err(e) => {
+                // the head is gone, the client sees a truncated body
+                Logger::error(format!("[{}] {}: output interrupted: {}", tag, name, e).as_str());
+                Some((code, 0))
+            }
+        }
+    }
+
+    // the rest of the output, until the script closes it
+    fn pump(
+        start: &[u8],
+        next: &mut impl FnMut() -> io::Result<Option<Vec<u8>>>,
+        writer: &mut impl Write,
+    ) -> io::Result<usize> {
+        writer.write_all(start)?;
+        let mut size = start.len();
+        while let Some(data) = next()? {
+            writer.write_all(&data)?;
+            size += data.len();
+        }
+        Ok(size)
+    }
+
+    // end of the header block and length of the blank line, scripts may use bare \n
+    fn head_end(buffer: &[u8]) -> Option<(usize, usize)> {
+        let crlf = buffer.windows(4).position(|window| window == b"\r\n\r\n").map(|index| (index, 4));
+        let lf = buffer.windows(2).position(|window| window == b"\n\n").map(|index| (index, 2));
+        match (crlf, lf) {
+            (Some(crlf), Some(lf)) => Some(if crlf.0 < lf.0 { crlf } else { lf }),
+            (crlf, lf) => crlf.or(lf),
+        }
+    }
+
+    /// Status code, reason phrase and headers of a script answer (RFC 3875 section 6.2).
+    ///
+    /// `Status` sets the code, a `Location` without status redirects with 302.
+    pub fn parse_head(head: &str) -> Option<CgiHead> {
+        let mut status = None;
+        let mut headers = Vec::new();
+        for line in head.lines() {
+            let (key, value) = line.split_once(':')?;
+            let (key, value) = (key.trim(), value.trim());
+            if key.is_empty() {
+                return None;
+            }
+            if key.eq_ignore_ascii_case("Status") {
+                let (code, reason) = value.split_once(' ').unwrap_or((value, ""));
+                let code = code.parse::<u16>().ok().filter(|code| (200..600).contains(code))?;
+                status = Some((code, reason.trim().to_string()));
+            } else {
+                headers.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        let location = headers.iter().any(|(key, _)| key.eq_ignore_ascii_case("Location"));
+        let (code, reason) = match status {
+            Some(status) => status,
+            None if location => (HttpStatus::Found.to_code(), String::new()),
+            None => (HttpStatus::Ok.to_code(), String::new()),
+        };
+        let reason = match reason.is_empty() {
+            true => HttpStatus::from_code(code).map(|status| status.to_message().to_string()).unwrap_or_else(|| "Unknown".to_string()),
+            false => reason,
+        };
+        Some(CgiHead { code, reason, headers })
+    }
+}
+
+pub(crate) enum Body {
+    Buffered(Vec<u8>),
+    /// Content-Length bytes left on the socket
+    Streamed(u64),
+}
+
+impl Body {
+    pub(crate) fn len(&self) -> u64 {
+        match self {
+            Self::Buffered(data) => data.len() as u64,
+            Self::Streamed(length) => *length,
+        }
+    }
+}
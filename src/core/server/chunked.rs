@@ -0,0 +1,108 @@
+use std::io::{BufRead, Error, ErrorKind, Read, Result, Write};
+
+// @see: https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Headers/Transfer-Encoding#chunked
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+    written: usize,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, written: 0 }
+    }
+
+    /// Number of payload bytes written so far, chunk framing excluded.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Writes the terminating zero-length chunk.
+    pub fn finish(mut self) -> Result<W> {
+        self.inner.write_all(b"0\r\n\r\n")?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        // an empty chunk would end the body
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.inner.write_all(format!("{:x}\r\n", buf.len()).as_bytes())?;
+        self.inner.write_all(buf)?;
+        self.inner.write_all(b"\r\n")?;
+        self.written += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decodes a `Transfer-Encoding: chunked` request body, trailers are skipped.
+pub struct ChunkedReader<R: BufRead> {
+    inner: R,
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    const MAX_LINE: u64 = 4096;
+
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        (&mut self.inner).take(Self::MAX_LINE).read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            return Err(Error::new(ErrorKind::InvalidData, "chunk line too long or truncated"));
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let line = self.read_line()?;
+            // chunk extensions after ';' carry nothing we need
+            let size = line.split(';').next().unwrap_or("").trim();
+            self.remaining = usize::from_str_radix(size, 16)
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid chunk size"))?;
+
+            if self.remaining == 0 {
+                while !self.read_line()?.is_empty() {}
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let limit = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..limit])?;
+        if read == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "chunked body truncated"));
+        }
+        self.remaining -= read;
+
+        if self.remaining == 0 && !self.read_line()?.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "missing CRLF after chunk"));
+        }
+
+        Ok(read)
+    }
+}
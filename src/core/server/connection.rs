@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionLimit {
+    /// the server already holds `max_connections` connections
+    Global,
+    /// the client already holds `max_connections_per_ip` connections
+    PerClient,
+}
+
+#[derive(Debug, Default)]
+struct Connections {
+    total: usize,
+    clients: HashMap<IpAddr, usize>,
+}
+
+/// Counts open connections, globally and per client address.
+///
+/// Cloning is cheap and every clone shares the same counters.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimiter {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    connections: Arc<Mutex<Connections>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_connections: usize, max_connections_per_ip: usize) -> Self {
+        Self {
+            max_connections,
+            max_connections_per_ip,
+            connections: Arc::new(Mutex::new(Connections::default())),
+        }
+    }
+
+    /// Registers a connection from `ip`, it stays counted until the guard is dropped.
+    pub fn acquire(&self, ip: Option<IpAddr>) -> Result<ConnectionGuard, ConnectionLimit> {
+        let ip = ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let mut connections = Self::lock(&self.connections);
+
+        if connections.total >= self.max_connections {
+            return Err(ConnectionLimit::Global);
+        }
+        if connections.clients.get(&ip).copied().unwrap_or(0) >= self.max_connections_per_ip {
+            return Err(ConnectionLimit::PerClient);
+        }
+
+        *connections.clients.entry(ip).or_insert(0) += 1;
+        connections.total += 1;
+
+        Ok(ConnectionGuard {
+            ip,
+            connections: Arc::clone(&self.connections),
+        })
+    }
+
+    /// Number of open connections, from `ip` only when given.
+    pub fn count(&self, ip: Option<&IpAddr>) -> usize {
+        let connections = Self::lock(&self.connections);
+        match ip {
+            Some(ip) => connections.clients.get(ip).copied().unwrap_or(0),
+            None => connections.total,
+        }
+    }
+
+    // counters stay consistent even if a connection thread panicked
+    fn lock(connections: &Mutex<Connections>) -> MutexGuard<'_, Connections> {
+        match connections.lock() {
+            Ok(connections) => connections,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Releases its connection slot when dropped.
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    ip: IpAddr,
+    connections: Arc<Mutex<Connections>>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut connections = ConnectionLimiter::lock(&self.connections);
+        connections.total = connections.total.saturating_sub(1);
+        if let Some(count) = connections.clients.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                // forget idle clients so the map does not grow with every address ever seen
+                connections.clients.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use crate::core::server::cgi::{Body, Cgi, CgiRule, CgiScript};
+use crate::core::server::http::HttpStatus;
+use crate::core::server::request::Request;
+use crate::core::server::response::Response;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+// @see: https://fastcgi-archives.github.io/FastCGI_Specification.html
+
+/// A FastCGI backend, `host:port` or `unix:/path/to.sock`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FastCgiAddress {
+    Tcp { host: String, port: u16 },
+    Unix(PathBuf),
+}
+
+impl FastCgiAddress {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(socket) = value.strip_prefix("unix:") {
+            let socket = socket.trim_start_matches("//");
+            return (!socket.is_empty()).then(|| Self::Unix(PathBuf::from(socket)));
+        }
+
+        let authority = value.strip_prefix("tcp://").unwrap_or(value);
+        let (host, port) = authority.rsplit_once(':')?;
+        let port = port.parse().ok()?;
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self::Tcp {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    fn connect(&self, timeout: Duration) -> io::Result<Connection> {
+        let connection = match self {
+            Self::Tcp { host, port } => {
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                let mut last_error = Error::new(ErrorKind::NotFound, "backend host not found");
+                let mut connected = None;
+                for address in (host, *port).to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&address, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(e) => last_error = e,
+                    }
+                }
+                Connection::Tcp(connected.ok_or(last_error)?)
+            }
+            #[cfg(unix)]
+            Self::Unix(socket) => Connection::Unix(UnixStream::connect(socket)?),
+            #[cfg(not(unix))]
+            Self::Unix(_) => return Err(Error::new(ErrorKind::Unsupported, "unix sockets are not available")),
+        };
+        connection.set_timeout(timeout)?;
+        Ok(connection)
+    }
+}
+
+impl fmt::Display for FastCgiAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp { host, port } => write!(f, "{}:{}", host, port),
+            Self::Unix(socket) => write!(f, "unix:{}", socket.display()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn set_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => {
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))
+            }
+            #[cfg(unix)]
+            Self::Unix(stream) => {
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))
+            }
+        }
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+
+    // an idle connection has nothing to read, unless the backend closed it meanwhile
+    fn is_alive(&mut self) -> bool {
+        if self.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let alive = matches!(self.read(&mut [0; 1]), Err(e) if e.kind() == ErrorKind::WouldBlock);
+        alive && self.set_nonblocking(false).is_ok()
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Scripts passed to a FastCGI backend, every file under a folder prefix (`/app`) or every
+/// file with an extension (`.php`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastCgiRule {
+    pub pattern: String,
+    pub address: FastCgiAddress,
+    /// script run for folders and for paths matching no file, `index.php`
+    pub index: Option<String>,
+    pub timeout: Duration,
+}
+
+impl FastCgiRule {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(pattern: &str, address: FastCgiAddress) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            address,
+            index: None,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn is_extension(&self) -> bool {
+        self.pattern.starts_with('.')
+    }
+
+    /// Applies one `index index.php`, `index off` or `timeout 10` (seconds) option.
+    pub fn set(&mut self, option: &str) -> bool {
+        let (key, value) = match option.trim().split_once(char::is_whitespace) {
+            Some((key, value)) => (key.to_lowercase(), value.trim()),
+            None => return false,
+        };
+
+        match key.as_str() {
+            "index" if matches!(value, "off" | "false") => self.index = None,
+            "index" if Utils::is_valid_entry(value) && !value.contains('/') => self.index = Some(value.to_string()),
+            "timeout" => match value.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => self.timeout = Duration::from_secs(seconds),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn matches(&self, script_name: &str) -> bool {
+        match self.is_extension() {
+            true => script_name.to_lowercase().ends_with(&self.pattern.to_lowercase()),
+            false => Utils::path_matches_prefix(script_name, &self.pattern),
+        }
+    }
+}
+
+/// A request path resolved to a script of the document root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastCgiScript {
+    pub rule: FastCgiRule,
+    pub file: PathBuf,
+    /// URL path of the script
+    pub script_name: String,
+    /// rest of the request path after the script, may be empty
+    pub path_info: String,
+}
+
+/// Idle backend connections kept open for the next requests, shared by every clone.
+#[derive(Debug, Clone, Default)]
+pub struct FastCgiPool {
+    idle: Arc<Mutex<HashMap<String, Vec<Connection>>>>,
+}
+
+impl FastCgiPool {
+    pub const MAX_IDLE: usize = 8;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of idle connections to `address`.
+    pub fn idle(&self, address: &FastCgiAddress) -> usize {
+        self.lock().get(&address.to_string()).map_or(0, Vec::len)
+    }
+
+    fn take(&self, address: &FastCgiAddress) -> Option<Connection> {
+        let mut idle = self.lock();
+        let connections = idle.get_mut(&address.to_string())?;
+        while let Some(mut connection) = connections.pop() {
+            if connection.is_alive() {
+                return Some(connection);
+            }
+        }
+        None
+    }
+
+    fn give(&self, address: &FastCgiAddress, connection: Connection) {
+        let mut idle = self.lock();
+        let connections = idle.entry(address.to_string()).or_default();
+        if connections.len() < Self::MAX_IDLE {
+            connections.push(connection);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Vec<Connection>>> {
+        match self.idle.lock() {
+            Ok(idle) => idle,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// One record of the protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: u8,
+    pub request_id: u16,
+    pub content: Vec<u8>,
+}
+
+pub struct FastCgi<'a> {
+    root: &'a Path,
+    script: &'a FastCgiScript,
+    port: u16,
+    pool: &'a FastCgiPool,
+}
+
+impl<'a> FastCgi<'a> {
+    pub const BEGIN_REQUEST: u8 = 1;
+    pub const END_REQUEST: u8 = 3;
+    pub const PARAMS: u8 = 4;
+    pub const STDIN: u8 = 5;
+    pub const STDOUT: u8 = 6;
+    pub const STDERR: u8 = 7;
+    const VERSION: u8 = 1;
+    const RESPONDER: u16 = 1;
+    const KEEP_CONN: u8 = 1;
+    // one request at a time on a connection
+    const REQUEST_ID: u16 = 1;
+    const MAX_CONTENT: usize = 65535;
+
+    pub fn new(root: &'a Path, script: &'a FastCgiScript, port: u16, pool: &'a FastCgiPool) -> Self {
+        Self { root, script, port, pool }
+    }
+
+    /// Finds the script named by `path`: the first existing file along the path covered by a
+    /// rule, or else the `index` script of the folder, or of the rule for missing paths.
+    ///
+    /// Existing files no rule covers are left to the static file server.
+    pub fn script_for(rules: &[FastCgiRule], root: &Path, path: &str) -> Option<FastCgiScript> {
+        if rules.is_empty() {
+            return None;
+        }
+        let path = Utils::request_path(path)?;
+
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        let mut file = root.to_path_buf();
+        for (index, segment) in segments.iter().enumerate() {
+            file.push(segment);
+            if file.is_dir() {
+                continue;
+            }
+            if !file.is_file() {
+                return Self::fallback(rules, root, &path);
+            }
+
+            let script_name = format!("/{}", segments[..=index].join("/"));
+            let path_info = segments[index + 1..]
+                .iter()
+                .map(|segment| format!("/{}", segment))
+                .collect::<String>();
+            let rule = Self::rule_for(rules, &script_name)?;
+            return Some(FastCgiScript {
+                rule: rule.clone(),
+                file: fs::canonicalize(&file).unwrap_or(file),
+                script_name,
+                path_info,
+            });
+        }
+
+        // a folder runs its index script, like a directory index
+        let index = Self::covering(rules, &path)?.index.as_ref()?;
+        Self::script(rules, root, &format!("{}/{}", path.trim_end_matches('/'), index))
+    }
+
+    /// Whether a file at `path` would run as a script.
+    pub fn runs(rules: &[FastCgiRule], path: &str) -> bool {
+        Self::rule_for(rules, path).is_some()
+    }
+
+    // missing paths go to the front controller of the rule covering them
+    fn fallback(rules: &[FastCgiRule], root: &Path, path: &str) -> Option<FastCgiScript> {
+        let rule = Self::covering(rules, path)?;
+        let base = match rule.is_extension() {
+            true => "",
+            false => rule.pattern.trim_end_matches('/'),
+        };
+        Self::script(rules, root, &format!("{}/{}", base, rule.index.as_ref()?))
+    }
+
+    // the folder rule of a path, or else the extension rule routing the whole site
+    fn covering<'r>(rules: &'r [FastCgiRule], path: &str) -> Option<&'r FastCgiRule> {
+        Self::folder_rule(rules, path).or_else(|| rules.iter().find(|rule| rule.is_extension() && rule.index.is_some()))
+    }
+
+    fn script(rules: &[FastCgiRule], root: &Path, script_name: &str) -> Option<FastCgiScript> {
+        let file = root.join(script_name.trim_start_matches('/'));
+        if !file.is_file() {
+            return None;
+        }
+        Some(FastCgiScript {
+            rule: Self::rule_for(rules, script_name)?.clone(),
+            file: fs::canonicalize(&file).unwrap_or(file),
+            script_name: script_name.to_string(),
+            path_info: String::new(),
+        })
+    }
+
+    // a folder rule first, the longest prefix wins, or else an extension rule
+    fn rule_for<'r>(rules: &'r [FastCgiRule], script_name: &str) -> Option<&'r FastCgiRule> {
+        Self::folder_rule(rules, script_name).or_else(|| rules.iter().find(|rule| rule.is_extension() && rule.matches(script_name)))
+    }
+
+    fn folder_rule<'r>(rules: &'r [FastCgiRule], path: &str) -> Option<&'r FastCgiRule> {
+        Utils::longest_prefix(rules.iter().filter(|rule| !rule.is_extension()), path, |rule| &rule.pattern)
+    }
+
+    /// Parameters sent to the backend, the CGI meta-variables.
+    pub fn variables(&self, request: &Request, content_length: Option<u64>) -> Vec<(String, String)> {
+        let script = CgiScript {
+            rule: CgiRule::new(&self.script.rule.pattern, None),
+            file: self.script.file.clone(),
+            script_name: self.script.script_name.clone(),
+            path_info: self.script.path_info.clone(),
+        };
+        let mut variables = Cgi::new(self.root, &script, self.port).variables(request, content_length);
+        // PHP refuses to run without it when cgi.force_redirect is on
+        variables.push(("REDIRECT_STATUS".to_string(), "200".to_string()));
+        variables
+    }
+
+    /// Passes the request to the backend and streams its answer to the client.
+    ///
+    /// Returns the status code and the body size once the answer was sent, or leaves an error
+    /// in `response` when the backend could not be reached, failed or timed out before its
+    /// headers.
+    pub fn handle(&self, response: &mut Response, stream: &TcpStream) -> Option<(u16, usize)> {
+        let name = &self.script.script_name;
+        let address = &self.script.rule.address;
+        let request = &response.request;
+        let body = match Cgi::body(request, stream) {
+            Ok(body) => body,
+            Err(e) => {
+                Logger::warn(format!("[FastCGI] Refusing body for {}: {}", name, e).as_str());
+                response.serve_error_response(Cgi::body_failure(&e));
+                return None;
+            }
+        };
+        let content_length = body.as_ref().map(Body::len);
+
+        let connection = match self.pool.take(address) {
+            Some(connection) => connection.set_timeout(self.script.rule.timeout).map(|_| connection),
+            None => address.connect(self.script.rule.timeout),
+        };
+        let mut connection = match connection {
+            Ok(connection) => connection,
+            Err(e) => {
+                Logger::error(format!("[FastCGI] Unable to reach {}: {}", address, e).as_str());
+                request.discard_body(stream);
+                response.serve_error_response(match e.kind() {
+                    ErrorKind::TimedOut | ErrorKind::WouldBlock => HttpStatus::GatewayTimeout,
+                    _ => HttpStatus::BadGateway,
+                });
+                return None;
+            }
+        };
+
+        let variables = self.variables(request, content_length);
+        if let Err(e) = Self::send(&mut connection, &variables, request, stream, body) {
+            Logger::error(format!("[FastCGI] Unable to send {} to {}: {}", name, address, e).as_str());
+            response.serve_error_response(match e.kind() {
+                ErrorKind::TimedOut | ErrorKind::WouldBlock => HttpStatus::GatewayTimeout,
+                _ => HttpStatus::BadGateway,
+            });
+            return None;
+        }
+
+        let mut finished = false;
+        let result = Cgi::respond(response, stream, "FastCGI", name, self.script.rule.timeout, || {
+            Self::next(&mut connection, name, &mut finished)
+        });
+        // only a connection read to the end of its request can serve another one, the body of
+        // a HEAD answer or the rest of an interrupted one is read and dropped
+        if result.is_some() && !finished {
+            while let Ok(Some(_)) = Self::next(&mut connection, name, &mut finished) {}
+        }
+        if finished {
+            self.pool.give(address, connection);
+        }
+        result
+    }
+
+    fn send(
+        connection: &mut Connection,
+        variables: &[(String, String)],
+        request: &Request,
+        stream: &TcpStream,
+        body: Option<Body>,
+    ) -> io::Result<()> {
+        let mut begin = Self::RESPONDER.to_be_bytes().to_vec();
+        begin.extend([Self::KEEP_CONN, 0, 0, 0, 0, 0]);
+        let mut head = Self::record(Self::BEGIN_REQUEST, Self::REQUEST_ID, &begin);
+        head.extend(Self::records(Self::PARAMS, &Self::params(variables)));
+        connection.write_all(&head)?;
+
+        match body {
+            Some(Body::Buffered(data)) => connection.write_all(&Self::records(Self::STDIN, &data))?,
+            Some(Body::Streamed(length)) => {
+                let mut reader = request.body_reader(stream).take(length);
+                let mut buffer = vec![0; Self::MAX_CONTENT];
+                loop {
+                    let size = reader.read(&mut buffer)?;
+                    if size == 0 {
+                        break;
+                    }
+                    connection.write_all(&Self::record(Self::STDIN, Self::REQUEST_ID, &buffer[..size]))?;
+                }
+            }
+            None => {}
+        }
+        // an empty record ends the stream
+        connection.write_all(&Self::record(Self::STDIN, Self::REQUEST_ID, &[]))?;
+        connection.flush()
+    }
+
+    // the next piece of the answer, stderr goes to the log on the way
+    fn next(connection: &mut Connection, name: &str, finished: &mut bool) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let record = match Self::read_record(connection) {
+                Ok(record) => record,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Err(Error::new(ErrorKind::TimedOut, "backend timed out"));
+                }
+                // the backend closed the connection, the answer ends there
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            if record.request_id != Self::REQUEST_ID {
+                continue;
+            }
+
+            match record.kind {
+                Self::STDOUT if !record.content.is_empty() => return Ok(Some(record.content)),
+                Self::STDERR => {
+                    for line in String::from_utf8_lossy(&record.content).lines().filter(|line| !line.trim().is_empty()) {
+                        Logger::warn(format!("[FastCGI] {}: {}", name, line).as_str());
+                    }
+                }
+                Self::END_REQUEST => {
+                    // protocol status at byte 4, 0 when the request completed
+                    *finished = record.content.get(4) == Some(&0);
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// One record, padded to a multiple of 8 bytes. The content must fit in 65535 bytes.
+    pub fn record(kind: u8, request_id: u16, content: &[u8]) -> Vec<u8> {
+        let padding = (8 - content.len() % 8) % 8;
+        let mut record = vec![Self::VERSION, kind];
+        record.extend(request_id.to_be_bytes());
+        record.extend((content.len() as u16).to_be_bytes());
+        record.extend([padding as u8, 0]);
+        record.extend(content);
+        record.extend(vec![0; padding]);
+        record
+    }
+
+    // a whole stream split into records, ended by an empty one
+    fn records(kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut records = Vec::new();
+        for chunk in data.chunks(Self::MAX_CONTENT) {
+            records.extend(Self::record(kind, Self::REQUEST_ID, chunk));
+        }
+        records.extend(Self::record(kind, Self::REQUEST_ID, &[]));
+        records
+    }
+
+    /// Name-value pairs of a `PARAMS` stream, lengths over 127 take 4 bytes.
+    pub fn params(variables: &[(String, String)]) -> Vec<u8> {
+        let mut params = Vec::new();
+        for (name, value) in variables {
+            for length in [name.len(), value.len()] {
+                match length {
+                    0..=127 => params.push(length as u8),
+                    _ => params.extend((length as u32 | 0x8000_0000).to_be_bytes()),
+                }
+            }
+            params.extend(name.as_bytes());
+            params.extend(value.as_bytes());
+        }
+        params
+    }
+
+    /// Reads one record, skipping its padding.
+    pub fn read_record(reader: &mut impl Read) -> io::Result<Record> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if header[0] != Self::VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, "unsupported FastCGI version"));
+        }
+
+        let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+        let mut content = vec![0; length + header[6] as usize];
+        reader.read_exact(&mut content)?;
+        content.truncate(length);
+        Ok(Record {
+            kind: header[1],
+            request_id: u16::from_be_bytes([header[2], header[3]]),
+            content,
+        })
+    }
+}
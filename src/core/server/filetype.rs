@@ -1,3 +1,15 @@
+use crate::core::utils::logger::Logger;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+// what `Sniffer::sniff_file` found, by file, with the modification time and size it was read at
+type Sniffed = (Option<SystemTime>, u64, Option<&'static str>);
+static SNIFFED: OnceLock<Mutex<HashMap<PathBuf, Sniffed>>> = OnceLock::new();
+
 #[derive(Debug, Clone)]
 pub struct FileType {
     pub extension: String,
@@ -12,56 +24,43 @@ impl FileType {
         }
     }
 
-    pub fn all_file_types() -> Vec<Self> {
-        vec![
-            FileType::new("html", "text/html"),
-            FileType::new("css", "text/css"),
-            FileType::new("js", "application/javascript"),
-            FileType::new("map", "application/json"),
-            FileType::new("json", "application/json"),
-            FileType::new("xml", "application/xml"),
-            FileType::new("txt", "text/plain"),
-            FileType::new("md", "text/markdown"),
-            FileType::new("jpg", "image/jpeg"),
-            FileType::new("jpeg", "image/jpeg"),
-            FileType::new("png", "image/png"),
-            FileType::new("gif", "image/gif"),
-            FileType::new("svg", "image/svg+xml"),
-            FileType::new("weba", "audio/webm"),
-            FileType::new("webp", "image/webm"),
-            FileType::new("webm", "video/webm"),
-            FileType::new("ico", "image/x-icon"),
-            FileType::new("woff", "font/woff"),
-            FileType::new("woff2", "font/woff2"),
-            FileType::new("ttf", "font/ttf"),
-            FileType::new("otf", "font/otf"),
-            FileType::new("mp4", "video/mp4"),
-            FileType::new("avi", "video/x-msvideo"),
-            FileType::new("mp3", "audio/mpeg"),
-            FileType::new("ogg", "audio/ogg"),
-            FileType::new("wav", "audio/wav"),
-            FileType::new("flac", "audio/flac"),
-            FileType::new("zip", "application/zip"),
-            FileType::new("tar", "application/x-tar"),
-            FileType::new("pdf", "application/pdf"),
-            FileType::new("exe", "application/octet-stream"),
-            FileType::new("dll", "application/octet-stream"),
-            FileType::new("iso", "application/x-iso9660-image"),
-        ]
-    }
-
-    pub fn from_extension(extension: &str) -> Option<Self> {
-        Self::all_file_types()
-            .into_iter()
-            .find(|ft| ft.extension == extension.to_lowercase())
+    /// Every extension of `registry`.
+    pub fn all_file_types(registry: &MimeRegistry) -> Vec<Self> {
+        registry
+            .extensions()
+            .map(|(extension, _)| Self::new(extension, &registry.content_type(extension)))
+            .collect()
+    }
+
+    /// The type of `extension` in `registry`, charset included.
+    pub fn from_extension(registry: &MimeRegistry, extension: &str) -> Option<Self> {
+        registry
+            .get(extension)
+            .map(|_| Self::new(&extension.to_lowercase(), &registry.content_type(extension)))
+    }
+
+    /// `from_extension`, with the default type of the registry for unknown extensions.
+    pub fn for_extension(registry: &MimeRegistry, extension: &str) -> Self {
+        Self::new(&extension.to_lowercase(), &registry.content_type(extension))
+    }
+
+    /// The type of the file at `path` from its first bytes, when `sniff` is on in `registry`.
+    /// Binary files get the default type, `None` when sniffing is off or the file cannot be
+    /// read.
+    pub fn sniff(registry: &MimeRegistry, path: &Path) -> Option<Self> {
+        if !registry.sniff {
+            return None;
+        }
+        let media = Sniffer::sniff_file(path).ok()?.unwrap_or(&registry.default_type);
+        Some(Self::new("", &registry.with_charset(media)))
     }
 
     pub fn content_type(&self) -> &str {
         &self.content_type
     }
 
-    pub fn is_supported(extension: &str) -> bool {
-        Self::from_extension(extension).is_some()
+    pub fn is_supported(registry: &MimeRegistry, extension: &str) -> bool {
+        Self::from_extension(registry, extension).is_some()
     }
 
     pub fn content_disposition(&self) -> &'static str {
@@ -69,3 +68,362 @@ impl FileType {
         "inline"
     }
 }
+
+/// Media types by file extension: the built-in table, `mime.types` files and the
+/// `[mime]` section of `.katana`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MimeRegistry {
+    // lowercase extensions without the dot
+    types: BTreeMap<String, String>,
+    /// type of the files whose extension is unknown
+    pub default_type: String,
+    /// added to text types, `None` leaves them without
+    pub charset: Option<String>,
+    /// files without a known extension are typed from their content, see `FileType::sniff`
+    pub sniff: bool,
+}
+
+impl Default for MimeRegistry {
+    fn default() -> Self {
+        Self::builtin().as_ref().clone()
+    }
+}
+
+impl MimeRegistry {
+    pub const DEFAULT_TYPE: &'static str = "application/octet-stream";
+    pub const DEFAULT_CHARSET: &'static str = "utf-8";
+
+    // @see: https://www.iana.org/assignments/media-types/media-types.xhtml
+    const BUILTIN: [(&'static str, &'static str); 118] = [
+        // text
+        ("html", "text/html"),
+        ("htm", "text/html"),
+        ("shtml", "text/html"),
+        ("xhtml", "application/xhtml+xml"),
+        ("css", "text/css"),
+        ("js", "text/javascript"),
+        ("mjs", "text/javascript"),
+        ("cjs", "text/javascript"),
+        ("txt", "text/plain"),
+        ("text", "text/plain"),
+        ("log", "text/plain"),
+        ("conf", "text/plain"),
+        ("ini", "text/plain"),
+        ("md", "text/markdown"),
+        ("markdown", "text/markdown"),
+        ("csv", "text/csv"),
+        ("tsv", "text/tab-separated-values"),
+        ("ics", "text/calendar"),
+        ("vcf", "text/vcard"),
+        ("vtt", "text/vtt"),
+        ("srt", "application/x-subrip"),
+        ("rtf", "application/rtf"),
+        ("yaml", "application/yaml"),
+        ("yml", "application/yaml"),
+        ("toml", "application/toml"),
+        // data
+        ("json", "application/json"),
+        ("map", "application/json"),
+        ("jsonld", "application/ld+json"),
+        ("geojson", "application/geo+json"),
+        ("webmanifest", "application/manifest+json"),
+        ("xml", "application/xml"),
+        ("xsl", "application/xml"),
+        ("xsd", "application/xml"),
+        ("rss", "application/rss+xml"),
+        ("atom", "application/atom+xml"),
+        ("wasm", "application/wasm"),
+        ("pdf", "application/pdf"),
+        ("ps", "application/postscript"),
+        ("eps", "application/postscript"),
+        ("epub", "application/epub+zip"),
+        ("sql", "application/sql"),
+        ("bin", "application/octet-stream"),
+        ("exe", "application/octet-stream"),
+        ("dll", "application/octet-stream"),
+        ("so", "application/octet-stream"),
+        ("dmg", "application/x-apple-diskimage"),
+        ("iso", "application/x-iso9660-image"),
+        ("deb", "application/vnd.debian.binary-package"),
+        ("rpm", "application/x-rpm"),
+        ("apk", "application/vnd.android.package-archive"),
+        ("jar", "application/java-archive"),
+        ("swf", "application/x-shockwave-flash"),
+        // archives
+        ("zip", "application/zip"),
+        ("tar", "application/x-tar"),
+        ("gz", "application/gzip"),
+        ("tgz", "application/gzip"),
+        ("bz2", "application/x-bzip2"),
+        ("xz", "application/x-xz"),
+        ("zst", "application/zstd"),
+        ("7z", "application/x-7z-compressed"),
+        ("rar", "application/vnd.rar"),
+        // office
+        ("doc", "application/msword"),
+        ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+        ("xls", "application/vnd.ms-excel"),
+        ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        ("ppt", "application/vnd.ms-powerpoint"),
+        ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+        ("odt", "application/vnd.oasis.opendocument.text"),
+        ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+        ("odp", "application/vnd.oasis.opendocument.presentation"),
+        // images
+        ("png", "image/png"),
+        ("apng", "image/apng"),
+        ("jpg", "image/jpeg"),
+        ("jpeg", "image/jpeg"),
+        ("jpe", "image/jpeg"),
+        ("jfif", "image/jpeg"),
+        ("gif", "image/gif"),
+        ("webp", "image/webp"),
+        ("avif", "image/avif"),
+        ("heic", "image/heic"),
+        ("heif", "image/heif"),
+        ("jxl", "image/jxl"),
+        ("svg", "image/svg+xml"),
+        ("svgz", "image/svg+xml"),
+        ("ico", "image/x-icon"),
+        ("cur", "image/x-icon"),
+        ("bmp", "image/bmp"),
+        ("tif", "image/tiff"),
+        ("tiff", "image/tiff"),
+        ("psd", "image/vnd.adobe.photoshop"),
+        // fonts
+        ("woff", "font/woff"),
+        ("woff2", "font/woff2"),
+        ("ttf", "font/ttf"),
+        ("otf", "font/otf"),
+        ("eot", "application/vnd.ms-fontobject"),
+        // audio
+        ("mp3", "audio/mpeg"),
+        ("m4a", "audio/mp4"),
+        ("aac", "audio/aac"),
+        ("oga", "audio/ogg"),
+        ("ogg", "audio/ogg"),
+        ("opus", "audio/ogg"),
+        ("wav", "audio/wav"),
+        ("flac", "audio/flac"),
+        ("weba", "audio/webm"),
+        ("mid", "audio/midi"),
+        ("midi", "audio/midi"),
+        // video
+        ("mp4", "video/mp4"),
+        ("m4v", "video/mp4"),
+        ("webm", "video/webm"),
+        ("ogv", "video/ogg"),
+        ("mov", "video/quicktime"),
+        ("avi", "video/x-msvideo"),
+        ("mkv", "video/x-matroska"),
+        ("mpeg", "video/mpeg"),
+        ("mpg", "video/mpeg"),
+        ("ts", "video/mp2t"),
+        ("3gp", "video/3gpp"),
+        ("flv", "video/x-flv"),
+    ];
+
+    /// Types that are text without starting with `text/`.
+    const TEXT_TYPES: [&'static str; 4] = ["application/javascript", "application/json", "application/xml", "application/yaml"];
+
+    /// The built-in table, UTF-8 text and `application/octet-stream` for the rest. Built once.
+    pub fn builtin() -> Arc<Self> {
+        static BUILTIN: OnceLock<Arc<MimeRegistry>> = OnceLock::new();
+        BUILTIN
+            .get_or_init(|| {
+                Arc::new(Self {
+                    types: Self::BUILTIN.iter().map(|(extension, media)| (extension.to_string(), media.to_string())).collect(),
+                    default_type: Self::DEFAULT_TYPE.to_string(),
+                    charset: Some(Self::DEFAULT_CHARSET.to_string()),
+                    sniff: false,
+                })
+            })
+            .clone()
+    }
+
+    /// The type registered for `extension`, without charset.
+    pub fn get(&self, extension: &str) -> Option<&str> {
+        self.types.get(&extension.trim_start_matches('.').to_lowercase()).map(String::as_str)
+    }
+
+    /// Registers `media` for `extension`, replacing any previous type.
+    pub fn insert(&mut self, extension: &str, media: &str) {
+        self.types.insert(extension.trim_start_matches('.').to_lowercase(), media.to_lowercase());
+    }
+
+    pub fn extensions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.types.iter().map(|(extension, media)| (extension.as_str(), media.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// The `Content-Type` of `extension`: its type, or the default one, with the charset
+    /// for text types.
+    pub fn content_type(&self, extension: &str) -> String {
+        self.with_charset(self.get(extension).unwrap_or(&self.default_type))
+    }
+
+    /// `media` with the charset when it is a text type without parameters.
+    pub fn with_charset(&self, media: &str) -> String {
+        match &self.charset {
+            Some(charset) if Self::is_text(media) && !media.contains(';') => format!("{}; charset={}", media, charset),
+            _ => media.to_string(),
+        }
+    }
+
+    /// Whether `media` is text: `text/*`, JSON, XML and JavaScript.
+    pub fn is_text(media: &str) -> bool {
+        let media = media.split(';').next().unwrap_or("").trim().to_lowercase();
+        media.starts_with("text/")
+            || Self::TEXT_TYPES.contains(&media.as_str())
+            || media.ends_with("+json")
+            || media.ends_with("+xml")
+    }
+
+    /// Reads `/etc/mime.types` lines, `type ext1 ext2`, `#` starting a comment. Returns the
+    /// number of extensions read, the lines that are not a type are skipped.
+    pub fn load_types(&mut self, content: &str) -> usize {
+        let mut read = 0;
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut fields = line.split_whitespace();
+            let Some(media) = fields.next().filter(|media| Self::is_media_type(media)) else {
+                continue;
+            };
+            for extension in fields {
+                self.insert(extension, media);
+                read += 1;
+            }
+        }
+        read
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> io::Result<usize> {
+        Ok(self.load_types(&fs::read_to_string(path)?))
+    }
+
+    /// Whether `media` looks like `type/subtype`.
+    pub fn is_media_type(media: &str) -> bool {
+        let token = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c));
+        media.split_once('/').is_some_and(|(kind, subtype)| token(kind) && token(subtype))
+    }
+}
+
+/// The `[mime]` section, the registry is built from it when the server starts and reloads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MimeSettings {
+    /// `mime.types` files read over the built-in table, in order
+    pub files: Vec<PathBuf>,
+    /// extensions and their type, over the files
+    pub types: Vec<(String, String)>,
+    pub default_type: String,
+    pub charset: Option<String>,
+    pub sniff: bool,
+}
+
+impl Default for MimeSettings {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            types: Vec::new(),
+            default_type: MimeRegistry::DEFAULT_TYPE.to_string(),
+            charset: Some(MimeRegistry::DEFAULT_CHARSET.to_string()),
+            sniff: false,
+        }
+    }
+}
+
+impl MimeSettings {
+    /// The built-in table, then the files, then the types. Unreadable files are skipped.
+    pub fn build(&self) -> MimeRegistry {
+        let mut registry = MimeRegistry::default();
+        for file in &self.files {
+            match registry.load_file(file) {
+                Ok(read) => Logger::debug(format!("[Mime] {} type(s) read from {}", read, file.display()).as_str()),
+                Err(e) => Logger::warn(format!("[Mime] Unable to read {}: {}", file.display(), e).as_str()),
+            }
+        }
+        for (extension, media) in &self.types {
+            registry.insert(extension, media);
+        }
+        registry.default_type = self.default_type.clone();
+        registry.charset = self.charset.clone();
+        registry.sniff = self.sniff;
+        registry
+    }
+}
+
+/// Content sniffing: the type of a file from its magic bytes, for files without a known
+/// extension.
+pub struct Sniffer;
+
+impl Sniffer {
+    /// Bytes read from the start of a file.
+    pub const SAMPLE_SIZE: usize = 512;
+    /// Files remembered by `sniff_file`, the cache is emptied beyond.
+    pub const MAX_CACHED: usize = 4096;
+
+    const MAGIC: [(&'static [u8], &'static str); 10] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"PK\x05\x06", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"\0asm", "application/wasm"),
+    ];
+
+    /// The type of `sample`, the first bytes of a file: a known signature, `text/plain` for
+    /// UTF-8 text, `None` for other binary content.
+    pub fn detect(sample: &[u8]) -> Option<&'static str> {
+        if let Some((_, media)) = Self::MAGIC.iter().find(|(magic, _)| sample.starts_with(magic)) {
+            return Some(media);
+        }
+        Self::is_text(sample).then_some("text/plain")
+    }
+
+    /// Whether `sample` is UTF-8 without control characters other than whitespace and
+    /// escape. A character cut at the end of the sample does not count against it.
+    pub fn is_text(sample: &[u8]) -> bool {
+        let valid = match std::str::from_utf8(sample) {
+            Ok(text) => text,
+            Err(e) if e.error_len().is_none() => std::str::from_utf8(&sample[..e.valid_up_to()]).unwrap_or(""),
+            Err(_) => return false,
+        };
+        !valid.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+    }
+
+    /// `detect` on the start of the file at `path`. Results are kept by file until its
+    /// modification time or size change.
+    pub fn sniff_file(path: &Path) -> io::Result<Option<&'static str>> {
+        let metadata = fs::metadata(path)?;
+        let (modified, size) = (metadata.modified().ok(), metadata.len());
+        let cache = SNIFFED.get_or_init(|| Mutex::new(HashMap::new()));
+        if let Some((cached_modified, cached_size, media)) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(path) {
+            if *cached_modified == modified && *cached_size == size {
+                return Ok(*media);
+            }
+        }
+
+        let mut sample = Vec::with_capacity(Self::SAMPLE_SIZE);
+        File::open(path)?.take(Self::SAMPLE_SIZE as u64).read_to_end(&mut sample)?;
+        let media = Self::detect(&sample);
+        Logger::debug(format!("[Mime] Sniffed {} as {}", path.display(), media.unwrap_or("binary")).as_str());
+
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= Self::MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(path.to_path_buf(), (modified, size, media));
+        Ok(media)
+    }
+}
@@ -0,0 +1,217 @@
+use crate::core::server::location::LocationMatch;
+use crate::core::utils::keyval::KeyVal;
+use crate::core::utils::utils::Utils;
+use std::path::PathBuf;
+
+/// A change to the headers of an answer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderAction {
+    /// sets the header unless the answer already has it
+    Add(String, String),
+    /// sets the header, replacing any value
+    Set(String, String),
+    Remove(String),
+}
+
+impl HeaderAction {
+    /// Headers describing the body or the connection, the server keeps them to itself.
+    pub const RESERVED: [&'static str; 5] = ["Content-Length", "Transfer-Encoding", "Connection", "Content-Range", "Upgrade"];
+
+    /// Parses `"add X-Robots-Tag noindex"`, `"set Cache-Control no-store"` or `"remove X-Powered-By"`.
+    pub fn parse(directive: &str) -> Option<Self> {
+        let directive = directive.trim();
+        let (verb, rest) = directive.split_once(char::is_whitespace)?;
+        let rest = rest.trim_start();
+        let (name, value) = match rest.split_once(char::is_whitespace) {
+            Some((name, value)) => (name, value.trim()),
+            None => (rest, ""),
+        };
+        if !Utils::is_header_name(name) || value.contains(['\r', '\n']) {
+            return None;
+        }
+
+        match verb.to_lowercase().as_str() {
+            "add" if !value.is_empty() => Some(Self::Add(name.to_string(), value.to_string())),
+            "set" if !value.is_empty() => Some(Self::Set(name.to_string(), value.to_string())),
+            "remove" if value.is_empty() => Some(Self::Remove(name.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Add(name, _) | Self::Set(name, _) | Self::Remove(name) => name,
+        }
+    }
+
+    /// Applies the action to the headers of an answer, repeated headers like `Set-Cookie`
+    /// are replaced or removed together.
+    pub fn apply(&self, headers: &mut Vec<(String, String)>) {
+        let name = self.name();
+        let exists = headers.iter().any(|(key, _)| key.eq_ignore_ascii_case(name));
+        match self {
+            Self::Add(_, _) if exists => {}
+            Self::Add(name, value) => headers.push((name.clone(), value.clone())),
+            Self::Set(name, value) => {
+                headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+                headers.push((name.clone(), value.clone()));
+            }
+            Self::Remove(name) => headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name)),
+        }
+    }
+}
+
+/// What a `[headers]` rule applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderMatch {
+    /// a path prefix or a glob, as `[[location]]` blocks
+    Path(LocationMatch),
+    /// a media type of the answer, `text/html` or `image/*`
+    ContentType(String),
+}
+
+impl HeaderMatch {
+    /// A media type when `key` does not start with `/` and has one, a path otherwise.
+    pub fn new(key: &str) -> Self {
+        let is_media_type = !key.starts_with('/')
+            && key.split_once('/').is_some_and(|(kind, subtype)| {
+                !kind.is_empty() && !subtype.is_empty() && Utils::is_header_name(kind) && Utils::is_header_name(subtype)
+            });
+        match is_media_type {
+            true => Self::ContentType(key.to_lowercase()),
+            false => Self::Path(LocationMatch::new(key)),
+        }
+    }
+
+    pub fn matches(&self, path: &str, content_type: Option<&str>) -> bool {
+        match self {
+            Self::Path(matcher) => matcher.matches(path),
+            Self::ContentType(pattern) => content_type.is_some_and(|content_type| {
+                let media = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+                match pattern.strip_suffix("/*") {
+                    Some(kind) => media.split_once('/').is_some_and(|(other, _)| other == kind),
+                    None => media == *pattern,
+                }
+            }),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Path(matcher) => matcher.as_str(),
+            Self::ContentType(media) => media,
+        }
+    }
+
+    // prefixes from the shortest, globs, then media types with wildcards first
+    pub(crate) fn precedence(&self) -> (u8, usize) {
+        match self {
+            Self::Path(LocationMatch::Prefix(prefix)) => (0, prefix.trim_end_matches('/').len()),
+            Self::Path(LocationMatch::Glob(_)) => (1, 0),
+            Self::ContentType(media) if media.ends_with("/*") => (2, 0),
+            Self::ContentType(_) => (3, 0),
+        }
+    }
+}
+
+/// A key of the `[headers]` section and its directives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderRule {
+    pub matcher: HeaderMatch,
+    pub actions: Vec<HeaderAction>,
+}
+
+impl HeaderRule {
+    pub fn new(key: &str, actions: Vec<HeaderAction>) -> Self {
+        Self {
+            matcher: HeaderMatch::new(key),
+            actions,
+        }
+    }
+
+    /// The `security = true` preset, for every path. Answers keep the values they already
+    /// have, later rules may replace or remove them.
+    pub fn security() -> Self {
+        let actions = ResponseHeaders::SECURITY
+            .iter()
+            .map(|(name, value)| HeaderAction::Add(name.to_string(), value.to_string()))
+            .collect();
+        Self::new("/", actions)
+    }
+
+    /// `server = false` removes the `Server` header, `server = "name"` replaces it.
+    pub fn server(name: Option<&str>) -> Self {
+        let action = match name {
+            Some(name) => HeaderAction::Set("Server".to_string(), name.to_string()),
+            None => HeaderAction::Remove("Server".to_string()),
+        };
+        Self::new("/", vec![action])
+    }
+
+    /// Orders `rules` as they are applied, see `ResponseHeaders::apply`. Rules of the same
+    /// kind keep their order.
+    pub fn sort(rules: &mut [HeaderRule]) {
+        rules.sort_by_key(|rule| rule.matcher.precedence());
+    }
+}
+
+pub struct ResponseHeaders;
+
+impl ResponseHeaders {
+    /// Headers of the `security = true` preset. HSTS only has an effect over HTTPS, when
+    /// Katana runs behind a TLS proxy. The built-in pages use inline styles and scripts.
+    pub const SECURITY: [(&'static str, &'static str); 6] = [
+        ("Strict-Transport-Security", "max-age=31536000"),
+        (
+            "Content-Security-Policy",
+            "default-src 'self'; script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; object-src 'none'; base-uri 'self'; frame-ancestors 'self'",
+        ),
+        ("X-Content-Type-Options", "nosniff"),
+        ("Referrer-Policy", "strict-origin-when-cross-origin"),
+        ("Permissions-Policy", "camera=(), microphone=(), geolocation=()"),
+        ("X-Frame-Options", "SAMEORIGIN"),
+    ];
+
+    /// Applies the rules matching `path` and the `Content-Type` of the answer, in the
+    /// order of `HeaderRule::sort`: path prefixes from the shortest, globs, then media types.
+    pub fn apply(rules: &[HeaderRule], path: &str, headers: &mut KeyVal) {
+        if rules.is_empty() {
+            return;
+        }
+        let mut list: Vec<(String, String)> = headers.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+        Self::apply_list(rules, path, &mut list);
+        headers.clear();
+        for (key, value) in list {
+            headers.add(key, value);
+        }
+    }
+
+    /// `apply` for the headers of a backend answer, as the reverse proxy and CGI relay them.
+    pub fn apply_list(rules: &[HeaderRule], path: &str, headers: &mut Vec<(String, String)>) {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        let normalized = normalized.to_string_lossy();
+        let content_type = headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Content-Type"))
+            .map(|(_, value)| value.clone());
+
+        for rule in rules.iter().filter(|rule| rule.matcher.matches(&normalized, content_type.as_deref())) {
+            for action in &rule.actions {
+                action.apply(headers);
+            }
+        }
+    }
+
+    /// The spelling of `name` used by `headers`, header names ignore case.
+    pub fn find(headers: &KeyVal, name: &str) -> Option<String> {
+        headers.iter().map(|(key, _)| key).find(|key| key.eq_ignore_ascii_case(name)).cloned()
+    }
+
+    /// Sets `name`, replacing the value of any spelling of it.
+    pub fn set(headers: &mut KeyVal, name: &str, value: &str) {
+        if let Some(existing) = Self::find(headers, name) {
+            headers.del(&existing);
+        }
+        headers.add(name.to_string(), value.to_string());
+    }
+}
@@ -106,6 +106,73 @@ impl HttpStatus {
         *self as u16
     }
 
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            100 => Some(Self::Continue),
+            101 => Some(Self::SwitchingProtocols),
+            102 => Some(Self::Processing),
+            103 => Some(Self::EarlyHints),
+            200 => Some(Self::Ok),
+            201 => Some(Self::Created),
+            202 => Some(Self::Accepted),
+            203 => Some(Self::NonAuthoritativeInformation),
+            204 => Some(Self::NoContent),
+            205 => Some(Self::ResetContent),
+            206 => Some(Self::PartialContent),
+            207 => Some(Self::MultiStatus),
+            208 => Some(Self::AlreadyReported),
+            226 => Some(Self::IMUsed),
+            301 => Some(Self::MovedPermanently),
+            302 => Some(Self::Found),
+            303 => Some(Self::SeeOther),
+            304 => Some(Self::NotModified),
+            305 => Some(Self::UseProxy),
+            307 => Some(Self::TemporaryRedirect),
+            308 => Some(Self::PermanentRedirect),
+            400 => Some(Self::BadRequest),
+            401 => Some(Self::Unauthorized),
+            402 => Some(Self::PaymentRequired),
+            403 => Some(Self::Forbidden),
+            404 => Some(Self::NotFound),
+            405 => Some(Self::MethodNotAllowed),
+            406 => Some(Self::NotAcceptable),
+            407 => Some(Self::ProxyAuthenticationRequired),
+            408 => Some(Self::RequestTimeout),
+            409 => Some(Self::Conflict),
+            410 => Some(Self::Gone),
+            411 => Some(Self::LengthRequired),
+            412 => Some(Self::PreconditionFailed),
+            413 => Some(Self::PayloadTooLarge),
+            414 => Some(Self::URITooLong),
+            415 => Some(Self::UnsupportedMediaType),
+            416 => Some(Self::RangeNotSatisfiable),
+            417 => Some(Self::ExpectationFailed),
+            418 => Some(Self::ImATeapot),
+            421 => Some(Self::MisdirectedRequest),
+            422 => Some(Self::UnprocessableEntity),
+            423 => Some(Self::Locked),
+            424 => Some(Self::FailedDependency),
+            425 => Some(Self::TooEarly),
+            426 => Some(Self::UpgradeRequired),
+            428 => Some(Self::PreconditionRequired),
+            429 => Some(Self::TooManyRequests),
+            431 => Some(Self::RequestHeaderFieldsTooLarge),
+            451 => Some(Self::UnavailableForLegalReasons),
+            500 => Some(Self::InternalServerError),
+            501 => Some(Self::NotImplemented),
+            502 => Some(Self::BadGateway),
+            503 => Some(Self::ServiceUnavailable),
+            504 => Some(Self::GatewayTimeout),
+            505 => Some(Self::HTTPVersionNotSupported),
+            506 => Some(Self::VariantAlsoNegotiates),
+            507 => Some(Self::InsufficientStorage),
+            508 => Some(Self::LoopDetected),
+            510 => Some(Self::NotExtended),
+            511 => Some(Self::NetworkAuthenticationRequired),
+            _ => None,
+        }
+    }
+
     pub fn to_message(&self) -> &str {
         match self {
             // Informational responses (100–199)
@@ -193,6 +260,14 @@ pub enum HttpMethod {
     OPTIONS,
     TRACE,
     PATCH,
+    // WebDAV, @see: https://www.rfc-editor.org/rfc/rfc4918#section-9
+    PROPFIND,
+    PROPPATCH,
+    MKCOL,
+    COPY,
+    MOVE,
+    LOCK,
+    UNLOCK,
 }
 
 impl HttpMethod {
@@ -207,6 +282,13 @@ impl HttpMethod {
             Self::OPTIONS => "OPTIONS",
             Self::TRACE => "TRACE",
             Self::PATCH => "PATCH",
+            Self::PROPFIND => "PROPFIND",
+            Self::PROPPATCH => "PROPPATCH",
+            Self::MKCOL => "MKCOL",
+            Self::COPY => "COPY",
+            Self::MOVE => "MOVE",
+            Self::LOCK => "LOCK",
+            Self::UNLOCK => "UNLOCK",
         }
     }
 
@@ -221,6 +303,13 @@ impl HttpMethod {
             "OPTIONS" => Some(Self::OPTIONS),
             "TRACE" => Some(Self::TRACE),
             "PATCH" => Some(Self::PATCH),
+            "PROPFIND" => Some(Self::PROPFIND),
+            "PROPPATCH" => Some(Self::PROPPATCH),
+            "MKCOL" => Some(Self::MKCOL),
+            "COPY" => Some(Self::COPY),
+            "MOVE" => Some(Self::MOVE),
+            "LOCK" => Some(Self::LOCK),
+            "UNLOCK" => Some(Self::UNLOCK),
             _ => None, // none for unsupported version
         }
     }
@@ -236,11 +325,18 @@ impl HttpMethod {
             Self::OPTIONS,
             Self::TRACE,
             Self::PATCH,
+            Self::PROPFIND,
+            Self::PROPPATCH,
+            Self::MKCOL,
+            Self::COPY,
+            Self::MOVE,
+            Self::LOCK,
+            Self::UNLOCK,
         ]
     }
 
     pub fn immutable() -> Vec<Self> {
-        vec![Self::GET, Self::HEAD, Self::OPTIONS, Self::TRACE]
+        vec![Self::GET, Self::HEAD, Self::OPTIONS, Self::TRACE, Self::PROPFIND]
     }
 
     pub fn mutable() -> Vec<Self> {
@@ -250,9 +346,39 @@ impl HttpMethod {
             Self::DELETE,
             Self::PATCH,
             Self::CONNECT,
+            Self::PROPPATCH,
+            Self::MKCOL,
+            Self::COPY,
+            Self::MOVE,
+            Self::LOCK,
+            Self::UNLOCK,
         ]
     }
 
+    /// Methods read by a WebDAV client besides the plain HTTP ones.
+    pub fn webdav_read() -> Vec<Self> {
+        vec![Self::PROPFIND]
+    }
+
+    /// Methods changing the document root through WebDAV.
+    pub fn webdav_write() -> Vec<Self> {
+        vec![
+            Self::PUT,
+            Self::DELETE,
+            Self::PROPPATCH,
+            Self::MKCOL,
+            Self::COPY,
+            Self::MOVE,
+            Self::LOCK,
+            Self::UNLOCK,
+        ]
+    }
+
+    /// Methods accepted on the writable paths of the `[upload]` section.
+    pub fn upload() -> Vec<Self> {
+        vec![Self::PUT, Self::POST]
+    }
+
     pub fn comma_separated(methods: &[HttpMethod]) -> String {
         methods
             .iter()
@@ -0,0 +1,211 @@
+use crate::core::server::request::Request;
+use crate::core::server::websocket::{Message, WebSocketHandler, WebSocketSender};
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Modification time and size of every file under the document root.
+pub type Snapshot = HashMap<PathBuf, (SystemTime, u64)>;
+
+/// Development mode: watches the document root and tells the connected browsers to reload.
+///
+/// Browsers connect to `PATH` through the script injected in HTML pages, they receive
+/// `reload`, or `css` followed by one changed stylesheet path per line when only
+/// stylesheets changed, those are swapped without reloading the page.
+#[derive(Clone, Default)]
+pub struct LiveReload {
+    clients: Arc<Mutex<Vec<WebSocketSender>>>,
+}
+
+impl LiveReload {
+    pub const PATH: &'static str = "/__katana/livereload";
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+    // stop scanning huge trees, a dev server is not meant to watch a whole disk
+    const MAX_FILES: usize = 50_000;
+    const SCRIPT: &'static str = r#"<script>
+(function () {
+  var url = (location.protocol === "https:" ? "wss://" : "ws://") + location.host + "__PATH__";
+  var retry = 0;
+  function swap(paths) {
+    var links = document.querySelectorAll('link[rel="stylesheet"]');
+    var swapped = false;
+    links.forEach(function (link) {
+      var href = new URL(link.href, location.href);
+      if (href.origin === location.origin && paths.indexOf(href.pathname) !== -1) {
+        href.searchParams.set("livereload", Date.now());
+        link.href = href.toString();
+        swapped = true;
+      }
+    });
+    if (!swapped) location.reload();
+  }
+  function connect() {
+    var socket = new WebSocket(url);
+    socket.onopen = function () {
+      // the server came back after a restart, pages may be outdated
+      if (retry > 0) location.reload();
+      retry = 0;
+    };
+    socket.onmessage = function (event) {
+      var lines = String(event.data).split("\n");
+      if (lines[0] === "css") swap(lines.slice(1)); else location.reload();
+    };
+    socket.onclose = function () {
+      retry = Math.min(retry + 1, 10);
+      setTimeout(connect, retry * 500);
+    };
+  }
+  connect();
+})();
+</script>
+"#;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The client script, to be placed in every served HTML page.
+    pub fn script() -> String {
+        Self::SCRIPT.replace("__PATH__", Self::PATH)
+    }
+
+    /// Polls `root` in a background thread and notifies the browsers on every change.
+    pub fn watch(&self, root: &Path) {
+        let root = root.to_path_buf();
+        let live_reload = self.clone();
+
+        thread::spawn(move || {
+            let mut previous = Self::snapshot(&root);
+            Logger::info(format!("[LiveReload] Watching {} files in {}", previous.len(), root.display()).as_str());
+
+            loop {
+                thread::sleep(Self::POLL_INTERVAL);
+                let current = Self::snapshot(&root);
+                let changes = Self::changes(&previous, &current);
+                previous = current;
+
+                if changes.is_empty() {
+                    continue;
+                }
+                let message = Self::message(&root, &changes);
+                Logger::info(
+                    format!(
+                        "[LiveReload] {} file(s) changed, notifying {} browser(s)",
+                        changes.len(),
+                        live_reload.clients()
+                    )
+                    .as_str(),
+                );
+                live_reload.broadcast(&message);
+            }
+        });
+    }
+
+    /// Every visible file under `root`, hidden files and folders are skipped like when serving.
+    pub fn snapshot(root: &Path) -> Snapshot {
+        let mut snapshot = Snapshot::new();
+        let mut folders = vec![root.to_path_buf()];
+
+        while let Some(folder) = folders.pop() {
+            let entries = match fs::read_dir(&folder) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                if metadata.is_dir() {
+                    folders.push(entry.path());
+                } else if snapshot.len() < Self::MAX_FILES {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    snapshot.insert(entry.path(), (modified, metadata.len()));
+                }
+            }
+        }
+
+        snapshot
+    }
+
+    /// Files added, modified or removed between two snapshots, sorted.
+    pub fn changes(previous: &Snapshot, current: &Snapshot) -> Vec<PathBuf> {
+        let mut changes: Vec<PathBuf> = current
+            .iter()
+            .filter(|(path, state)| previous.get(*path) != Some(*state))
+            .map(|(path, _)| path.clone())
+            .chain(previous.keys().filter(|path| !current.contains_key(*path)).cloned())
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    /// `css` and the URL paths of the stylesheets when nothing else changed, `reload` otherwise.
+    pub fn message(root: &Path, changes: &[PathBuf]) -> String {
+        let stylesheets_only = !changes.is_empty()
+            && changes
+                .iter()
+                .all(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("css")));
+        if !stylesheets_only {
+            return "reload".to_string();
+        }
+
+        let mut lines = vec!["css".to_string()];
+        for path in changes {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let segments: Vec<String> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+            // as the browser reports link pathnames
+            lines.push(Utils::encode_path(&format!("/{}", segments.join("/"))));
+        }
+        lines.join("\n")
+    }
+
+    /// Sends `message` to every connected browser, forgetting the ones that went away.
+    pub fn broadcast(&self, message: &str) {
+        let mut clients = match self.clients.lock() {
+            Ok(clients) => clients,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        clients.retain(|client| !client.is_closed() && client.send_text(message).is_ok());
+    }
+
+    /// Number of connected browsers.
+    pub fn clients(&self) -> usize {
+        match self.clients.lock() {
+            Ok(clients) => clients.len(),
+            Err(poisoned) => poisoned.into_inner().len(),
+        }
+    }
+}
+
+impl WebSocketHandler for LiveReload {
+    fn on_open(&self, sender: &WebSocketSender, _request: &Request) {
+        let mut clients = match self.clients.lock() {
+            Ok(clients) => clients,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        clients.push(sender.clone());
+    }
+
+    // browsers only listen
+    fn on_message(&self, _sender: &WebSocketSender, _message: Message) {}
+
+    fn on_close(&self, sender: &WebSocketSender) {
+        let mut clients = match self.clients.lock() {
+            Ok(clients) => clients,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        clients.retain(|client| !client.same_connection(sender));
+    }
+}
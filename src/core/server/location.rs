@@ -0,0 +1,247 @@
+use crate::core::server::auth::AuthRule;
+use crate::core::server::headers::ResponseHeaders;
+use crate::core::server::http::{HttpMethod, HttpStatus};
+use crate::core::utils::keyval::KeyVal;
+use crate::core::utils::utils::Utils;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// How a `[[location]]` block picks the requests it applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocationMatch {
+    /// every path under `/docs`, on segment boundaries
+    Prefix(String),
+    /// `*.pdf` matches file names, `/docs/**/*.md` whole paths
+    Glob(String),
+}
+
+impl LocationMatch {
+    /// A glob when `path` has `*`, `?` or `[`, a prefix otherwise.
+    pub fn new(path: &str) -> Self {
+        match path.contains(['*', '?', '[']) {
+            true => Self::Glob(path.to_string()),
+            false => Self::Prefix(path.to_string()),
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Prefix(prefix) => Utils::path_matches_prefix(path, prefix),
+            // without a slash, the pattern is about the file name
+            Self::Glob(pattern) if !pattern.contains('/') => {
+                let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+                Location::glob_matches(pattern, name)
+            }
+            Self::Glob(pattern) => Location::glob_matches(pattern, path),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Prefix(path) | Self::Glob(path) => path,
+        }
+    }
+}
+
+/// Authentication of a location, on top of the `[auth]` rules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocationAuth {
+    /// no password, even under an `[auth]` prefix
+    Off,
+    Required(AuthRule),
+}
+
+/// A `[[location]]` block, the settings it leaves out keep their value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationRule {
+    pub matcher: LocationMatch,
+    pub headers: Vec<(String, String)>,
+    pub cache_control: Option<String>,
+    pub directory_listing: Option<bool>,
+    pub index: Option<Vec<String>>,
+    pub methods: Option<Vec<HttpMethod>>,
+    pub auth: Option<LocationAuth>,
+    /// status code and page, a path of the document root
+    pub error_pages: Vec<(u16, String)>,
+}
+
+impl LocationRule {
+    pub fn new(path: &str) -> Self {
+        Self {
+            matcher: LocationMatch::new(path),
+            headers: Vec::new(),
+            cache_control: None,
+            directory_listing: None,
+            index: None,
+            methods: None,
+            auth: None,
+            error_pages: Vec::new(),
+        }
+    }
+
+    // prefixes from the shortest, then globs in file order
+    fn precedence(&self) -> (u8, usize) {
+        match &self.matcher {
+            LocationMatch::Prefix(prefix) => (0, prefix.trim_end_matches('/').len()),
+            LocationMatch::Glob(_) => (1, 0),
+        }
+    }
+}
+
+/// The settings of one request: the blocks matching its path applied over the defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub headers: Vec<(String, String)>,
+    pub cache_control: Option<String>,
+    pub directory_listing: bool,
+    /// files tried in order when a folder is requested
+    pub index: Vec<String>,
+    /// `None` leaves the methods to the enabled features
+    pub methods: Option<Vec<HttpMethod>>,
+    /// `None` leaves the authentication to the `[auth]` rules
+    pub auth: Option<LocationAuth>,
+    pub error_pages: HashMap<u16, String>,
+    /// paths of the blocks applied, in order
+    pub matched: Vec<String>,
+}
+
+impl Default for Location {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            cache_control: None,
+            directory_listing: true,
+            index: vec![Self::DEFAULT_INDEX.to_string()],
+            methods: None,
+            auth: None,
+            error_pages: HashMap::new(),
+            matched: Vec::new(),
+        }
+    }
+}
+
+impl Location {
+    pub const DEFAULT_INDEX: &'static str = "index.html";
+
+    /// Applies every block matching `path` from the least to the most specific: prefix
+    /// blocks by length, then glob blocks in file order. A later block overrides the
+    /// settings it sets, headers are merged by name.
+    pub fn resolve(rules: &[LocationRule], path: &str) -> Self {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        let normalized = normalized.to_string_lossy();
+
+        let mut matching: Vec<&LocationRule> = rules.iter().filter(|rule| rule.matcher.matches(&normalized)).collect();
+        // stable, globs keep the order of the file
+        matching.sort_by_key(|rule| rule.precedence());
+
+        let mut location = Self::default();
+        for rule in matching {
+            for (name, value) in &rule.headers {
+                location.headers.retain(|(other, _)| !other.eq_ignore_ascii_case(name));
+                location.headers.push((name.clone(), value.clone()));
+            }
+            if let Some(cache_control) = &rule.cache_control {
+                location.cache_control = Some(cache_control.clone());
+            }
+            if let Some(directory_listing) = rule.directory_listing {
+                location.directory_listing = directory_listing;
+            }
+            if let Some(index) = &rule.index {
+                location.index = index.clone();
+            }
+            if let Some(methods) = &rule.methods {
+                location.methods = Some(methods.clone());
+            }
+            if let Some(auth) = &rule.auth {
+                location.auth = Some(auth.clone());
+            }
+            for (code, page) in &rule.error_pages {
+                location.error_pages.insert(*code, page.clone());
+            }
+            location.matched.push(rule.matcher.as_str().to_string());
+        }
+        location
+    }
+
+    /// Whether the location lets `method` through, HEAD goes with GET.
+    pub fn allows(&self, method: &HttpMethod) -> bool {
+        match &self.methods {
+            Some(methods) => {
+                methods.contains(method) || (*method == HttpMethod::HEAD && methods.contains(&HttpMethod::GET))
+            }
+            None => true,
+        }
+    }
+
+    /// Adds the headers of the location to an answer, its `cache_control` replaces the
+    /// `[cache]` policy, `Expires` included. Like that policy, it only goes with successful
+    /// and not modified answers: caches must not keep challenges and error pages.
+    pub fn add_headers(&self, status: &HttpStatus, headers: &mut KeyVal) {
+        let code = status.to_code();
+        if let Some(cache_control) = self.cache_control.as_ref().filter(|_| (200..300).contains(&code) || code == 304) {
+            if let Some(expires) = ResponseHeaders::find(headers, "Expires") {
+                headers.del(&expires);
+            }
+            ResponseHeaders::set(headers, "Cache-Control", cache_control);
+        }
+        for (name, value) in &self.headers {
+            ResponseHeaders::set(headers, name, value);
+        }
+    }
+
+    /// Shell-like matching: `?` is one character, `*` any run of characters but `/`, `**`
+    /// any run including `/`, `[abc]` and `[a-z]` one character of the set.
+    pub fn glob_matches(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        Self::glob(&pattern, &text)
+    }
+
+    fn glob(pattern: &[char], text: &[char]) -> bool {
+        match pattern.first() {
+            None => text.is_empty(),
+            Some('*') if pattern.get(1) == Some(&'*') => {
+                // `/**/` also matches a single slash
+                let rest = &pattern[2..];
+                let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+                (0..=text.len()).any(|i| Self::glob(rest, &text[i..]) || Self::glob(rest_after_slash, &text[i..]))
+            }
+            Some('*') => {
+                let rest = &pattern[1..];
+                for i in 0..=text.len() {
+                    if Self::glob(rest, &text[i..]) {
+                        return true;
+                    }
+                    if text.get(i) == Some(&'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            Some('?') => text.first().is_some_and(|c| *c != '/') && Self::glob(&pattern[1..], &text[1..]),
+            Some('[') => match (pattern.iter().position(|c| *c == ']'), text.first()) {
+                (Some(end), Some(c)) if end > 1 => {
+                    let set = &pattern[1..end];
+                    let mut found = false;
+                    let mut i = 0;
+                    while i < set.len() {
+                        match (set.get(i + 1), set.get(i + 2)) {
+                            (Some('-'), Some(last)) => {
+                                found |= (set[i]..=*last).contains(c);
+                                i += 3;
+                            }
+                            _ => {
+                                found |= set[i] == *c;
+                                i += 1;
+                            }
+                        }
+                    }
+                    found && Self::glob(&pattern[end + 1..], &text[1..])
+                }
+                // a lone `[` is literal
+                _ => text.first() == Some(&'[') && Self::glob(&pattern[1..], &text[1..]),
+            },
+            Some(c) => text.first() == Some(c) && Self::glob(&pattern[1..], &text[1..]),
+        }
+    }
+}
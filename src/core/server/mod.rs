@@ -1,5 +1,25 @@
+pub mod access;
+pub mod archive;
+pub mod auth;
+pub mod cache;
+pub mod cgi;
+pub mod chunked;
+pub mod connection;
+pub mod fastcgi;
 pub mod filetype;
+pub mod headers;
+pub mod livereload;
+pub mod location;
+pub mod multipart;
+pub mod proxy;
 pub mod http;
+pub mod ratelimit;
+pub mod reload;
 pub mod server;
+pub mod spa;
+pub mod sse;
+pub mod upload;
+pub mod webdav;
+pub mod websocket;
 pub mod request;
 pub mod response;
@@ -0,0 +1,232 @@
+use std::io::{self, Error, ErrorKind, Read};
+
+// @see: https://www.rfc-editor.org/rfc/rfc7578
+// @see: https://www.rfc-editor.org/rfc/rfc2046#section-5.1.1
+
+/// Headers of one `multipart/form-data` part.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MultipartPart {
+    pub name: String,
+    /// set for file inputs, may be empty when no file was chosen
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+}
+
+pub struct Multipart;
+
+impl Multipart {
+    /// Extracts the boundary of a `multipart/form-data` content type.
+    pub fn boundary(content_type: &str) -> Option<String> {
+        let (mime, parameters) = content_type.split_once(';')?;
+        if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+            return None;
+        }
+
+        let boundary = Self::parameters(parameters)
+            .into_iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| value)?;
+        // @see: https://www.rfc-editor.org/rfc/rfc2046#section-5.1.1, 1 to 70 characters
+        if boundary.is_empty() || boundary.len() > 70 {
+            return None;
+        }
+        Some(boundary)
+    }
+
+    /// `key=value` and `key="quoted value"` pairs separated by semicolons, as in
+    /// `Content-Type` and `Content-Disposition` headers.
+    pub fn parameters(input: &str) -> Vec<(String, String)> {
+        let mut parameters = Vec::new();
+        let mut chars = input.chars().peekable();
+
+        loop {
+            let mut key = String::new();
+            for c in chars.by_ref() {
+                if c == '=' {
+                    break;
+                }
+                key.push(c);
+            }
+            let key = key.trim_matches(|c: char| c == ';' || c.is_whitespace()).to_string();
+            if key.is_empty() {
+                return parameters;
+            }
+
+            let mut value = String::new();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        _ => value.push(c),
+                    }
+                }
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                }
+            } else {
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                    value.push(c);
+                }
+                value = value.trim().to_string();
+            }
+
+            parameters.push((key, value));
+        }
+    }
+}
+
+/// Streams the parts of a `multipart/form-data` body.
+///
+/// `next_part` moves to the next part and returns its headers, the part content is then
+/// read through the `Read` implementation, which stops at the next boundary.
+pub struct MultipartReader<R: Read> {
+    inner: R,
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> MultipartReader<R> {
+    const READ_SIZE: usize = 8 * 1024;
+    const MAX_HEADERS: usize = 8 * 1024;
+
+    pub fn new(inner: R, boundary: &str) -> Self {
+        Self {
+            inner,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            // the first boundary is not preceded by a line break, pretend it is
+            buffer: b"\r\n".to_vec(),
+            done: false,
+        }
+    }
+
+    /// Skips what is left of the current part and reads the headers of the next one,
+    /// `None` after the closing boundary.
+    pub fn next_part(&mut self) -> io::Result<Option<MultipartPart>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        // the preamble, or the rest of the current part
+        io::copy(self, &mut io::sink())?;
+
+        self.fill_to(self.delimiter.len() + 2)?;
+        self.buffer.drain(..self.delimiter.len());
+        if self.buffer.starts_with(b"--") {
+            self.done = true;
+            return Ok(None);
+        }
+
+        // rest of the boundary line, only transport padding is allowed
+        if !self.read_line()?.trim().is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "malformed multipart boundary"));
+        }
+
+        let mut part = MultipartPart::default();
+        let mut headers_size = 0;
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                break;
+            }
+            headers_size += line.len();
+            if headers_size > Self::MAX_HEADERS {
+                return Err(Error::new(ErrorKind::InvalidData, "multipart headers too large"));
+            }
+
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            if key.eq_ignore_ascii_case("Content-Disposition") {
+                for (key, value) in Multipart::parameters(value.split_once(';').map(|(_, rest)| rest).unwrap_or("")) {
+                    match key.to_lowercase().as_str() {
+                        "name" => part.name = value,
+                        "filename" => part.filename = Some(value),
+                        _ => {}
+                    }
+                }
+            } else if key.eq_ignore_ascii_case("Content-Type") {
+                part.content_type = Some(value.to_string());
+            }
+        }
+
+        Ok(Some(part))
+    }
+
+    // reads more data from the body, returns the number of new bytes
+    fn fill(&mut self) -> io::Result<usize> {
+        let start = self.buffer.len();
+        self.buffer.resize(start + Self::READ_SIZE, 0);
+        let read = self.inner.read(&mut self.buffer[start..]);
+        self.buffer.truncate(start + *read.as_ref().unwrap_or(&0));
+        read
+    }
+
+    fn fill_to(&mut self, size: usize) -> io::Result<()> {
+        while self.buffer.len() < size {
+            if self.fill()? == 0 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "truncated multipart body"));
+            }
+        }
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        loop {
+            if let Some(end) = Self::find(&self.buffer, b"\r\n") {
+                let line = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+                self.buffer.drain(..end + 2);
+                return Ok(line);
+            }
+            if self.buffer.len() > Self::MAX_HEADERS {
+                return Err(Error::new(ErrorKind::InvalidData, "multipart header line too long"));
+            }
+            if self.fill()? == 0 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "truncated multipart body"));
+            }
+        }
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|window| window == needle)
+    }
+}
+
+impl<R: Read> Read for MultipartReader<R> {
+    /// Reads the content of the current part, returns 0 once its boundary is reached.
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.done || out.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if let Some(index) = Self::find(&self.buffer, &self.delimiter) {
+                let size = index.min(out.len());
+                out[..size].copy_from_slice(&self.buffer[..size]);
+                self.buffer.drain(..size);
+                return Ok(size);
+            }
+
+            // the end of the buffer may be the start of a boundary, keep it for the next round
+            let safe = self.buffer.len().saturating_sub(self.delimiter.len() - 1);
+            if safe > 0 {
+                let size = safe.min(out.len());
+                out[..size].copy_from_slice(&self.buffer[..size]);
+                self.buffer.drain(..size);
+                return Ok(size);
+            }
+
+            if self.fill()? == 0 {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "truncated multipart body"));
+            }
+        }
+    }
+}
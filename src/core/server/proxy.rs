@@ -0,0 +1,484 @@
+use crate::core::server::chunked::{ChunkedReader, ChunkedWriter};
+use crate::core::server::headers::{HeaderRule, ResponseHeaders};
+use crate::core::server::http::{HttpMethod, HttpStatus, HttpVersion};
+use crate::core::server::request::Request;
+use crate::core::server::response::Response;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Cursor, Error, ErrorKind, Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// @see: https://www.rfc-editor.org/rfc/rfc9110#section-7.6
+// @see: https://www.rfc-editor.org/rfc/rfc7239
+
+/// Where proxied requests go, `http://host:port/base` or `unix:/path/to.sock`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Upstream {
+    Http { host: String, port: u16, path: String },
+    Unix(PathBuf),
+}
+
+impl Upstream {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(socket) = value.strip_prefix("unix:") {
+            let socket = socket.trim_start_matches("//");
+            return (!socket.is_empty()).then(|| Self::Unix(PathBuf::from(socket)));
+        }
+
+        // no TLS client, only plain HTTP upstreams
+        let rest = value.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], rest[index..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            // an IPv6 address without port, "[::1]"
+            Some((host, port)) if !port.ends_with(']') => (host, port.parse().ok()?),
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return None;
+        }
+
+        Some(Self::Http {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// Value of the `Host` header sent upstream.
+    pub fn authority(&self) -> String {
+        match self {
+            Self::Http { host, port: 80, .. } => host.clone(),
+            Self::Http { host, port, .. } => format!("{}:{}", host, port),
+            Self::Unix(_) => "localhost".to_string(),
+        }
+    }
+
+    fn base_path(&self) -> &str {
+        match self {
+            Self::Http { path, .. } => path,
+            Self::Unix(_) => "",
+        }
+    }
+
+    fn connect(&self, timeout: Duration) -> io::Result<Box<dyn Connection>> {
+        match self {
+            Self::Http { host, port, .. } => {
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                let mut last_error = Error::new(ErrorKind::NotFound, "upstream host not found");
+                for address in (host, *port).to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&address, timeout) {
+                        Ok(stream) => {
+                            stream.set_read_timeout(Some(timeout))?;
+                            stream.set_write_timeout(Some(timeout))?;
+                            return Ok(Box::new(stream));
+                        }
+                        Err(e) => last_error = e,
+                    }
+                }
+                Err(last_error)
+            }
+            #[cfg(unix)]
+            Self::Unix(socket) => {
+                let stream = UnixStream::connect(socket)?;
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(not(unix))]
+            Self::Unix(_) => Err(Error::new(ErrorKind::Unsupported, "unix sockets are not available")),
+        }
+    }
+}
+
+impl fmt::Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http { path, .. } => write!(f, "http://{}{}", self.authority(), path),
+            Self::Unix(socket) => write!(f, "unix:{}", socket.display()),
+        }
+    }
+}
+
+trait Connection: Read + Write {}
+
+impl<T: Read + Write> Connection for T {}
+
+/// A path prefix forwarded to an upstream server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyRule {
+    pub prefix: String,
+    pub upstream: Upstream,
+    /// serve existing local files, proxy the other paths
+    pub try_files: bool,
+    /// remove the prefix from the forwarded path, `/api/users` becomes `/users`
+    pub strip_prefix: bool,
+    /// limit for connecting and for every read or write on the upstream connection
+    pub timeout: Duration,
+}
+
+impl ProxyRule {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(prefix: &str, upstream: Upstream) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            upstream,
+            try_files: false,
+            strip_prefix: false,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Applies one `try_files on`, `strip_prefix on` or `timeout 10` (seconds) option.
+    pub fn set(&mut self, option: &str) -> bool {
+        let (key, value) = match option.trim().split_once(char::is_whitespace) {
+            Some((key, value)) => (key, value.trim().to_lowercase()),
+            None => (option.trim(), "on".to_string()),
+        };
+        let switch = match value.as_str() {
+            "on" | "true" => Some(true),
+            "off" | "false" => Some(false),
+            _ => None,
+        };
+
+        match (key.to_lowercase().as_str(), switch) {
+            ("try_files", Some(switch)) => self.try_files = switch,
+            ("strip_prefix", Some(switch)) => self.strip_prefix = switch,
+            ("timeout", None) => match value.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => self.timeout = Duration::from_secs(seconds),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+pub struct Proxy<'a> {
+    rule: &'a ProxyRule,
+}
+
+impl<'a> Proxy<'a> {
+    const MAX_HEAD: usize = 64 * 1024;
+    // @see: https://www.rfc-editor.org/rfc/rfc9110#section-7.6.1
+    const HOP_BY_HOP: &'static [&'static str] = &[
+        "connection",
+        "keep-alive",
+        "proxy-connection",
+        "proxy-authenticate",
+        "proxy-authorization",
+        "te",
+        "trailer",
+        "transfer-encoding",
+        "upgrade",
+    ];
+
+    pub fn new(rule: &'a ProxyRule) -> Self {
+        Self { rule }
+    }
+
+    /// Returns the rule with the longest prefix covering `path`, if any.
+    pub fn rule_for<'r>(rules: &'r [ProxyRule], path: &str) -> Option<&'r ProxyRule> {
+        Utils::longest_prefix(rules, path, |rule| &rule.prefix)
+    }
+
+    /// Whether `path` names a file or folder of the document root, for `try_files`.
+    pub fn is_local(root: &Path, path: &str) -> bool {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        root.join(normalized.to_string_lossy().trim_start_matches('/')).exists()
+    }
+
+    /// Request target on the upstream server, the query string is kept as sent.
+    pub fn target(&self, request: &Request) -> String {
+        let mut target = self.upstream_path(&request.path);
+        if !request.query_string.is_empty() {
+            target.push('?');
+            target.push_str(&request.query_string);
+        }
+        target
+    }
+
+    /// Path on the upstream server for `path`, normalized as `rule_for` matched it: `/api//x`
+    /// and `/api/./x` are forwarded as `/api/x`. A trailing slash is kept.
+    pub fn upstream_path(&self, path: &str) -> String {
+        let mut normalized = Utils::normalize_path(PathBuf::from(path)).to_string_lossy().to_string();
+        if self.rule.strip_prefix {
+            let prefix = self.rule.prefix.trim_end_matches('/');
+            normalized = normalized.strip_prefix(prefix).unwrap_or(&normalized).to_string();
+        }
+        if !normalized.starts_with('/') {
+            normalized.insert(0, '/');
+        }
+        if path.ends_with('/') && !normalized.ends_with('/') {
+            normalized.push('/');
+        }
+        format!("{}{}", self.rule.upstream.base_path(), Utils::encode_path(&normalized))
+    }
+
+    /// Head of the forwarded request, client headers minus the hop-by-hop ones plus the
+    /// `X-Forwarded-*` and `Forwarded` headers.
+    pub fn request_head(&self, request: &Request) -> String {
+        let dropped = Self::dropped_headers(request.header("Connection"));
+        let client = request.remote_addr.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", request.method.as_str(), self.target(request));
+        head.push_str(&format!("Host: {}\r\n", self.rule.upstream.authority()));
+        for (key, value) in request.headers.iter() {
+            let lower = key.to_lowercase();
+            if dropped.contains(&lower)
+                || matches!(
+                    lower.as_str(),
+                    "host" | "content-length" | "expect" | "forwarded" | "x-forwarded-for" | "x-forwarded-host" | "x-forwarded-proto"
+                )
+            {
+                continue;
+            }
+            head.push_str(&format!("{}: {}\r\n", key.trim(), value.trim()));
+        }
+
+        let forwarded_for = match request.header("X-Forwarded-For") {
+            Some(previous) => format!("{}, {}", previous.trim(), client),
+            None => client,
+        };
+        head.push_str(&format!("X-Forwarded-For: {}\r\n", forwarded_for));
+        head.push_str("X-Forwarded-Proto: http\r\n");
+        if !request.domain.is_empty() {
+            head.push_str(&format!("X-Forwarded-Host: {}\r\n", request.domain));
+        }
+        let forwarded = Self::forwarded(request.remote_addr, &request.domain);
+        let forwarded = match request.header("Forwarded") {
+            Some(previous) => format!("{}, {}", previous.trim(), forwarded),
+            None => forwarded,
+        };
+        head.push_str(&format!("Forwarded: {}\r\n", forwarded));
+
+        if request.is_chunked() {
+            head.push_str("Transfer-Encoding: chunked\r\n");
+        } else if let Some(length) = self.body_length(request) {
+            head.push_str(&format!("Content-Length: {}\r\n", length));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        head
+    }
+
+    /// One `Forwarded` element, IPv6 addresses are quoted as the RFC requires.
+    pub fn forwarded(client: Option<IpAddr>, host: &str) -> String {
+        let node = match client {
+            Some(IpAddr::V6(ip)) => format!("\"[{}]\"", ip),
+            Some(ip) => ip.to_string(),
+            None => "unknown".to_string(),
+        };
+        let mut element = format!("for={}", node);
+        if !host.is_empty() {
+            element.push_str(&format!(";host=\"{}\"", host.replace(['"', '\\'], "")));
+        }
+        element.push_str(";proto=http");
+        element
+    }
+
+    /// Forwards the request and streams the upstream answer to the client.
+    ///
+    /// Returns the upstream status code and the body size once the answer was sent, or
+    /// leaves a `502 Bad Gateway` or `504 Gateway Timeout` in `response` when the upstream
+    /// failed before answering.
+    pub fn handle(&self, response: &mut Response, stream: &TcpStream) -> Option<(u16, usize)> {
+        let upstream = &self.rule.upstream;
+        let mut connection = match upstream.connect(self.rule.timeout) {
+            Ok(connection) => connection,
+            Err(e) => {
+                Logger::error(format!("[Proxy] Unable to reach {}: {}", upstream, e).as_str());
+                response.request.discard_body(stream);
+                response.serve_error_response(Self::failure(&e));
+                return None;
+            }
+        };
+
+        // the upstream may answer before reading the whole body, 413 for example
+        if let Err(e) = self.send_request(&response.request, stream, &mut connection) {
+            Logger::warn(format!("[Proxy] Sending to {} failed: {}", upstream, e).as_str());
+        }
+
+        let mut reader = BufReader::new(connection);
+        let head = match Self::read_head(&mut reader) {
+            Ok(head) => head,
+            Err(e) => {
+                Logger::error(format!("[Proxy] Invalid answer from {}: {}", upstream, e).as_str());
+                response.serve_error_response(Self::failure(&e));
+                return None;
+            }
+        };
+
+        match self.relay(&response.request, &response.header_rules, head, reader, stream) {
+            Ok(answer) => Some(answer),
+            Err(e) => {
+                // the head is gone, the client sees a truncated body
+                Logger::error(format!("[Proxy] Relaying the answer of {} failed: {}", upstream, e).as_str());
+                Some((HttpStatus::BadGateway.to_code(), 0))
+            }
+        }
+    }
+
+    fn send_request(&self, request: &Request, stream: &TcpStream, connection: &mut Box<dyn Connection>) -> io::Result<()> {
+        connection.write_all(self.request_head(request).as_bytes())?;
+
+        // bodies of methods Katana does not stream were read with the request
+        let mut body: Box<dyn Read> = if request.body.is_empty() {
+            request.body_reader(stream)
+        } else {
+            Box::new(Cursor::new(request.body.as_bytes()))
+        };
+        if request.is_chunked() {
+            let mut writer = ChunkedWriter::new(&mut *connection);
+            io::copy(&mut body, &mut writer)?;
+            writer.finish()?;
+        } else {
+            io::copy(&mut body, connection)?;
+        }
+        connection.flush()
+    }
+
+    fn body_length(&self, request: &Request) -> Option<u64> {
+        if !request.body.is_empty() {
+            return Some(request.body.len() as u64);
+        }
+        request.content_length().filter(|length| *length > 0)
+    }
+
+    // status code and header lines, interim 1xx answers skipped
+    fn read_head(reader: &mut impl BufRead) -> io::Result<(u16, Vec<(String, String)>)> {
+        loop {
+            let mut size = 0;
+            let status_line = Self::read_line(reader, &mut size)?;
+            let code = status_line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse::<u16>().ok())
+                .filter(|code| (100..600).contains(code) && status_line.starts_with("HTTP/"))
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "malformed status line"))?;
+
+            let mut headers = Vec::new();
+            loop {
+                let line = Self::read_line(reader, &mut size)?;
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.push((key.trim().to_string(), value.trim().to_string()));
+                }
+            }
+
+            if code >= 200 {
+                return Ok((code, headers));
+            }
+        }
+    }
+
+    fn read_line(reader: &mut impl BufRead, size: &mut usize) -> io::Result<String> {
+        let mut line = Vec::new();
+        let read = reader.take((Self::MAX_HEAD - *size) as u64).read_until(b'\n', &mut line)?;
+        *size += read;
+        if read == 0 || !line.ends_with(b"\n") {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated answer head"));
+        }
+        Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+    }
+
+    fn relay(
+        &self,
+        request: &Request,
+        rules: &[HeaderRule],
+        (code, headers): (u16, Vec<(String, String)>),
+        mut reader: BufReader<Box<dyn Connection>>,
+        mut stream: &TcpStream,
+    ) -> io::Result<(u16, usize)> {
+        let connection_tokens = headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Connection"))
+            .map(|(_, value)| value.clone());
+        let dropped = Self::dropped_headers(connection_tokens.as_ref());
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let no_body = request.method == HttpMethod::HEAD || code == 204 || code == 304;
+        let chunked = !no_body && header("Transfer-Encoding").is_some_and(|value| value.to_lowercase().contains("chunked"));
+        let length = header("Content-Length").and_then(|value| value.trim().parse::<u64>().ok());
+        // HTTP/1.0 clients do not know chunked bodies, closing the connection ends theirs
+        let rechunk = chunked && request.version == HttpVersion::Http11;
+
+        // reason phrases are informative only, unknown codes get a generic one
+        let reason = HttpStatus::from_code(code).map(|status| status.to_message().to_string()).unwrap_or_else(|| "Unknown".to_string());
+        let mut relayed: Vec<(String, String)> = headers
+            .iter()
+            .filter(|(key, _)| {
+                let lower = key.to_lowercase();
+                !(dropped.contains(&lower) || (chunked && lower == "content-length"))
+            })
+            .cloned()
+            .collect();
+        ResponseHeaders::apply_list(rules, &request.path, &mut relayed);
+
+        let mut head = format!("{} {} {}\r\n", request.version.as_str(), code, reason);
+        for (key, value) in &relayed {
+            head.push_str(&format!("{}: {}\r\n", key, value));
+        }
+        if rechunk {
+            head.push_str("Transfer-Encoding: chunked\r\n");
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        stream.write_all(head.as_bytes())?;
+
+        let size = if no_body {
+            0
+        } else if chunked {
+            let mut body = ChunkedReader::new(reader);
+            if rechunk {
+                let mut writer = ChunkedWriter::new(stream);
+                let size = io::copy(&mut body, &mut writer)?;
+                writer.finish()?;
+                size
+            } else {
+                io::copy(&mut body, &mut stream)?
+            }
+        } else if let Some(length) = length {
+            let size = io::copy(&mut (&mut reader).take(length), &mut stream)?;
+            if size < length {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "upstream body shorter than announced"));
+            }
+            size
+        } else {
+            io::copy(&mut reader, &mut stream)?
+        };
+        stream.flush()?;
+
+        Ok((code, size as usize))
+    }
+
+    // hop-by-hop headers plus the ones named by the Connection header
+    fn dropped_headers(connection: Option<&String>) -> Vec<String> {
+        let mut dropped: Vec<String> = Self::HOP_BY_HOP.iter().map(|name| name.to_string()).collect();
+        if let Some(connection) = connection {
+            dropped.extend(connection.split(',').map(|token| token.trim().to_lowercase()));
+        }
+        dropped
+    }
+
+    fn failure(error: &Error) -> HttpStatus {
+        match error.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => HttpStatus::GatewayTimeout,
+            _ => HttpStatus::BadGateway,
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::core::utils::keyval::KeyVal;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket settings for every request path under `prefix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitRule {
+    pub prefix: String,
+    /// tokens added per second
+    pub rate: f64,
+    /// bucket size, the number of requests a client may send at once
+    pub burst: u32,
+}
+
+impl RateLimitRule {
+    pub fn new(prefix: &str, rate: f64, burst: u32) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            rate,
+            burst: burst.max(1),
+        }
+    }
+
+    // seconds needed to go from `tokens` to `target` tokens
+    fn refill_time(&self, tokens: f64, target: f64) -> u64 {
+        if tokens >= target {
+            return 0;
+        }
+        ((target - tokens) / self.rate).ceil() as u64
+    }
+}
+
+/// Outcome of a rate limit check, rendered as `RateLimit-*` headers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// seconds until the bucket is full again
+    pub reset: u64,
+    /// seconds until the next request is accepted, zero when allowed
+    pub retry_after: u64,
+}
+
+impl RateLimitStatus {
+    // @see: https://datatracker.ietf.org/doc/draft-ietf-httpapi-ratelimit-headers/
+    pub fn add_headers(&self, headers: &mut KeyVal) {
+        headers.add("RateLimit-Limit".to_string(), self.limit.to_string());
+        headers.add("RateLimit-Remaining".to_string(), self.remaining.to_string());
+        headers.add("RateLimit-Reset".to_string(), self.reset.to_string());
+        if !self.allowed {
+            headers.add("Retry-After".to_string(), self.retry_after.to_string());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    clients: HashMap<(IpAddr, String), Bucket>,
+    swept: Instant,
+}
+
+/// Shared token buckets keyed by client address and rule prefix.
+///
+/// Cloning is cheap and every clone sees the same buckets, so one limiter can be handed
+/// to each connection thread.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rules: Vec<RateLimitRule>,
+    buckets: Arc<Mutex<Buckets>>,
+    max_clients: usize,
+}
+
+impl RateLimiter {
+    pub const DEFAULT_MAX_CLIENTS: usize = 10_000;
+    const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(rules: Vec<RateLimitRule>) -> Self {
+        Self::with_capacity(rules, Self::DEFAULT_MAX_CLIENTS)
+    }
+
+    /// Keeps at most `max_clients` buckets, idle ones are dropped first.
+    pub fn with_capacity(rules: Vec<RateLimitRule>, max_clients: usize) -> Self {
+        Self {
+            rules,
+            buckets: Arc::new(Mutex::new(Buckets {
+                clients: HashMap::new(),
+                swept: Instant::now(),
+            })),
+            max_clients: max_clients.max(1),
+        }
+    }
+
+    pub fn rules(&self) -> &[RateLimitRule] {
+        &self.rules
+    }
+
+    /// Returns the rule with the longest prefix covering `path`, if any.
+    pub fn rule_for(&self, path: &str) -> Option<&RateLimitRule> {
+        Utils::longest_prefix(&self.rules, path, |rule| &rule.prefix)
+    }
+
+    /// Takes a token for `ip` on `path`, `None` when no rule covers the path.
+    pub fn check(&self, ip: Option<&IpAddr>, path: &str) -> Option<RateLimitStatus> {
+        self.check_at(ip, path, Instant::now())
+    }
+
+    pub fn check_at(&self, ip: Option<&IpAddr>, path: &str, now: Instant) -> Option<RateLimitStatus> {
+        let rule = self.rule_for(path)?;
+        let ip = ip.copied().unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let burst = rule.burst as f64;
+
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            // a panicking thread cannot leave a bucket half updated, keep going
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let key = (ip, rule.prefix.clone());
+        let full = !buckets.clients.contains_key(&key) && buckets.clients.len() >= self.max_clients;
+        if full || now.saturating_duration_since(buckets.swept) >= Self::SWEEP_INTERVAL {
+            self.sweep(&mut buckets, now);
+        }
+
+        let bucket = buckets.clients.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rule.rate).min(burst);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        Some(RateLimitStatus {
+            allowed,
+            limit: rule.burst,
+            remaining: bucket.tokens.floor() as u32,
+            reset: rule.refill_time(bucket.tokens, burst),
+            retry_after: if allowed { 0 } else { rule.refill_time(bucket.tokens, 1.0).max(1) },
+        })
+    }
+
+    /// Number of clients currently tracked.
+    pub fn len(&self) -> usize {
+        self.buckets.lock().map(|buckets| buckets.clients.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // drop every bucket that has refilled completely, it is the same as a new one;
+    // when all clients are still active, drop the least recently seen instead
+    fn sweep(&self, buckets: &mut Buckets, now: Instant) {
+        let rules = &self.rules;
+        buckets.swept = now;
+        buckets.clients.retain(|(_, prefix), bucket| {
+            let rule = match rules.iter().find(|rule| &rule.prefix == prefix) {
+                Some(rule) => rule,
+                None => return false,
+            };
+            let full_after = Duration::from_secs(rule.refill_time(bucket.tokens, rule.burst as f64));
+            now.saturating_duration_since(bucket.updated) < full_after
+        });
+
+        if buckets.clients.len() >= self.max_clients {
+            if let Some(oldest) = buckets
+                .clients
+                .iter()
+                .min_by_key(|(_, bucket)| bucket.updated)
+                .map(|(key, _)| key.clone())
+            {
+                buckets.clients.remove(&oldest);
+            }
+        }
+
+        Logger::debug(format!("[RateLimit] {} clients tracked after cleanup", buckets.clients.len()).as_str());
+    }
+}
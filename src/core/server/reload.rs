@@ -0,0 +1,140 @@
+use crate::core::config::config::{Config, ConfigChange, ConfigIssue};
+use crate::core::utils::logger::Logger;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+// set by the SIGHUP handler, a signal handler may not do more than that
+static HANGUP: AtomicBool = AtomicBool::new(false);
+
+struct Active {
+    generation: u64,
+    config: Config,
+}
+
+/// The configuration new connections are served with.
+///
+/// A reload loads the configuration again and swaps it in when it is valid, connections
+/// already accepted keep the configuration they started with. Settings in
+/// `Config::RESTART_KEYS` keep their running value until the server is restarted.
+#[derive(Clone)]
+pub struct ConfigReload {
+    active: Arc<RwLock<Active>>,
+}
+
+impl ConfigReload {
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(mut config: Config) -> Self {
+        config.mime_types = Arc::new(config.mime.build());
+        Self {
+            active: Arc::new(RwLock::new(Active { generation: 0, config })),
+        }
+    }
+
+    // replaces the configuration before the server starts, nothing to report yet
+    pub(crate) fn set(&self, mut config: Config) {
+        config.mime_types = Arc::new(config.mime.build());
+        let mut active = self.active.write().unwrap_or_else(|e| e.into_inner());
+        active.config = config;
+    }
+
+    /// The active configuration and its generation, increased by every applied reload.
+    pub fn current(&self) -> (u64, Config) {
+        let active = self.active.read().unwrap_or_else(|e| e.into_inner());
+        (active.generation, active.config.clone())
+    }
+
+    /// Loads the configuration from its sources again, see `apply`.
+    pub fn reload(&self) -> Result<Vec<ConfigChange>, Vec<ConfigIssue>> {
+        Logger::info("[Config] Reloading the configuration");
+        self.apply(Config::load())
+    }
+
+    /// Swaps `config` in and logs what changed. An invalid configuration is refused, the
+    /// active one stays in place.
+    pub fn apply(&self, mut config: Config) -> Result<Vec<ConfigChange>, Vec<ConfigIssue>> {
+        if !config.issues.is_empty() {
+            for issue in &config.issues {
+                Logger::error(format!("[Config] {}", issue).as_str());
+            }
+            Logger::error(
+                format!("[Config] Reload refused, {} problem(s) in the configuration", config.issues.len()).as_str(),
+            );
+            return Err(config.issues);
+        }
+
+        let mut active = self.active.write().unwrap_or_else(|e| e.into_inner());
+        let changes = active.config.diff(&config);
+        // the listening socket, the connection limits and the watcher are already set up
+        config.host = active.config.host.clone();
+        config.port = active.config.port;
+        config.worker = active.config.worker;
+        config.max_connections = active.config.max_connections;
+        config.max_connections_per_ip = active.config.max_connections_per_ip;
+        config.watch = active.config.watch;
+        for key in Config::RESTART_KEYS {
+            match active.config.origins.get(key) {
+                Some(origin) => config.origins.insert(key, origin.clone()),
+                None => config.origins.remove(key),
+            };
+        }
+
+        // connections already accepted keep the log level and media types of their snapshot
+        config.mime_types = Arc::new(config.mime.build());
+        Logger::set_min_log_level(config.log_level.clone());
+        active.config = config;
+        active.generation += 1;
+        drop(active);
+
+        if changes.is_empty() {
+            Logger::info("[Config] Reloaded, nothing changed");
+        }
+        for change in &changes {
+            match change.restart {
+                true => Logger::warn(format!("[Config] {}", change).as_str()),
+                false => Logger::info(format!("[Config] {}", change).as_str()),
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Reloads the configuration whenever the process receives SIGHUP. Does nothing where
+    /// there are no signals.
+    pub fn on_hangup(&self) {
+        if !Self::install_handler() {
+            return;
+        }
+
+        let reload = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Self::POLL_INTERVAL);
+            if HANGUP.swap(false, Ordering::SeqCst) {
+                let _ = reload.reload();
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    fn install_handler() -> bool {
+        const SIGHUP: i32 = 1;
+        const SIG_ERR: usize = usize::MAX;
+
+        extern "C" {
+            fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        }
+
+        extern "C" fn handle(_signum: i32) {
+            HANGUP.store(true, Ordering::SeqCst);
+        }
+
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        unsafe { signal(SIGHUP, handle) != SIG_ERR }
+    }
+
+    #[cfg(not(unix))]
+    fn install_handler() -> bool {
+        false
+    }
+}
@@ -1,9 +1,10 @@
+use crate::core::server::chunked::ChunkedReader;
 use crate::core::server::http::{HttpMethod, HttpVersion};
 use crate::core::utils::keyval::KeyVal;
 use crate::core::utils::logger::Logger;
 use crate::core::server::server::Server;
-use std::io::{BufRead, BufReader, Read};
-use std::net::TcpStream;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::net::{IpAddr, TcpStream};
 
 #[derive(Debug, Clone)]
 pub struct Request {
@@ -12,14 +13,25 @@ pub struct Request {
     pub path: String,
     pub method: HttpMethod,
     pub queries: KeyVal,
+    /// raw query string, without the `?`, as sent by the client
+    pub query_string: String,
     pub headers: KeyVal,
     pub cookies: KeyVal,
     pub body: String,
+    pub remote_user: Option<String>,
+    pub remote_addr: Option<IpAddr>,
+    // bytes buffered past the headers: the start of a streamed body or of a WebSocket stream
+    pending: Vec<u8>,
 }
 
 impl Request {
+    /// Methods whose body is left on the socket, to be read through `body_reader`.
+    pub const STREAMED_BODY_METHODS: &'static [HttpMethod] = &[HttpMethod::PUT, HttpMethod::POST];
+    const MAX_DISCARD: u64 = 1024 * 1024;
+
     pub fn from_stream(mut stream: &TcpStream) -> Option<Self> {
         Logger::debug("[Request] Starting to parse new request from stream");
+        let remote_addr = stream.peer_addr().ok().map(|addr| addr.ip());
         let mut reader = BufReader::new(&mut stream);
 
         // read the request line
@@ -40,38 +52,48 @@ impl Request {
             return None;
         }
 
-        let method = HttpMethod::from_str(parts[0]).unwrap();
+        let method = match HttpMethod::from_str(parts[0]) {
+            Some(method) => method,
+            None => {
+                Logger::warn(format!("[Request] Unknown method: {}", parts[0]).as_str());
+                return None;
+            }
+        };
         let raw_path = parts[1];
-        let mut path = Self::decode_url(raw_path);
         let version = HttpVersion::from_str(&parts[2].replace("HTTP/", "")).unwrap();
 
-        Logger::debug(
-            format!(
-                "[Request] Method: {}, Path: {}, Version: {}",
-                method.as_str(),
-                path,
-                version.as_str()
-            )
-            .as_str(),
-        );
-
         let mut domain = String::new();
         let mut queries = KeyVal::new();
         let mut headers = KeyVal::new();
         let mut cookies = KeyVal::new();
         let mut body = String::new();
 
-        // Modify query parsing
-        if let Some((path_part, query_part)) = path.clone().split_once('?') {
-            path = path_part.to_string();
+        // split before decoding, "%3F" in a file name is not a query
+        let (path_part, query_part) = match raw_path.split_once('?') {
+            Some((path_part, query_part)) => (path_part, Some(query_part)),
+            None => (raw_path, None),
+        };
+        let path = Self::decode_path(path_part);
+
+        if let Some(query_part) = query_part {
             for pair in query_part.split('&') {
                 if let Some((k, v)) = pair.split_once('=') {
-                    queries.add(k.to_string(), v.to_string());
+                    queries.add(Self::decode_url(k), Self::decode_url(v));
                 }
             }
             Logger::debug(format!("[Request] Parsed {} query parameters", queries.len()).as_str());
         }
 
+        Logger::debug(
+            format!(
+                "[Request] Method: {}, Path: {}, Version: {}",
+                method.as_str(),
+                path,
+                version.as_str()
+            )
+            .as_str(),
+        );
+
         // read headers
         Logger::debug("[Request] Starting to parse headers");
         loop {
@@ -108,7 +130,9 @@ impl Request {
         }
 
         // process body
-        if Server::SUPPORTED_HTTP_METHODS.contains(&method) {
+        if Self::STREAMED_BODY_METHODS.contains(&method) {
+            Logger::debug("[Request] Body left on the socket, read on demand");
+        } else if Server::SUPPORTED_HTTP_METHODS.contains(&method) || HttpMethod::webdav_read().contains(&method) || HttpMethod::webdav_write().contains(&method) {
             if let Some((_, cl_value)) = headers
                 .iter()
                 .find(|(key, _)| key.to_lowercase() == "content-length")
@@ -137,6 +161,9 @@ impl Request {
             );
         }
 
+        // keep what the reader already pulled from the socket
+        let pending = reader.buffer().to_vec();
+
         Logger::debug("[Request] Request parsing completed successfully");
         Some(Self {
             method,
@@ -144,16 +171,75 @@ impl Request {
             version,
             domain,
             queries,
+            query_string: query_part.unwrap_or_default().to_string(),
             headers,
             cookies,
             body,
+            remote_user: None,
+            remote_addr,
+            pending,
         })
     }
 
+    /// Declared body length, `None` for chunked or absent bodies.
+    pub fn content_length(&self) -> Option<u64> {
+        if self.is_chunked() {
+            return None;
+        }
+        self.header("Content-Length")?.trim().parse().ok()
+    }
+
+    pub fn is_chunked(&self) -> bool {
+        self.header("Transfer-Encoding")
+            .map(|value| value.to_lowercase().contains("chunked"))
+            .unwrap_or(false)
+    }
+
+    pub fn has_body(&self) -> bool {
+        self.is_chunked() || self.content_length().unwrap_or(0) > 0
+    }
+
+    /// Reads the body of a streamed method (see `STREAMED_BODY_METHODS`) from `stream`.
+    pub fn body_reader<'a>(&'a self, stream: &'a TcpStream) -> Box<dyn Read + 'a> {
+        let source = Cursor::new(self.pending.as_slice()).chain(stream);
+        if self.is_chunked() {
+            Box::new(ChunkedReader::new(BufReader::new(source)))
+        } else {
+            Box::new(source.take(self.content_length().unwrap_or(0)))
+        }
+    }
+
+    /// Reads and drops a refused streamed body, up to `MAX_DISCARD` bytes. Closing the
+    /// connection on unread data would reset it before the client sees the answer.
+    pub fn discard_body(&self, stream: &TcpStream) {
+        if Self::STREAMED_BODY_METHODS.contains(&self.method) && self.content_length().unwrap_or(0) <= Self::MAX_DISCARD {
+            let _ = io::copy(&mut self.body_reader(stream).take(Self::MAX_DISCARD), &mut io::sink());
+        }
+    }
+
+    /// Bytes received past the request, handed over when the connection switches protocols.
+    pub fn buffered(&self) -> &[u8] {
+        &self.pending
+    }
+
+    /// Looks up a header by name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Decodes the path part of a URL, unlike in queries `+` stays a plus sign.
+    pub fn decode_path(path: &str) -> String {
+        Self::decode_url(&path.replace('+', "%2B"))
+    }
+
     pub fn decode_url(url: &str) -> String {
         Logger::debug(format!("[Request] Decoding URL: {}", url).as_str());
         let result = {
-            let mut result = String::with_capacity(url.len());
+            // collect bytes first, an escaped multi-byte character spans several "%XX"
+            let mut result: Vec<u8> = Vec::with_capacity(url.len());
             let mut chars = url.chars().peekable();
 
             while let Some(c) = chars.next() {
@@ -164,22 +250,21 @@ impl Request {
                         if let Some(h2) = chars.next() {
                             hex.push(h2);
                             if let Ok(byte) = u8::from_str_radix(&hex, 16) {
-                                result.push(byte as char);
+                                result.push(byte);
                                 continue;
                             }
                         }
                     }
-                    result.push('%');
-                    for h in hex.chars() {
-                        result.push(h);
-                    }
+                    result.push(b'%');
+                    result.extend_from_slice(hex.as_bytes());
                 } else if c == '+' {
-                    result.push(' ');
+                    result.push(b' ');
                 } else {
-                    result.push(c);
+                    let mut buffer = [0; 4];
+                    result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
                 }
             }
-            result
+            String::from_utf8_lossy(&result).to_string()
         };
         Logger::debug(format!("[Request] Decoded URL: {}", result).as_str());
         result
@@ -1,5 +1,10 @@
-use crate::core::server::filetype::FileType;
-use crate::core::server::http::{HttpStatus, HttpVersion};
+use crate::core::server::archive::{Archive, ArchiveFormat};
+use crate::core::server::cache::{Cache, CacheRule};
+use crate::core::server::chunked::ChunkedWriter;
+use crate::core::server::filetype::{FileType, MimeRegistry};
+use crate::core::server::headers::HeaderRule;
+use crate::core::server::http::{HttpMethod, HttpStatus, HttpVersion};
+use crate::core::server::location::Location;
 use crate::core::utils::keyval::KeyVal;
 use crate::core::utils::logger::Logger;
 use crate::core::server::request::Request;
@@ -8,9 +13,10 @@ use crate::core::utils::utils::Utils;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Error, Read, Seek, SeekFrom, Write};
+use std::io::{BufWriter, Error, Read, Seek, SeekFrom, Write};
 use std::net::{Shutdown, TcpStream};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct Response {
@@ -23,8 +29,21 @@ pub struct Response {
     pub body: Vec<u8>,
     pub size: usize,
     pub _path: PathBuf,
+    pub archive_exclude: Vec<PathBuf>,
+    pub upload_form: bool,
+    /// entry page served for unknown paths, see `Spa::fallback_for`
+    pub spa_fallback: Option<String>,
+    /// `/about` serves `about.html`, which redirects to `/about`
+    pub clean_urls: bool,
+    /// `[headers]` rules, for the answers of the reverse proxy, CGI and FastCGI
+    pub header_rules: Vec<HeaderRule>,
+    /// `[cache]` rules, see `Cache::policy_for`
+    pub cache_rules: Vec<CacheRule>,
+    /// media types of the configuration the connection is served with
+    pub mime_types: Arc<MimeRegistry>,
     _need_stream: bool,
     _is_compiled: bool,
+    _archive: Option<(ArchiveFormat, String)>,
 }
 
 impl Response {
@@ -42,27 +61,63 @@ impl Response {
             body: Vec::new(),
             size: 0,
             _path: PathBuf::new(),
+            archive_exclude: Vec::new(),
+            upload_form: false,
+            spa_fallback: None,
+            clean_urls: false,
+            header_rules: Vec::new(),
+            cache_rules: Vec::new(),
+            mime_types: MimeRegistry::builtin(),
             _need_stream: false,
             _is_compiled: false,
+            _archive: None,
         };
 
         Some(response)
     }
 
-    pub fn serve(&mut self, root_dir: &Path) -> &mut Response {
+    /// Answers from the document root, with the index files and listing of `location`.
+    pub fn serve(&mut self, root_dir: &Path, location: &Location) -> &mut Response {
         Logger::debug(
             format!("[Response] Serving request for path: {}", self.request.path).as_str(),
         );
 
-        let file_path = root_dir.join(&self.request.path[1..]);
+        let path = Self::local_path(&self.request.path);
+        let file_path = root_dir.join(&path[1..]);
 
         file_path.clone_into(&mut self._path);
 
+        if let Some((status, target)) = self.canonical_path(root_dir, &path, &file_path) {
+            Logger::debug(format!("[Response] Redirecting to {}", target).as_str());
+            self.serve_redirect(status, &target);
+            return self;
+        }
+
+        let page_html = PathBuf::from(format!("{}.html", file_path.to_string_lossy()));
         if file_path.is_dir() {
-            let index_html = file_path.join("index.html");
-            if index_html.is_file() {
-                Logger::debug("[Response] Serving index.html from directory");
-                self.serve_file(root_dir, index_html);
+            let index = location.index.iter().map(|name| file_path.join(name)).find(|index| index.is_file());
+            if let Some(archive) = self.request.queries.get("archive").cloned() {
+                match ArchiveFormat::from_name(&archive) {
+                    // an archive lists the folder too
+                    Some(_) if !location.directory_listing => {
+                        Logger::debug("[Response] Directory listing is disabled, no archive");
+                        self.serve_error_response(HttpStatus::Forbidden);
+                    }
+                    Some(format) => {
+                        Logger::debug("[Response] Serving directory archive");
+                        self.serve_archive(root_dir, file_path, format);
+                    }
+                    None => {
+                        Logger::warn(format!("[Response] Unsupported archive format: {}", archive).as_str());
+                        self.serve_error_response(HttpStatus::BadRequest);
+                    }
+                }
+            } else if let Some(index) = index {
+                Logger::debug("[Response] Serving index file from directory");
+                self.serve_file(root_dir, index);
+            } else if !location.directory_listing {
+                Logger::debug("[Response] Directory listing is disabled");
+                self.serve_error_response(HttpStatus::Forbidden);
             } else {
                 Logger::debug("[Response] Serving directory listing");
                 self.serve_directory(root_dir, file_path);
@@ -70,6 +125,17 @@ impl Response {
         } else if file_path.is_file() {
             Logger::debug("[Response] Serving file");
             self.serve_file(root_dir, file_path);
+        } else if self.clean_urls && !path.ends_with('/') && page_html.is_file() {
+            Logger::debug("[Response] Serving clean URL page");
+            self.serve_file(root_dir, page_html);
+        } else if let Some(fallback) = self
+            .spa_fallback
+            .as_ref()
+            .map(|fallback| root_dir.join(fallback.trim_start_matches('/')))
+            .filter(|fallback| fallback.is_file())
+        {
+            Logger::debug("[Response] Serving single-page application entry");
+            self.serve_file(root_dir, fallback);
         } else {
             let display_path = Utils::path_prettifier(file_path.clone());
             Logger::warn(format!("[Response] Path not found: {}", display_path).as_str());
@@ -79,6 +145,52 @@ impl Response {
         self
     }
 
+    // the request path with `..` resolved and repeated slashes collapsed, a trailing slash
+    // kept: `//usr` would join the document root as the absolute `/usr`
+    fn local_path(path: &str) -> String {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        let normalized = normalized.to_string_lossy();
+        let normalized = normalized.trim_matches('/');
+        match normalized.is_empty() || !path.ends_with('/') {
+            true => format!("/{}", normalized),
+            false => format!("/{}/", normalized),
+        }
+    }
+
+    // folders end with a slash so relative links resolve inside them, for good. Clean URLs drop
+    // `.html` with a temporary redirect: browsers would keep a permanent one after
+    // `clean_urls` is turned off, and `/about` would no longer answer. `path` is normalized,
+    // `//host/` would send the client to another host.
+    fn canonical_path(&self, root_dir: &Path, path: &str, file_path: &Path) -> Option<(HttpStatus, String)> {
+        if !matches!(self.request.method, HttpMethod::GET | HttpMethod::HEAD) || !file_path.starts_with(root_dir) {
+            return None;
+        }
+
+        let (status, location) = if file_path.is_dir() {
+            match path.ends_with('/') {
+                true => return None,
+                false => (HttpStatus::MovedPermanently, format!("{}/", path)),
+            }
+        } else if self.clean_urls && file_path.is_file() {
+            let page = path.strip_suffix(".html")?;
+            match page.strip_suffix("index") {
+                // /docs/index.html is the folder itself
+                Some(folder) if folder.ends_with('/') => (HttpStatus::Found, folder.to_string()),
+                // unless the clean path is taken by a file or folder of its own
+                _ if page.ends_with('/') || root_dir.join(&page[1..]).exists() => return None,
+                _ => (HttpStatus::Found, page.to_string()),
+            }
+        } else {
+            return None;
+        };
+
+        let location = Utils::encode_path(&location);
+        match self.request.query_string.is_empty() {
+            true => Some((status, location)),
+            false => Some((status, format!("{}?{}", location, self.request.query_string))),
+        }
+    }
+
     fn serve_file(&mut self, root_path: &Path, path: PathBuf) {
         let display_path = Utils::path_prettifier(path.clone());
         Logger::debug(format!("[Response] Attempting to serve file: {}", display_path).as_str());
@@ -111,11 +223,11 @@ impl Response {
             Ok(_file) => {
                 let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
 
-                let file_type = FileType::from_extension(extension)
-                    .unwrap_or_else(|| FileType::new("bin", "application/octet-stream"));
-
-                // @see: https://developer.mozilla.org/fr/docs/Web/HTTP/Headers/Content-Disposition
-                let content_disposition = file_type.content_disposition();
+                // a known extension, then the content when sniffing is on, then the default
+                // type of the registry for unknown extensions
+                let file_type = FileType::from_extension(&self.mime_types, extension)
+                    .or_else(|| FileType::sniff(&self.mime_types, &path))
+                    .or_else(|| Some(FileType::for_extension(&self.mime_types, extension)).filter(|_| !extension.is_empty()));
 
                 // get file size without reading
                 let metadata = std::fs::metadata(&path).expect("Unable to read metadata"); // self.body.len().to_string()
@@ -145,13 +257,23 @@ impl Response {
                 self.status_code = HttpStatus::Ok;
                 self.headers.clear();
 
+                let file_path = format!("/{}", relative_path.replace('\\', "/").trim_start_matches('/'));
+                let content_type = file_type.as_ref().map(FileType::content_type);
+                if let Some(policy) = Cache::policy_for(&self.cache_rules, &file_path, content_type) {
+                    let now = Utils::unix_timestamp().parse().unwrap_or(0);
+                    policy.add_headers(&mut self.headers, now);
+                }
+
                 // @see: https://stackoverflow.com/a/28652339/13158370
-                if extension == "" {
+                let Some(file_type) = file_type else {
                     Logger::debug("[Response] No extension found, not using content type");
                     return;
-                }
+                };
+
+                Logger::debug(format!("[Response] Found content type: {}", file_type.content_type).as_str());
 
-                Logger::debug(format!("[Response] Found extension: {}", extension).as_str());
+                // @see: https://developer.mozilla.org/fr/docs/Web/HTTP/Headers/Content-Disposition
+                let content_disposition = file_type.content_disposition();
 
                 self.headers.add(
                     "Content-Type".to_string(),
@@ -241,6 +363,10 @@ impl Response {
         let mut params = HashMap::new();
         params.insert("folder".to_string(), relative_path);
         params.insert("directory_content".to_string(), listing_html);
+        params.insert(
+            "upload".to_string(),
+            if self.upload_form { "on" } else { "off" }.to_string(),
+        );
 
         self.body = self
             .templates
@@ -254,7 +380,41 @@ impl Response {
         self.size = self.body.len()
     }
 
-    fn serve_error_response(&mut self, status: HttpStatus) {
+    fn serve_archive(&mut self, root_path: &Path, path: PathBuf, format: ArchiveFormat) {
+        let root_dir = root_path.to_str().unwrap();
+
+        let relative_path = match path.strip_prefix(root_dir) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => String::from("/"), // fallback in case of error
+        };
+
+        if relative_path.starts_with("/.") || relative_path.starts_with('.') {
+            self.serve_error_response(HttpStatus::Forbidden);
+            return;
+        }
+
+        // name the archive (and its top folder) after the directory, "root" for the document root
+        let name = path
+            .file_name()
+            .filter(|_| !relative_path.is_empty())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "root".to_string());
+
+        self._path.clone_from(&path);
+        self._archive = Some((format, name.clone()));
+
+        self.status_code = HttpStatus::Ok;
+        self.headers.clear();
+        self.headers
+            .add("Content-Type".to_string(), format.content_type().to_string());
+        self.headers.add(
+            "Content-Disposition".to_string(),
+            format!("attachment; filename=\"{}.{}\"", name.replace('"', ""), format.extension()),
+        );
+    }
+
+    pub fn serve_error_response(&mut self, status: HttpStatus) {
+        self._archive = None;
         self._is_compiled = true; // mark as compiled to avoid streaming
 
         let mut params = HashMap::new();
@@ -277,6 +437,100 @@ impl Response {
         self.size = self.body.len()
     }
 
+    /// Replaces the error page by the one `location` sets for its status, a file of the
+    /// document root. Headers such as `Allow` or `WWW-Authenticate` are kept.
+    pub fn serve_error_page(&mut self, root_dir: &Path, location: &Location) {
+        let code = self.status_code.to_code();
+        let page = match location.error_pages.get(&code) {
+            Some(page) if code >= 400 => page,
+            _ => return,
+        };
+        let path = root_dir.join(page.trim_start_matches('/'));
+        let body = match std::fs::read(&path) {
+            Ok(body) => body,
+            Err(e) => {
+                Logger::warn(format!("[Response] Error page {} unavailable: {}", page, e).as_str());
+                return;
+            }
+        };
+
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let content_type = FileType::from_extension(&self.mime_types, extension)
+            .map_or("text/html".to_string(), |file_type| file_type.content_type.to_string());
+
+        self._archive = None;
+        self._need_stream = false;
+        self._is_compiled = true;
+        self.body = body;
+        self.headers.add("Content-Type".to_string(), content_type);
+        self.size = self.body.len()
+    }
+
+    /// Answers with a body built in memory.
+    pub fn serve_content(&mut self, status: HttpStatus, content_type: &str, body: Vec<u8>) {
+        self._archive = None;
+        self._is_compiled = true;
+
+        self.status_code = status;
+        self.body = body;
+        self.headers.clear();
+        self.headers
+            .add("Content-Type".to_string(), content_type.to_string());
+
+        self.size = self.body.len()
+    }
+
+    /// Redirects the client to `location`, an encoded URL.
+    pub fn serve_redirect(&mut self, status: HttpStatus, location: &str) {
+        self.serve_empty(status);
+        self.headers.add("Location".to_string(), location.to_string());
+    }
+
+    /// Answers with a status only, like `201 Created` or `204 No Content`.
+    pub fn serve_empty(&mut self, status: HttpStatus) {
+        self._archive = None;
+        self._is_compiled = true;
+
+        self.status_code = status;
+        self.body = Vec::new();
+        self.headers.clear();
+
+        self.size = 0
+    }
+
+    /// Inserts `snippet` before the closing body tag of an HTML answer, or at its end.
+    ///
+    /// Files are loaded in memory to be rewritten, those too large to be served at once are
+    /// left untouched.
+    pub fn inject_html(&mut self, snippet: &str) {
+        let is_html = self
+            .headers
+            .get("Content-Type")
+            .is_some_and(|content_type| content_type.starts_with("text/html"));
+        if !is_html || self._archive.is_some() || self._need_stream {
+            return;
+        }
+
+        if !self._is_compiled {
+            match std::fs::read(&self._path) {
+                Ok(body) => self.body = body,
+                Err(e) => {
+                    Logger::warn(format!("[Response] Unable to read {} for injection: {}", self._path.display(), e).as_str());
+                    return;
+                }
+            }
+            self._is_compiled = true;
+        }
+
+        let position = self
+            .body
+            .windows(7)
+            .rposition(|window| window.eq_ignore_ascii_case(b"</body>"))
+            .unwrap_or(self.body.len());
+        self.body.splice(position..position, snippet.bytes());
+        self.size = self.body.len();
+    }
+
     pub fn http_description(&self) -> String {
         let mut result = String::new();
 
@@ -322,6 +576,10 @@ impl Response {
     pub fn stream(&mut self, stream: &mut TcpStream) -> Result<(), Error> {
         Logger::debug("[Response] Starting stream response");
 
+        if self._archive.is_some() {
+            return self.stream_archive(stream);
+        }
+
         self.headers
             .add("Content-Length".to_string(), self.size.to_string());
 
@@ -333,7 +591,7 @@ impl Response {
         }
 
         if self._is_compiled {
-            if self.body.is_empty() {
+            if self.body.is_empty() && self.size > 0 && self.request.method != HttpMethod::HEAD {
                 Logger::error("[Response] Body is empty while expecting compiled content");
                 self.serve_error_response(HttpStatus::InternalServerError);
                 stream.write_all(self.to_bytes().as_slice())?;
@@ -391,6 +649,59 @@ impl Response {
         Ok(())
     }
 
+    fn stream_archive(&mut self, stream: &mut TcpStream) -> Result<(), Error> {
+        let (format, name) = self._archive.clone().unwrap();
+
+        // other methods (TRACE, OPTIONS, not allowed...) already compiled their own answer
+        if self.request.method != HttpMethod::GET && self.request.method != HttpMethod::HEAD {
+            self.headers
+                .add("Content-Length".to_string(), self.body.len().to_string());
+            stream.write_all(self.to_bytes().as_slice())?;
+            stream.flush()?;
+            return Ok(());
+        }
+
+        // archive size is unknown until the whole tree has been walked
+        let chunked = self.request.version == HttpVersion::Http11;
+        if chunked {
+            self.headers
+                .add("Transfer-Encoding".to_string(), "chunked".to_string());
+        }
+        self.headers
+            .add("Connection".to_string(), "close".to_string());
+
+        stream.write_all(self.http_description().as_bytes())?;
+        stream.write_all(b"\r\n")?;
+
+        if self.request.method == HttpMethod::HEAD {
+            stream.flush()?;
+            return Ok(());
+        }
+
+        let result = if chunked {
+            let writer = BufWriter::with_capacity(Response::CHUNK_SIZE * 16, ChunkedWriter::new(&mut *stream));
+            Archive::write_excluding(format, &self._path, &name, &self.archive_exclude, writer)
+                .and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+                .and_then(|writer| {
+                    self.size = writer.written();
+                    writer.finish().map(|_| ())
+                })
+        } else {
+            Archive::write_excluding(format, &self._path, &name, &self.archive_exclude, BufWriter::new(&mut *stream))
+                .map(|_| ())
+        };
+
+        if let Err(error) = result {
+            // headers are gone already, the only thing left to do is to cut the connection
+            Logger::error(format!("[Response] Error while streaming archive: {}", error).as_str());
+        }
+
+        stream.flush()?;
+        stream.shutdown(Shutdown::Both)?;
+
+        Ok(())
+    }
+
     fn stream_by_chunk(&mut self, stream: &mut TcpStream) -> Result<(), Error> {
         // @see: https://developer.mozilla.org/fr/docs/Web/HTTP/Reference/Status/206
         // @see: https://www.rfc-editor.org/rfc/rfc2616.html#section-14.35
@@ -1,22 +1,52 @@
 use crate::core::config::config::Config;
+use crate::core::server::access::AccessControl;
+use crate::core::server::auth::{AuthRule, BasicAuth};
+use crate::core::server::cgi::{Cgi, ScriptRules};
+use crate::core::server::connection::{ConnectionLimit, ConnectionLimiter};
+use crate::core::server::fastcgi::{FastCgi, FastCgiPool};
+use crate::core::server::headers::ResponseHeaders;
 use crate::core::server::http::{HttpMethod, HttpStatus};
+use crate::core::server::livereload::LiveReload;
+use crate::core::server::location::{Location, LocationAuth, LocationMatch, LocationRule};
+use crate::core::server::proxy::Proxy;
+use crate::core::server::ratelimit::{RateLimitStatus, RateLimiter};
+use crate::core::server::reload::ConfigReload;
+use crate::core::server::spa::Spa;
+use crate::core::server::sse::{EventPublisher, EventRoutes};
+use crate::core::server::upload::Upload;
+use crate::core::server::webdav::{LockManager, WebDav, WebDavMode};
+use crate::core::server::websocket::{WebSocket, WebSocketHandler, WebSocketRoutes};
 use crate::core::utils::logger::Logger;
 use crate::core::server::request::Request;
 use crate::core::server::response::Response;
 use crate::core::resources::templates::Templates;
 use crate::core::utils::utils::Utils;
+use std::fs;
 use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::io::Write;
 use std::ops::DerefMut;
 use std::thread;
+use std::time::Duration;
 
 pub struct Server {
     config: Config,
     templates: Templates,
+    limiter: RateLimiter,
+    connections: ConnectionLimiter,
+    locks: LockManager,
+    backends: FastCgiPool,
+    websockets: WebSocketRoutes,
+    events: EventRoutes,
+    live_reload: Option<LiveReload>,
+    reload: ConfigReload,
 }
 
 impl Server {
     const SERVER_NAME: &'static str = "Katana";
     const SERVER_VERSION: &'static str = "0.1.0";
+    const REJECT_READ_TIMEOUT: Duration = Duration::from_secs(2);
     pub const SUPPORTED_HTTP_METHODS: &'static [HttpMethod] = &[
         HttpMethod::GET,
         HttpMethod::HEAD,
@@ -25,7 +55,40 @@ impl Server {
     ];
 
     pub fn new(config: Config, templates: Templates) -> Self {
-        Self { config, templates }
+        let limiter = RateLimiter::new(config.rate_limit.clone());
+        let connections = ConnectionLimiter::new(config.max_connections, config.max_connections_per_ip);
+        let live_reload = config.watch.then(LiveReload::new);
+        let reload = ConfigReload::new(config.clone());
+        Self {
+            config,
+            templates,
+            limiter,
+            connections,
+            locks: LockManager::new(),
+            backends: FastCgiPool::new(),
+            websockets: WebSocketRoutes::new(),
+            events: EventRoutes::new(),
+            live_reload,
+            reload,
+        }
+    }
+
+    /// Serves new connections with the active configuration of `reload`, see `Katana::reload`.
+    pub fn with_reload(mut self, reload: ConfigReload) -> Self {
+        self.reload = reload;
+        self
+    }
+
+    /// Serves the given WebSocket endpoints, see `Katana::websocket`.
+    pub fn with_websockets(mut self, websockets: WebSocketRoutes) -> Self {
+        self.websockets = websockets;
+        self
+    }
+
+    /// Serves the given event streams, see `Katana::event_stream`.
+    pub fn with_events(mut self, events: EventRoutes) -> Self {
+        self.events = events;
+        self
     }
 
     pub fn serve(&self) {
@@ -39,15 +102,52 @@ impl Server {
         };
         Logger::debug("[Server] Server is ready to accept connections");
 
+        let mut websockets = self.websockets.clone();
+        if let Some(live_reload) = &self.live_reload {
+            websockets.mount(LiveReload::PATH, Arc::new(live_reload.clone()));
+            live_reload.watch(&self.config.document_root);
+        }
+
+        let mut limiter = self.limiter.clone();
+        let mut generation = 0;
         for stream in listener.incoming().flatten() {
+            // count the connection before spawning, so a flood of connections is refused at once
+            let slot = self
+                .connections
+                .acquire(stream.peer_addr().ok().map(|addr| addr.ip()));
+
+            // a reload only affects the connections accepted after it
+            let (current, config) = self.reload.current();
+            if current != generation {
+                generation = current;
+                // the clients are counted again when the rules change
+                if config.rate_limit != limiter.rules() {
+                    limiter = RateLimiter::new(config.rate_limit.clone());
+                }
+            }
+
             // spawn a new thread for each connection
-            let config = self.config.clone();
             let templates = self.templates.clone();
+            // clones share their state, every thread must see the same clients
+            let limiter = limiter.clone();
+            let connections = self.connections.clone();
+            let locks = self.locks.clone();
+            let backends = self.backends.clone();
+            let websockets = websockets.clone();
+            let events = self.events.clone();
+            let live_reload = self.live_reload.clone();
+            let reload = self.reload.clone();
 
             thread::spawn(move || {
+                // the thread logs at the level of its configuration, whatever a reload sets
+                Logger::set_thread_log_level(config.log_level.clone());
                 // create a new server instance for the thread with the necessary data
-                let server = Server::new(config, templates);
-                server.handle_request(stream);
+                let server = Server { config, templates, limiter, connections, locks, backends, websockets, events, live_reload, reload };
+                match slot {
+                    // the slot is released when the guard goes out of scope
+                    Ok(_guard) => server.handle_request(stream),
+                    Err(limit) => server.reject_connection(stream, limit),
+                }
             });
         }
     }
@@ -80,11 +180,115 @@ impl Server {
         }
     }
 
-    pub fn handle_response(&self, request: Request, mut stream: &mut TcpStream) {
+    /// Answers a connection over the limits with 503, without serving anything.
+    pub fn reject_connection(&self, mut stream: TcpStream, limit: ConnectionLimit) {
+        let ip = Utils::get_peer_ip(&stream);
+        match limit {
+            ConnectionLimit::Global => Logger::warn(
+                format!(
+                    "[Server] Connection from {} refused: {} connections open",
+                    ip, self.config.max_connections
+                )
+                .as_str(),
+            ),
+            ConnectionLimit::PerClient => Logger::warn(
+                format!(
+                    "[Server] Connection from {} refused: {} connections open from this address",
+                    ip, self.config.max_connections_per_ip
+                )
+                .as_str(),
+            ),
+        }
+
+        // read the request first, closing a socket with unread data resets it and the
+        // client would never see the answer; do not let a silent client hold the thread
+        let _ = stream.set_read_timeout(Some(Self::REJECT_READ_TIMEOUT));
+        let request = match Request::from_stream(&stream) {
+            Some(request) => request,
+            None => return,
+        };
+
         if let Some(mut response) = Response::new(request, self.templates.to_owned()) {
-            response.serve(&self.config.document_root);
-            self.method_handle(&mut response);
+            response.serve_error_response(HttpStatus::ServiceUnavailable);
+            response.headers.add("Retry-After".to_string(), "1".to_string());
             self.server_transformation(&mut response);
+            if let Err(e) = response.stream(&mut stream) {
+                Logger::debug(format!("[Server] Stream error: {}", e).as_str());
+            }
+        }
+    }
+
+    pub fn handle_response(&self, request: Request, mut stream: &mut TcpStream) {
+        if let Some(mut response) = Response::new(request, self.templates.to_owned()) {
+            response.mime_types = self.config.mime_types.clone();
+            let rate_limit = self
+                .limiter
+                .check(response.request.remote_addr.as_ref(), &response.request.path);
+            let location = Location::resolve(&self.config.location, &response.request.path);
+            let admitted = self.throttle(&mut response, rate_limit.as_ref())
+                && self.check_access(&mut response)
+                && self.check_method(&mut response, &location)
+                && self.authorize(&mut response, &location);
+            if admitted {
+                response.archive_exclude = self.protected_paths(&response.request, &location);
+                let upload = Upload::rule_for(&self.config.upload, &response.request.path);
+                response.upload_form = upload.is_some_and(|rule| rule.form);
+                response.spa_fallback = Spa::fallback_for(&self.config.spa, &response.request);
+                response.clean_urls = self.config.clean_urls;
+                response.header_rules = self.config.headers.clone();
+                response.cache_rules = self.config.cache.clone();
+                if let Some(handler) = self.websockets.handler_for(&response.request.path) {
+                    if WebSocket::is_upgrade(&response.request) {
+                        return self.upgrade_websocket(response, stream, handler);
+                    }
+                    Self::refuse_websocket(&mut response);
+                } else if let Some(publisher) = self.events.publisher_for(&response.request.path) {
+                    if response.request.method == HttpMethod::GET {
+                        return self.stream_events(response, stream, publisher);
+                    }
+                    // HEAD and OPTIONS describe the stream, other methods are refused later
+                    response.serve_content(HttpStatus::Ok, EventPublisher::CONTENT_TYPE, Vec::new());
+                } else if let Some(rule) = Proxy::rule_for(&self.config.proxy, &response.request.path)
+                    .filter(|rule| !(rule.try_files && Proxy::is_local(&self.config.document_root, &response.request.path)))
+                {
+                    if let Some((code, size)) = Proxy::new(rule).handle(&mut response, stream) {
+                        return Self::log_status(&response.request, code, size);
+                    }
+                } else if let Some(script) = Cgi::script_for(&self.config.cgi, &self.config.document_root, &response.request.path) {
+                    let cgi = Cgi::new(&self.config.document_root, &script, self.config.port);
+                    if let Some((code, size)) = cgi.handle(&mut response, stream) {
+                        return Self::log_status(&response.request, code, size);
+                    }
+                } else if let Some(script) = FastCgi::script_for(&self.config.fastcgi, &self.config.document_root, &response.request.path) {
+                    let fastcgi = FastCgi::new(&self.config.document_root, &script, self.config.port, &self.backends);
+                    if let Some((code, size)) = fastcgi.handle(&mut response, stream) {
+                        return Self::log_status(&response.request, code, size);
+                    }
+                } else if let Some(rule) = upload.filter(|_| HttpMethod::upload().contains(&response.request.method)) {
+                    Upload::new(&self.config.document_root, rule)
+                        .with_scripts(self.scripts())
+                        .handle(&mut response, stream);
+                } else if self.config.webdav.methods().contains(&response.request.method) {
+                    if self.authorize_destination(&mut response) {
+                        WebDav::new(&self.config.document_root, &self.locks, &self.config.mime_types)
+                            .with_scripts(self.scripts())
+                            .handle(&mut response, stream);
+                    }
+                } else {
+                    response.serve(&self.config.document_root, &location);
+                }
+            }
+            if self.live_reload.is_some() {
+                response.inject_html(&LiveReload::script());
+            }
+            if let Some(status) = &rate_limit {
+                status.add_headers(&mut response.headers);
+            }
+            self.method_handle(&mut response, &location, admitted);
+            if response.request.method != HttpMethod::HEAD {
+                response.serve_error_page(&self.config.document_root, &location);
+            }
+            self.add_headers(&mut response, &location);
 
             let result = response.stream(stream.deref_mut());
             match result {
@@ -107,6 +311,339 @@ impl Server {
         }
     }
 
+    /// Completes the opening handshake and hands the connection to `handler` until it closes.
+    pub fn upgrade_websocket(&self, mut response: Response, stream: &mut TcpStream, handler: Arc<dyn WebSocketHandler>) {
+        let key = response.request.header("Sec-WebSocket-Key").cloned().unwrap_or_default();
+        let protocol = handler.protocol(&WebSocket::protocols(&response.request));
+
+        response.serve_empty(HttpStatus::SwitchingProtocols);
+        response.headers.add("Upgrade".to_string(), "websocket".to_string());
+        response.headers.add("Connection".to_string(), "Upgrade".to_string());
+        response
+            .headers
+            .add("Sec-WebSocket-Accept".to_string(), WebSocket::accept_key(&key));
+        if let Some(protocol) = protocol {
+            response.headers.add("Sec-WebSocket-Protocol".to_string(), protocol);
+        }
+        self.server_transformation(&mut response);
+
+        // no body and no Content-Length, the connection now speaks WebSocket
+        let handshake = format!("{}\r\n", response.http_description());
+        if let Err(e) = stream.write_all(handshake.as_bytes()).and_then(|_| stream.flush()) {
+            Logger::error(format!("[Server] Stream error: {}", e).as_str());
+            return;
+        }
+        Self::log_response(&response);
+
+        let mut socket = match stream.try_clone().and_then(|stream| WebSocket::new(stream, response.request.buffered().to_vec())) {
+            Ok(socket) => socket,
+            Err(e) => {
+                Logger::error(format!("[WebSocket] Unable to set up {}: {}", response.request.path, e).as_str());
+                return;
+            }
+        };
+        let sender = socket.sender();
+        handler.on_open(&sender, &response.request);
+
+        loop {
+            match socket.recv() {
+                Ok(Some(message)) => {
+                    Logger::debug(format!("[WebSocket] {} received on {}", message, response.request.path).as_str());
+                    handler.on_message(&sender, message);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    Logger::warn(format!("[WebSocket] Connection on {} closed: {}", response.request.path, e).as_str());
+                    break;
+                }
+            }
+        }
+
+        handler.on_close(&sender);
+        Logger::debug(format!("[WebSocket] Connection on {} ended", response.request.path).as_str());
+    }
+
+    /// Sends the head of an event stream and keeps the connection for `publisher` until the client leaves.
+    pub fn stream_events(&self, mut response: Response, stream: &mut TcpStream, publisher: EventPublisher) {
+        response.serve_empty(HttpStatus::Ok);
+        response
+            .headers
+            .add("Content-Type".to_string(), EventPublisher::CONTENT_TYPE.to_string());
+        response.headers.add("Cache-Control".to_string(), "no-cache".to_string());
+        // reverse proxies must not buffer the stream
+        response.headers.add("X-Accel-Buffering".to_string(), "no".to_string());
+        self.server_transformation(&mut response);
+
+        // no Content-Length, the body lasts as long as the connection
+        let head = format!("{}\r\n", response.http_description());
+        if let Err(e) = stream.write_all(head.as_bytes()).and_then(|_| stream.flush()) {
+            Logger::error(format!("[Server] Stream error: {}", e).as_str());
+            return;
+        }
+        Self::log_response(&response);
+
+        match publisher.serve(stream, &response.request) {
+            Ok(_) => Logger::debug(format!("[SSE] Client left {}", response.request.path).as_str()),
+            Err(e) => Logger::debug(format!("[SSE] Stream on {} closed: {}", response.request.path, e).as_str()),
+        }
+    }
+
+    // a plain request on a WebSocket endpoint, or a broken handshake
+    fn refuse_websocket(response: &mut Response) {
+        if response.request.header("Upgrade").is_some() {
+            Logger::warn(format!("[Server] Invalid WebSocket handshake on {}", response.request.path).as_str());
+            response.serve_error_response(HttpStatus::BadRequest);
+        } else {
+            response.serve_error_response(HttpStatus::UpgradeRequired);
+            response.headers.add("Upgrade".to_string(), "websocket".to_string());
+        }
+        // @see: https://www.rfc-editor.org/rfc/rfc6455#section-4.4
+        response
+            .headers
+            .add("Sec-WebSocket-Version".to_string(), WebSocket::VERSION.to_string());
+    }
+
+    /// Answers with 429 and returns false when the client ran out of tokens.
+    pub fn throttle(&self, response: &mut Response, status: Option<&RateLimitStatus>) -> bool {
+        match status {
+            Some(status) if !status.allowed => {
+                Logger::warn(
+                    format!(
+                        "[Server] Rate limit exceeded on {}, retry in {}s",
+                        response.request.path, status.retry_after
+                    )
+                    .as_str(),
+                );
+                response.serve_error_response(HttpStatus::TooManyRequests);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Applies the IP access rules, answers with 403 and returns false when the client is refused.
+    pub fn check_access(&self, response: &mut Response) -> bool {
+        let request = &response.request;
+        if AccessControl::is_allowed(&self.config.access, &request.path, request.remote_addr.as_ref()) {
+            return true;
+        }
+
+        Logger::warn(
+            format!(
+                "[Server] Access denied to {} for {}",
+                request.path,
+                request
+                    .remote_addr
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_else(|| "unknown".to_string())
+            )
+            .as_str(),
+        );
+        response.serve_error_response(HttpStatus::Forbidden);
+        false
+    }
+
+    /// Answers with 405 and returns false for the methods `location` leaves out,
+    /// `method_handle` adds the `Allow` header.
+    pub fn check_method(&self, response: &mut Response, location: &Location) -> bool {
+        if location.allows(&response.request.method) {
+            return true;
+        }
+
+        Logger::warn(
+            format!(
+                "[Server] Method {} not allowed on {}",
+                response.request.method.as_str(),
+                response.request.path
+            )
+            .as_str(),
+        );
+        response.serve_empty(HttpStatus::MethodNotAllowed);
+        false
+    }
+
+    /// The auth rule guarding `path`: the one of its location, or of the `[auth]` section.
+    pub fn auth_rule<'a>(&'a self, location: &'a Location, path: &str) -> Option<&'a AuthRule> {
+        match &location.auth {
+            Some(LocationAuth::Off) => None,
+            Some(LocationAuth::Required(rule)) => Some(rule),
+            None => BasicAuth::rule_for(&self.config.auth, path),
+        }
+    }
+
+    /// Applies the auth rules, answers with 401 and returns false when access is refused.
+    pub fn authorize(&self, response: &mut Response, location: &Location) -> bool {
+        let rule = match self.auth_rule(location, &response.request.path) {
+            Some(rule) => rule,
+            None => return true,
+        };
+
+        if let Some(user) = BasicAuth::authenticate(rule, &response.request) {
+            Logger::debug(format!("[Server] Authenticated user '{}'", user).as_str());
+            response.request.remote_user = Some(user);
+            return true;
+        }
+
+        Logger::debug(
+            format!(
+                "[Server] Authentication required for {}",
+                response.request.path
+            )
+            .as_str(),
+        );
+        response.serve_error_response(HttpStatus::Unauthorized);
+        response
+            .headers
+            .add("WWW-Authenticate".to_string(), rule.challenge());
+        false
+    }
+
+    /// COPY and MOVE write to a second path, the client must pass its access and auth rules too.
+    pub fn authorize_destination(&self, response: &mut Response) -> bool {
+        let method = response.request.method;
+        if method != HttpMethod::COPY && method != HttpMethod::MOVE {
+            return true;
+        }
+        let destination = match WebDav::destination_path(&response.request) {
+            Some(destination) => destination,
+            None => return true, // answered with 400 by WebDAV
+        };
+
+        if !AccessControl::is_allowed(&self.config.access, &destination, response.request.remote_addr.as_ref()) {
+            Logger::warn(format!("[Server] Access denied to destination {}", destination).as_str());
+            response.serve_error_response(HttpStatus::Forbidden);
+            return false;
+        }
+
+        let location = Location::resolve(&self.config.location, &destination);
+        if let Some(rule) = self.auth_rule(&location, &destination) {
+            if BasicAuth::authenticate(rule, &response.request).is_none() {
+                Logger::warn(format!("[Server] Authentication required for destination {}", destination).as_str());
+                response.serve_error_response(HttpStatus::Forbidden);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// The CGI and FastCGI rules, uploads and WebDAV do not write the files they run.
+    pub fn scripts(&self) -> ScriptRules<'_> {
+        ScriptRules { cgi: &self.config.cgi, fastcgi: &self.config.fastcgi }
+    }
+
+    /// Folders guarded by another auth rule than the one covering the request path, or
+    /// refused to the client by an access rule, they must not leak through a directory archive.
+    pub fn protected_paths(&self, request: &Request, location: &Location) -> Vec<PathBuf> {
+        let current = self.auth_rule(location, &request.path);
+        let guarded = |rule: &LocationRule| match &rule.auth {
+            Some(LocationAuth::Required(auth)) => Some(auth) != current,
+            _ => false,
+        };
+
+        let auth = self
+            .config
+            .auth
+            .iter()
+            .filter(|rule| Some(*rule) != current)
+            .map(|rule| rule.prefix.as_str());
+        let locations = self
+            .config
+            .location
+            .iter()
+            .filter(|rule| guarded(rule))
+            .filter_map(|rule| match &rule.matcher {
+                LocationMatch::Prefix(prefix) => Some(prefix.as_str()),
+                LocationMatch::Glob(_) => None,
+            });
+        let access = self
+            .config
+            .access
+            .iter()
+            .filter(|rule| {
+                !AccessControl::is_allowed(&self.config.access, &rule.prefix, request.remote_addr.as_ref())
+            })
+            .map(|rule| rule.prefix.as_str());
+
+        let mut paths: Vec<PathBuf> = auth
+            .chain(locations)
+            .chain(access)
+            .map(|prefix| self.config.document_root.join(prefix.trim_start_matches('/')))
+            .collect();
+
+        // files picked by a glob can be anywhere, only look for them when archiving
+        let globs: Vec<&LocationRule> = self
+            .config
+            .location
+            .iter()
+            .filter(|rule| matches!(rule.matcher, LocationMatch::Glob(_)) && guarded(rule))
+            .collect();
+        if !globs.is_empty() && request.queries.exists("archive") {
+            paths.extend(self.matching_paths(&request.path, &globs));
+        }
+        paths
+    }
+
+    // entries under `path` matched by one of `rules`, without following links
+    fn matching_paths(&self, path: &str, rules: &[&LocationRule]) -> Vec<PathBuf> {
+        let root = &self.config.document_root;
+        let mut found = Vec::new();
+        // the folder the archive is made of, the request path resolved as the response does
+        let path = Utils::normalize_path(PathBuf::from(path));
+        let mut pending = vec![root.join(path.to_string_lossy().trim_start_matches('/'))];
+
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                let request_path = match entry_path.strip_prefix(root) {
+                    Ok(relative) => format!("/{}", relative.to_string_lossy().replace('\\', "/")),
+                    Err(_) => continue,
+                };
+                if rules.iter().any(|rule| rule.matcher.matches(&request_path)) {
+                    found.push(Utils::normalize_path(entry_path));
+                } else if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    pending.push(entry_path);
+                }
+            }
+        }
+        found
+    }
+
+    /// Plain HTTP methods plus the WebDAV and upload ones enabled for `path`, within the
+    /// methods of its location.
+    pub fn allowed_methods(&self, path: &str, location: &Location) -> Vec<HttpMethod> {
+        let mut methods = [Self::SUPPORTED_HTTP_METHODS.to_vec(), self.config.webdav.methods()].concat();
+        if Proxy::rule_for(&self.config.proxy, path).is_some() {
+            // the upstream decides, CONNECT would turn Katana into an open tunnel
+            for method in HttpMethod::GET.to_vec() {
+                if method != HttpMethod::CONNECT && !methods.contains(&method) {
+                    methods.push(method);
+                }
+            }
+        }
+        if Cgi::script_for(&self.config.cgi, &self.config.document_root, path).is_some()
+            || FastCgi::script_for(&self.config.fastcgi, &self.config.document_root, path).is_some()
+        {
+            for method in [HttpMethod::POST, HttpMethod::PUT, HttpMethod::DELETE, HttpMethod::PATCH] {
+                if !methods.contains(&method) {
+                    methods.push(method);
+                }
+            }
+        }
+        if Upload::rule_for(&self.config.upload, path).is_some() {
+            for method in HttpMethod::upload() {
+                if !methods.contains(&method) {
+                    methods.push(method);
+                }
+            }
+        }
+        methods.retain(|method| location.allows(method));
+        methods
+    }
+
     pub fn addr(&self) -> String {
         format!("{}:{}", self.config.host, self.config.port)
     }
@@ -119,11 +656,24 @@ impl Server {
         format!("{} {}", Self::SERVER_NAME, Self::SERVER_VERSION)
     }
 
+    /// Adds the `Server` header, then the `[headers]` rules matching the answer.
+    /// The headers of an answer: `Server`, the `[headers]` rules, then those of the location
+    /// over them, the most specific setting wins.
+    pub fn add_headers(&self, response: &mut Response, location: &Location) {
+        self.server_transformation(response);
+        location.add_headers(&response.status_code, &mut response.headers);
+    }
+
     pub fn server_transformation(&self, response: &mut Response) {
         response.headers.add("Server".to_string(), Self::version());
+        ResponseHeaders::apply(&self.config.headers, &response.request.path, &mut response.headers);
     }
 
-    pub fn method_handle(&self, response: &mut Response) {
+    /// Finishes the answer for the request method, `admitted` tells whether the request
+    /// passed the rate limit, access, method and auth checks.
+    pub fn method_handle(&self, response: &mut Response, location: &Location, admitted: bool) {
+        let allowed_methods = self.allowed_methods(&response.request.path, location);
+
         if response.request.method == HttpMethod::GET {
             // nothing, process as usual
         }
@@ -136,21 +686,31 @@ impl Server {
         if response.request.method == HttpMethod::OPTIONS {
             // do not return body
             response.body = Vec::new();
+            response.size = 0;
 
             response
                 .headers
                 .add("Date".to_string(), Utils::datetime_rfc_1123().to_string());
             response.headers.add(
                 "Allow".to_string(),
-                HttpMethod::comma_separated(Self::SUPPORTED_HTTP_METHODS),
+                HttpMethod::comma_separated(&allowed_methods),
             );
+            if self.config.webdav != WebDavMode::Off {
+                // @see: https://www.rfc-editor.org/rfc/rfc4918#section-10.1
+                response
+                    .headers
+                    .add("DAV".to_string(), self.config.webdav.compliance().to_string());
+                response
+                    .headers
+                    .add("MS-Author-Via".to_string(), "DAV".to_string());
+            }
             // @see: https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS
             response
                 .headers
                 .add("Access-Control-Allow-Origin".to_string(), "*".to_string());
             response.headers.add(
                 "Access-Control-Allow-Methods".to_string(),
-                HttpMethod::comma_separated(Self::SUPPORTED_HTTP_METHODS),
+                HttpMethod::comma_separated(&allowed_methods),
             );
             // response.headers.add(
             //     "Access-Control-Allow-Headers".to_string(),
@@ -158,7 +718,8 @@ impl Server {
             // );
         }
 
-        if response.request.method == HttpMethod::TRACE {
+        // a refused request keeps its answer, an auth challenge must not turn into an echo
+        if response.request.method == HttpMethod::TRACE && admitted {
             // do not return body
             response.body = Vec::new();
 
@@ -186,14 +747,15 @@ impl Server {
             response.body = body.into_bytes();
         }
 
-        if !Self::SUPPORTED_HTTP_METHODS.contains(&response.request.method) {
+        if !allowed_methods.contains(&response.request.method) {
             // do not return body
             response.body = Vec::new();
+            response.size = 0;
             // headers
             response.headers.clear();
             response.headers.add(
                 "Allow".to_string(),
-                HttpMethod::comma_separated(Self::SUPPORTED_HTTP_METHODS),
+                HttpMethod::comma_separated(&allowed_methods),
             );
             // status
             response.status_code = HttpStatus::MethodNotAllowed;
@@ -201,18 +763,27 @@ impl Server {
     }
 
     pub fn log_response(response: &Response) {
-        let status_line = response
-            .request
+        Self::log_status(&response.request, response.status_code.to_code(), response.size);
+    }
+
+    /// Access log line for an answer that was not built as a `Response`, like a proxied one.
+    pub fn log_status(request: &Request, code: u16, size: usize) {
+        let status_line = request
             .to_string()
             .lines()
             .next()
             .unwrap()
             .to_string();
         let log_message = &format!(
-            "\"{}\" {} {}",
+            "{}\"{}\" {} {}",
+            request
+                .remote_user
+                .as_ref()
+                .map(|user| format!("{} ", user))
+                .unwrap_or_default(),
             status_line,
-            response.status_code.to_code(),
-            response.size,
+            code,
+            size,
         );
         Logger::info(log_message);
     }
@@ -0,0 +1,60 @@
+use crate::core::server::http::HttpMethod;
+use crate::core::server::request::Request;
+use crate::core::utils::utils::Utils;
+
+/// A path prefix served by a single-page application: unknown paths get its entry page, the
+/// client-side router shows the right view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaRule {
+    pub prefix: String,
+    /// entry page, a path of the document root, `/index.html`
+    pub fallback: String,
+}
+
+impl SpaRule {
+    /// A rule for `prefix`, `None` when `fallback` leaves the document root or is hidden.
+    pub fn new(prefix: &str, fallback: &str) -> Option<Self> {
+        let fallback = Utils::request_path(fallback).filter(|fallback| fallback != "/")?;
+        Some(Self {
+            prefix: prefix.to_string(),
+            fallback,
+        })
+    }
+}
+
+pub struct Spa;
+
+impl Spa {
+    /// Returns the rule with the longest prefix covering `path`, if any.
+    pub fn rule_for<'r>(rules: &'r [SpaRule], path: &str) -> Option<&'r SpaRule> {
+        Utils::longest_prefix(rules, path, |rule| &rule.prefix)
+    }
+
+    /// Entry page to serve instead of a 404 for `request`: a page navigation, GET or HEAD
+    /// accepting HTML, to a path without extension. Missing assets stay missing.
+    pub fn fallback_for(rules: &[SpaRule], request: &Request) -> Option<String> {
+        if !matches!(request.method, HttpMethod::GET | HttpMethod::HEAD)
+            || Self::is_asset(&request.path)
+            || !Self::accepts_html(request)
+        {
+            return None;
+        }
+        Self::rule_for(rules, &request.path).map(|rule| rule.fallback.clone())
+    }
+
+    /// Whether the last segment of `path` has an extension, `/app.js` or `/logo.png`.
+    pub fn is_asset(path: &str) -> bool {
+        let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        matches!(name.rsplit_once('.'), Some((stem, extension)) if !stem.is_empty() && !extension.is_empty())
+    }
+
+    /// Whether the client asks for an HTML page, as browsers do when following a link.
+    pub fn accepts_html(request: &Request) -> bool {
+        request.header("Accept").is_some_and(|accept| {
+            accept.split(',').any(|range| {
+                let media = range.split(';').next().unwrap_or("").trim().to_lowercase();
+                media == "text/html" || media == "application/xhtml+xml"
+            })
+        })
+    }
+}
@@ -0,0 +1,275 @@
+use crate::core::server::request::Request;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// @see: https://html.spec.whatwg.org/multipage/server-sent-events.html
+
+/// One message of an event stream.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Event {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    /// reconnection delay the browser should use, in milliseconds
+    pub retry: Option<u64>,
+}
+
+impl Event {
+    pub fn new(data: &str) -> Self {
+        Self {
+            data: data.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn with_event(mut self, event: &str) -> Self {
+        self.event = Some(event.to_string());
+        self
+    }
+
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry.as_millis() as u64);
+        self
+    }
+
+    // a line break would end the field, a NUL in the id makes browsers ignore it
+    fn field(value: &str) -> String {
+        value.chars().filter(|c| !matches!(c, '\r' | '\n' | '\0')).collect()
+    }
+}
+
+impl fmt::Display for Event {
+    /// The frame sent on the wire, ended by a blank line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {}", Self::field(id))?;
+        }
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {}", Self::field(event))?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry)?;
+        }
+        // every line of the data gets its own field, browsers join them back with \n
+        for line in self.data.replace("\r\n", "\n").replace('\r', "\n").split('\n') {
+            writeln!(f, "data: {}", line)?;
+        }
+        writeln!(f)
+    }
+}
+
+// one open connection, the connection thread keeps it alive with heartbeats
+#[derive(Debug, Clone)]
+struct Subscriber {
+    stream: Arc<Mutex<TcpStream>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Subscriber {
+    fn write(&self, frame: &str) -> io::Result<()> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(io::Error::new(ErrorKind::BrokenPipe, "event stream closed"));
+        }
+        let mut stream = match self.stream.lock() {
+            Ok(stream) => stream,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let result = stream.write_all(frame.as_bytes()).and_then(|_| stream.flush());
+        if result.is_err() {
+            self.closed.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+}
+
+#[derive(Debug, Default)]
+struct PublisherState {
+    subscribers: Vec<Subscriber>,
+    history: VecDeque<Event>,
+    next_id: u64,
+    retry: Option<u64>,
+}
+
+/// Sends events to every client connected to an event stream, cheap to clone and safe to
+/// use from other threads.
+///
+/// Events without an id are numbered, the last `HISTORY` events are kept so reconnecting
+/// clients get what they missed, from their `Last-Event-ID`.
+#[derive(Debug, Clone, Default)]
+pub struct EventPublisher {
+    state: Arc<Mutex<PublisherState>>,
+}
+
+impl EventPublisher {
+    pub const CONTENT_TYPE: &'static str = "text/event-stream";
+    pub const HISTORY: usize = 100;
+    pub const HEARTBEAT: Duration = Duration::from_secs(15);
+    // a client that does not read must not hold the publisher
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reconnection delay announced to the clients when they connect.
+    pub fn set_retry(&self, retry: Duration) {
+        self.lock().retry = Some(retry.as_millis() as u64);
+    }
+
+    /// Sends `event` to the connected clients and returns how many received it.
+    pub fn publish(&self, mut event: Event) -> usize {
+        let mut state = self.lock();
+        if event.id.is_none() {
+            state.next_id += 1;
+            event.id = Some(state.next_id.to_string());
+        }
+
+        let frame = event.to_string();
+        state.subscribers.retain(|subscriber| subscriber.write(&frame).is_ok());
+
+        state.history.push_back(event);
+        if state.history.len() > Self::HISTORY {
+            state.history.pop_front();
+        }
+        state.subscribers.len()
+    }
+
+    /// Publishes `data` as an event named `event`.
+    pub fn send(&self, event: &str, data: &str) -> usize {
+        self.publish(Event::new(data).with_event(event))
+    }
+
+    /// Number of connected clients.
+    pub fn subscribers(&self) -> usize {
+        self.lock().subscribers.len()
+    }
+
+    /// Events published after `last_event_id`, none when the id is unknown or too old.
+    pub fn missed(&self, last_event_id: &str) -> Vec<Event> {
+        Self::after(&self.lock(), last_event_id)
+    }
+
+    fn after(state: &PublisherState, last_event_id: &str) -> Vec<Event> {
+        match state.history.iter().position(|event| event.id.as_deref() == Some(last_event_id)) {
+            Some(index) => state.history.iter().skip(index + 1).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Writes the stream preamble on a connection whose response head was sent, replays the
+    /// missed events and keeps the connection open with heartbeats until the client leaves.
+    pub fn serve(&self, stream: &TcpStream, request: &Request) -> io::Result<()> {
+        let writer = stream.try_clone()?;
+        writer.set_write_timeout(Some(Self::WRITE_TIMEOUT))?;
+        let subscriber = Subscriber {
+            stream: Arc::new(Mutex::new(writer)),
+            closed: Arc::new(AtomicBool::new(false)),
+        };
+
+        {
+            // under the lock, no event can be published between the replay and the subscription
+            let mut state = self.lock();
+            let mut preamble = String::from(": connected\n\n");
+            if let Some(retry) = state.retry {
+                preamble.push_str(&format!("retry: {}\n\n", retry));
+            }
+            if let Some(last_event_id) = request.header("Last-Event-ID") {
+                let missed = Self::after(&state, last_event_id);
+                Logger::debug(format!("[SSE] Replaying {} event(s) after {}", missed.len(), last_event_id).as_str());
+                for event in &missed {
+                    preamble.push_str(&event.to_string());
+                }
+            }
+            subscriber.write(&preamble)?;
+            state.subscribers.push(subscriber.clone());
+        }
+
+        // clients never send anything, a read only ends when the connection does
+        let mut reader = stream.try_clone()?;
+        reader.set_read_timeout(Some(Self::HEARTBEAT))?;
+        let mut buffer = [0; 512];
+        let result = loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(_) => continue,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if let Err(e) = subscriber.write(": heartbeat\n\n") {
+                        break Err(e);
+                    }
+                }
+                Err(e) => break Err(e),
+            }
+            if subscriber.closed.load(Ordering::SeqCst) {
+                break Ok(());
+            }
+        };
+
+        subscriber.closed.store(true, Ordering::SeqCst);
+        self.lock()
+            .subscribers
+            .retain(|other| !Arc::ptr_eq(&other.stream, &subscriber.stream));
+        result
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PublisherState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Event streams mounted on exact paths.
+#[derive(Clone, Default)]
+pub struct EventRoutes {
+    routes: Vec<(String, EventPublisher)>,
+}
+
+impl EventRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mount(&mut self, path: &str, publisher: EventPublisher) {
+        let path = Self::normalize(path);
+        self.routes.retain(|(route, _)| *route != path);
+        self.routes.push((path, publisher));
+    }
+
+    /// Publisher mounted exactly on `path`, a trailing slash does not matter.
+    pub fn publisher_for(&self, path: &str) -> Option<EventPublisher> {
+        let path = Self::normalize(path);
+        self.routes
+            .iter()
+            .find(|(route, _)| *route == path)
+            .map(|(_, publisher)| publisher.clone())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    fn normalize(path: &str) -> String {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        format!("/{}", normalized.to_string_lossy().trim_matches('/'))
+    }
+}
+
+impl fmt::Debug for EventRoutes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.routes.iter().map(|(path, _)| path)).finish()
+    }
+}
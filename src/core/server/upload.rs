@@ -0,0 +1,382 @@
+use crate::core::server::cgi::ScriptRules;
+use crate::core::server::http::{HttpMethod, HttpStatus};
+use crate::core::server::multipart::{Multipart, MultipartReader};
+use crate::core::server::response::Response;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::utils::Utils;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Error, ErrorKind, Read};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// What happens when an upload targets an existing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// replace the file
+    Allow,
+    /// refuse with 409 Conflict
+    Deny,
+    /// store under a free name, `report (1).pdf`
+    Rename,
+}
+
+impl OverwritePolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "allow" | "true" => Some(Self::Allow),
+            "deny" | "false" => Some(Self::Deny),
+            "rename" => Some(Self::Rename),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Rename => "rename",
+        }
+    }
+}
+
+/// A writable path prefix, accepting `PUT` and `multipart/form-data` `POST` uploads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadRule {
+    pub prefix: String,
+    /// largest accepted file in bytes, 0 for no limit
+    pub max_size: u64,
+    /// bytes all files under the prefix may take together, 0 for no limit
+    pub quota: u64,
+    pub overwrite: OverwritePolicy,
+    /// show the upload form in directory listings
+    pub form: bool,
+}
+
+impl UploadRule {
+    pub const DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            max_size: Self::DEFAULT_MAX_SIZE,
+            quota: 0,
+            overwrite: OverwritePolicy::Deny,
+            form: true,
+        }
+    }
+
+    /// Applies one `max_size 2G`, `quota 10G`, `overwrite rename` or `form off` option.
+    pub fn set(&mut self, option: &str) -> bool {
+        let (key, value) = match option.trim().split_once(char::is_whitespace) {
+            Some((key, value)) => (key, value.trim()),
+            None => return false,
+        };
+
+        match key.to_lowercase().as_str() {
+            "max_size" => Utils::parse_size(value).map(|size| self.max_size = size).is_some(),
+            "quota" => Utils::parse_size(value).map(|size| self.quota = size).is_some(),
+            "overwrite" => OverwritePolicy::from_name(value).map(|policy| self.overwrite = policy).is_some(),
+            "form" => match value.to_lowercase().as_str() {
+                "on" | "true" => {
+                    self.form = true;
+                    true
+                }
+                "off" | "false" => {
+                    self.form = false;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+pub struct Upload<'a> {
+    root: &'a Path,
+    rule: &'a UploadRule,
+    // files these would run are refused
+    scripts: ScriptRules<'a>,
+}
+
+impl<'a> Upload<'a> {
+    const MAX_RENAME: usize = 1000;
+
+    pub fn new(root: &'a Path, rule: &'a UploadRule) -> Self {
+        Self { root, rule, scripts: ScriptRules::default() }
+    }
+
+    pub fn with_scripts(mut self, scripts: ScriptRules<'a>) -> Self {
+        self.scripts = scripts;
+        self
+    }
+
+    /// Returns the rule with the longest prefix covering `path`, if any.
+    pub fn rule_for<'r>(rules: &'r [UploadRule], path: &str) -> Option<&'r UploadRule> {
+        Utils::longest_prefix(rules, path, |rule| &rule.prefix)
+    }
+
+    pub fn handle(&self, response: &mut Response, stream: &TcpStream) {
+        let path = match Utils::request_path(&response.request.path) {
+            Some(path) => path,
+            None => {
+                Logger::warn(format!("[Upload] Refusing hidden path {}", response.request.path).as_str());
+                response.request.discard_body(stream);
+                response.serve_error_response(HttpStatus::Forbidden);
+                return;
+            }
+        };
+
+        match response.request.method {
+            HttpMethod::PUT => self.put(response, &path, stream),
+            HttpMethod::POST => self.post(response, &path, stream),
+            _ => response.serve_error_response(HttpStatus::MethodNotAllowed),
+        }
+    }
+
+    /// Keeps only the last segment of a client file name, `None` when nothing usable is left.
+    pub fn file_name(name: &str) -> Option<String> {
+        // browsers on Windows used to send the full local path
+        let name = name.rsplit(['/', '\\']).next()?.trim();
+        if name.is_empty() || !Utils::is_valid_entry(name) || name.chars().any(|c| c.is_control()) {
+            return None;
+        }
+        Some(name.to_string())
+    }
+
+    /// Writes to a hidden temporary file next to `target` and renames it once complete,
+    /// readers never see a half written file. Fails with `FileTooLarge` past `limit` bytes.
+    pub fn write_atomically(target: &Path, reader: &mut dyn Read, limit: Option<u64>) -> io::Result<u64> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let name = target.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let temporary = target.with_file_name(format!(
+            ".{}.{}-{}.part",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = File::create(&temporary)
+            .and_then(|mut file| {
+                let size = match limit {
+                    // one byte more tells an exact fit from an overflow
+                    Some(limit) => io::copy(&mut reader.take(limit.saturating_add(1)), &mut file)?,
+                    None => io::copy(reader, &mut file)?,
+                };
+                if limit.is_some_and(|limit| size > limit) {
+                    return Err(Error::new(ErrorKind::FileTooLarge, "upload exceeds the size limit"));
+                }
+                file.sync_all().map(|_| size)
+            })
+            .and_then(|size| fs::rename(&temporary, target).map(|_| size));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        result
+    }
+
+    fn put(&self, response: &mut Response, path: &str, stream: &TcpStream) {
+        let file_path = self.file_path(path);
+        let request = &response.request;
+
+        let refused = if request.header("Content-Range").is_some() {
+            // partial PUT is not part of HTTP, @see: https://www.rfc-editor.org/rfc/rfc9110#section-14.5
+            Some(HttpStatus::BadRequest)
+        } else if self.scripts.run(path) {
+            Some(HttpStatus::Forbidden)
+        } else if path == "/" || file_path.is_dir() {
+            Some(HttpStatus::MethodNotAllowed)
+        } else if request.header("If-None-Match").is_some_and(|value| value.trim() == "*") && file_path.exists() {
+            Some(HttpStatus::PreconditionFailed)
+        } else if self.rule.max_size > 0 && request.content_length().unwrap_or(0) > self.rule.max_size {
+            Some(HttpStatus::PayloadTooLarge)
+        } else {
+            None
+        };
+        if let Some(status) = refused {
+            request.discard_body(stream);
+            return response.serve_error_response(status);
+        }
+
+        let result = self.store(&file_path, &mut request.body_reader(stream));
+        match result {
+            Ok((target, size, existed)) => {
+                let location = self.url_path(&target);
+                Logger::info(format!("[Upload] Stored {} ({} bytes)", location, size).as_str());
+                if existed {
+                    response.serve_empty(HttpStatus::NoContent);
+                } else {
+                    response.serve_empty(HttpStatus::Created);
+                    response.headers.add("Location".to_string(), Utils::encode_path(&location));
+                }
+            }
+            Err(e) => self.fail(response, path, e),
+        }
+    }
+
+    fn post(&self, response: &mut Response, path: &str, stream: &TcpStream) {
+        let folder = self.file_path(path);
+        let request = &response.request;
+
+        let boundary = request.header("Content-Type").and_then(|value| Multipart::boundary(value));
+        let boundary = match boundary {
+            Some(boundary) if folder.is_dir() => boundary,
+            boundary => {
+                request.discard_body(stream);
+                let status = if boundary.is_some() { HttpStatus::NotFound } else { HttpStatus::UnsupportedMediaType };
+                return response.serve_error_response(status);
+            }
+        };
+
+        let mut stored = Vec::new();
+        let result = {
+            let mut reader = MultipartReader::new(request.body_reader(stream), &boundary);
+            self.store_parts(&folder, &mut reader, &mut stored)
+        };
+
+        match result {
+            Err(e) => self.fail(response, path, e),
+            Ok(_) if stored.is_empty() => response.serve_error_response(HttpStatus::BadRequest),
+            Ok(_) => {
+                let body = stored.iter().map(|location| format!("{}\n", location)).collect::<String>();
+                response.serve_content(HttpStatus::Created, "text/plain; charset=utf-8", body.into_bytes());
+                response.headers.add("Location".to_string(), Utils::encode_path(&stored[0]));
+            }
+        }
+    }
+
+    // stores every file part of the form, plain fields are skipped
+    fn store_parts<R: Read>(&self, folder: &Path, reader: &mut MultipartReader<R>, stored: &mut Vec<String>) -> io::Result<()> {
+        while let Some(part) = reader.next_part()? {
+            // plain form fields and file inputs left empty
+            let name = match part.filename.as_deref() {
+                None | Some("") => continue,
+                Some(name) => name,
+            };
+            let name = Self::file_name(name)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("invalid file name '{}'", name)))?;
+
+            let (target, size, _) = self.store(&folder.join(&name), reader)?;
+            let location = self.url_path(&target);
+            Logger::info(format!("[Upload] Stored {} ({} bytes)", location, size).as_str());
+            stored.push(location);
+        }
+        Ok(())
+    }
+
+    // applies the overwrite policy and the quota, then writes the file;
+    // returns where it went, its size and whether a file was replaced
+    fn store(&self, file_path: &Path, reader: &mut dyn Read) -> io::Result<(PathBuf, u64, bool)> {
+        if self.scripts.run(&self.url_path(file_path)) {
+            return Err(Error::new(ErrorKind::PermissionDenied, "the file would run as a script"));
+        }
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // deny and rename claim the name with an empty file first, two uploads of one name
+        // cannot both find it free; the upload then replaces its own placeholder
+        let (target, replaced) = match self.rule.overwrite {
+            OverwritePolicy::Allow => (file_path.to_path_buf(), file_path.exists()),
+            OverwritePolicy::Deny if !Self::claim(file_path)? => {
+                return Err(Error::new(ErrorKind::AlreadyExists, "file exists"));
+            }
+            OverwritePolicy::Deny => (file_path.to_path_buf(), false),
+            OverwritePolicy::Rename => (Self::free_name(file_path)?, false),
+        };
+
+        let result = Self::write_atomically(&target, reader, self.limit(&target));
+        if result.is_err() && !replaced {
+            let _ = fs::remove_file(&target);
+        }
+        match result {
+            // the quota is exceeded when the file took all the space left
+            Err(e) if e.kind() == ErrorKind::FileTooLarge && self.limit(&target) != self.max_size() => {
+                Err(Error::new(ErrorKind::StorageFull, "upload quota exceeded"))
+            }
+            result => result.map(|size| (target, size, replaced)),
+        }
+    }
+
+    // room left for a file at `target`, the file it replaces does not count
+    fn limit(&self, target: &Path) -> Option<u64> {
+        if self.rule.quota == 0 {
+            return self.max_size();
+        }
+
+        let replaced = fs::metadata(target).map(|metadata| metadata.len()).unwrap_or(0);
+        let used = Self::disk_usage(&self.file_path(&self.rule.prefix)).saturating_sub(replaced);
+        let left = self.rule.quota.saturating_sub(used);
+        Some(self.max_size().map_or(left, |max_size| max_size.min(left)))
+    }
+
+    fn max_size(&self) -> Option<u64> {
+        Some(self.rule.max_size).filter(|size| *size > 0)
+    }
+
+    fn disk_usage(path: &Path) -> u64 {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+                .map(|entries| entries.flatten().map(|entry| Self::disk_usage(&entry.path())).sum())
+                .unwrap_or(0),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        }
+    }
+
+    // "name (1).ext", "name (2).ext"... next to `path`
+    // the name itself when free, else the first free `name (n).ext`, claimed
+    fn free_name(path: &Path) -> io::Result<PathBuf> {
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+
+        let candidates = (1..=Self::MAX_RENAME).map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)));
+        for candidate in std::iter::once(path.to_path_buf()).chain(candidates) {
+            if Self::claim(&candidate)? {
+                return Ok(candidate);
+            }
+        }
+        Err(Error::new(ErrorKind::AlreadyExists, "no free file name left"))
+    }
+
+    // creates `path` empty, `false` when something already has the name
+    fn claim(path: &Path) -> io::Result<bool> {
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn fail(&self, response: &mut Response, path: &str, e: Error) {
+        let status = match e.kind() {
+            ErrorKind::AlreadyExists => HttpStatus::Conflict,
+            ErrorKind::PermissionDenied => HttpStatus::Forbidden,
+            ErrorKind::FileTooLarge => HttpStatus::PayloadTooLarge,
+            ErrorKind::StorageFull => HttpStatus::InsufficientStorage,
+            ErrorKind::InvalidInput | ErrorKind::InvalidData | ErrorKind::UnexpectedEof => HttpStatus::BadRequest,
+            _ => HttpStatus::InternalServerError,
+        };
+        if status.to_code() >= 500 {
+            Logger::error(format!("[Upload] Unable to store {}: {}", path, e).as_str());
+        } else {
+            Logger::warn(format!("[Upload] Refused upload to {}: {}", path, e).as_str());
+        }
+        response.serve_error_response(status);
+    }
+
+    fn file_path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    fn url_path(&self, file_path: &Path) -> String {
+        let relative = file_path.strip_prefix(self.root).unwrap_or(file_path);
+        format!("/{}", relative.to_string_lossy().replace('\\', "/"))
+    }
+}
@@ -0,0 +1,887 @@
+use crate::core::server::cgi::ScriptRules;
+use crate::core::server::filetype::{FileType, MimeRegistry};
+use crate::core::server::http::{HttpMethod, HttpStatus};
+use crate::core::server::request::Request;
+use crate::core::server::response::Response;
+use crate::core::server::upload::Upload;
+use crate::core::utils::logger::Logger;
+use crate::core::utils::sha2::Sha256;
+use crate::core::utils::utils::Utils;
+use crate::core::utils::xml::{Xml, XmlElement};
+use std::fs::{self, File, Metadata};
+use std::io::{self, ErrorKind};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DAV: &str = "DAV:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebDavMode {
+    Off,
+    /// PROPFIND only, enough to browse and download from a file manager
+    ReadOnly,
+    ReadWrite,
+}
+
+impl WebDavMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "off" | "false" => Some(Self::Off),
+            "read-only" | "readonly" => Some(Self::ReadOnly),
+            "read-write" | "readwrite" | "on" | "true" => Some(Self::ReadWrite),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Off => "off",
+            Self::ReadOnly => "read-only",
+            Self::ReadWrite => "read-write",
+        }
+    }
+
+    /// Methods answered by WebDAV on top of the plain HTTP ones.
+    pub fn methods(&self) -> Vec<HttpMethod> {
+        match self {
+            Self::Off => Vec::new(),
+            Self::ReadOnly => HttpMethod::webdav_read(),
+            Self::ReadWrite => [HttpMethod::webdav_read(), HttpMethod::webdav_write()].concat(),
+        }
+    }
+
+    /// Value of the `DAV` header, class 2 comes with locking.
+    pub fn compliance(&self) -> &str {
+        match self {
+            Self::Off => "",
+            Self::ReadOnly => "1",
+            Self::ReadWrite => "1, 2",
+        }
+    }
+}
+
+/// A write lock taken with LOCK, kept in memory only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DavLock {
+    pub token: String,
+    pub path: String,
+    pub exclusive: bool,
+    pub infinite: bool,
+    pub owner: String,
+    pub timeout: Duration,
+    expires: Instant,
+}
+
+impl DavLock {
+    /// Tells whether the lock applies to the resource at `path`.
+    fn applies_to(&self, path: &str) -> bool {
+        path == self.path || (self.infinite && Utils::path_matches_prefix(path, &self.path))
+    }
+
+    /// Like `applies_to`, a locked collection also protects its list of members.
+    fn covers(&self, path: &str) -> bool {
+        self.applies_to(path) || WebDav::parent(path) == self.path
+    }
+
+    fn to_xml(&self) -> String {
+        format!(
+            "<D:activelock><D:locktype><D:write/></D:locktype><D:lockscope>{}</D:lockscope>\
+             <D:depth>{}</D:depth>{}<D:timeout>Second-{}</D:timeout>\
+             <D:locktoken><D:href>{}</D:href></D:locktoken>\
+             <D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock>",
+            if self.exclusive { "<D:exclusive/>" } else { "<D:shared/>" },
+            if self.infinite { "infinity" } else { "0" },
+            if self.owner.is_empty() { String::new() } else { format!("<D:owner>{}</D:owner>", self.owner) },
+            self.timeout.as_secs(),
+            Xml::escape(&self.token),
+            Xml::escape(&Utils::encode_path(&self.path)),
+        )
+    }
+}
+
+/// Shared lock table, every clone sees the same locks.
+#[derive(Debug, Clone, Default)]
+pub struct LockManager {
+    locks: Arc<Mutex<Vec<DavLock>>>,
+}
+
+impl LockManager {
+    pub const MAX_TIMEOUT: Duration = Duration::from_secs(3600);
+    const MAX_LOCKS: usize = 10_000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lock(
+        &self,
+        path: &str,
+        exclusive: bool,
+        infinite: bool,
+        owner: &str,
+        timeout: Duration,
+    ) -> Option<DavLock> {
+        let mut locks = self.active();
+        if locks.len() >= Self::MAX_LOCKS {
+            return None;
+        }
+
+        let conflict = locks.iter().any(|lock| {
+            let overlaps = lock.applies_to(path) || (infinite && Utils::path_matches_prefix(&lock.path, path));
+            overlaps && (exclusive || lock.exclusive)
+        });
+        if conflict {
+            return None;
+        }
+
+        let lock = DavLock {
+            token: Self::new_token(),
+            path: path.to_string(),
+            exclusive,
+            infinite,
+            owner: owner.to_string(),
+            timeout,
+            expires: Instant::now() + timeout,
+        };
+        locks.push(lock.clone());
+        Some(lock)
+    }
+
+    /// Extends the lock named in the `If` header, when it applies to `path`.
+    pub fn refresh(&self, path: &str, if_header: &str, timeout: Duration) -> Option<DavLock> {
+        let mut locks = self.active();
+        let lock = locks
+            .iter_mut()
+            .find(|lock| if_header.contains(&lock.token) && lock.applies_to(path))?;
+        lock.timeout = timeout;
+        lock.expires = Instant::now() + timeout;
+        Some(lock.clone())
+    }
+
+    pub fn unlock(&self, path: &str, token: &str) -> bool {
+        let mut locks = self.active();
+        let before = locks.len();
+        locks.retain(|lock| !(lock.token == token && lock.applies_to(path)));
+        locks.len() != before
+    }
+
+    /// Locks applying to `path` itself, for the lockdiscovery property.
+    pub fn discover(&self, path: &str) -> Vec<DavLock> {
+        self.active()
+            .iter()
+            .filter(|lock| lock.applies_to(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether `path` may be changed, every lock on it (or below it when `recursive`)
+    /// must have its token submitted in the `If` header.
+    pub fn permits(&self, path: &str, recursive: bool, if_header: Option<&String>) -> bool {
+        let submitted = if_header.map(|value| value.as_str()).unwrap_or("");
+        self.active()
+            .iter()
+            .filter(|lock| lock.covers(path) || (recursive && Utils::path_matches_prefix(&lock.path, path)))
+            .all(|lock| submitted.contains(&lock.token))
+    }
+
+    /// Drops the locks on `path` and below, after the resource is gone.
+    pub fn release(&self, path: &str) {
+        self.active()
+            .retain(|lock| !Utils::path_matches_prefix(&lock.path, path));
+    }
+
+    fn active(&self) -> MutexGuard<'_, Vec<DavLock>> {
+        let mut locks = match self.locks.lock() {
+            Ok(locks) => locks,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let now = Instant::now();
+        locks.retain(|lock| lock.expires > now);
+        locks
+    }
+
+    // @see: https://www.rfc-editor.org/rfc/rfc4918#appendix-C
+    fn new_token() -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let mut seed = Vec::new();
+        seed.extend_from_slice(&nanos.to_le_bytes());
+        seed.extend_from_slice(&COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+        seed.extend_from_slice(&(&seed as *const Vec<u8> as usize).to_le_bytes());
+        seed.extend_from_slice(format!("{:?}", std::thread::current().id()).as_bytes());
+
+        let mut bytes = Sha256::digest(&seed);
+        // version 4 and RFC 4122 variant bits
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex: String = bytes[..16].iter().map(|b| format!("{:02x}", b)).collect();
+        format!(
+            "opaquelocktoken:{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+}
+
+/// WebDAV class 1 and 2 on top of the document root.
+// @see: https://www.rfc-editor.org/rfc/rfc4918
+pub struct WebDav<'a> {
+    root: &'a Path,
+    locks: &'a LockManager,
+    // answers `getcontenttype`
+    mime_types: &'a MimeRegistry,
+    // files these would run are never written
+    scripts: ScriptRules<'a>,
+}
+
+impl<'a> WebDav<'a> {
+    pub fn new(root: &'a Path, locks: &'a LockManager, mime_types: &'a MimeRegistry) -> Self {
+        Self { root, locks, mime_types, scripts: ScriptRules::default() }
+    }
+
+    pub fn with_scripts(mut self, scripts: ScriptRules<'a>) -> Self {
+        self.scripts = scripts;
+        self
+    }
+
+    pub fn handle(&self, response: &mut Response, stream: &TcpStream) {
+        let path = match Utils::request_path(&response.request.path) {
+            Some(path) => path,
+            None => {
+                Logger::warn(format!("[WebDAV] Refusing hidden path {}", response.request.path).as_str());
+                response.request.discard_body(stream);
+                response.serve_error_response(HttpStatus::Forbidden);
+                return;
+            }
+        };
+
+        match response.request.method {
+            HttpMethod::PROPFIND => self.propfind(response, &path),
+            HttpMethod::PROPPATCH => self.proppatch(response, &path),
+            HttpMethod::MKCOL => self.mkcol(response, &path),
+            HttpMethod::PUT => self.put(response, &path, stream),
+            HttpMethod::DELETE => self.delete(response, &path),
+            HttpMethod::COPY => self.copy_or_move(response, &path, false),
+            HttpMethod::MOVE => self.copy_or_move(response, &path, true),
+            HttpMethod::LOCK => self.lock(response, &path),
+            HttpMethod::UNLOCK => self.unlock(response, &path),
+            _ => response.serve_error_response(HttpStatus::MethodNotAllowed),
+        }
+    }
+
+    /// Path of the `Destination` header of COPY and MOVE, normalized.
+    pub fn destination_path(request: &Request) -> Option<String> {
+        let destination = request.header("Destination")?.trim();
+        let path = match destination.split_once("://") {
+            // absolute URI, drop scheme and authority
+            Some((_, rest)) => rest.find('/').map(|index| &rest[index..]).unwrap_or("/"),
+            None => destination,
+        };
+        let path = path.split(['?', '#']).next().unwrap_or("/");
+        Utils::request_path(&Request::decode_path(path))
+    }
+
+    fn parent(path: &str) -> String {
+        match path.trim_end_matches('/').rsplit_once('/') {
+            Some(("", _)) | None => "/".to_string(),
+            Some((parent, _)) => parent.to_string(),
+        }
+    }
+
+    fn file_path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    fn propfind(&self, response: &mut Response, path: &str) {
+        let file_path = self.file_path(path);
+        let metadata = match fs::metadata(&file_path) {
+            Ok(metadata) => metadata,
+            Err(_) => return response.serve_error_response(HttpStatus::NotFound),
+        };
+
+        let depth = response.request.header("Depth").map(|d| d.trim().to_lowercase());
+        let include_children = match depth.as_deref() {
+            Some("0") => false,
+            Some("1") => true,
+            // "infinity" is the default, walking a whole tree on request is too expensive
+            _ => {
+                return Self::serve_error_condition(response, HttpStatus::Forbidden, "propfind-finite-depth");
+            }
+        };
+
+        let query = match PropfindQuery::parse(&response.request.body) {
+            Some(query) => query,
+            None => return response.serve_error_response(HttpStatus::BadRequest),
+        };
+
+        let mut body = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">");
+        body.push_str(&self.propfind_response(path, &metadata, &query));
+
+        if include_children && metadata.is_dir() {
+            let mut entries: Vec<(String, Metadata)> = fs::read_dir(&file_path)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .filter_map(|entry| {
+                            let name = entry.file_name().to_str()?.to_string();
+                            let metadata = fs::metadata(entry.path()).ok()?;
+                            Utils::is_valid_entry(&name).then_some((name, metadata))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            for (name, metadata) in entries {
+                let child = format!("{}/{}", path.trim_end_matches('/'), name);
+                body.push_str(&self.propfind_response(&child, &metadata, &query));
+            }
+        }
+
+        body.push_str("</D:multistatus>");
+        Self::serve_multistatus(response, body);
+    }
+
+    fn propfind_response(&self, path: &str, metadata: &Metadata, query: &PropfindQuery) -> String {
+        let mut href = Utils::encode_path(path);
+        if metadata.is_dir() && !href.ends_with('/') {
+            href.push('/');
+        }
+
+        let mut found = String::new();
+        let mut missing = String::new();
+
+        match query {
+            PropfindQuery::PropName => {
+                for name in self.live_properties(metadata) {
+                    found.push_str(&format!("<D:{}/>", name));
+                }
+            }
+            PropfindQuery::AllProp => {
+                for name in self.live_properties(metadata) {
+                    if let Some(value) = self.property(path, metadata, name) {
+                        found.push_str(&value);
+                    }
+                }
+            }
+            PropfindQuery::Prop(properties) => {
+                for property in properties {
+                    let value = if property.namespace == DAV {
+                        self.property(path, metadata, &property.name)
+                    } else {
+                        None
+                    };
+                    match value {
+                        Some(value) => found.push_str(&value),
+                        None => missing.push_str(&property.to_xml()),
+                    }
+                }
+            }
+        }
+
+        let mut result = format!("<D:response><D:href>{}</D:href>", Xml::escape(&href));
+        if !found.is_empty() || missing.is_empty() {
+            result.push_str(&Self::propstat(&found, HttpStatus::Ok));
+        }
+        if !missing.is_empty() {
+            result.push_str(&Self::propstat(&missing, HttpStatus::NotFound));
+        }
+        result.push_str("</D:response>");
+        result
+    }
+
+    fn live_properties(&self, metadata: &Metadata) -> Vec<&'static str> {
+        let mut names = vec![
+            "displayname",
+            "resourcetype",
+            "creationdate",
+            "getlastmodified",
+            "getetag",
+            "supportedlock",
+            "lockdiscovery",
+        ];
+        if metadata.is_file() {
+            names.extend(["getcontentlength", "getcontenttype"]);
+        }
+        names
+    }
+
+    fn property(&self, path: &str, metadata: &Metadata, name: &str) -> Option<String> {
+        let modified = Self::timestamp(metadata.modified().ok());
+
+        let value = match name {
+            "displayname" => {
+                let name = path.rsplit('/').next().unwrap_or("");
+                Xml::escape(if name.is_empty() { "/" } else { name })
+            }
+            "resourcetype" => {
+                if metadata.is_dir() {
+                    "<D:collection/>".to_string()
+                } else {
+                    String::new()
+                }
+            }
+            "creationdate" => {
+                let created = Self::timestamp(metadata.created().ok()).unwrap_or(modified.unwrap_or(0));
+                let (year, month, day, hours, minutes, seconds) = Utils::date_components(created);
+                format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+                    year, month, day, hours, minutes, seconds
+                )
+            }
+            "getlastmodified" => Utils::http_date(modified?),
+            "getetag" => format!("\"{:x}-{:x}\"", modified.unwrap_or(0), metadata.len()),
+            "getcontentlength" if metadata.is_file() => metadata.len().to_string(),
+            "getcontenttype" if metadata.is_file() => {
+                let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+                FileType::for_extension(self.mime_types, extension).content_type
+            }
+            "supportedlock" => "<D:lockentry><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry>\
+                 <D:lockentry><D:lockscope><D:shared/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry>"
+                .to_string(),
+            "lockdiscovery" => self
+                .locks
+                .discover(path)
+                .iter()
+                .map(|lock| lock.to_xml())
+                .collect(),
+            _ => return None,
+        };
+
+        Some(if value.is_empty() {
+            format!("<D:{}/>", name)
+        } else {
+            format!("<D:{}>{}</D:{}>", name, value, name)
+        })
+    }
+
+    fn proppatch(&self, response: &mut Response, path: &str) {
+        if !self.file_path(path).exists() {
+            return response.serve_error_response(HttpStatus::NotFound);
+        }
+        if !self.locks.permits(path, false, response.request.header("If")) {
+            return response.serve_error_response(HttpStatus::Locked);
+        }
+
+        let update = match Xml::parse(&response.request.body) {
+            Some(update) if update.is(DAV, "propertyupdate") => update,
+            _ => return response.serve_error_response(HttpStatus::BadRequest),
+        };
+
+        // properties are not stored, every change is refused as a whole
+        let properties: String = update
+            .children
+            .iter()
+            .filter(|child| child.is(DAV, "set") || child.is(DAV, "remove"))
+            .filter_map(|child| child.child(DAV, "prop"))
+            .flat_map(|prop| prop.children.iter())
+            .map(|property| XmlElement {
+                children: Vec::new(),
+                text: String::new(),
+                ..property.clone()
+            }
+            .to_xml())
+            .collect();
+
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\"><D:response><D:href>{}</D:href>{}</D:response></D:multistatus>",
+            Xml::escape(&Utils::encode_path(path)),
+            Self::propstat(&properties, HttpStatus::Forbidden)
+        );
+        Self::serve_multistatus(response, body);
+    }
+
+    fn mkcol(&self, response: &mut Response, path: &str) {
+        let file_path = self.file_path(path);
+
+        if response.request.has_body() {
+            return response.serve_error_response(HttpStatus::UnsupportedMediaType);
+        }
+        if file_path.exists() {
+            return response.serve_error_response(HttpStatus::MethodNotAllowed);
+        }
+        if !self.file_path(&Self::parent(path)).is_dir() {
+            return response.serve_error_response(HttpStatus::Conflict);
+        }
+        if !self.locks.permits(path, false, response.request.header("If")) {
+            return response.serve_error_response(HttpStatus::Locked);
+        }
+
+        match fs::create_dir(&file_path) {
+            Ok(_) => {
+                Logger::info(format!("[WebDAV] Created collection {}", path).as_str());
+                response.serve_empty(HttpStatus::Created);
+            }
+            Err(e) => {
+                Logger::error(format!("[WebDAV] Unable to create {}: {}", path, e).as_str());
+                response.serve_error_response(HttpStatus::InternalServerError);
+            }
+        }
+    }
+
+    fn put(&self, response: &mut Response, path: &str, stream: &TcpStream) {
+        let file_path = self.file_path(path);
+        let request = &response.request;
+
+        let refused = if request.header("Content-Range").is_some() {
+            // partial PUT is not part of HTTP, @see: https://www.rfc-editor.org/rfc/rfc9110#section-14.5
+            Some(HttpStatus::BadRequest)
+        } else if self.scripts.run(path) {
+            Some(HttpStatus::Forbidden)
+        } else if file_path.is_dir() {
+            Some(HttpStatus::MethodNotAllowed)
+        } else if !self.file_path(&Self::parent(path)).is_dir() {
+            Some(HttpStatus::Conflict)
+        } else if !self.locks.permits(path, false, request.header("If")) {
+            Some(HttpStatus::Locked)
+        } else {
+            None
+        };
+        if let Some(status) = refused {
+            request.discard_body(stream);
+            return response.serve_error_response(status);
+        }
+
+        let existed = file_path.exists();
+        let result = Upload::write_atomically(&file_path, &mut request.body_reader(stream), None);
+        match result {
+            Ok(size) => {
+                Logger::info(format!("[WebDAV] Stored {} ({} bytes)", path, size).as_str());
+                response.serve_empty(if existed { HttpStatus::NoContent } else { HttpStatus::Created });
+            }
+            Err(e) => {
+                Logger::error(format!("[WebDAV] Unable to store {}: {}", path, e).as_str());
+                let status = match e.kind() {
+                    ErrorKind::InvalidData | ErrorKind::UnexpectedEof => HttpStatus::BadRequest,
+                    _ => HttpStatus::InternalServerError,
+                };
+                response.serve_error_response(status);
+            }
+        }
+    }
+
+    fn delete(&self, response: &mut Response, path: &str) {
+        let file_path = self.file_path(path);
+
+        if path == "/" {
+            return response.serve_error_response(HttpStatus::Forbidden);
+        }
+        if !file_path.exists() {
+            return response.serve_error_response(HttpStatus::NotFound);
+        }
+        if !self.locks.permits(path, true, response.request.header("If")) {
+            return response.serve_error_response(HttpStatus::Locked);
+        }
+
+        match Self::remove(&file_path) {
+            Ok(_) => {
+                Logger::info(format!("[WebDAV] Deleted {}", path).as_str());
+                self.locks.release(path);
+                response.serve_empty(HttpStatus::NoContent);
+            }
+            Err(e) => {
+                Logger::error(format!("[WebDAV] Unable to delete {}: {}", path, e).as_str());
+                response.serve_error_response(HttpStatus::InternalServerError);
+            }
+        }
+    }
+
+    fn copy_or_move(&self, response: &mut Response, path: &str, is_move: bool) {
+        let source = self.file_path(path);
+        let request = &response.request;
+
+        let destination = match Self::destination_path(request) {
+            Some(destination) => destination,
+            None => return response.serve_error_response(HttpStatus::BadRequest),
+        };
+        let target = self.file_path(&destination);
+        let overwrite = !request
+            .header("Overwrite")
+            .map(|value| value.trim().eq_ignore_ascii_case("F"))
+            .unwrap_or(false);
+        let infinite = match request.header("Depth").map(|d| d.trim().to_lowercase()).as_deref() {
+            None | Some("infinity") => true,
+            Some("0") if !is_move => false,
+            _ => return response.serve_error_response(HttpStatus::BadRequest),
+        };
+
+        if !source.exists() {
+            return response.serve_error_response(HttpStatus::NotFound);
+        }
+        if path == "/" || destination == "/" || Utils::path_matches_prefix(&destination, path) || Utils::path_matches_prefix(path, &destination) {
+            return response.serve_error_response(HttpStatus::Forbidden);
+        }
+        if self.places_script(&source, &destination) {
+            Logger::warn(format!("[WebDAV] Refusing to place a script at {}", destination).as_str());
+            return response.serve_error_response(HttpStatus::Forbidden);
+        }
+        if !self.file_path(&Self::parent(&destination)).is_dir() {
+            return response.serve_error_response(HttpStatus::Conflict);
+        }
+        let existed = target.exists();
+        if existed && !overwrite {
+            return response.serve_error_response(HttpStatus::PreconditionFailed);
+        }
+        let if_header = request.header("If");
+        if (is_move && !self.locks.permits(path, true, if_header))
+            || !self.locks.permits(&destination, true, if_header)
+        {
+            return response.serve_error_response(HttpStatus::Locked);
+        }
+
+        let result = (if existed { Self::remove(&target) } else { Ok(()) }).and_then(|_| {
+            if is_move {
+                fs::rename(&source, &target).or_else(|_| {
+                    // rename fails across file systems, fall back to copy and delete
+                    Self::copy(&source, &target, true).and_then(|_| Self::remove(&source))
+                })
+            } else {
+                Self::copy(&source, &target, infinite)
+            }
+        });
+
+        match result {
+            Ok(_) => {
+                Logger::info(
+                    format!(
+                        "[WebDAV] {} {} to {}",
+                        if is_move { "Moved" } else { "Copied" },
+                        path,
+                        destination
+                    )
+                    .as_str(),
+                );
+                if is_move {
+                    self.locks.release(path);
+                }
+                response.serve_empty(if existed { HttpStatus::NoContent } else { HttpStatus::Created });
+            }
+            Err(e) => {
+                Logger::error(format!("[WebDAV] Unable to copy {} to {}: {}", path, destination, e).as_str());
+                response.serve_error_response(HttpStatus::InternalServerError);
+            }
+        }
+    }
+
+    fn lock(&self, response: &mut Response, path: &str) {
+        let request = &response.request;
+        let timeout = Self::timeout(request.header("Timeout"));
+
+        // an empty body refreshes a lock named in the If header
+        if request.body.trim().is_empty() {
+            let refreshed = request
+                .header("If")
+                .and_then(|if_header| self.locks.refresh(path, if_header, timeout));
+            return match refreshed {
+                Some(lock) => Self::serve_lock(response, &lock, HttpStatus::Ok),
+                None => response.serve_error_response(HttpStatus::PreconditionFailed),
+            };
+        }
+
+        let info = match Xml::parse(&request.body) {
+            Some(info) if info.is(DAV, "lockinfo") => info,
+            _ => return response.serve_error_response(HttpStatus::BadRequest),
+        };
+        let exclusive = info
+            .child(DAV, "lockscope")
+            .map(|scope| scope.child(DAV, "exclusive").is_some())
+            .unwrap_or(true);
+        let owner = info
+            .child(DAV, "owner")
+            .map(|owner| {
+                let mut content = Xml::escape(owner.text.trim());
+                for child in &owner.children {
+                    content.push_str(&child.to_xml());
+                }
+                content
+            })
+            .unwrap_or_default();
+        let infinite = match request.header("Depth").map(|d| d.trim().to_lowercase()).as_deref() {
+            None | Some("infinity") => true,
+            Some("0") => false,
+            _ => return response.serve_error_response(HttpStatus::BadRequest),
+        };
+
+        let file_path = self.file_path(path);
+        let parent_exists = self.file_path(&Self::parent(path)).is_dir();
+        if !file_path.exists() && !parent_exists {
+            return response.serve_error_response(HttpStatus::Conflict);
+        }
+        if !file_path.exists() && self.scripts.run(path) {
+            return response.serve_error_response(HttpStatus::Forbidden);
+        }
+
+        let lock = match self.locks.lock(path, exclusive, infinite, &owner, timeout) {
+            Some(lock) => lock,
+            None => return response.serve_error_response(HttpStatus::Locked),
+        };
+
+        // locking an unmapped URL creates an empty resource
+        // @see: https://www.rfc-editor.org/rfc/rfc4918#section-7.3
+        if !file_path.exists() {
+            if let Err(e) = File::create(&file_path) {
+                Logger::error(format!("[WebDAV] Unable to create {}: {}", path, e).as_str());
+                self.locks.unlock(path, &lock.token);
+                return response.serve_error_response(HttpStatus::InternalServerError);
+            }
+            return Self::serve_lock(response, &lock, HttpStatus::Created);
+        }
+
+        Self::serve_lock(response, &lock, HttpStatus::Ok);
+    }
+
+    fn unlock(&self, response: &mut Response, path: &str) {
+        let token = response
+            .request
+            .header("Lock-Token")
+            .map(|token| token.trim().trim_start_matches('<').trim_end_matches('>').to_string());
+
+        match token {
+            Some(token) if self.locks.unlock(path, &token) => response.serve_empty(HttpStatus::NoContent),
+            Some(_) => Self::serve_error_condition(response, HttpStatus::Conflict, "lock-token-matches-request-uri"),
+            None => response.serve_error_response(HttpStatus::BadRequest),
+        }
+    }
+
+    fn serve_lock(response: &mut Response, lock: &DavLock, status: HttpStatus) {
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery>{}</D:lockdiscovery></D:prop>",
+            lock.to_xml()
+        );
+        response.serve_content(status, "application/xml; charset=utf-8", body.into_bytes());
+        response
+            .headers
+            .add("Lock-Token".to_string(), format!("<{}>", lock.token));
+    }
+
+    fn serve_multistatus(response: &mut Response, body: String) {
+        response.serve_content(HttpStatus::MultiStatus, "application/xml; charset=utf-8", body.into_bytes());
+    }
+
+    // @see: https://www.rfc-editor.org/rfc/rfc4918#section-16
+    fn serve_error_condition(response: &mut Response, status: HttpStatus, condition: &str) {
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error xmlns:D=\"DAV:\"><D:{}/></D:error>",
+            condition
+        );
+        response.serve_content(status, "application/xml; charset=utf-8", body.into_bytes());
+    }
+
+    fn propstat(properties: &str, status: HttpStatus) -> String {
+        format!(
+            "<D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 {} {}</D:status></D:propstat>",
+            properties,
+            status.to_code(),
+            status.to_message()
+        )
+    }
+
+    // "Second-600", "Infinite" or a list of both, capped by `LockManager::MAX_TIMEOUT`
+    fn timeout(header: Option<&String>) -> Duration {
+        let requested = header
+            .and_then(|value| {
+                value.split(',').find_map(|value| {
+                    let value = value.trim();
+                    if value.eq_ignore_ascii_case("infinite") {
+                        Some(LockManager::MAX_TIMEOUT)
+                    } else {
+                        value
+                            .strip_prefix("Second-")
+                            .and_then(|seconds| seconds.parse::<u64>().ok())
+                            .map(Duration::from_secs)
+                    }
+                })
+            })
+            .unwrap_or(LockManager::MAX_TIMEOUT);
+        requested.min(LockManager::MAX_TIMEOUT)
+    }
+
+    fn timestamp(time: Option<SystemTime>) -> Option<u64> {
+        time?.duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs())
+    }
+
+    fn copy(source: &Path, target: &Path, infinite: bool) -> io::Result<()> {
+        if source.is_dir() {
+            fs::create_dir(target)?;
+            if infinite {
+                for entry in fs::read_dir(source)? {
+                    let entry = entry?;
+                    // symlinked folders could loop forever, copy what they point to only for files
+                    if entry.file_type()?.is_symlink() && entry.path().is_dir() {
+                        continue;
+                    }
+                    Self::copy(&entry.path(), &target.join(entry.file_name()), true)?;
+                }
+            }
+            Ok(())
+        } else {
+            fs::copy(source, target).map(|_| ())
+        }
+    }
+
+    // whether `source` copied to `destination` holds a file run as a script, symlinked folders
+    // are skipped as by `copy`
+    fn places_script(&self, source: &Path, destination: &str) -> bool {
+        if self.scripts.run(destination) {
+            return true;
+        }
+        let entries = fs::read_dir(source).into_iter().flatten().flatten();
+        entries
+            .filter(|entry| !(entry.file_type().is_ok_and(|file_type| file_type.is_symlink()) && entry.path().is_dir()))
+            .any(|entry| {
+                let name = entry.file_name();
+                let destination = format!("{}/{}", destination.trim_end_matches('/'), name.to_string_lossy());
+                self.places_script(&entry.path(), &destination)
+            })
+    }
+
+    fn remove(path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PropfindQuery {
+    AllProp,
+    PropName,
+    Prop(Vec<XmlElement>),
+}
+
+impl PropfindQuery {
+    fn parse(body: &str) -> Option<Self> {
+        // an empty body means allprop
+        if body.trim().is_empty() {
+            return Some(Self::AllProp);
+        }
+
+        let propfind = Xml::parse(body)?;
+        if !propfind.is(DAV, "propfind") {
+            return None;
+        }
+
+        if propfind.child(DAV, "propname").is_some() {
+            Some(Self::PropName)
+        } else if let Some(prop) = propfind.child(DAV, "prop") {
+            Some(Self::Prop(prop.children.clone()))
+        } else if propfind.child(DAV, "allprop").is_some() {
+            Some(Self::AllProp)
+        } else {
+            None
+        }
+    }
+}
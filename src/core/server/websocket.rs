@@ -0,0 +1,481 @@
+use crate::core::server::http::{HttpMethod, HttpVersion};
+use crate::core::server::request::Request;
+use crate::core::utils::base64::Base64;
+use crate::core::utils::sha1::Sha1;
+use crate::core::utils::utils::Utils;
+use std::fmt;
+use std::io::{self, BufReader, Cursor, Error, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// @see: https://www.rfc-editor.org/rfc/rfc6455
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x0 => Some(Self::Continuation),
+            0x1 => Some(Self::Text),
+            0x2 => Some(Self::Binary),
+            0x8 => Some(Self::Close),
+            0x9 => Some(Self::Ping),
+            0xA => Some(Self::Pong),
+            _ => None,
+        }
+    }
+
+    pub fn to_code(&self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+
+    pub fn is_control(&self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+/// Status codes sent in close frames.
+// @see: https://www.rfc-editor.org/rfc/rfc6455#section-7.4.1
+pub struct CloseCode;
+
+impl CloseCode {
+    pub const NORMAL: u16 = 1000;
+    pub const GOING_AWAY: u16 = 1001;
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    pub const UNSUPPORTED_DATA: u16 = 1003;
+    pub const INVALID_PAYLOAD: u16 = 1007;
+    pub const POLICY_VIOLATION: u16 = 1008;
+    pub const MESSAGE_TOO_BIG: u16 = 1009;
+    pub const INTERNAL_ERROR: u16 = 1011;
+
+    /// Whether a peer may send `code`, reserved and unassigned codes are protocol errors.
+    pub fn is_valid(code: u16) -> bool {
+        matches!(code, 1000..=1003 | 1007..=1011 | 3000..=4999)
+    }
+}
+
+/// A single frame on the wire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub const MAX_CONTROL_PAYLOAD: usize = 125;
+
+    pub fn new(opcode: Opcode, payload: Vec<u8>) -> Self {
+        Self { fin: true, opcode, payload }
+    }
+
+    /// Reads one frame, unmasking its payload. Client frames must be masked (`masked`),
+    /// payloads over `max_size` bytes are refused with `FileTooLarge`.
+    pub fn read(reader: &mut dyn Read, masked: bool, max_size: usize) -> io::Result<Self> {
+        let mut header = [0u8; 2];
+        reader.read_exact(&mut header)?;
+
+        let fin = header[0] & 0x80 != 0;
+        // no extension is negotiated, the reserved bits must be clear
+        if header[0] & 0x70 != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "reserved bits set"));
+        }
+        let opcode = Opcode::from_code(header[0] & 0x0F)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "unknown opcode"))?;
+        if (header[1] & 0x80 != 0) != masked {
+            return Err(Error::new(ErrorKind::InvalidData, "unexpected masking"));
+        }
+
+        let length = match header[1] & 0x7F {
+            126 => {
+                let mut length = [0u8; 2];
+                reader.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as u64
+            }
+            127 => {
+                let mut length = [0u8; 8];
+                reader.read_exact(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => length as u64,
+        };
+
+        if opcode.is_control() && (!fin || length > Self::MAX_CONTROL_PAYLOAD as u64) {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid control frame"));
+        }
+        if length > max_size as u64 {
+            return Err(Error::new(ErrorKind::FileTooLarge, "frame too large"));
+        }
+
+        let mut mask = [0u8; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+
+        let mut payload = vec![0u8; length as usize];
+        reader.read_exact(&mut payload)?;
+        if masked {
+            Self::apply_mask(&mut payload, mask);
+        }
+
+        Ok(Self { fin, opcode, payload })
+    }
+
+    /// Encodes the frame, clients must pass a `mask`, servers must not.
+    pub fn to_bytes(&self, mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.payload.len() + 14);
+        bytes.push(if self.fin { 0x80 } else { 0 } | self.opcode.to_code());
+
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        match self.payload.len() {
+            length if length < 126 => bytes.push(mask_bit | length as u8),
+            length if length <= u16::MAX as usize => {
+                bytes.push(mask_bit | 126);
+                bytes.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                bytes.push(mask_bit | 127);
+                bytes.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+
+        let start = bytes.len();
+        if let Some(mask) = mask {
+            bytes.extend_from_slice(&mask);
+        }
+        bytes.extend_from_slice(&self.payload);
+        if let Some(mask) = mask {
+            Self::apply_mask(&mut bytes[start + 4..], mask);
+        }
+        bytes
+    }
+
+    fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+}
+
+/// A complete data message, fragments already joined.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => write!(f, "text ({} bytes)", text.len()),
+            Self::Binary(data) => write!(f, "binary ({} bytes)", data.len()),
+        }
+    }
+}
+
+/// Writing half of a connection, cheap to clone and safe to use from other threads,
+/// to push messages while the connection thread waits for the client.
+#[derive(Debug, Clone)]
+pub struct WebSocketSender {
+    stream: Arc<Mutex<TcpStream>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl WebSocketSender {
+    pub fn send(&self, message: Message) -> io::Result<()> {
+        match message {
+            Message::Text(text) => self.write(Frame::new(Opcode::Text, text.into_bytes())),
+            Message::Binary(data) => self.write(Frame::new(Opcode::Binary, data)),
+        }
+    }
+
+    pub fn send_text(&self, text: &str) -> io::Result<()> {
+        self.send(Message::Text(text.to_string()))
+    }
+
+    pub fn ping(&self, payload: &[u8]) -> io::Result<()> {
+        self.write(Frame::new(Opcode::Ping, payload[..payload.len().min(Frame::MAX_CONTROL_PAYLOAD)].to_vec()))
+    }
+
+    /// Starts the closing handshake, the client answers with its own close frame.
+    pub fn close(&self, code: u16, reason: &str) -> io::Result<()> {
+        let mut payload = code.to_be_bytes().to_vec();
+        // keep the frame under the control frame limit without splitting a character
+        let mut end = reason.len().min(Frame::MAX_CONTROL_PAYLOAD - 2);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        payload.extend_from_slice(&reason.as_bytes()[..end]);
+        self.close_with(payload)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Whether both senders write to the same connection.
+    pub fn same_connection(&self, other: &WebSocketSender) -> bool {
+        Arc::ptr_eq(&self.stream, &other.stream)
+    }
+
+    // nothing may be sent after a close frame
+    fn close_with(&self, payload: Vec<u8>) -> io::Result<()> {
+        let result = self.write(Frame::new(Opcode::Close, payload));
+        self.closed.store(true, Ordering::SeqCst);
+        result
+    }
+
+    fn write(&self, frame: Frame) -> io::Result<()> {
+        if self.is_closed() {
+            return Err(Error::new(ErrorKind::BrokenPipe, "websocket closed"));
+        }
+        let mut stream = match self.stream.lock() {
+            Ok(stream) => stream,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        stream.write_all(&frame.to_bytes(None))?;
+        stream.flush()
+    }
+}
+
+/// Server side of an established WebSocket connection.
+pub struct WebSocket {
+    reader: Box<dyn Read + Send>,
+    sender: WebSocketSender,
+    max_message_size: usize,
+}
+
+impl WebSocket {
+    // @see: https://www.rfc-editor.org/rfc/rfc6455#section-1.3
+    const GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    pub const VERSION: &'static str = "13";
+    pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+    /// Wraps a connection whose handshake has been answered, `buffered` holds the bytes
+    /// already read past the request headers.
+    pub fn new(stream: TcpStream, buffered: Vec<u8>) -> io::Result<Self> {
+        let writer = stream.try_clone()?;
+        Ok(Self {
+            reader: Box::new(BufReader::new(Cursor::new(buffered).chain(stream))),
+            sender: WebSocketSender {
+                stream: Arc::new(Mutex::new(writer)),
+                closed: Arc::new(AtomicBool::new(false)),
+            },
+            max_message_size: Self::DEFAULT_MAX_MESSAGE_SIZE,
+        })
+    }
+
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// `Sec-WebSocket-Accept` value answering a `Sec-WebSocket-Key`.
+    pub fn accept_key(key: &str) -> String {
+        Base64::encode(&Sha1::digest(format!("{}{}", key.trim(), Self::GUID).as_bytes()))
+    }
+
+    /// Whether the request is a valid opening handshake.
+    pub fn is_upgrade(request: &Request) -> bool {
+        let has_token = |name: &str, token: &str| {
+            request
+                .header(name)
+                .map(|value| value.split(',').any(|item| item.trim().eq_ignore_ascii_case(token)))
+                .unwrap_or(false)
+        };
+        let valid_key = request
+            .header("Sec-WebSocket-Key")
+            .and_then(|key| Base64::decode(key.trim()))
+            .map(|key| key.len() == 16)
+            .unwrap_or(false);
+
+        request.method == HttpMethod::GET
+            && request.version == HttpVersion::Http11
+            && has_token("Upgrade", "websocket")
+            && has_token("Connection", "upgrade")
+            && request.header("Sec-WebSocket-Version").map(|v| v.trim()) == Some(Self::VERSION)
+            && valid_key
+    }
+
+    /// Subprotocols offered by the client in `Sec-WebSocket-Protocol`.
+    pub fn protocols(request: &Request) -> Vec<String> {
+        request
+            .header("Sec-WebSocket-Protocol")
+            .map(|value| value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn sender(&self) -> WebSocketSender {
+        self.sender.clone()
+    }
+
+    /// Waits for the next data message. Pings are answered and fragments joined on the way,
+    /// `None` once the connection is closed.
+    pub fn recv(&mut self) -> io::Result<Option<Message>> {
+        let mut fragments: Option<(Opcode, Vec<u8>)> = None;
+
+        loop {
+            let frame = match Frame::read(&mut self.reader, true, self.max_message_size) {
+                Ok(frame) => frame,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(self.fail(e)),
+            };
+
+            match frame.opcode {
+                Opcode::Ping => self.sender.write(Frame::new(Opcode::Pong, frame.payload))?,
+                Opcode::Pong => {}
+                Opcode::Close => {
+                    self.answer_close(&frame.payload)?;
+                    return Ok(None);
+                }
+                Opcode::Text | Opcode::Binary if fragments.is_some() => {
+                    return Err(self.fail(Error::new(ErrorKind::InvalidData, "expected a continuation frame")));
+                }
+                Opcode::Continuation if fragments.is_none() => {
+                    return Err(self.fail(Error::new(ErrorKind::InvalidData, "unexpected continuation frame")));
+                }
+                opcode => {
+                    let (kind, mut data) = fragments.take().unwrap_or((opcode, Vec::new()));
+                    if data.len() + frame.payload.len() > self.max_message_size {
+                        return Err(self.fail(Error::new(ErrorKind::FileTooLarge, "message too large")));
+                    }
+                    data.extend_from_slice(&frame.payload);
+
+                    if !frame.fin {
+                        fragments = Some((kind, data));
+                        continue;
+                    }
+                    return match kind {
+                        Opcode::Text => match String::from_utf8(data) {
+                            Ok(text) => Ok(Some(Message::Text(text))),
+                            Err(_) => {
+                                let _ = self.sender.close(CloseCode::INVALID_PAYLOAD, "invalid UTF-8");
+                                self.shutdown();
+                                Err(Error::new(ErrorKind::InvalidData, "invalid UTF-8 in text message"))
+                            }
+                        },
+                        _ => Ok(Some(Message::Binary(data))),
+                    };
+                }
+            }
+        }
+    }
+
+    // echoes the close frame of the client, then ends the connection
+    fn answer_close(&mut self, payload: &[u8]) -> io::Result<()> {
+        let code = match payload.len() {
+            0 => None,
+            1 => Some(CloseCode::PROTOCOL_ERROR),
+            _ => {
+                let code = u16::from_be_bytes([payload[0], payload[1]]);
+                if !CloseCode::is_valid(code) || std::str::from_utf8(&payload[2..]).is_err() {
+                    Some(CloseCode::PROTOCOL_ERROR)
+                } else {
+                    Some(code)
+                }
+            }
+        };
+
+        if !self.sender.is_closed() {
+            let payload = code.map(|code| code.to_be_bytes().to_vec()).unwrap_or_default();
+            let _ = self.sender.close_with(payload);
+        }
+        self.shutdown();
+        Ok(())
+    }
+
+    // closes with the status matching the error, the error is handed back to the caller
+    fn fail(&mut self, error: Error) -> Error {
+        let code = match error.kind() {
+            ErrorKind::FileTooLarge => CloseCode::MESSAGE_TOO_BIG,
+            ErrorKind::InvalidData => CloseCode::PROTOCOL_ERROR,
+            _ => return error,
+        };
+        let _ = self.sender.close(code, "");
+        self.shutdown();
+        error
+    }
+
+    fn shutdown(&self) {
+        if let Ok(stream) = self.sender.stream.lock() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Application side of a WebSocket endpoint, mounted on a path with `Katana::websocket`.
+///
+/// Every callback runs on the connection thread; keep the `WebSocketSender` from
+/// `on_open` to push messages from elsewhere.
+pub trait WebSocketHandler: Send + Sync {
+    /// Picks one of the subprotocols offered by the client, none by default.
+    fn protocol(&self, _offered: &[String]) -> Option<String> {
+        None
+    }
+
+    fn on_open(&self, _sender: &WebSocketSender, _request: &Request) {}
+
+    fn on_message(&self, sender: &WebSocketSender, message: Message);
+
+    /// Called once, when the client closed the connection or it was lost.
+    fn on_close(&self, _sender: &WebSocketSender) {}
+}
+
+/// WebSocket endpoints by path, shared by every connection thread.
+#[derive(Clone, Default)]
+pub struct WebSocketRoutes {
+    routes: Vec<(String, Arc<dyn WebSocketHandler>)>,
+}
+
+impl WebSocketRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mount(&mut self, path: &str, handler: Arc<dyn WebSocketHandler>) {
+        let path = Self::normalize(path);
+        self.routes.retain(|(route, _)| *route != path);
+        self.routes.push((path, handler));
+    }
+
+    /// Handler mounted exactly on `path`, a trailing slash does not matter.
+    pub fn handler_for(&self, path: &str) -> Option<Arc<dyn WebSocketHandler>> {
+        let path = Self::normalize(path);
+        self.routes
+            .iter()
+            .find(|(route, _)| *route == path)
+            .map(|(_, handler)| Arc::clone(handler))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    fn normalize(path: &str) -> String {
+        let normalized = Utils::normalize_path(PathBuf::from(path));
+        format!("/{}", normalized.to_string_lossy().trim_matches('/'))
+    }
+}
+
+impl fmt::Debug for WebSocketRoutes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.routes.iter().map(|(path, _)| path)).finish()
+    }
+}
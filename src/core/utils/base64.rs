@@ -0,0 +1,59 @@
+// @see: https://www.rfc-editor.org/rfc/rfc4648#section-4
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub struct Base64;
+
+impl Base64 {
+    pub fn encode(data: &[u8]) -> String {
+        let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+
+        for chunk in data.chunks(3) {
+            let b0 = chunk[0] as u32;
+            let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+            let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+            let triple = (b0 << 16) | (b1 << 8) | b2;
+
+            result.push(ALPHABET[(triple >> 18) as usize & 0x3F] as char);
+            result.push(ALPHABET[(triple >> 12) as usize & 0x3F] as char);
+            if chunk.len() > 1 {
+                result.push(ALPHABET[(triple >> 6) as usize & 0x3F] as char);
+            } else {
+                result.push('=');
+            }
+            if chunk.len() > 2 {
+                result.push(ALPHABET[triple as usize & 0x3F] as char);
+            } else {
+                result.push('=');
+            }
+        }
+
+        result
+    }
+
+    /// Decodes padded or unpadded input, returns `None` on any invalid character.
+    pub fn decode(input: &str) -> Option<Vec<u8>> {
+        let input = input.trim().trim_end_matches('=');
+        let mut result = Vec::with_capacity(input.len() * 3 / 4);
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+
+        for c in input.bytes() {
+            let value = ALPHABET.iter().position(|a| *a == c)? as u32;
+            buffer = (buffer << 6) | value;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                result.push((buffer >> bits) as u8);
+                buffer &= (1 << bits) - 1;
+            }
+        }
+
+        // a single leftover character cannot encode a byte
+        if bits >= 6 {
+            return None;
+        }
+
+        Some(result)
+    }
+}
@@ -0,0 +1,259 @@
+/*
+   Verification of the password hash formats found in Apache htpasswd files.
+
+   Supported: bcrypt ("$2y$", "$2a$", "$2b$"), SHA-crypt ("$5$" and "$6$") and "{SHA}".
+   Apache MD5 ("$apr1$"), DES crypt and plain text entries are rejected on purpose.
+
+   https://httpd.apache.org/docs/current/misc/password_encryptions.html
+   https://www.akkadia.org/drepper/SHA-crypt.txt
+*/
+
+use crate::core::utils::base64::Base64;
+use crate::core::utils::bcrypt::Bcrypt;
+use crate::core::utils::sha1::Sha1;
+use crate::core::utils::sha2::{Sha256, Sha512};
+use crate::core::utils::utils::Utils;
+
+const ALPHABET: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// order in which SHA-crypt reads the digest bytes, three at a time
+const SHA256_ORDER: [(usize, usize, usize); 10] = [
+    (0, 10, 20), (21, 1, 11), (12, 22, 2), (3, 13, 23), (24, 4, 14),
+    (15, 25, 5), (6, 16, 26), (27, 7, 17), (18, 28, 8), (9, 19, 29),
+];
+
+const SHA512_ORDER: [(usize, usize, usize); 21] = [
+    (0, 21, 42), (22, 43, 1), (44, 2, 23), (3, 24, 45), (25, 46, 4),
+    (47, 5, 26), (6, 27, 48), (28, 49, 7), (50, 8, 29), (9, 30, 51),
+    (31, 52, 10), (53, 11, 32), (12, 33, 54), (34, 55, 13), (56, 14, 35),
+    (15, 36, 57), (37, 58, 16), (59, 17, 38), (18, 39, 60), (40, 61, 19),
+    (62, 20, 41),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptScheme {
+    Bcrypt,
+    Sha256Crypt,
+    Sha512Crypt,
+    Sha1,
+    Unsupported,
+}
+
+impl CryptScheme {
+    pub fn detect(hash: &str) -> Self {
+        if hash.starts_with("$2y$") || hash.starts_with("$2a$") || hash.starts_with("$2b$") {
+            Self::Bcrypt
+        } else if hash.starts_with("$5$") {
+            Self::Sha256Crypt
+        } else if hash.starts_with("$6$") {
+            Self::Sha512Crypt
+        } else if hash.starts_with("{SHA}") {
+            Self::Sha1
+        } else {
+            Self::Unsupported
+        }
+    }
+}
+
+pub struct Crypt;
+
+impl Crypt {
+    pub fn verify(password: &str, hash: &str) -> bool {
+        match CryptScheme::detect(hash) {
+            CryptScheme::Bcrypt => Bcrypt::verify(password.as_bytes(), hash),
+            CryptScheme::Sha256Crypt | CryptScheme::Sha512Crypt => {
+                match Self::sha_crypt(password.as_bytes(), hash) {
+                    Some(computed) => Utils::constant_time_eq(computed.as_bytes(), hash.as_bytes()),
+                    None => false,
+                }
+            }
+            CryptScheme::Sha1 => {
+                let computed = format!("{{SHA}}{}", Base64::encode(&Sha1::digest(password.as_bytes())));
+                Utils::constant_time_eq(computed.as_bytes(), hash.as_bytes())
+            }
+            CryptScheme::Unsupported => false,
+        }
+    }
+
+    /// Recomputes a SHA-crypt hash string using the id, rounds and salt of `setting`.
+    pub fn sha_crypt(password: &[u8], setting: &str) -> Option<String> {
+        const DEFAULT_ROUNDS: u32 = 5000;
+
+        let (id, rest) = if let Some(rest) = setting.strip_prefix("$5$") {
+            ("5", rest)
+        } else if let Some(rest) = setting.strip_prefix("$6$") {
+            ("6", rest)
+        } else {
+            return None;
+        };
+
+        let (rounds, custom_rounds, rest) = match rest.strip_prefix("rounds=") {
+            Some(rounds) => {
+                let (value, rest) = rounds.split_once('$')?;
+                let value = value.parse::<u64>().ok()?.clamp(1000, 999_999_999) as u32;
+                (value, true, rest)
+            }
+            None => (DEFAULT_ROUNDS, false, rest),
+        };
+
+        // salt stops at the next '$' and is at most 16 characters
+        let salt = rest.split('$').next().unwrap_or("");
+        let salt = &salt.as_bytes()[..salt.len().min(16)];
+
+        let digest = if id == "5" {
+            Self::sha_crypt_digest::<Sha256Hasher>(password, salt, rounds)
+        } else {
+            Self::sha_crypt_digest::<Sha512Hasher>(password, salt, rounds)
+        };
+
+        let mut encoded = String::new();
+        if id == "5" {
+            for (b2, b1, b0) in SHA256_ORDER {
+                Self::encode_24bit(&mut encoded, digest[b2], digest[b1], digest[b0], 4);
+            }
+            Self::encode_24bit(&mut encoded, 0, digest[31], digest[30], 3);
+        } else {
+            for (b2, b1, b0) in SHA512_ORDER {
+                Self::encode_24bit(&mut encoded, digest[b2], digest[b1], digest[b0], 4);
+            }
+            Self::encode_24bit(&mut encoded, 0, 0, digest[63], 2);
+        }
+
+        let rounds = if custom_rounds {
+            format!("rounds={}$", rounds)
+        } else {
+            String::new()
+        };
+
+        Some(format!(
+            "${}${}{}${}",
+            id,
+            rounds,
+            String::from_utf8_lossy(salt),
+            encoded
+        ))
+    }
+
+    fn sha_crypt_digest<H: Hasher>(password: &[u8], salt: &[u8], rounds: u32) -> Vec<u8> {
+        let size = H::SIZE;
+
+        let mut b = H::new();
+        b.update(password);
+        b.update(salt);
+        b.update(password);
+        let b = b.finalize();
+
+        let mut a = H::new();
+        a.update(password);
+        a.update(salt);
+        let mut remaining = password.len();
+        while remaining > size {
+            a.update(&b);
+            remaining -= size;
+        }
+        a.update(&b[..remaining]);
+        let mut length = password.len();
+        while length > 0 {
+            if length & 1 == 1 {
+                a.update(&b);
+            } else {
+                a.update(password);
+            }
+            length >>= 1;
+        }
+        let mut result = a.finalize();
+
+        let mut dp = H::new();
+        for _ in 0..password.len() {
+            dp.update(password);
+        }
+        let dp = dp.finalize();
+        let p_sequence = Self::repeat(&dp, password.len());
+
+        let mut ds = H::new();
+        for _ in 0..(16 + result[0] as usize) {
+            ds.update(salt);
+        }
+        let ds = ds.finalize();
+        let s_sequence = Self::repeat(&ds, salt.len());
+
+        for round in 0..rounds {
+            let mut c = H::new();
+            if round % 2 == 1 {
+                c.update(&p_sequence);
+            } else {
+                c.update(&result);
+            }
+            if round % 3 != 0 {
+                c.update(&s_sequence);
+            }
+            if round % 7 != 0 {
+                c.update(&p_sequence);
+            }
+            if round % 2 == 1 {
+                c.update(&result);
+            } else {
+                c.update(&p_sequence);
+            }
+            result = c.finalize();
+        }
+
+        result
+    }
+
+    fn repeat(digest: &[u8], length: usize) -> Vec<u8> {
+        digest.iter().copied().cycle().take(length).collect()
+    }
+
+    fn encode_24bit(output: &mut String, b2: u8, b1: u8, b0: u8, count: usize) {
+        let mut word = ((b2 as u32) << 16) | ((b1 as u32) << 8) | b0 as u32;
+        for _ in 0..count {
+            output.push(ALPHABET[(word & 0x3F) as usize] as char);
+            word >>= 6;
+        }
+    }
+}
+
+// small adapter so SHA-crypt can be written once for both digests
+trait Hasher {
+    const SIZE: usize;
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+}
+
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    const SIZE: usize = Sha256::OUTPUT_SIZE;
+
+    fn new() -> Self {
+        Self(Sha256::new())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.0.finalize().to_vec()
+    }
+}
+
+struct Sha512Hasher(Sha512);
+
+impl Hasher for Sha512Hasher {
+    const SIZE: usize = Sha512::OUTPUT_SIZE;
+
+    fn new() -> Self {
+        Self(Sha512::new())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.0.finalize().to_vec()
+    }
+}
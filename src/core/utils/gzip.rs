@@ -0,0 +1,282 @@
+/*
+   Streaming gzip encoder, so we don't need to pull a compression crate for a couple of features.
+
+   The deflate stream only uses fixed Huffman blocks (BTYPE=01) with a simple LZ77 match finder.
+   It will not beat zlib on ratio, but it is fast, runs in bounded memory and is readable by any
+   gzip implementation.
+
+   https://www.rfc-editor.org/rfc/rfc1951 (DEFLATE)
+   https://www.rfc-editor.org/rfc/rfc1952 (GZIP)
+*/
+
+use crate::core::utils::crc32::Crc32;
+use std::io::{Result, Write};
+
+const BLOCK_SIZE: usize = 64 * 1024;
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+// (base length, extra bits) for length codes 257..=285
+const LENGTH_CODES: [(u16, u8); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0),
+    (11, 1), (13, 1), (15, 1), (17, 1),
+    (19, 2), (23, 2), (27, 2), (31, 2),
+    (35, 3), (43, 3), (51, 3), (59, 3),
+    (67, 4), (83, 4), (99, 4), (115, 4),
+    (131, 5), (163, 5), (195, 5), (227, 5),
+    (258, 0),
+];
+
+// (base distance, extra bits) for distance codes 0..=29
+const DISTANCE_CODES: [(u16, u8); 30] = [
+    (1, 0), (2, 0), (3, 0), (4, 0),
+    (5, 1), (7, 1), (9, 2), (13, 2),
+    (17, 3), (25, 3), (33, 4), (49, 4),
+    (65, 5), (97, 5), (129, 6), (193, 6),
+    (257, 7), (385, 7), (513, 8), (769, 8),
+    (1025, 9), (1537, 9), (2049, 10), (3073, 10),
+    (4097, 11), (6145, 11), (8193, 12), (12289, 12),
+    (16385, 13), (24577, 13),
+];
+
+struct BitWriter {
+    buffer: u64,
+    count: u32,
+    out: Vec<u8>,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            buffer: 0,
+            count: 0,
+            out: Vec::new(),
+        }
+    }
+
+    // deflate packs values starting from the least significant bit
+    fn write_bits(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // huffman codes are stored most significant bit first
+    fn write_code(&mut self, code: u32, bits: u32) {
+        let reversed = code.reverse_bits() >> (32 - bits);
+        self.write_bits(reversed, bits);
+    }
+
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+            self.buffer = 0;
+            self.count = 0;
+        }
+    }
+
+    fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.out)
+    }
+}
+
+pub struct GzipWriter<W: Write> {
+    inner: W,
+    crc: Crc32,
+    size: u32,
+    pending: Vec<u8>,
+    history: Vec<u8>,
+    bits: BitWriter,
+}
+
+impl<W: Write> GzipWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        // magic, CM=deflate, no flags, no mtime, no extra flags, OS=unknown
+        inner.write_all(&[0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0x00, 0xff])?;
+
+        Ok(Self {
+            inner,
+            crc: Crc32::new(),
+            size: 0,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+            history: Vec::with_capacity(WINDOW_SIZE),
+            bits: BitWriter::new(),
+        })
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.compress_pending(true)?;
+        self.bits.align();
+        let tail = self.bits.take();
+        self.inner.write_all(&tail)?;
+        self.inner.write_all(&self.crc.finalize().to_le_bytes())?;
+        self.inner.write_all(&self.size.to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn compress_pending(&mut self, last: bool) -> Result<()> {
+        let data = std::mem::take(&mut self.pending);
+        compress_block(&self.history, &data, last, &mut self.bits);
+
+        // keep the tail of what we just compressed as the next block's window
+        let mut window = std::mem::take(&mut self.history);
+        window.extend_from_slice(&data);
+        let keep = window.len().saturating_sub(WINDOW_SIZE);
+        window.drain(..keep);
+        self.history = window;
+
+        let compressed = self.bits.take();
+        self.inner.write_all(&compressed)?;
+
+        self.pending = data;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for GzipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let available = BLOCK_SIZE - self.pending.len();
+        let taken = available.min(buf.len());
+        self.pending.extend_from_slice(&buf[..taken]);
+        self.crc.update(&buf[..taken]);
+        self.size = self.size.wrapping_add(taken as u32);
+
+        if self.pending.len() == BLOCK_SIZE {
+            self.compress_pending(false)?;
+        }
+
+        Ok(taken)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let value = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+    (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+fn compress_block(history: &[u8], data: &[u8], last: bool, bits: &mut BitWriter) {
+    // block header: BFINAL then BTYPE=01 (fixed huffman)
+    bits.write_bits(if last { 1 } else { 0 }, 1);
+    bits.write_bits(1, 2);
+
+    let mut window = Vec::with_capacity(history.len() + data.len());
+    window.extend_from_slice(history);
+    window.extend_from_slice(data);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; window.len()];
+
+    let insert = |head: &mut Vec<usize>, prev: &mut Vec<usize>, pos: usize| {
+        if pos + MIN_MATCH <= window.len() {
+            let h = hash(&window, pos);
+            prev[pos] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    for pos in 0..history.len() {
+        insert(&mut head, &mut prev, pos);
+    }
+
+    let mut pos = history.len();
+    while pos < window.len() {
+        let (length, distance) = longest_match(&window, &head, &prev, pos);
+
+        if length >= MIN_MATCH {
+            write_length(bits, length);
+            write_distance(bits, distance);
+            for i in pos..pos + length {
+                insert(&mut head, &mut prev, i);
+            }
+            pos += length;
+        } else {
+            write_literal(bits, window[pos] as u32);
+            insert(&mut head, &mut prev, pos);
+            pos += 1;
+        }
+    }
+
+    // end of block
+    write_literal(bits, 256);
+}
+
+fn longest_match(window: &[u8], head: &[usize], prev: &[usize], pos: usize) -> (usize, usize) {
+    if pos + MIN_MATCH > window.len() {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(window.len() - pos);
+    let mut candidate = head[hash(window, pos)];
+    let mut best = (0, 0);
+    let mut chain = 0;
+
+    while candidate != usize::MAX && chain < MAX_CHAIN {
+        let distance = pos - candidate;
+        if distance > WINDOW_SIZE {
+            break;
+        }
+
+        let mut length = 0;
+        while length < max_length && window[candidate + length] == window[pos + length] {
+            length += 1;
+        }
+
+        if length > best.0 {
+            best = (length, distance);
+            if length == max_length {
+                break;
+            }
+        }
+
+        candidate = prev[candidate];
+        chain += 1;
+    }
+
+    best
+}
+
+fn write_literal(bits: &mut BitWriter, value: u32) {
+    match value {
+        0..=143 => bits.write_code(0x30 + value, 8),
+        144..=255 => bits.write_code(0x190 + value - 144, 9),
+        256..=279 => bits.write_code(value - 256, 7),
+        _ => bits.write_code(0xC0 + value - 280, 8),
+    }
+}
+
+fn write_length(bits: &mut BitWriter, length: usize) {
+    let index = LENGTH_CODES
+        .iter()
+        .rposition(|(base, _)| *base as usize <= length)
+        .unwrap();
+    let (base, extra) = LENGTH_CODES[index];
+    write_literal(bits, 257 + index as u32);
+    if extra > 0 {
+        bits.write_bits((length - base as usize) as u32, extra as u32);
+    }
+}
+
+fn write_distance(bits: &mut BitWriter, distance: usize) {
+    let index = DISTANCE_CODES
+        .iter()
+        .rposition(|(base, _)| *base as usize <= distance)
+        .unwrap();
+    let (base, extra) = DISTANCE_CODES[index];
+    bits.write_code(index as u32, 5);
+    if extra > 0 {
+        bits.write_bits((distance - base as usize) as u32, extra as u32);
+    }
+}
@@ -1,7 +1,17 @@
 use crate::core::utils::colorful::Colored;
 use crate::core::utils::utils::Utils;
 use crate::core::config::config::Config;
+use std::cell::Cell;
 use std::io::Write;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// the level of the running configuration, `UNSET` until the server starts
+static MIN_LOG_LEVEL: AtomicU8 = AtomicU8::new(Logger::UNSET);
+
+thread_local! {
+    // the level of the configuration a connection thread started with, over `MIN_LOG_LEVEL`
+    static THREAD_LOG_LEVEL: Cell<u8> = const { Cell::new(Logger::UNSET) };
+}
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 #[allow(dead_code)]
@@ -26,6 +36,16 @@ impl LogLevel {
         self >= min_level
     }
 
+    fn from_u8(level: u8) -> Option<Self> {
+        match level {
+            0 => Some(LogLevel::DEBUG),
+            1 => Some(LogLevel::INFO),
+            2 => Some(LogLevel::WARN),
+            3 => Some(LogLevel::ERROR),
+            _ => None,
+        }
+    }
+
     pub fn from_str(level: &str) -> Option<Self> {
         match level {
             "DEBUG" => Some(LogLevel::DEBUG),
@@ -40,9 +60,34 @@ impl LogLevel {
 pub struct Logger;
 
 impl Logger {
+    const UNSET: u8 = u8::MAX;
+
+    /// The level set by `set_thread_log_level` for this thread, or by `set_min_log_level`.
+    /// Before the server starts, the command line or environment one, the file is not read
+    /// as reading it logs.
     pub fn get_min_log_level() -> LogLevel {
-        let config = Config::load_args();
-        config.log_level
+        let level = match THREAD_LOG_LEVEL.with(Cell::get) {
+            Self::UNSET => MIN_LOG_LEVEL.load(Ordering::Relaxed),
+            level => level,
+        };
+        match LogLevel::from_u8(level) {
+            Some(level) => level,
+            None => Config::load_args()
+                .log_level
+                .or(Config::load_env().log_level)
+                .unwrap_or(LogLevel::INFO),
+        }
+    }
+
+    /// Applies the level of the running configuration, on start and on reload.
+    pub fn set_min_log_level(level: LogLevel) {
+        MIN_LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Pins the level of the current thread, a connection keeps the level of the
+    /// configuration it was accepted with when a reload changes it.
+    pub fn set_thread_log_level(level: LogLevel) {
+        THREAD_LOG_LEVEL.with(|cell| cell.set(level as u8));
     }
 
     pub fn debug(message: &str) {
@@ -1,5 +1,14 @@
 pub mod keyval;
+pub mod base64;
+pub mod bcrypt;
 pub mod colorful;
+pub mod crc32;
+pub mod crypt;
+pub mod gzip;
 pub mod logger;
+pub mod sha1;
+pub mod sha2;
 pub mod utils;
+pub mod xml;
 pub mod toml;
+pub mod regex;
@@ -0,0 +1,292 @@
+use std::fmt;
+
+// A backtracking subset of the usual syntax: literals, `.`, `^`, `$`, classes `[a-z]` and
+// `[^.]`, `\d \w \s` and their negations, groups with `|`, and the quantifiers `* + ?`,
+// `{n}`, `{n,}` and `{n,m}`.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char(char),
+    Any,
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize> },
+}
+
+/// A compiled pattern, see `Regex::new` for the syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    pattern: String,
+    alternatives: Vec<Vec<Node>>,
+}
+
+impl Regex {
+    // `{n,m}` beyond this is a typo rather than a pattern
+    const MAX_REPEAT: usize = 1000;
+
+    /// Compiles `pattern`, the error tells what is wrong and where.
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        match parser.peek() {
+            None => Ok(Self {
+                pattern: pattern.to_string(),
+                alternatives,
+            }),
+            Some(_) => Err(format!("unmatched `)` at {}", parser.pos)),
+        }
+    }
+
+    /// Whether the pattern matches somewhere in `text`, `^` and `$` anchor it.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let matcher = Matcher { text: &text };
+        (0..=text.len()).any(|start| {
+            self.alternatives
+                .iter()
+                .any(|nodes| matcher.sequence(nodes, start, &|_| true))
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            let node = match c {
+                '|' | ')' => break,
+                '(' => {
+                    self.pos += 1;
+                    // `(?:...)` is a group too
+                    if self.chars[self.pos..].starts_with(&['?', ':']) {
+                        self.pos += 2;
+                    }
+                    let group = self.alternatives()?;
+                    if self.next() != Some(')') {
+                        return Err("unclosed `(`".to_string());
+                    }
+                    Node::Group(group)
+                }
+                '[' => self.class()?,
+                '.' => {
+                    self.pos += 1;
+                    Node::Any
+                }
+                '^' => {
+                    self.pos += 1;
+                    Node::Start
+                }
+                '$' => {
+                    self.pos += 1;
+                    Node::End
+                }
+                '\\' => {
+                    self.pos += 1;
+                    self.escape()?
+                }
+                '*' | '+' | '?' => return Err(format!("nothing to repeat at {}", self.pos)),
+                _ => {
+                    self.pos += 1;
+                    Node::Char(c)
+                }
+            };
+            let node = self.quantifier(node)?;
+            nodes.push(node);
+        }
+        Ok(nodes)
+    }
+
+    fn quantifier(&mut self, node: Node) -> Result<Node, String> {
+        let at = self.pos;
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.bounds() {
+                Some(bounds) => bounds,
+                None => return Ok(node),
+            },
+            _ => return Ok(node),
+        };
+        if self.pos == at {
+            self.pos += 1;
+        }
+        if matches!(node, Node::Start | Node::End) {
+            return Err(format!("nothing to repeat at {}", at));
+        }
+        if max.is_some_and(|max| max < min) || min.max(max.unwrap_or(0)) > Regex::MAX_REPEAT {
+            return Err(format!("invalid repetition {{{},{}}}", min, max.map_or(String::new(), |max| max.to_string())));
+        }
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    // `{n}`, `{n,}` or `{n,m}`, a `{` starting nothing of the kind is a literal
+    fn bounds(&mut self) -> Option<(usize, Option<usize>)> {
+        let end = self.chars[self.pos..].iter().position(|c| *c == '}')? + self.pos;
+        let inside: String = self.chars[self.pos + 1..end].iter().collect();
+        let bounds = match inside.split_once(',') {
+            None => {
+                let n = inside.parse().ok()?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+        };
+        self.pos = end + 1;
+        Some(bounds)
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        let class = |ranges: &[(char, char)], negated| Node::Class {
+            ranges: ranges.to_vec(),
+            negated,
+        };
+        const DIGIT: [(char, char); 1] = [('0', '9')];
+        const WORD: [(char, char); 4] = [('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+        const SPACE: [(char, char); 4] = [(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')];
+
+        match self.next() {
+            None => Err("trailing `\\`".to_string()),
+            Some('d') => Ok(class(&DIGIT, false)),
+            Some('D') => Ok(class(&DIGIT, true)),
+            Some('w') => Ok(class(&WORD, false)),
+            Some('W') => Ok(class(&WORD, true)),
+            Some('s') => Ok(class(&SPACE, false)),
+            Some('S') => Ok(class(&SPACE, true)),
+            Some('n') => Ok(Node::Char('\n')),
+            Some('t') => Ok(Node::Char('\t')),
+            Some(c) if c.is_ascii_alphanumeric() => Err(format!("unknown escape `\\{}`", c)),
+            Some(c) => Ok(Node::Char(c)),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        self.pos += 1;
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let start = match self.next() {
+                None => return Err("unclosed `[`".to_string()),
+                // a `]` first is a literal
+                Some(']') if !first => break,
+                Some('\\') => match self.escape()? {
+                    Node::Char(c) => c,
+                    Node::Class { ranges: escaped, negated: false } => {
+                        ranges.extend(escaped);
+                        first = false;
+                        continue;
+                    }
+                    _ => return Err("negated escapes are not supported in `[...]`".to_string()),
+                },
+                Some(c) => c,
+            };
+            first = false;
+
+            let end = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(end)) if *end != ']' => {
+                    self.pos += 2;
+                    match *end {
+                        '\\' => match self.escape()? {
+                            Node::Char(c) => c,
+                            _ => return Err("invalid range in `[...]`".to_string()),
+                        },
+                        end => end,
+                    }
+                }
+                _ => start,
+            };
+            if end < start {
+                return Err(format!("invalid range `{}-{}`", start, end));
+            }
+            ranges.push((start, end));
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+}
+
+struct Matcher<'t> {
+    text: &'t [char],
+}
+
+impl Matcher<'_> {
+    // whether `nodes` match at `pos` with `next` accepting where they end
+    fn sequence(&self, nodes: &[Node], pos: usize, next: &dyn Fn(usize) -> bool) -> bool {
+        match nodes.split_first() {
+            None => next(pos),
+            Some((node, rest)) => self.node(node, pos, &|end| self.sequence(rest, end, next)),
+        }
+    }
+
+    fn node(&self, node: &Node, pos: usize, next: &dyn Fn(usize) -> bool) -> bool {
+        match node {
+            Node::Char(c) => self.text.get(pos) == Some(c) && next(pos + 1),
+            Node::Any => self.text.get(pos).is_some_and(|c| *c != '\n') && next(pos + 1),
+            Node::Class { ranges, negated } => {
+                self.text.get(pos).is_some_and(|c| ranges.iter().any(|(start, end)| (start..=end).contains(&c)) != *negated)
+                    && next(pos + 1)
+            }
+            Node::Start => pos == 0 && next(pos),
+            Node::End => pos == self.text.len() && next(pos),
+            Node::Group(alternatives) => alternatives.iter().any(|nodes| self.sequence(nodes, pos, next)),
+            Node::Repeat { node, min, max } => self.repeat(node, *min, *max, 0, pos, next),
+        }
+    }
+
+    // greedy, as many as possible before giving back
+    fn repeat(&self, node: &Node, min: usize, max: Option<usize>, count: usize, pos: usize, next: &dyn Fn(usize) -> bool) -> bool {
+        if max.is_none_or(|max| count < max)
+            && self.node(node, pos, &|end| (end != pos || count < min) && self.repeat(node, min, max, count + 1, end, next))
+        {
+            return true;
+        }
+        count >= min && next(pos)
+    }
+}
@@ -1,83 +1,922 @@
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug)]
-#[derive(Clone)]
+// @see: https://toml.io/en/v1.0.0
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TomlValue {
     String(String),
     Integer(i64),
     Float(f64),
     Boolean(bool),
+    Datetime(TomlDatetime),
     Array(Vec<TomlValue>),
     Table(HashMap<String, TomlValue>),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TomlDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TomlTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+/// An offset date-time, a local date-time, a local date or a local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TomlDatetime {
+    pub date: Option<TomlDate>,
+    pub time: Option<TomlTime>,
+    /// minutes east of UTC, `Z` is 0, local values have none
+    pub offset: Option<i16>,
+}
+
+impl fmt::Display for TomlDatetime {
+    /// RFC 3339 form, `1979-05-27T07:32:00Z`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(date) = &self.date {
+            write!(f, "{:04}-{:02}-{:02}", date.year, date.month, date.day)?;
+            if self.time.is_some() {
+                write!(f, "T")?;
+            }
+        }
+        if let Some(time) = &self.time {
+            write!(f, "{:02}:{:02}:{:02}", time.hour, time.minute, time.second)?;
+            if time.nanosecond > 0 {
+                let fraction = format!("{:09}", time.nanosecond);
+                write!(f, ".{}", fraction.trim_end_matches('0'))?;
+            }
+        }
+        match self.offset {
+            Some(0) => write!(f, "Z"),
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                write!(f, "{}{:02}:{:02}", sign, offset.abs() / 60, offset.abs() % 60)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// A syntax error, or a key or table defined twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for TomlError {}
+
 #[derive(Clone, Debug)]
 pub struct TomlParser {
     pub data: HashMap<String, TomlValue>,
+    // line and column of every key and table header
+    positions: HashMap<String, (usize, usize)>,
 }
 
 impl TomlParser {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Parses a TOML document, its top-level keys are added to `data`.
+    pub fn parse(&mut self, input: &str) -> Result<(), TomlError> {
+        let mut parser = Parser::new(input);
+        let root = parser.document()?;
+        if let TomlValue::Table(data) = root.into_value() {
+            self.data.extend(data);
+        }
+        self.positions.extend(parser.positions);
+        Ok(())
+    }
+
+    /// Line and column where a key or table was defined, `path` joins the keys with dots and
+    /// counts from 0 in arrays of tables, `katana.port` or `location.1.path`.
+    pub fn position(&self, path: &str) -> Option<(usize, usize)> {
+        self.positions.get(path).copied()
+    }
+
+    /// Parses a single value, `"text"`, `[1, 2]` or `{ a = 1 }`.
+    pub fn parse_value(value: &str) -> Result<TomlValue, TomlError> {
+        let mut parser = Parser::new(value);
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(value),
+            Some(_) => parser.error("unexpected characters after the value"),
+        }
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&TomlValue> {
+        self.data.get(key)
+    }
+
+    pub fn get_nested_value(&self, section: &str, key: &str) -> Option<&TomlValue> {
+        if let Some(TomlValue::Table(table)) = self.data.get(section) {
+            return table.get(key);
+        }
+        None
+    }
+}
+
+impl Default for TomlParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// how a table came to exist, which decides whether it may still be defined or extended
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Defined {
+    /// parent of a `[a.b]` header, `[a]` may still define it
+    Implicit,
+    /// by a `[header]`
+    Header,
+    /// by a dotted key, `a.b = 1`
+    Dotted,
+}
+
+#[derive(Debug)]
+enum Node {
+    /// plain values, inline tables and static arrays can never be extended
+    Value(TomlValue),
+    Table(Table),
+    /// `[[array.of.tables]]`
+    Tables(Vec<Table>),
+}
+
+impl Node {
+    fn into_value(self) -> TomlValue {
+        match self {
+            Self::Value(value) => value,
+            Self::Table(table) => table.into_value(),
+            Self::Tables(tables) => TomlValue::Array(tables.into_iter().map(Table::into_value).collect()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Table {
+    defined: Defined,
+    entries: HashMap<String, Node>,
+}
+
+impl Table {
+    fn new(defined: Defined) -> Self {
+        Self {
+            defined,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn into_value(self) -> TomlValue {
+        TomlValue::Table(
+            self.entries
+                .into_iter()
+                .map(|(key, node)| (key, node.into_value()))
+                .collect(),
+        )
+    }
+
+    // the table a header or the key-value pairs after it write to
+    fn at<'t>(mut table: &'t mut Table, path: &[String]) -> Option<&'t mut Table> {
+        for key in path {
+            table = match table.entries.get_mut(key)? {
+                Node::Table(child) => child,
+                Node::Tables(tables) => tables.last_mut()?,
+                Node::Value(_) => return None,
+            };
+        }
+        Some(table)
+    }
+
+    // [a.b.c] or [[a.b.c]], returns the path of the opened table
+    fn open(mut table: &mut Table, keys: &[String], array: bool) -> Result<String, String> {
+        let Some((last, parents)) = keys.split_last() else {
+            return Err("expected a table name".to_string());
+        };
+        let mut path = Vec::new();
+        for (index, key) in parents.iter().enumerate() {
+            path.push(key.clone());
+            let node = table
+                .entries
+                .entry(key.clone())
+                .or_insert_with(|| Node::Table(Table::new(Defined::Implicit)));
+            table = match node {
+                Node::Table(child) => child,
+                Node::Tables(tables) => {
+                    path.push((tables.len().max(1) - 1).to_string());
+                    match tables.last_mut() {
+                        Some(child) => child,
+                        None => return Err(format!("table `{}` is empty", keys[..=index].join("."))),
+                    }
+                }
+                Node::Value(_) => return Err(format!("key `{}` is already defined as a value", keys[..=index].join("."))),
+            };
+        }
+        path.push(last.clone());
+
+        match table.entries.get_mut(last) {
+            None if array => {
+                table.entries.insert(last.clone(), Node::Tables(vec![Table::new(Defined::Header)]));
+                path.push("0".to_string());
+            }
+            None => {
+                table.entries.insert(last.clone(), Node::Table(Table::new(Defined::Header)));
+            }
+            Some(Node::Table(child)) if !array && child.defined == Defined::Implicit => child.defined = Defined::Header,
+            Some(Node::Tables(tables)) if array => {
+                path.push(tables.len().to_string());
+                tables.push(Table::new(Defined::Header));
+            }
+            Some(Node::Value(TomlValue::Array(_))) if array => {
+                return Err(format!("array `{}` is static and cannot be extended", keys.join(".")));
+            }
+            Some(_) => return Err(format!("table `{}` is already defined", keys.join("."))),
+        }
+        Ok(path.join("."))
+    }
+
+    // a.b.c = value, the dotted keys define the tables on the way
+    fn insert(mut table: &mut Table, keys: &[String], value: TomlValue) -> Result<(), String> {
+        let Some((last, parents)) = keys.split_last() else {
+            return Err("expected a key".to_string());
+        };
+        for (index, key) in parents.iter().enumerate() {
+            let node = table
+                .entries
+                .entry(key.clone())
+                .or_insert_with(|| Node::Table(Table::new(Defined::Dotted)));
+            table = match node {
+                Node::Table(child) if child.defined == Defined::Dotted => child,
+                _ => return Err(format!("key `{}` is already defined", keys[..=index].join("."))),
+            };
+        }
+
+        if table.entries.contains_key(last) {
+            return Err(format!("key `{}` is already defined", keys.join(".")));
+        }
+        table.entries.insert(last.clone(), Node::Value(value));
+        Ok(())
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // index of the first character of every line
+    lines: Vec<usize>,
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        let chars: Vec<char> = input.trim_start_matches('\u{feff}').chars().collect();
+        let lines = std::iter::once(0)
+            .chain(chars.iter().enumerate().filter(|(_, c)| **c == '\n').map(|(index, _)| index + 1))
+            .collect();
+        Self {
+            chars,
+            pos: 0,
+            lines,
+            positions: HashMap::new(),
         }
     }
 
-    pub fn parse(&mut self, input: &str) {
-        let mut current_section = String::new();
+    fn document(&mut self) -> Result<Table, TomlError> {
+        let mut root = Table::new(Defined::Header);
+        let mut current: Vec<String> = Vec::new();
+        let mut current_path = String::new();
 
-        for line in input.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some('#') => self.skip_comment()?,
+                Some('\n') | Some('\r') => {
+                    if !self.newline() {
+                        return self.error("bare carriage return");
+                    }
+                }
+                Some('[') => {
+                    let start = self.pos;
+                    let array = self.peek_at(1) == Some('[');
+                    self.pos += if array { 2 } else { 1 };
+                    self.skip_whitespace();
+                    let keys = self.key()?;
+                    self.skip_whitespace();
+                    self.expect(']', "expected ']' after the table name")?;
+                    if array {
+                        self.expect(']', "expected ']]' after the table name")?;
+                    }
+                    current_path = Table::open(&mut root, &keys, array).map_err(|message| self.error_at(start, &message))?;
+                    self.positions.insert(current_path.clone(), self.line_column(start));
+                    current = keys;
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let start = self.pos;
+                    let (keys, value) = self.keyval()?;
+                    let table = match Table::at(&mut root, &current) {
+                        Some(table) => table,
+                        None => return Err(self.error_at(start, "table is not defined")),
+                    };
+                    Table::insert(table, &keys, value).map_err(|message| self.error_at(start, &message))?;
+                    let path = match current_path.is_empty() {
+                        true => keys.join("."),
+                        false => format!("{}.{}", current_path, keys.join(".")),
+                    };
+                    self.positions.insert(path, self.line_column(start));
+                    self.end_of_line()?;
+                }
+            }
+        }
+        Ok(root)
+    }
+
+    fn keyval(&mut self) -> Result<(Vec<String>, TomlValue), TomlError> {
+        let keys = self.key()?;
+        self.skip_whitespace();
+        self.expect('=', "expected '=' after the key")?;
+        self.skip_whitespace();
+        let value = self.value()?;
+        Ok((keys, value))
+    }
+
+    // a, "a b", 'a', a.b."c"
+    fn key(&mut self) -> Result<Vec<String>, TomlError> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"') if self.peek_at(1) == Some('"') && self.peek_at(2) == Some('"') => {
+                    return self.error("multi-line strings cannot be keys");
+                }
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return self.error("expected a key");
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            keys.push(key);
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(keys);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<TomlValue, TomlError> {
+        match self.peek() {
+            Some('"') if self.peek_at(1) == Some('"') && self.peek_at(2) == Some('"') => {
+                self.multiline_basic_string().map(TomlValue::String)
+            }
+            Some('"') => self.basic_string().map(TomlValue::String),
+            Some('\'') if self.peek_at(1) == Some('\'') && self.peek_at(2) == Some('\'') => {
+                self.multiline_literal_string().map(TomlValue::String)
+            }
+            Some('\'') => self.literal_string().map(TomlValue::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some('t') if self.keyword("true") => Ok(TomlValue::Boolean(true)),
+            Some('f') if self.keyword("false") => Ok(TomlValue::Boolean(false)),
+            Some(c) if c.is_ascii_digit() || matches!(c, '+' | '-' | 'i' | 'n') => self.number_or_datetime(),
+            Some(_) => self.error("expected a value, strings must be quoted"),
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let matches = keyword.chars().enumerate().all(|(index, c)| self.peek_at(index) == Some(c));
+        if matches {
+            self.pos += keyword.len();
+        }
+        matches
+    }
+
+    fn array(&mut self) -> Result<TomlValue, TomlError> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank()?;
+            if self.eat(']') {
+                break;
+            }
+            values.push(self.value()?);
+            self.skip_blank()?;
+            if self.eat(',') {
                 continue;
             }
+            self.expect(']', "expected ',' or ']' in the array")?;
+            break;
+        }
+        Ok(TomlValue::Array(values))
+    }
 
-            if line.starts_with('[') && line.ends_with(']') {
-                current_section = line.trim_matches(&['[', ']'][..]).to_string();
-                self.data.insert(current_section.clone(), TomlValue::Table(HashMap::new()));
-            } else if let Some((key, value)) = line.split_once('=') {
-                let mut key = key.trim().to_string();
-                if key.starts_with("\"") && key.ends_with("\"") {
-                    key = key.trim_matches('"').to_string();
+    // { a = 1, b.c = 2 }, on a single line and without trailing comma
+    fn inline_table(&mut self) -> Result<TomlValue, TomlError> {
+        self.pos += 1;
+        let mut table = Table::new(Defined::Dotted);
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(table.into_value());
+        }
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let (keys, value) = self.keyval()?;
+            Table::insert(&mut table, &keys, value).map_err(|message| self.error_at(start, &message))?;
+            self.skip_whitespace();
+            if self.eat(',') {
+                continue;
+            }
+            if self.eat('}') {
+                return Ok(table.into_value());
+            }
+            return self.error("expected ',' or '}' in the inline table");
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, TomlError> {
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') | Some('\r') => return self.error("unterminated string"),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(value);
                 }
-                let value = Self::parse_value(value.trim());
-                if let Some(TomlValue::Table(section)) = self.data.get_mut(&current_section) {
-                    section.insert(key, value);
-                } else {
-                    self.data.insert(key, value);
+                Some('\\') => value.push(self.escape()?),
+                Some(c) if Self::is_control(c) => return self.error("control characters must be escaped"),
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
                 }
             }
         }
     }
 
-    pub fn parse_value(value: &str) -> TomlValue {
-        if value.starts_with('"') && value.ends_with('"') {
-            TomlValue::String(value.trim_matches('"').to_string())
-        } else if let Ok(int) = value.parse::<i64>() {
-            TomlValue::Integer(int)
-        } else if let Ok(float) = value.parse::<f64>() {
-            TomlValue::Float(float)
-        } else if let Ok(boolean) = value.parse::<bool>() {
-            TomlValue::Boolean(boolean)
-        } else if value.starts_with('[') && value.ends_with(']') {
-            let elements = value.trim_matches(&['[', ']'][..])
-                .split(',')
-                .map(|v| Self::parse_value(v.trim()))
-                .collect();
-            TomlValue::Array(elements)
-        } else {
-            TomlValue::String(value.to_string())
+    fn multiline_basic_string(&mut self) -> Result<String, TomlError> {
+        self.pos += 3;
+        // a new line right after the delimiter is trimmed
+        self.newline();
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return self.error("unterminated string"),
+                Some('"') if self.peek_at(1) == Some('"') && self.peek_at(2) == Some('"') => {
+                    self.pos += 3;
+                    // up to two quotes right before the delimiter belong to the string
+                    for _ in 0..2 {
+                        if self.eat('"') {
+                            value.push('"');
+                        }
+                    }
+                    return Ok(value);
+                }
+                Some('\\') if self.line_ending_backslash() => {
+                    self.pos += 1;
+                    loop {
+                        self.skip_whitespace();
+                        if !self.newline() {
+                            break;
+                        }
+                    }
+                }
+                Some('\\') => value.push(self.escape()?),
+                Some('\n') | Some('\r') => {
+                    if !self.newline() {
+                        return self.error("bare carriage return");
+                    }
+                    value.push('\n');
+                }
+                Some(c) if Self::is_control(c) => return self.error("control characters must be escaped"),
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
         }
     }
 
-    pub fn get_value(&self, key: &str) -> Option<&TomlValue> {
-        self.data.get(key)
+    // a backslash followed by blanks up to the end of the line joins the lines
+    fn line_ending_backslash(&self) -> bool {
+        let mut offset = 1;
+        while matches!(self.peek_at(offset), Some(' ' | '\t')) {
+            offset += 1;
+        }
+        match self.peek_at(offset) {
+            Some('\n') => true,
+            Some('\r') => self.peek_at(offset + 1) == Some('\n'),
+            _ => false,
+        }
     }
 
-    pub fn get_nested_value(&self, section: &str, key: &str) -> Option<&TomlValue> {
-        if let Some(TomlValue::Table(table)) = self.data.get(section) {
-            return table.get(key);
+    fn escape(&mut self) -> Result<char, TomlError> {
+        let start = self.pos;
+        self.pos += 1;
+        let escaped = match self.peek() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(c @ ('u' | 'U')) => {
+                let length = if c == 'u' { 4 } else { 8 };
+                let digits: String = (1..=length).filter_map(|offset| self.peek_at(offset)).collect();
+                let scalar = match digits.len() == length && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    true => u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32),
+                    false => None,
+                };
+                match scalar {
+                    Some(scalar) => {
+                        self.pos += length;
+                        scalar
+                    }
+                    None => return Err(self.error_at(start, "invalid unicode escape")),
+                }
+            }
+            _ => return Err(self.error_at(start, "invalid escape sequence")),
+        };
+        self.pos += 1;
+        Ok(escaped)
+    }
+
+    fn literal_string(&mut self) -> Result<String, TomlError> {
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') | Some('\r') => return self.error("unterminated string"),
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some(c) if Self::is_control(c) => return self.error("control characters are not allowed in literal strings"),
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
         }
-        None
+    }
+
+    fn multiline_literal_string(&mut self) -> Result<String, TomlError> {
+        self.pos += 3;
+        self.newline();
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return self.error("unterminated string"),
+                Some('\'') if self.peek_at(1) == Some('\'') && self.peek_at(2) == Some('\'') => {
+                    self.pos += 3;
+                    for _ in 0..2 {
+                        if self.eat('\'') {
+                            value.push('\'');
+                        }
+                    }
+                    return Ok(value);
+                }
+                Some('\n') | Some('\r') => {
+                    if !self.newline() {
+                        return self.error("bare carriage return");
+                    }
+                    value.push('\n');
+                }
+                Some(c) if Self::is_control(c) => return self.error("control characters are not allowed in literal strings"),
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn number_or_datetime(&mut self) -> Result<TomlValue, TomlError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':')) {
+            self.pos += 1;
+        }
+        // a space may separate the date from the time, 1979-05-27 07:32:00
+        if self.pos - start == 10
+            && self.peek() == Some(' ')
+            && self.peek_at(1).is_some_and(|c| c.is_ascii_digit())
+            && self.peek_at(2).is_some_and(|c| c.is_ascii_digit())
+            && self.peek_at(3) == Some(':')
+        {
+            self.pos += 1;
+            while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | ':')) {
+                self.pos += 1;
+            }
+        }
+
+        let token: String = self.chars[start..self.pos].iter().collect();
+        let bytes = token.as_bytes();
+        let is_date = bytes.len() >= 10 && bytes[4] == b'-' && bytes[..4].iter().all(u8::is_ascii_digit);
+        let is_time = bytes.len() >= 8 && bytes[2] == b':' && bytes[..2].iter().all(u8::is_ascii_digit);
+        let value = match is_date || is_time {
+            true => Self::datetime(&token).map(TomlValue::Datetime),
+            false => Self::number(&token),
+        };
+        value.map_err(|message| self.error_at(start, &message))
+    }
+
+    fn number(token: &str) -> Result<TomlValue, String> {
+        let (negative, body) = match token.as_bytes().first() {
+            Some(b'+') => (false, &token[1..]),
+            Some(b'-') => (true, &token[1..]),
+            _ => (false, token),
+        };
+        match body {
+            "inf" => return Ok(TomlValue::Float(if negative { f64::NEG_INFINITY } else { f64::INFINITY })),
+            "nan" => return Ok(TomlValue::Float(f64::NAN)),
+            _ => {}
+        }
+
+        for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+            if let Some(digits) = body.strip_prefix(prefix) {
+                if body.len() != token.len() {
+                    return Err(format!("{} integers cannot have a sign", prefix));
+                }
+                let digits = Self::digits(digits, radix).ok_or_else(|| format!("invalid number `{}`", token))?;
+                return i64::from_str_radix(&digits, radix)
+                    .map(TomlValue::Integer)
+                    .map_err(|_| format!("integer `{}` is out of range", token));
+            }
+        }
+
+        let invalid = || format!("invalid number `{}`", token);
+        let (mantissa, exponent) = match body.find(['e', 'E']) {
+            Some(index) => (&body[..index], Some(&body[index + 1..])),
+            None => (body, None),
+        };
+        let (integer, fraction) = match mantissa.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (mantissa, None),
+        };
+        let integer = Self::digits(integer, 10).ok_or_else(invalid)?;
+        if integer.len() > 1 && integer.starts_with('0') {
+            return Err(format!("leading zeros are not allowed in `{}`", token));
+        }
+        let sign = if negative { "-" } else { "" };
+
+        if fraction.is_none() && exponent.is_none() {
+            return format!("{}{}", sign, integer)
+                .parse::<i64>()
+                .map(TomlValue::Integer)
+                .map_err(|_| format!("integer `{}` is out of range", token));
+        }
+
+        let mut float = format!("{}{}", sign, integer);
+        if let Some(fraction) = fraction {
+            float.push('.');
+            float.push_str(&Self::digits(fraction, 10).ok_or_else(invalid)?);
+        }
+        if let Some(exponent) = exponent {
+            let (exponent_sign, exponent) = match exponent.as_bytes().first() {
+                Some(b'+') => ("", &exponent[1..]),
+                Some(b'-') => ("-", &exponent[1..]),
+                _ => ("", exponent),
+            };
+            float.push_str(&format!("e{}{}", exponent_sign, Self::digits(exponent, 10).ok_or_else(invalid)?));
+        }
+        float.parse::<f64>().map(TomlValue::Float).map_err(|_| invalid())
+    }
+
+    // digits of `radix`, underscores only between two digits
+    fn digits(value: &str, radix: u32) -> Option<String> {
+        let bytes = value.as_bytes();
+        let valid = !bytes.is_empty()
+            && bytes[0] != b'_'
+            && bytes[bytes.len() - 1] != b'_'
+            && !value.contains("__")
+            && value.chars().all(|c| c == '_' || c.is_digit(radix));
+        valid.then(|| value.replace('_', ""))
+    }
+
+    fn datetime(token: &str) -> Result<TomlDatetime, String> {
+        let invalid = || format!("invalid date or time `{}`", token);
+        let mut datetime = TomlDatetime {
+            date: None,
+            time: None,
+            offset: None,
+        };
+
+        let mut rest = token;
+        if token.len() >= 10 && token.as_bytes()[4] == b'-' {
+            let year = Self::fixed(&token[0..4]).ok_or_else(invalid)?;
+            let month = Self::fixed(&token[5..7]).ok_or_else(invalid)?;
+            let day = Self::fixed(&token[8..10]).ok_or_else(invalid)?;
+            if token.as_bytes()[7] != b'-' || !(1..=12).contains(&month) || day < 1 || day > Self::days_in_month(year, month) {
+                return Err(invalid());
+            }
+            datetime.date = Some(TomlDate {
+                year: year as u16,
+                month: month as u8,
+                day: day as u8,
+            });
+            rest = &token[10..];
+            if rest.is_empty() {
+                return Ok(datetime);
+            }
+            rest = match rest.as_bytes()[0] {
+                b'T' | b't' | b' ' => &rest[1..],
+                _ => return Err(invalid()),
+            };
+        }
+
+        if rest.len() < 8 || rest.as_bytes()[2] != b':' || rest.as_bytes()[5] != b':' {
+            return Err(invalid());
+        }
+        let hour = Self::fixed(&rest[0..2]).ok_or_else(invalid)?;
+        let minute = Self::fixed(&rest[3..5]).ok_or_else(invalid)?;
+        let second = Self::fixed(&rest[6..8]).ok_or_else(invalid)?;
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(invalid());
+        }
+        rest = &rest[8..];
+
+        let mut nanosecond = 0;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let length = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if length == 0 {
+                return Err(invalid());
+            }
+            // precision past nanoseconds is truncated
+            let digits = format!("{:0<9}", &fraction[..length.min(9)]);
+            nanosecond = digits.parse::<u32>().map_err(|_| invalid())?;
+            rest = &fraction[length..];
+        }
+        datetime.time = Some(TomlTime {
+            hour: hour as u8,
+            minute: minute as u8,
+            second: second as u8,
+            nanosecond,
+        });
+
+        datetime.offset = match rest.as_bytes() {
+            [] => None,
+            [b'Z' | b'z'] => Some(0),
+            [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+                let hours = Self::fixed(&rest[1..3]).ok_or_else(invalid)?;
+                let minutes = Self::fixed(&rest[4..6]).ok_or_else(invalid)?;
+                if hours > 23 || minutes > 59 {
+                    return Err(invalid());
+                }
+                let offset = (hours * 60 + minutes) as i16;
+                Some(if *sign == b'-' { -offset } else { offset })
+            }
+            _ => return Err(invalid()),
+        };
+        if datetime.offset.is_some() && datetime.date.is_none() {
+            return Err(invalid());
+        }
+        Ok(datetime)
+    }
+
+    // exactly these digits, no sign nor underscore
+    fn fixed(value: &str) -> Option<u32> {
+        match value.bytes().all(|b| b.is_ascii_digit()) {
+            true => value.parse().ok(),
+            false => None,
+        }
+    }
+
+    fn days_in_month(year: u32, month: u32) -> u32 {
+        match month {
+            2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    fn is_control(c: char) -> bool {
+        (c < ' ' && c != '\t') || c == '\u{7f}'
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matches = self.peek() == Some(c);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn expect(&mut self, c: char, message: &str) -> Result<(), TomlError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => self.error(message),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), TomlError> {
+        if self.peek() != Some('#') {
+            return Ok(());
+        }
+        while let Some(c) = self.peek() {
+            if c == '\n' || (c == '\r' && self.peek_at(1) == Some('\n')) {
+                break;
+            }
+            if Self::is_control(c) {
+                return self.error("control characters are not allowed in comments");
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    // \n or \r\n
+    fn newline(&mut self) -> bool {
+        match (self.peek(), self.peek_at(1)) {
+            (Some('\n'), _) => self.pos += 1,
+            (Some('\r'), Some('\n')) => self.pos += 2,
+            _ => return false,
+        }
+        true
+    }
+
+    // blanks, comments and new lines between array values
+    fn skip_blank(&mut self) -> Result<(), TomlError> {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment()?;
+            if !self.newline() {
+                return match self.peek() {
+                    Some('\r') => self.error("bare carriage return"),
+                    _ => Ok(()),
+                };
+            }
+        }
+    }
+
+    // only a comment may follow a value or a header on its line
+    fn end_of_line(&mut self) -> Result<(), TomlError> {
+        self.skip_whitespace();
+        self.skip_comment()?;
+        if self.peek().is_none() || self.newline() {
+            return Ok(());
+        }
+        self.error("expected a new line")
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, TomlError> {
+        Err(self.error_at(self.pos, message))
+    }
+
+    fn error_at(&self, pos: usize, message: &str) -> TomlError {
+        let (line, column) = self.line_column(pos);
+        TomlError {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    fn line_column(&self, pos: usize) -> (usize, usize) {
+        let line = self.lines.partition_point(|start| *start <= pos);
+        (line, pos - self.lines[line - 1] + 1)
     }
 }
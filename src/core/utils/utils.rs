@@ -55,6 +55,49 @@ impl Utils {
         PathBuf::from(normalized.to_string_lossy().replace('\\', "/"))
     }
 
+    /// Tells whether a request path sits under `prefix`, on path segment boundaries
+    /// (`/private` covers `/private` and `/private/a` but not `/privateer`).
+    pub fn path_matches_prefix(path: &str, prefix: &str) -> bool {
+        let prefix = prefix.trim_end_matches('/');
+        if prefix.is_empty() {
+            return true;
+        }
+
+        match path.strip_prefix(prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    /// The rule with the longest prefix covering `path`, if any. The path is normalized
+    /// first, otherwise `/public/../private` walks around `/private`.
+    pub fn longest_prefix<'r, T: 'r>(rules: impl IntoIterator<Item = &'r T>, path: &str, prefix: impl Fn(&T) -> &str) -> Option<&'r T> {
+        let normalized = Self::normalize_path(PathBuf::from(path));
+        let normalized = normalized.to_string_lossy();
+
+        rules
+            .into_iter()
+            .filter(|rule| Self::path_matches_prefix(&normalized, prefix(rule)))
+            .max_by_key(|rule| prefix(rule).len())
+    }
+
+    /// Normalized request path, `None` when it goes through a hidden (dot) segment.
+    pub fn request_path(path: &str) -> Option<String> {
+        let normalized = Self::normalize_path(PathBuf::from(path));
+        let normalized = normalized.to_string_lossy();
+        let normalized = format!("/{}", normalized.trim_matches('/'));
+
+        if normalized.split('/').any(|segment| !segment.is_empty() && !Self::is_valid_entry(segment)) {
+            return None;
+        }
+        Some(normalized)
+    }
+
+    /// Whether `name` can be sent as a header name, a token of RFC 9110.
+    pub fn is_header_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+    }
+
     pub fn timezone_from_env() -> String {
         env::var("TZ").unwrap_or("00:00".to_string())
     }
@@ -218,6 +261,97 @@ impl Utils {
         }
     }
 
+    /// Splits a unix timestamp into (year, month, day, hours, minutes, seconds), all in UTC.
+    // @see: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn date_components(timestamp: u64) -> (i64, u32, u32, u32, u32, u32) {
+        let days = (timestamp / 86400) as i64;
+        let secs_of_day = timestamp % 86400;
+
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        (
+            year,
+            month,
+            day,
+            (secs_of_day / 3600) as u32,
+            ((secs_of_day % 3600) / 60) as u32,
+            (secs_of_day % 60) as u32,
+        )
+    }
+
+    /// Formats a unix timestamp as an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`).
+    // @see: https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7
+    pub fn http_date(timestamp: u64) -> String {
+        const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+
+        let (year, month, day, hours, minutes, seconds) = Self::date_components(timestamp);
+        format!(
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[((timestamp / 86400) % 7) as usize],
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            hours,
+            minutes,
+            seconds
+        )
+    }
+
+    /// Percent-encodes everything but unreserved characters and `/`, for use in URLs.
+    // @see: https://www.rfc-editor.org/rfc/rfc3986#section-2.3
+    pub fn encode_path(path: &str) -> String {
+        let mut result = String::with_capacity(path.len());
+        for byte in path.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    result.push(byte as char)
+                }
+                _ => result.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        result
+    }
+
+    /// Parses a byte size like `512`, `64K`, `100M` or `2G` (binary multiples).
+    pub fn parse_size(value: &str) -> Option<u64> {
+        let value = value.trim().to_uppercase();
+        let value = value.strip_suffix("IB").or_else(|| value.strip_suffix('B')).unwrap_or(&value);
+        let (number, multiplier) = match value.chars().last()? {
+            'K' => (&value[..value.len() - 1], 1u64 << 10),
+            'M' => (&value[..value.len() - 1], 1 << 20),
+            'G' => (&value[..value.len() - 1], 1 << 30),
+            'T' => (&value[..value.len() - 1], 1 << 40),
+            _ => (value, 1),
+        };
+        number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+    }
+
+    /// Seconds in `"90"`, `"90s"`, `"10m"`, `"2h"`, `"1d"`, `"1w"` or `"1y"` (365 days).
+    pub fn parse_duration(value: &str) -> Option<u64> {
+        let value = value.trim().to_lowercase();
+        let (number, multiplier) = match value.chars().last()? {
+            's' => (&value[..value.len() - 1], 1u64),
+            'm' => (&value[..value.len() - 1], 60),
+            'h' => (&value[..value.len() - 1], 3600),
+            'd' => (&value[..value.len() - 1], 86400),
+            'w' => (&value[..value.len() - 1], 7 * 86400),
+            'y' => (&value[..value.len() - 1], 365 * 86400),
+            _ => (value.as_str(), 1),
+        };
+        number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+    }
+
     pub fn log_datetime() -> String {
         let now = SystemTime::now().duration_since(UNIX_EPOCH);
         let seconds = now.unwrap().as_secs();
@@ -272,6 +406,19 @@ impl Utils {
         prettified
     }
 
+    /// Compares two byte strings without exiting early, so the timing doesn't leak how much matched.
+    pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+
+        let mut difference = 0u8;
+        for (x, y) in a.iter().zip(b.iter()) {
+            difference |= x ^ y;
+        }
+        difference == 0
+    }
+
     pub fn get_peer_ip(stream: &TcpStream) -> String {
         match stream.peer_addr() {
             Ok(addr) => addr.ip().to_string(),
@@ -0,0 +1,323 @@
+/*
+   Minimal namespace-aware XML reader, enough for the small request bodies of WebDAV
+   (PROPFIND, PROPPATCH, LOCK). Element names are resolved to (namespace, local name),
+   DTDs, comments and processing instructions are skipped.
+
+   https://www.w3.org/TR/xml/
+   https://www.w3.org/TR/xml-names/
+*/
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XmlElement {
+    pub namespace: String,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+    pub text: String,
+}
+
+impl XmlElement {
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    pub fn child(&self, namespace: &str, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.is(namespace, name))
+    }
+
+    pub fn children_named<'a>(&'a self, namespace: &'a str, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.is(namespace, name))
+    }
+
+    /// Serializes the element back, namespaces declared inline.
+    pub fn to_xml(&self) -> String {
+        let tag = if self.namespace.is_empty() {
+            self.name.clone()
+        } else {
+            format!("x:{} xmlns:x=\"{}\"", self.name, Xml::escape(&self.namespace))
+        };
+        let closing = if self.namespace.is_empty() {
+            self.name.clone()
+        } else {
+            format!("x:{}", self.name)
+        };
+
+        if self.children.is_empty() && self.text.is_empty() {
+            return format!("<{}/>", tag);
+        }
+
+        let mut content = Xml::escape(&self.text);
+        for child in &self.children {
+            content.push_str(&child.to_xml());
+        }
+        format!("<{}>{}</{}>", tag, content, closing)
+    }
+}
+
+pub struct Xml;
+
+impl Xml {
+    const MAX_DEPTH: usize = 64;
+
+    /// Parses a document and returns its root element, `None` when it is not well-formed.
+    pub fn parse(input: &str) -> Option<XmlElement> {
+        let mut parser = Parser {
+            input: input.as_bytes(),
+            position: 0,
+            scopes: vec![HashMap::from([(
+                "xml".to_string(),
+                "http://www.w3.org/XML/1998/namespace".to_string(),
+            )])],
+        };
+
+        parser.skip_misc()?;
+        let root = parser.element(0)?;
+        parser.skip_misc()?;
+        if parser.position != parser.input.len() {
+            return None;
+        }
+        Some(root)
+    }
+
+    pub fn escape(value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '&' => result.push_str("&amp;"),
+                '<' => result.push_str("&lt;"),
+                '>' => result.push_str("&gt;"),
+                '"' => result.push_str("&quot;"),
+                '\'' => result.push_str("&apos;"),
+                _ => result.push(c),
+            }
+        }
+        result
+    }
+
+    fn unescape(value: &str) -> Option<String> {
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value;
+
+        while let Some(start) = rest.find('&') {
+            result.push_str(&rest[..start]);
+            let end = rest[start..].find(';')? + start;
+            let entity = &rest[start + 1..end];
+            let c = match entity {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = if let Some(hex) = entity.strip_prefix("#x") {
+                        u32::from_str_radix(hex, 16).ok()?
+                    } else if let Some(decimal) = entity.strip_prefix('#') {
+                        decimal.parse::<u32>().ok()?
+                    } else {
+                        return None;
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            result.push(c);
+            rest = &rest[end + 1..];
+        }
+
+        result.push_str(rest);
+        Some(result)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    scopes: Vec<HashMap<String, String>>,
+}
+
+impl Parser<'_> {
+    fn peek(&self, token: &str) -> bool {
+        self.input[self.position..].starts_with(token.as_bytes())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.input.len() && self.input[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn skip_until(&mut self, token: &str) -> Option<()> {
+        let offset = self.input[self.position..]
+            .windows(token.len())
+            .position(|window| window == token.as_bytes())?;
+        self.position += offset + token.len();
+        Some(())
+    }
+
+    // whitespace, comments, processing instructions and doctype around the root element
+    fn skip_misc(&mut self) -> Option<()> {
+        loop {
+            self.skip_whitespace();
+            if self.peek("<?") {
+                self.skip_until("?>")?;
+            } else if self.peek("<!--") {
+                self.skip_until("-->")?;
+            } else if self.peek("<!DOCTYPE") {
+                // internal subsets are not supported, entity expansion is a classic attack
+                if self.input[self.position..].iter().take_while(|b| **b != b'>').any(|b| *b == b'[') {
+                    return None;
+                }
+                self.skip_until(">")?;
+            } else {
+                return Some(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let start = self.position;
+        while self.position < self.input.len() {
+            let b = self.input[self.position];
+            if b.is_ascii_whitespace() || b == b'>' || b == b'/' || b == b'=' {
+                break;
+            }
+            self.position += 1;
+        }
+        if start == self.position {
+            return None;
+        }
+        String::from_utf8(self.input[start..self.position].to_vec()).ok()
+    }
+
+    fn resolve(&self, qualified: &str) -> Option<(String, String)> {
+        let (prefix, local) = match qualified.split_once(':') {
+            Some((prefix, local)) => (prefix, local),
+            None => ("", qualified),
+        };
+        let namespace = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(prefix))
+            .cloned();
+
+        match namespace {
+            Some(namespace) => Some((namespace, local.to_string())),
+            // the default namespace may be undeclared, a prefix may not
+            None if prefix.is_empty() => Some((String::new(), local.to_string())),
+            None => None,
+        }
+    }
+
+    fn element(&mut self, depth: usize) -> Option<XmlElement> {
+        if depth > Xml::MAX_DEPTH || !self.peek("<") {
+            return None;
+        }
+        self.position += 1;
+        let qualified = self.name()?;
+
+        let mut raw_attributes = Vec::new();
+        let empty;
+        loop {
+            self.skip_whitespace();
+            if self.peek("/>") {
+                self.position += 2;
+                empty = true;
+                break;
+            }
+            if self.peek(">") {
+                self.position += 1;
+                empty = false;
+                break;
+            }
+
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.peek("=") {
+                return None;
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = *self.input.get(self.position)?;
+            if quote != b'"' && quote != b'\'' {
+                return None;
+            }
+            self.position += 1;
+            let start = self.position;
+            while *self.input.get(self.position)? != quote {
+                self.position += 1;
+            }
+            let value = std::str::from_utf8(&self.input[start..self.position]).ok()?;
+            self.position += 1;
+            raw_attributes.push((name, Xml::unescape(value)?));
+        }
+
+        let mut scope = HashMap::new();
+        let mut attributes = Vec::new();
+        for (name, value) in raw_attributes {
+            if name == "xmlns" {
+                scope.insert(String::new(), value);
+            } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+                scope.insert(prefix.to_string(), value);
+            } else {
+                attributes.push((name, value));
+            }
+        }
+        self.scopes.push(scope);
+
+        let (namespace, name) = self.resolve(&qualified)?;
+        let mut element = XmlElement {
+            namespace,
+            name,
+            attributes,
+            ..XmlElement::default()
+        };
+
+        if !empty {
+            self.content(&mut element, &qualified, depth)?;
+        }
+
+        self.scopes.pop();
+        Some(element)
+    }
+
+    fn content(&mut self, element: &mut XmlElement, qualified: &str, depth: usize) -> Option<()> {
+        loop {
+            if self.position >= self.input.len() {
+                return None;
+            }
+
+            if self.peek("</") {
+                self.position += 2;
+                let closing = self.name()?;
+                self.skip_whitespace();
+                if closing != qualified || !self.peek(">") {
+                    return None;
+                }
+                self.position += 1;
+                return Some(());
+            } else if self.peek("<!--") {
+                self.skip_until("-->")?;
+            } else if self.peek("<![CDATA[") {
+                self.position += 9;
+                let start = self.position;
+                self.skip_until("]]>")?;
+                let text = std::str::from_utf8(&self.input[start..self.position - 3]).ok()?;
+                element.text.push_str(text);
+            } else if self.peek("<?") {
+                self.skip_until("?>")?;
+            } else if self.peek("<") {
+                let child = self.element(depth + 1)?;
+                element.children.push(child);
+            } else {
+                let start = self.position;
+                while self.position < self.input.len() && self.input[self.position] != b'<' {
+                    self.position += 1;
+                }
+                let text = std::str::from_utf8(&self.input[start..self.position]).ok()?;
+                element.text.push_str(&Xml::unescape(text)?);
+            }
+        }
+    }
+}
@@ -1,14 +1,23 @@
 pub mod core;
 
 use std::collections::HashMap;
-use crate::core::config::config::Config;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use crate::core::config::config::{Config, ConfigChange, ConfigIssue};
+use crate::core::server::reload::ConfigReload;
 use crate::core::server::server::Server;
+use crate::core::server::sse::{EventPublisher, EventRoutes};
+use crate::core::server::websocket::{WebSocketHandler, WebSocketRoutes};
 use crate::core::resources::templates::{Templates, TemplatesPage};
 use crate::core::utils::logger::Logger;
 
 pub struct Katana {
     pub config: Config,
     pub templates: Templates,
+    pub websockets: WebSocketRoutes,
+    pub events: EventRoutes,
+    reload: ConfigReload,
 }
 
 impl Default for Katana {
@@ -19,15 +28,88 @@ impl Default for Katana {
 
 impl Katana {
     pub fn new() -> Self {
+        let config = Config::load();
         Self {
-            config: Config::load(),
+            reload: ConfigReload::new(config.clone()),
+            config,
             templates: Templates::load(),
+            websockets: WebSocketRoutes::new(),
+            events: EventRoutes::new(),
         }
     }
 
+    /// Mounts a WebSocket endpoint on `path`, plain requests to it get 426 Upgrade Required.
+    pub fn websocket(&mut self, path: &str, handler: impl WebSocketHandler + 'static) -> &mut Self {
+        self.websockets.mount(path, Arc::new(handler));
+        self
+    }
+
+    /// Mounts an event stream on `path` and returns its publisher, to be used from any thread.
+    pub fn event_stream(&mut self, path: &str) -> EventPublisher {
+        let publisher = EventPublisher::new();
+        self.events.mount(path, publisher.clone());
+        publisher
+    }
+
+    /// Loads the configuration again and applies it to the connections accepted from now on,
+    /// as SIGHUP does. Returns the changed settings, or the problems that kept the running
+    /// configuration in place.
+    pub fn reload(&self) -> Result<Vec<ConfigChange>, Vec<ConfigIssue>> {
+        self.reload.reload()
+    }
+
+    /// A handle to reload the configuration from another thread, `start` does not return.
+    pub fn reloader(&self) -> ConfigReload {
+        self.reload.clone()
+    }
+
+    /// Checks the `.katana` file at `path` as `katana check-config` does, printing every
+    /// problem found. Returns whether the file is valid.
+    pub fn check_config(path: &Path) -> bool {
+        if !path.is_file() {
+            eprintln!("{}: file not found", path.display());
+            return false;
+        }
+
+        let config = Config::from_file(path);
+        for issue in &config.issues {
+            eprintln!("{}", issue);
+        }
+        match config.issues.len() {
+            0 => println!("{}: configuration is valid", path.display()),
+            count => eprintln!("{}: {} problem(s) found", path.display(), count),
+        }
+        config.issues.is_empty()
+    }
+
+    /// Prints the effective configuration with the source of every value, as
+    /// `katana config dump` does, then its problems. Returns whether it is valid.
+    pub fn dump_config() -> bool {
+        let config = Config::load();
+        print!("{}", config.dump());
+        for issue in &config.issues {
+            eprintln!("{}", issue);
+        }
+        config.issues.is_empty()
+    }
+
     pub fn start(&self) {
+        if !self.config.issues.is_empty() {
+            for issue in &self.config.issues {
+                Logger::error(format!("[Config] {}", issue).as_str());
+            }
+            Logger::error(format!("[Config] Refusing to start, {} problem(s) in the configuration", self.config.issues.len()).as_str());
+            process::exit(1);
+        }
+
         self.show_banner();
-        let server = Server::new(self.config.to_owned(), self.templates.to_owned());
+        Logger::set_min_log_level(self.config.log_level.clone());
+        self.reload.set(self.config.clone());
+        self.reload.on_hangup();
+        let server = Server::new(self.config.to_owned(), self.templates.to_owned())
+            .with_websockets(self.websockets.clone())
+            .with_events(self.events.clone())
+            .with_reload(self.reload.clone());
         Logger::info(format!("Server starting on {}", server.addr_with_protocol()).as_str());
         server.serve();
     }
@@ -1,6 +1,35 @@
+use katana::core::config::arg::{Cli, CliCommand};
+use katana::core::server::server::Server;
 use katana::Katana;
+use std::env;
+use std::path::Path;
+use std::process;
 
 fn main() {
-    let katana: Katana = Katana::new();
-    katana.start();
+    let cli = Cli::parse(env::args().collect());
+
+    if !cli.config.issues.is_empty() && cli.command != CliCommand::Help {
+        for issue in &cli.config.issues {
+            eprintln!("katana: {}", issue);
+        }
+        eprintln!("Run `katana --help` for the list of options.");
+        process::exit(2);
+    }
+
+    match cli.command {
+        // `katana config` alone is reported above
+        CliCommand::Help | CliCommand::Config => print!("{}", Cli::help()),
+        CliCommand::Version => println!("{}", Server::version()),
+        CliCommand::CheckConfig(path) => {
+            let path = path.or(cli.config_file).unwrap_or_else(|| ".katana".into());
+            process::exit(if Katana::check_config(Path::new(&path)) { 0 } else { 1 });
+        }
+        CliCommand::ConfigDump => {
+            process::exit(if Katana::dump_config() { 0 } else { 1 });
+        }
+        CliCommand::Serve => {
+            let katana: Katana = Katana::new();
+            katana.start();
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use katana::core::server::access::{AccessAction, AccessControl, AccessDirective, AccessRule, IpNetwork};
+use std::net::IpAddr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn rule(prefix: &str, directives: &[&str]) -> AccessRule {
+        AccessRule::new(
+            prefix,
+            directives.iter().map(|d| AccessDirective::parse(d).unwrap()).collect(),
+        )
+    }
+
+    /// Test `IpNetwork::parse` and `IpNetwork::contains` for IPv4 and IPv6 ranges
+    #[test]
+    fn test_ip_network_contains() {
+        let private = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(private.contains(&ip("10.1.2.3")));
+        assert!(!private.contains(&ip("11.0.0.1")));
+        assert!(private.contains(&ip("::ffff:10.0.0.1")), "IPv4-mapped addresses should match");
+        assert!(!private.contains(&ip("fd00::1")));
+
+        let ula = IpNetwork::parse("fd00::/8").unwrap();
+        assert!(ula.contains(&ip("fd12:3456::1")));
+        assert!(!ula.contains(&ip("fe80::1")));
+
+        let single = IpNetwork::parse("192.168.1.10").unwrap();
+        assert_eq!(single.prefix_len, 32);
+        assert!(single.contains(&ip("192.168.1.10")));
+        assert!(!single.contains(&ip("192.168.1.11")));
+
+        let all = IpNetwork::parse("all").unwrap();
+        assert!(all.contains(&ip("8.8.8.8")));
+        assert!(all.contains(&ip("2001:db8::1")));
+    }
+
+    /// Test `IpNetwork::parse` and `AccessDirective::parse` reject malformed input
+    #[test]
+    fn test_parse_invalid() {
+        assert!(IpNetwork::parse("10.0.0.0/33").is_none());
+        assert!(IpNetwork::parse("::/129").is_none());
+        assert!(IpNetwork::parse("10.0.0").is_none());
+        assert!(IpNetwork::parse("localhost").is_none());
+
+        assert!(AccessDirective::parse("permit 10.0.0.0/8").is_none());
+        assert!(AccessDirective::parse("allow").is_none());
+        assert_eq!(
+            AccessDirective::parse("DENY  all").unwrap().action,
+            AccessAction::Deny
+        );
+    }
+
+    /// Test `AccessRule::allows` evaluates directives in order
+    #[test]
+    fn test_access_rule_order() {
+        let internal = rule("/internal", &["deny 10.0.0.13", "allow 10.0.0.0/8", "allow ::1", "deny all"]);
+
+        assert!(internal.allows(&ip("10.0.0.1")));
+        assert!(!internal.allows(&ip("10.0.0.13")), "Earlier deny should win");
+        assert!(internal.allows(&ip("::1")));
+        assert!(!internal.allows(&ip("203.0.113.5")));
+
+        let open = rule("/", &["deny 192.0.2.0/24"]);
+        assert!(open.allows(&ip("203.0.113.5")), "No matching directive should allow");
+    }
+
+    /// Test `AccessControl::is_allowed` picks the most specific prefix and resolves dot segments
+    #[test]
+    fn test_access_control_is_allowed() {
+        let rules = vec![
+            rule("/internal", &["allow 127.0.0.1", "deny all"]),
+            rule("/internal/status", &["allow all"]),
+        ];
+        let outside = ip("203.0.113.5");
+
+        assert!(AccessControl::is_allowed(&rules, "/index.html", Some(&outside)));
+        assert!(!AccessControl::is_allowed(&rules, "/internal/", Some(&outside)));
+        assert!(!AccessControl::is_allowed(&rules, "/public/../internal/a", Some(&outside)));
+        assert!(AccessControl::is_allowed(&rules, "/internal/status", Some(&outside)));
+        assert!(AccessControl::is_allowed(&rules, "/internal/a", Some(&ip("127.0.0.1"))));
+        assert!(AccessControl::is_allowed(&rules, "/internalfoo", Some(&outside)));
+        assert!(!AccessControl::is_allowed(&rules, "/internal", None), "Unknown peers should be refused");
+    }
+}
@@ -0,0 +1,112 @@
+use katana::core::server::archive::{Archive, ArchiveFormat};
+use katana::core::utils::crc32::Crc32;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to create a small tree with a hidden file and a hidden folder
+    fn create_temp_tree(name: &str) -> PathBuf {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("nested")).unwrap();
+        fs::create_dir_all(temp_dir.join(".git")).unwrap();
+        File::create(temp_dir.join("hello.txt"))
+            .unwrap()
+            .write_all(b"hello world")
+            .unwrap();
+        File::create(temp_dir.join("nested/data.txt"))
+            .unwrap()
+            .write_all(b"data")
+            .unwrap();
+        File::create(temp_dir.join(".env")).unwrap();
+        File::create(temp_dir.join(".git/config")).unwrap();
+        temp_dir
+    }
+
+    /// Test `ArchiveFormat::from_name` with supported and unsupported names
+    #[test]
+    fn test_archive_format_from_name() {
+        assert_eq!(ArchiveFormat::from_name("zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::from_name("TAR.GZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_name("tgz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_name("rar"), None);
+    }
+
+    /// Test `Crc32::checksum` against the standard check value
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(Crc32::checksum(b"123456789"), 0xCBF4_3926);
+        assert_eq!(Crc32::checksum(b""), 0);
+    }
+
+    /// Test that a tar archive lists visible entries only, under the given name
+    #[test]
+    fn test_tar_skips_hidden_entries() {
+        let temp_dir = create_temp_tree("archive_test_tar");
+        let archive = Archive::write(ArchiveFormat::Tar, &temp_dir, "share", Vec::new()).unwrap();
+
+        assert_eq!(archive.len() % 512, 0, "Tar archives are made of 512 bytes blocks");
+
+        let names: Vec<String> = archive
+            .chunks(512)
+            .filter(|block| &block[257..262] == b"ustar")
+            .map(|block| {
+                let end = block[..100].iter().position(|b| *b == 0).unwrap_or(100);
+                String::from_utf8_lossy(&block[..end]).to_string()
+            })
+            .collect();
+
+        assert_eq!(
+            names,
+            vec!["share/", "share/hello.txt", "share/nested/", "share/nested/data.txt"]
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test that a zip archive ends with a central directory matching its entries
+    #[test]
+    fn test_zip_end_of_central_directory() {
+        let temp_dir = create_temp_tree("archive_test_zip");
+        let archive = Archive::write(ArchiveFormat::Zip, &temp_dir, "share", Vec::new()).unwrap();
+
+        assert_eq!(&archive[..4], &[0x50, 0x4b, 0x03, 0x04], "Expected a local file header first");
+
+        let end = &archive[archive.len() - 22..];
+        assert_eq!(&end[..4], &[0x50, 0x4b, 0x05, 0x06], "Expected the end of central directory record");
+
+        let entries = u16::from_le_bytes([end[10], end[11]]);
+        assert_eq!(entries, 4, "Expected 2 folders and 2 files");
+
+        let hidden = archive.windows(4).any(|window| window == b".env");
+        assert!(!hidden, "Hidden files should not be archived");
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test that a gzip stream carries the header, checksum and size of its content
+    #[test]
+    fn test_tar_gz_trailer() {
+        let temp_dir = create_temp_tree("archive_test_tar_gz");
+        let tar = Archive::write(ArchiveFormat::Tar, &temp_dir, "share", Vec::new()).unwrap();
+        let archive = Archive::write(ArchiveFormat::TarGz, &temp_dir, "share", Vec::new()).unwrap();
+
+        assert_eq!(&archive[..3], &[0x1f, 0x8b, 0x08], "Expected gzip magic and deflate method");
+        assert!(archive.len() < tar.len(), "Expected the tar stream to be compressed");
+
+        let trailer = &archive[archive.len() - 8..];
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        assert_eq!(crc, Crc32::checksum(&tar));
+        assert_eq!(size as usize, tar.len());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use katana::core::config::config::Config;
+use katana::core::resources::templates::Templates;
+use katana::core::server::auth::{AuthRule, BasicAuth, Htpasswd};
+use katana::core::server::request::Request;
+use katana::core::server::server::Server;
+use katana::core::utils::crypt::Crypt;
+use katana::core::utils::utils::Utils;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTPASSWD: &str = "\
+# team members
+alice:$6$abcdefgh$e1o..VsKRS0O4M9J1Qb9u.strxNEAfDkCXcaYc5TsDrJFctQCTMkPeis45vy3ZQtqt4dqG4vXTonFJKbQgR2Q1
+bob:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=
+carol:$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW
+dave:$apr1$salt$hash
+";
+
+    /// Test `Htpasswd::verify` with every supported hash format
+    #[test]
+    fn test_htpasswd_verify_supported_formats() {
+        let htpasswd = Htpasswd::parse(HTPASSWD);
+
+        assert_eq!(htpasswd.len(), 4, "Comments should be skipped");
+        assert!(htpasswd.verify("alice", "wonderland"), "SHA-512 crypt should verify");
+        assert!(htpasswd.verify("bob", "password"), "{{SHA}} should verify");
+        assert!(htpasswd.verify("carol", "U*U"), "bcrypt should verify");
+    }
+
+    /// Test `Htpasswd::verify` rejects wrong passwords, unknown users and unsupported hashes
+    #[test]
+    fn test_htpasswd_verify_rejections() {
+        let htpasswd = Htpasswd::parse(HTPASSWD);
+
+        assert!(!htpasswd.verify("alice", "Wonderland"));
+        assert!(!htpasswd.verify("carol", "U*V"));
+        assert!(!htpasswd.verify("mallory", "password"));
+        assert!(!htpasswd.verify("dave", "anything"), "Apache MD5 is not supported");
+    }
+
+    /// Test SHA-256 crypt with custom rounds against a reference hash
+    #[test]
+    fn test_sha256_crypt_with_rounds() {
+        let hash = "$5$rounds=1200$abc$0/MncXuMpz0SS7GOSdEDIw.foBQJuXGoQEqZk5P3RD2";
+        assert!(Crypt::verify("hello", hash));
+        assert!(!Crypt::verify("hello!", hash));
+    }
+
+    /// Test `BasicAuth::rule_for` picks the longest prefix and resolves dot segments
+    #[test]
+    fn test_rule_for_longest_prefix() {
+        let rules = vec![
+            AuthRule::new("/private", PathBuf::from("a.htpasswd"), "Private"),
+            AuthRule::new("/private/admin", PathBuf::from("b.htpasswd"), "Admin"),
+        ];
+
+        assert_eq!(BasicAuth::rule_for(&rules, "/private/x.txt").unwrap().realm, "Private");
+        assert_eq!(BasicAuth::rule_for(&rules, "/private/admin/").unwrap().realm, "Admin");
+        assert_eq!(BasicAuth::rule_for(&rules, "/public/../private/x").unwrap().realm, "Private");
+        assert_eq!(BasicAuth::rule_for(&rules, "//private").unwrap().realm, "Private");
+        assert!(BasicAuth::rule_for(&rules, "/privateer").is_none());
+        assert!(BasicAuth::rule_for(&rules, "/").is_none());
+    }
+
+    /// Test `Utils::path_matches_prefix` on segment boundaries
+    #[test]
+    fn test_path_matches_prefix() {
+        assert!(Utils::path_matches_prefix("/docs", "/docs"));
+        assert!(Utils::path_matches_prefix("/docs/a", "/docs/"));
+        assert!(Utils::path_matches_prefix("/anything", "/"));
+        assert!(!Utils::path_matches_prefix("/docsx", "/docs"));
+    }
+
+    /// Test `AuthRule::challenge` header value
+    #[test]
+    fn test_challenge() {
+        let rule = AuthRule::new("/", PathBuf::from(".htpasswd"), "Team \"files\"");
+        assert_eq!(rule.challenge(), "Basic realm=\"Team 'files'\", charset=\"UTF-8\"");
+    }
+
+    /// Test `Htpasswd::load_cached` reads a file once, and again when it changes
+    #[test]
+    fn test_load_cached() {
+        let temp_dir = env::temp_dir().join("katana_auth_cached");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join(".htpasswd");
+        fs::write(&path, "bob:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n").unwrap();
+
+        let first = Htpasswd::load_cached(&path).unwrap();
+        assert!(Arc::ptr_eq(&first, &Htpasswd::load_cached(&path).unwrap()));
+        assert_eq!(first.len(), 1);
+
+        fs::write(&path, "bob:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\nalice:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n").unwrap();
+        assert_eq!(Htpasswd::load_cached(&path).unwrap().len(), 2);
+
+        fs::remove_file(&path).unwrap();
+        assert!(Htpasswd::load_cached(&path).is_err());
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test TRACE on a protected prefix is challenged like any method, and echoed elsewhere
+    #[test]
+    fn test_trace_challenged() {
+        let temp_dir = env::temp_dir().join("katana_auth_trace");
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        fs::write(temp_dir.join(".htpasswd"), "bob:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n").unwrap();
+        let path = temp_dir.join(".katana");
+        let content = format!(
+            "[katana]\ndocument_root = {:?}\n[auth]\n\"/private\" = {:?}\n",
+            temp_dir.join("public").to_string_lossy(),
+            temp_dir.join(".htpasswd").to_string_lossy()
+        );
+        fs::write(&path, content).unwrap();
+        let config = Config::from_file(&path);
+        assert!(config.issues.is_empty(), "{:?}", config.issues);
+        let server = Server::new(config, Templates::load());
+
+        let trace = |target: &str| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            write!(client, "TRACE {} HTTP/1.1\r\nHost: localhost\r\nX-Secret: 42\r\n\r\n", target).unwrap();
+            let (mut stream, _) = listener.accept().unwrap();
+            server.handle_response(Request::from_stream(&stream).unwrap(), &mut stream);
+            stream.shutdown(Shutdown::Write).unwrap();
+            let mut answer = String::new();
+            client.read_to_string(&mut answer).unwrap();
+            answer
+        };
+
+        let answer = trace("/private/notes.txt");
+        assert!(answer.starts_with("HTTP/1.1 401"), "{}", answer);
+        assert!(answer.contains("WWW-Authenticate: Basic"), "{}", answer);
+        assert!(!answer.contains("X-Secret"), "{}", answer);
+
+        let answer = trace("/missing.txt");
+        assert!(answer.starts_with("HTTP/1.1 200"), "{}", answer);
+        assert!(answer.contains("message/http"), "{}", answer);
+        assert!(answer.contains("X-Secret: 42"), "{}", answer);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+use katana::core::config::config::Config;
+use katana::core::server::cache::{Cache, CacheMatch, CachePolicy, CacheRule};
+use katana::core::utils::keyval::KeyVal;
+use katana::core::utils::regex::Regex;
+use katana::core::utils::utils::Utils;
+use std::env;
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `CachePolicy::parse` normalizes directives and durations
+    #[test]
+    fn test_parse() {
+        let policy = CachePolicy::parse("Public, max-age=1d, stale-while-revalidate=90").unwrap();
+        assert_eq!(policy.directives, ["public", "max-age=86400", "stale-while-revalidate=90"]);
+        assert_eq!(policy.max_age, Some(86400));
+        assert_eq!(policy.cache_control(), Some("public, max-age=86400, stale-while-revalidate=90".to_string()));
+
+        assert_eq!(CachePolicy::parse("private, no-cache=\"Set-Cookie\"").unwrap().directives.len(), 2);
+        assert_eq!(CachePolicy::parse("max-age"), None);
+        assert_eq!(CachePolicy::parse("max-age=soon"), None);
+        assert_eq!(CachePolicy::parse("public; max-age=60"), None);
+        assert_eq!(CachePolicy::parse(" , "), None);
+
+        assert_eq!(Utils::parse_duration("1y"), Some(31536000));
+        assert_eq!(Utils::parse_duration("2W"), Some(1209600));
+        assert_eq!(Utils::parse_duration("10 m"), Some(600));
+        assert_eq!(Utils::parse_duration("ten"), None);
+    }
+
+    /// Test `CachePolicy::add_headers` sends Expires from max-age, in the past to revalidate
+    #[test]
+    fn test_add_headers() {
+        let mut headers = KeyVal::new();
+        CachePolicy::immutable().add_headers(&mut headers, 0);
+        assert_eq!(headers.get("Cache-Control"), Some(&"public, max-age=31536000, immutable".to_string()));
+        assert_eq!(headers.get("Expires"), Some(&"Fri, 01 Jan 1971 00:00:00 GMT".to_string()));
+
+        let mut headers = KeyVal::new();
+        CachePolicy::parse("no-cache").unwrap().add_headers(&mut headers, 1_000_000);
+        assert_eq!(headers.get("Expires"), Some(&"Thu, 01 Jan 1970 00:00:00 GMT".to_string()));
+
+        let mut headers = KeyVal::new();
+        let policy = CachePolicy { expires: false, ..CachePolicy::parse("max-age=60").unwrap() };
+        policy.add_headers(&mut headers, 0);
+        assert!(!headers.exists("Expires"));
+
+        let mut headers = KeyVal::new();
+        CachePolicy::none().add_headers(&mut headers, 0);
+        assert!(headers.is_empty());
+    }
+
+    /// Test `Cache::policy_for` picks the most specific rule matching the file
+    #[test]
+    fn test_policy_for() {
+        let policy = |value: &str| CachePolicy::parse(value).unwrap();
+        let mut rules = vec![
+            CacheRule::immutable(Regex::new(Cache::DEFAULT_FINGERPRINT).unwrap()),
+            CacheRule::new("image/*", policy("max-age=1w")),
+            CacheRule::new(".css", policy("max-age=1d")),
+            CacheRule::new("/assets", policy("max-age=10m")),
+            CacheRule::new("/", policy("no-cache")),
+        ];
+        CacheRule::sort(&mut rules);
+        assert_eq!(CacheMatch::new(".css"), CacheMatch::new("*.css"));
+
+        let max_age = |path: &str, content_type: Option<&str>| Cache::policy_for(&rules, path, content_type).and_then(|policy| policy.max_age);
+        assert_eq!(max_age("/index.html", Some("text/html")), None);
+        assert_eq!(max_age("/assets/app.js", Some("application/javascript")), Some(600));
+        assert_eq!(max_age("/assets/site.css", Some("text/css")), Some(86400));
+        assert_eq!(max_age("/assets/logo.png", Some("image/png")), Some(604800));
+        assert_eq!(max_age("/assets/app.3f9a.js", Some("application/javascript")), Some(31536000));
+        assert_eq!(max_age("/assets/../app.3f9a.min.js", None), None);
+        assert!(Cache::policy_for(&[], "/app.js", None).is_none());
+    }
+
+    /// Test `Config::from_file` reads the `[cache]` section and reports its mistakes
+    #[test]
+    fn test_from_file() {
+        let temp_dir = env::temp_dir().join("katana_cache_file");
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let path = temp_dir.join(".katana");
+        let content = concat!(
+            "[katana]\n",
+            "document_root = \"{root}\"\n",
+            "[cache]\n",
+            "expires = false\n",
+            "immutable = true\n",
+            "fingerprint = '-[0-9a-f]{8}\\.'\n",
+            "\"/\" = \"no-cache\"\n",
+            "\"/downloads\" = false\n",
+            "\".js\" = \"max-age=forever\"\n",
+            "\"assets\" = \"max-age=60\"\n",
+        );
+        let root = temp_dir.join("public");
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy())).unwrap();
+
+        let config = Config::from_file(&path);
+        let keys: Vec<&str> = config.cache.iter().map(|rule| rule.matcher.as_str()).collect();
+        assert_eq!(keys, ["/", "/downloads", "-[0-9a-f]{8}\\."]);
+        assert!(config.cache.iter().all(|rule| !rule.policy.expires));
+        assert_eq!(config.cache[1].policy.cache_control(), None);
+
+        let issues: Vec<String> = config.issues.iter().map(|issue| issue.message.clone()).collect();
+        assert_eq!(
+            issues,
+            [
+                "`.js` must be Cache-Control directives like \"public, max-age=1d\", or false, got \"max-age=forever\"",
+                "unknown key `assets` in [cache]",
+            ]
+        );
+
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy()).replace("[0-9a-f]", "[0-9a-f")).unwrap();
+        let config = Config::from_file(&path);
+        assert!(config.issues[0].message.starts_with("`fingerprint` is not a valid pattern"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use katana::core::server::cgi::{Cgi, CgiRule, ScriptRules};
+use katana::core::server::fastcgi::{FastCgiAddress, FastCgiRule};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to create a document root with a cgi-bin folder and a Perl script
+    fn create_temp_root(name: &str) -> PathBuf {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("cgi-bin")).unwrap();
+        fs::create_dir_all(temp_dir.join("reports")).unwrap();
+        fs::write(temp_dir.join("cgi-bin/env.sh"), "#!/bin/sh\n").unwrap();
+        fs::write(temp_dir.join("cgi-bin/.secret.sh"), "#!/bin/sh\n").unwrap();
+        fs::write(temp_dir.join("reports/sum.PL"), "print 1;\n").unwrap();
+        fs::write(temp_dir.join("reports/data.txt"), "1").unwrap();
+        temp_dir
+    }
+
+    /// Test `CgiRule::is_extension` tells extensions from folders
+    #[test]
+    fn test_rule_kind() {
+        assert!(CgiRule::new(".pl", Some(PathBuf::from("/usr/bin/perl"))).is_extension());
+        assert!(!CgiRule::new("/cgi-bin", None).is_extension());
+        assert_eq!(CgiRule::new("/cgi-bin", None).timeout, CgiRule::DEFAULT_TIMEOUT);
+    }
+
+    /// Test `Cgi::script_for` splits the script from its path info
+    #[test]
+    fn test_script_for() {
+        let root = create_temp_root("katana_cgi_script_for");
+        let rules = vec![
+            CgiRule::new("/cgi-bin", None),
+            CgiRule::new(".pl", Some(PathBuf::from("/usr/bin/perl"))),
+        ];
+
+        let script = Cgi::script_for(&rules, &root, "/cgi-bin/env.sh/extra/info").unwrap();
+        assert_eq!(script.rule.pattern, "/cgi-bin");
+        assert_eq!(script.script_name, "/cgi-bin/env.sh");
+        assert_eq!(script.path_info, "/extra/info");
+        assert!(script.file.is_absolute());
+        assert!(script.file.ends_with("cgi-bin/env.sh"));
+
+        let script = Cgi::script_for(&rules, &root, "/reports/sum.PL").unwrap();
+        assert_eq!(script.rule.pattern, ".pl");
+        assert_eq!(script.path_info, "");
+
+        assert!(Cgi::script_for(&rules, &root, "/reports/data.txt").is_none());
+        assert!(Cgi::script_for(&rules, &root, "/cgi-bin").is_none());
+        assert!(Cgi::script_for(&rules, &root, "/cgi-bin/missing.sh").is_none());
+        assert!(Cgi::script_for(&rules, &root, "/cgi-bin/.secret.sh").is_none());
+        assert!(Cgi::script_for(&[], &root, "/cgi-bin/env.sh").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test `ScriptRules::run` names the files a CGI or FastCGI rule would run
+    #[test]
+    fn test_script_rules() {
+        let cgi = vec![
+            CgiRule::new("/cgi-bin", None),
+            CgiRule::new(".py", Some(PathBuf::from("/usr/bin/python3"))),
+        ];
+        let fastcgi = vec![FastCgiRule::new(".php", FastCgiAddress::parse("127.0.0.1:9000").unwrap())];
+        let scripts = ScriptRules { cgi: &cgi, fastcgi: &fastcgi };
+
+        assert!(scripts.run("/uploads/x.py"));
+        assert!(scripts.run("/uploads/X.PY"));
+        assert!(scripts.run("/cgi-bin/tool"));
+        assert!(scripts.run("/uploads/shell.php"));
+        assert!(!scripts.run("/uploads/x.txt"));
+        assert!(!scripts.run("/cgi-binary/tool"));
+        assert!(!ScriptRules::default().run("/uploads/x.py"));
+    }
+
+    /// Test `Cgi::parse_head` reads the status from the `Status` and `Location` headers
+    #[test]
+    fn test_parse_head() {
+        let head = Cgi::parse_head("Content-Type: text/plain\r\nX-Custom: yes").unwrap();
+        assert_eq!(head.code, 200);
+        assert_eq!(head.reason, "OK");
+        assert_eq!(
+            head.headers,
+            vec![
+                ("Content-Type".to_string(), "text/plain".to_string()),
+                ("X-Custom".to_string(), "yes".to_string()),
+            ]
+        );
+
+        let head = Cgi::parse_head("Status: 418 Short And Stout\nContent-Type: text/plain").unwrap();
+        assert_eq!(head.code, 418);
+        assert_eq!(head.reason, "Short And Stout");
+        assert_eq!(head.headers.len(), 1);
+
+        let head = Cgi::parse_head("Location: /elsewhere").unwrap();
+        assert_eq!(head.code, 302);
+
+        let head = Cgi::parse_head("Status: 404\nLocation: /elsewhere").unwrap();
+        assert_eq!(head.code, 404);
+        assert_eq!(head.reason, "Not Found");
+    }
+
+    /// Test `Cgi::parse_head` refuses malformed headers
+    #[test]
+    fn test_parse_head_malformed() {
+        assert!(Cgi::parse_head("no headers here").is_none());
+        assert!(Cgi::parse_head(": empty").is_none());
+        assert!(Cgi::parse_head("Status: 99 Too Low").is_none());
+        assert!(Cgi::parse_head("Status: abc").is_none());
+    }
+}
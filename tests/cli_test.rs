@@ -0,0 +1,140 @@
+use katana::core::config::arg::{Cli, CliCommand};
+use katana::core::config::config::{Config, ConfigSource};
+use katana::core::utils::logger::LogLevel;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        let args = std::iter::once("katana").chain(args.iter().copied()).map(String::from).collect();
+        Cli::parse(args)
+    }
+
+    /// Test `Cli::parse` reads long options, `--flag=value` and short aliases
+    #[test]
+    fn test_parse_options() {
+        let cli = parse(&["--port=3000", "-d", "site", "-l", "warn", "--watch", "-c", "deploy/.katana"]);
+
+        assert!(cli.config.issues.is_empty(), "{:?}", cli.config.issues);
+        assert_eq!(cli.command, CliCommand::Serve);
+        assert_eq!(cli.config.port, Some(3000));
+        assert_eq!(cli.config.document_root, Some(PathBuf::from("site")));
+        assert_eq!(cli.config.log_level, Some(LogLevel::WARN));
+        assert_eq!(cli.config.watch, Some(true));
+        assert_eq!(cli.config.host, None);
+        assert_eq!(cli.config_file, Some(PathBuf::from("deploy/.katana")));
+    }
+
+    /// Test `Cli::parse` reports unknown options, bad and missing values
+    #[test]
+    fn test_parse_invalid() {
+        let cli = parse(&["--prot", "--port", "abc", "--watch=yes", "extra", "-w"]);
+
+        let issues: Vec<String> = cli.config.issues.iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "--prot: unknown option",
+                "--port: expected a port between 1 and 65535, got \"abc\"",
+                "--watch: takes no value",
+                "extra: unexpected argument",
+                "-w: expected a value, -w <NUMBER>",
+            ]
+        );
+        assert_eq!(cli.config.port, None);
+
+        // a following option is not a value
+        let cli = parse(&["--host", "--watch"]);
+        assert_eq!(cli.config.issues.len(), 1);
+        assert_eq!(cli.config.watch, Some(true));
+    }
+
+    /// Test `Cli::parse` finds the help, version, check-config and config dump commands
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&["-V"]).command, CliCommand::Version);
+        assert_eq!(parse(&["--version", "--help"]).command, CliCommand::Help);
+        assert_eq!(parse(&["check-config"]).command, CliCommand::CheckConfig(None));
+        assert_eq!(
+            parse(&["check-config", "ci/.katana"]).command,
+            CliCommand::CheckConfig(Some(PathBuf::from("ci/.katana")))
+        );
+        assert_eq!(parse(&["check-config", "a", "b"]).config.issues.len(), 1);
+        assert_eq!(parse(&["config", "dump", "-p", "3000"]).command, CliCommand::ConfigDump);
+        assert_eq!(parse(&["config"]).config.issues.len(), 1);
+    }
+
+    /// Test `Cli::help` lists every option with its aliases and value
+    #[test]
+    fn test_help() {
+        let help = Cli::help();
+
+        assert!(help.starts_with("Katana "));
+        assert!(help.contains("  -p, --port <NUMBER>"));
+        assert!(help.contains("      --watch "));
+        assert_eq!(help.lines().filter(|line| line.trim_start().starts_with('-')).count(), Cli::OPTIONS.len());
+    }
+
+    /// Test every key of `.katana` is covered by exactly one of `Config::KEYS` and the file only keys
+    #[test]
+    fn test_keys_cover_file() {
+        let file_keys = Config::file_keys();
+        let file_only_keys = Config::file_only_keys();
+        let mapped = |key: &str| Config::KEYS.iter().filter(|config_key| config_key.name == key.replace('.', "_")).count();
+        let file_only = |key: &str| file_only_keys.iter().filter(|(name, _)| *name == key).count();
+
+        for key in &file_keys {
+            assert_eq!(mapped(key) + file_only(key), 1, "{} must be either mapped or file only", key);
+        }
+        for config_key in Config::KEYS {
+            assert!(file_keys.iter().any(|key| key.replace('.', "_") == config_key.name), "{} is not a key of .katana", config_key.name);
+            let option = config_key.name.replace('_', "-");
+            assert!(Cli::OPTIONS.iter().any(|cli_option| cli_option.long == option), "--{} has no option", option);
+        }
+        assert!(file_only_keys.contains(&("mime", "a table of rules keyed by path, extension or media type")));
+        assert_eq!(file_only("mime.files"), 1);
+        assert_eq!(mapped("mime.sniff"), 1);
+        assert_eq!(mapped("spa"), 1);
+    }
+
+    /// Test the `[mime]` options on the command line layer over the file, its types kept
+    #[test]
+    fn test_mime_options() {
+        let temp_dir = env::temp_dir().join("katana_cli_mime");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join(".katana");
+        fs::write(&path, "[mime]\nsniff = true\ncharset = \"latin1\"\nmd = \"text/markdown\"\n").unwrap();
+
+        let cli = parse(&["--mime-charset", "false", "--mime-default=text/plain"]);
+        assert!(cli.config.issues.is_empty(), "{:?}", cli.config.issues);
+        let mut config = Config::from_file(&path);
+        config.overlay(&cli.config);
+
+        assert!(config.mime.sniff);
+        assert_eq!(config.mime.charset, None);
+        assert_eq!(config.mime.default_type, "text/plain");
+        assert_eq!(config.mime.types, [("md".to_string(), "text/markdown".to_string())]);
+        assert_eq!(config.origin("mime_sniff").source, ConfigSource::File);
+        assert_eq!(config.origin("mime_charset").source, ConfigSource::Args);
+
+        let issues: Vec<String> = parse(&["--mime-default", "binary", "--mime-charset", "utf 8"])
+            .config
+            .issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "--mime-default: expected a media type like application/octet-stream, got \"binary\"",
+                "--mime-charset: expected a charset like utf-8, or false, got \"utf 8\"",
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
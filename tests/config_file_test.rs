@@ -0,0 +1,108 @@
+use katana::core::config::config::{Config, ConfigLayer, ConfigSource};
+use katana::core::utils::logger::LogLevel;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to write a `.katana` file next to a document root
+    fn create_temp_config(name: &str, content: &str) -> (PathBuf, PathBuf) {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let root = temp_dir.join("public");
+        let path = temp_dir.join(".katana");
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy())).unwrap();
+        (temp_dir, path)
+    }
+
+    /// Test `Config::from_file` applies a valid file without issues
+    #[test]
+    fn test_from_file_valid() {
+        let (temp_dir, path) = create_temp_config(
+            "katana_config_valid",
+            "[katana]\nport = 9090\ndocument_root = \"{root}\"\nworker = 2\n\n[rate_limit]\n\"/api\" = [2, 5]\n",
+        );
+
+        let config = Config::from_file(&path);
+        assert!(config.issues.is_empty(), "{:?}", config.issues);
+        assert_eq!(config.port, 9090);
+        assert_eq!(config.worker, 2);
+        assert_eq!(config.rate_limit.len(), 1);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test `Config::from_file` reports every invalid value with its line, in file order
+    #[test]
+    fn test_from_file_issues() {
+        let (temp_dir, path) = create_temp_config(
+            "katana_config_issues",
+            "[katana]\nport = 70000\nworker = \"four\"\nprot = 8080\ndocument_root = \"{root}/missing\"\n\n[rate_limit]\n\"/api\" = [0, 5]\n\n[plugins]\n",
+        );
+
+        let config = Config::from_file(&path);
+        let lines: Vec<String> = config
+            .issues
+            .iter()
+            .map(|issue| issue.location.rsplit(':').nth(1).unwrap().to_string())
+            .collect();
+        assert_eq!(lines, ["2", "3", "4", "5", "8", "10"]);
+        assert!(config.issues.iter().all(|issue| issue.source == ConfigSource::File));
+        assert!(config.issues[0].to_string().ends_with(":2:1: `port` must be a port between 1 and 65535, got 70000"));
+        assert_eq!(config.issues[2].message, "unknown key `prot` in [katana]");
+        assert_eq!(config.issues[5].message, "unknown section [plugins]");
+
+        // invalid values keep the defaults
+        assert_eq!(config.port, 8080);
+        assert!(config.rate_limit.is_empty());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test `Config::from_file` reports a syntax error at its position
+    #[test]
+    fn test_from_file_syntax_error() {
+        let (temp_dir, path) = create_temp_config("katana_config_syntax", "[katana]\nhost = localhost\n");
+
+        let config = Config::from_file(&path);
+        let issues: Vec<_> = config.issues.iter().filter(|issue| issue.source == ConfigSource::File).collect();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].location.ends_with(".katana:2:8"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test `Config::overlay` keeps the values a layer leaves unset and records where each comes from
+    #[test]
+    fn test_overlay_origins() {
+        let (temp_dir, path) = create_temp_config(
+            "katana_config_origins",
+            "[katana]\nlog_level = \"WARN\"\nport = 9090\ndocument_root = \"{root}\"\n",
+        );
+
+        let mut config = Config::from_file(&path);
+        let mut env = ConfigLayer::new(ConfigSource::Env);
+        env.port = Some(9191);
+        config.overlay(&env);
+
+        assert!(config.issues.is_empty(), "{:?}", config.issues);
+        assert_eq!(config.log_level, LogLevel::WARN);
+        assert_eq!(config.port, 9191);
+        assert_eq!(config.origin("port").location, "KATANA_PORT");
+        assert!(config.origin("log_level").location.ends_with(".katana:2:1"));
+        assert_eq!(config.origin("worker").source, ConfigSource::Default);
+
+        let dump = config.dump();
+        assert!(dump.starts_with("[katana]\n"));
+        assert!(dump.lines().any(|line| line.starts_with("port = 9191 ") && line.ends_with("# KATANA_PORT")));
+        assert!(dump.lines().any(|line| line.starts_with("worker = 4 ") && line.ends_with("# default")));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+use katana::core::server::connection::{ConnectionLimit, ConnectionLimiter};
+use std::net::IpAddr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    /// Test the per-client cap only affects the client holding the connections
+    #[test]
+    fn test_per_client_limit() {
+        let limiter = ConnectionLimiter::new(100, 2);
+
+        let _first = limiter.acquire(ip("192.0.2.1")).unwrap();
+        let second = limiter.acquire(ip("192.0.2.1")).unwrap();
+        assert_eq!(limiter.acquire(ip("192.0.2.1")).unwrap_err(), ConnectionLimit::PerClient);
+        assert!(limiter.acquire(ip("192.0.2.2")).is_ok(), "Other clients should not be affected");
+
+        drop(second);
+        assert!(limiter.acquire(ip("192.0.2.1")).is_ok(), "A closed connection should free its slot");
+    }
+
+    /// Test the global cap and the counters released by dropped guards
+    #[test]
+    fn test_global_limit() {
+        let limiter = ConnectionLimiter::new(2, 10);
+
+        let first = limiter.acquire(ip("192.0.2.1")).unwrap();
+        let _second = limiter.acquire(ip("2001:db8::1")).unwrap();
+        assert_eq!(limiter.acquire(ip("192.0.2.3")).unwrap_err(), ConnectionLimit::Global);
+        assert_eq!(limiter.count(None), 2);
+
+        drop(first);
+        assert_eq!(limiter.count(None), 1);
+        assert_eq!(limiter.count(ip("192.0.2.1").as_ref()), 0);
+        assert!(limiter.acquire(ip("192.0.2.3")).is_ok());
+    }
+
+    /// Test clones share the same counters
+    #[test]
+    fn test_clones_share_counters() {
+        let limiter = ConnectionLimiter::new(10, 1);
+        let clone = limiter.clone();
+
+        let _guard = limiter.acquire(ip("192.0.2.1")).unwrap();
+        assert!(clone.acquire(ip("192.0.2.1")).is_err());
+        assert_eq!(clone.count(None), 1);
+    }
+}
@@ -0,0 +1,152 @@
+use katana::core::server::fastcgi::{FastCgi, FastCgiAddress, FastCgiRule};
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to create a PHP site with a front controller, a blog and a stylesheet
+    fn create_temp_site(name: &str) -> PathBuf {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("blog")).unwrap();
+        fs::create_dir_all(temp_dir.join("api")).unwrap();
+        fs::create_dir_all(temp_dir.join("css")).unwrap();
+        fs::write(temp_dir.join("index.php"), "<?php").unwrap();
+        fs::write(temp_dir.join("info.php"), "<?php").unwrap();
+        fs::write(temp_dir.join("blog/index.php"), "<?php").unwrap();
+        fs::write(temp_dir.join("api/app.php"), "<?php").unwrap();
+        fs::write(temp_dir.join("css/site.css"), "body {}").unwrap();
+        temp_dir
+    }
+
+    fn php_rule() -> FastCgiRule {
+        let mut rule = FastCgiRule::new(".php", FastCgiAddress::parse("unix:/run/php/php-fpm.sock").unwrap());
+        assert!(rule.set("index index.php"));
+        rule
+    }
+
+    /// Test `FastCgiAddress::parse` reads TCP and Unix socket backends
+    #[test]
+    fn test_address_parse() {
+        assert_eq!(
+            FastCgiAddress::parse("127.0.0.1:9000"),
+            Some(FastCgiAddress::Tcp {
+                host: "127.0.0.1".to_string(),
+                port: 9000
+            })
+        );
+        assert_eq!(
+            FastCgiAddress::parse("tcp://php:9000").unwrap().to_string(),
+            "php:9000"
+        );
+        assert_eq!(
+            FastCgiAddress::parse("unix:/run/php/php-fpm.sock"),
+            Some(FastCgiAddress::Unix(PathBuf::from("/run/php/php-fpm.sock")))
+        );
+        assert_eq!(FastCgiAddress::parse("127.0.0.1"), None);
+        assert_eq!(FastCgiAddress::parse(":9000"), None);
+        assert_eq!(FastCgiAddress::parse("unix:"), None);
+    }
+
+    /// Test `FastCgiRule::set` applies the index and timeout options
+    #[test]
+    fn test_rule_options() {
+        let mut rule = php_rule();
+        assert_eq!(rule.index.as_deref(), Some("index.php"));
+        assert!(rule.set("timeout 5"));
+        assert_eq!(rule.timeout, Duration::from_secs(5));
+        assert!(rule.set("index off"));
+        assert_eq!(rule.index, None);
+
+        assert!(!rule.set("index ../index.php"));
+        assert!(!rule.set("index .hidden.php"));
+        assert!(!rule.set("timeout 0"));
+        assert!(!rule.set("keepalive on"));
+    }
+
+    /// Test `FastCgi::record` frames and pads records, `FastCgi::read_record` reads them back
+    #[test]
+    fn test_record() {
+        let record = FastCgi::record(FastCgi::STDOUT, 1, b"hello");
+        assert_eq!(&record[..8], &[1, FastCgi::STDOUT, 0, 1, 0, 5, 3, 0]);
+        assert_eq!(record.len(), 16);
+
+        let mut stream = record.clone();
+        stream.extend(FastCgi::record(FastCgi::END_REQUEST, 1, &[0; 8]));
+        let mut reader = Cursor::new(stream);
+        let first = FastCgi::read_record(&mut reader).unwrap();
+        assert_eq!(first.kind, FastCgi::STDOUT);
+        assert_eq!(first.request_id, 1);
+        assert_eq!(first.content, b"hello");
+        assert_eq!(FastCgi::read_record(&mut reader).unwrap().kind, FastCgi::END_REQUEST);
+        assert!(FastCgi::read_record(&mut reader).is_err());
+    }
+
+    /// Test `FastCgi::params` uses one byte for short lengths and four for long ones
+    #[test]
+    fn test_params() {
+        let params = FastCgi::params(&[("QUERY_STRING".to_string(), "a=1".to_string())]);
+        assert_eq!(params[0], 12);
+        assert_eq!(params[1], 3);
+        assert_eq!(&params[2..], b"QUERY_STRINGa=1");
+
+        let long = "x".repeat(300);
+        let params = FastCgi::params(&[("HTTP_COOKIE".to_string(), long)]);
+        assert_eq!(params[0], 11);
+        assert_eq!(&params[1..5], &[0x80, 0, 1, 44]);
+        assert_eq!(params.len(), 5 + 11 + 300);
+    }
+
+    /// Test `FastCgi::script_for` finds scripts, folder indexes and the front controller
+    #[test]
+    fn test_script_for() {
+        let root = create_temp_site("katana_fastcgi_script_for");
+        let rules = vec![php_rule()];
+
+        let script = FastCgi::script_for(&rules, &root, "/info.php/extra/path").unwrap();
+        assert_eq!(script.script_name, "/info.php");
+        assert_eq!(script.path_info, "/extra/path");
+        assert!(script.file.is_absolute());
+
+        let script = FastCgi::script_for(&rules, &root, "/blog/").unwrap();
+        assert_eq!(script.script_name, "/blog/index.php");
+
+        let script = FastCgi::script_for(&rules, &root, "/users/42").unwrap();
+        assert_eq!(script.script_name, "/index.php");
+        assert_eq!(script.path_info, "");
+
+        assert!(FastCgi::script_for(&rules, &root, "/css/site.css").is_none());
+        assert!(FastCgi::script_for(&rules, &root, "/.env").is_none());
+        assert!(FastCgi::script_for(&[], &root, "/info.php").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test `FastCgi::script_for` routes a folder prefix to its own front controller
+    #[test]
+    fn test_script_for_prefix() {
+        let root = create_temp_site("katana_fastcgi_script_for_prefix");
+        let mut api = FastCgiRule::new("/api", FastCgiAddress::parse("127.0.0.1:9000").unwrap());
+        assert!(api.set("index app.php"));
+        let rules = vec![api, FastCgiRule::new(".php", FastCgiAddress::parse("127.0.0.1:9001").unwrap())];
+
+        let script = FastCgi::script_for(&rules, &root, "/api/users/42").unwrap();
+        assert_eq!(script.script_name, "/api/app.php");
+        assert_eq!(script.rule.pattern, "/api");
+
+        let script = FastCgi::script_for(&rules, &root, "/info.php").unwrap();
+        assert_eq!(script.rule.pattern, ".php");
+
+        // no rule routes missing paths outside of /api
+        assert!(FastCgi::script_for(&rules, &root, "/users/42").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
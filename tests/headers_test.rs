@@ -0,0 +1,184 @@
+use katana::core::config::config::Config;
+use katana::core::resources::templates::Templates;
+use katana::core::server::headers::{HeaderAction, HeaderMatch, HeaderRule, ResponseHeaders};
+use katana::core::server::location::{Location, LocationMatch};
+use katana::core::server::request::Request;
+use katana::core::server::response::Response;
+use katana::core::server::server::Server;
+use katana::core::utils::keyval::KeyVal;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `HeaderAction::parse` reads add, set and remove directives
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            HeaderAction::parse("set Cache-Control no-store, max-age=0"),
+            Some(HeaderAction::Set("Cache-Control".to_string(), "no-store, max-age=0".to_string()))
+        );
+        assert_eq!(
+            HeaderAction::parse(" ADD  X-Robots-Tag noindex "),
+            Some(HeaderAction::Add("X-Robots-Tag".to_string(), "noindex".to_string()))
+        );
+        assert_eq!(HeaderAction::parse("remove X-Powered-By"), Some(HeaderAction::Remove("X-Powered-By".to_string())));
+        assert_eq!(HeaderAction::parse("remove X-Powered-By now"), None);
+        assert_eq!(HeaderAction::parse("set X-Empty"), None);
+        assert_eq!(HeaderAction::parse("set Bad:Name value"), None);
+        assert_eq!(HeaderAction::parse("append X-A b"), None);
+    }
+
+    /// Test `HeaderMatch` tells paths from media types and matches the Content-Type
+    #[test]
+    fn test_match() {
+        assert_eq!(HeaderMatch::new("/api"), HeaderMatch::Path(LocationMatch::Prefix("/api".to_string())));
+        assert_eq!(HeaderMatch::new("*.css"), HeaderMatch::Path(LocationMatch::Glob("*.css".to_string())));
+        assert_eq!(HeaderMatch::new("Text/HTML"), HeaderMatch::ContentType("text/html".to_string()));
+
+        let html = HeaderMatch::new("text/html");
+        assert!(html.matches("/", Some("text/html; charset=utf-8")));
+        assert!(!html.matches("/", Some("text/plain")));
+        assert!(!html.matches("/", None));
+        assert!(HeaderMatch::new("image/*").matches("/", Some("image/png")));
+        assert!(!HeaderMatch::new("image/*").matches("/", Some("text/png")));
+    }
+
+    /// Test `ResponseHeaders::apply` runs the presets first, then paths and media types
+    #[test]
+    fn test_apply() {
+        // the presets first, as the `[headers]` section loads them
+        let mut rules = vec![
+            HeaderRule::security(),
+            HeaderRule::server(None),
+            HeaderRule::new("text/html", vec![HeaderAction::Add("X-Frame-Options".to_string(), "DENY".to_string())]),
+            HeaderRule::new("/admin", vec![HeaderAction::Set("x-frame-options".to_string(), "DENY".to_string())]),
+            HeaderRule::new("/", vec![HeaderAction::Remove("Referrer-Policy".to_string())]),
+        ];
+        HeaderRule::sort(&mut rules);
+        assert_eq!(rules[2].matcher.as_str(), "/");
+        assert_eq!(rules[3].matcher.as_str(), "/admin");
+
+        let mut headers = KeyVal::new();
+        headers.add("Server".to_string(), "katana".to_string());
+        headers.add("Content-Type".to_string(), "text/html".to_string());
+        headers.add("Referrer-Policy".to_string(), "no-referrer".to_string());
+        ResponseHeaders::apply(&rules, "/admin/users", &mut headers);
+        assert!(!headers.exists("Server"));
+        assert!(!headers.exists("Referrer-Policy"));
+        assert_eq!(headers.get("x-frame-options"), Some(&"DENY".to_string()));
+        assert!(!headers.exists("X-Frame-Options"));
+        assert_eq!(headers.get("X-Content-Type-Options"), Some(&"nosniff".to_string()));
+
+        // answers keep the values they already have
+        let mut headers = KeyVal::new();
+        headers.add("Content-Security-Policy".to_string(), "default-src 'none'".to_string());
+        ResponseHeaders::apply(&rules, "/", &mut headers);
+        assert_eq!(headers.get("Content-Security-Policy"), Some(&"default-src 'none'".to_string()));
+        assert_eq!(headers.get("X-Frame-Options"), Some(&"SAMEORIGIN".to_string()));
+    }
+
+    /// Test a `[[location]]` header wins over a `[headers]` rule of the same name
+    #[test]
+    fn test_location_over_rules() {
+        let temp_dir = env::temp_dir().join("katana_headers_location");
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let path = temp_dir.join(".katana");
+        let content = concat!(
+            "[katana]\n",
+            "document_root = \"{root}\"\n",
+            "[headers]\n",
+            "\"/\" = [\"set X-Frame-Options DENY\", \"set X-Robots-Tag noindex\"]\n",
+            "[[location]]\n",
+            "path = \"/embed\"\n",
+            "headers = { \"x-frame-options\" = \"SAMEORIGIN\" }\n",
+        );
+        fs::write(&path, content.replace("{root}", &temp_dir.join("public").to_string_lossy())).unwrap();
+        let config = Config::from_file(&path);
+        assert!(config.issues.is_empty(), "{:?}", config.issues);
+        let location = Location::resolve(&config.location, "/embed/player.html");
+        let server = Server::new(config, Templates::load());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "GET /embed/player.html HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut response = Response::new(Request::from_stream(&stream).unwrap(), Templates::load()).unwrap();
+
+        server.add_headers(&mut response, &location);
+        let frame: Vec<&String> = response
+            .headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("X-Frame-Options"))
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(frame, ["SAMEORIGIN"]);
+        assert_eq!(response.headers.get("X-Robots-Tag"), Some(&"noindex".to_string()));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test `ResponseHeaders::apply_list` replaces repeated headers of a backend together
+    #[test]
+    fn test_apply_list() {
+        let rules = vec![HeaderRule::new("/", vec![HeaderAction::Set("Set-Cookie".to_string(), "a=1".to_string())])];
+        let mut headers = vec![
+            ("set-cookie".to_string(), "a=0".to_string()),
+            ("Set-Cookie".to_string(), "b=0".to_string()),
+            ("X-Backend".to_string(), "php".to_string()),
+        ];
+        ResponseHeaders::apply_list(&rules, "/index.php", &mut headers);
+        assert_eq!(
+            headers,
+            [("X-Backend".to_string(), "php".to_string()), ("Set-Cookie".to_string(), "a=1".to_string())]
+        );
+    }
+
+    /// Test `Config::from_file` reads the `[headers]` section and reports its mistakes
+    #[test]
+    fn test_from_file() {
+        let temp_dir = env::temp_dir().join("katana_headers_file");
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let path = temp_dir.join(".katana");
+        let content = concat!(
+            "[katana]\n",
+            "document_root = \"{root}\"\n",
+            "[headers]\n",
+            "security = true\n",
+            "server = \"edge\"\n",
+            "\"/api\" = [\"set Cache-Control no-store\", \"remove X-Powered-By\"]\n",
+            "\"text/html\" = \"add X-Robots-Tag noindex\"\n",
+            "\"/files\" = \"set Content-Length 0\"\n",
+            "\"api\" = \"remove Server\"\n",
+            "\"/typo\" = \"sett X-A b\"\n",
+        );
+        let root = temp_dir.join("public");
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy())).unwrap();
+
+        let config = Config::from_file(&path);
+        let keys: Vec<&str> = config.headers.iter().map(|rule| rule.matcher.as_str()).collect();
+        assert_eq!(keys, ["/", "/", "/api", "/typo", "/files", "text/html"]);
+        assert_eq!(config.headers[1].actions, [HeaderAction::Set("Server".to_string(), "edge".to_string())]);
+        assert_eq!(config.headers[2].actions.len(), 2);
+        assert!(config.headers[4].actions.is_empty());
+
+        let issues: Vec<String> = config.issues.iter().map(|issue| issue.message.clone()).collect();
+        assert_eq!(
+            issues,
+            [
+                "`Content-Length` is set by the server, it cannot be changed",
+                "unknown key `api` in [headers]",
+                "`/typo` must be directives like \"set X-Frame-Options DENY\" or \"remove X-Powered-By\", got \"sett X-A b\"",
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
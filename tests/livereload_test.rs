@@ -0,0 +1,88 @@
+use katana::core::server::livereload::{LiveReload, Snapshot};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to create a small site with a hidden folder
+    fn create_temp_site(name: &str) -> PathBuf {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("css")).unwrap();
+        fs::create_dir_all(temp_dir.join(".git")).unwrap();
+        fs::write(temp_dir.join("index.html"), "<html><body></body></html>").unwrap();
+        fs::write(temp_dir.join("css/site.css"), "body {}").unwrap();
+        fs::write(temp_dir.join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+        temp_dir
+    }
+
+    /// Test `LiveReload::snapshot` lists visible files only
+    #[test]
+    fn test_snapshot() {
+        let root = create_temp_site("katana_livereload_snapshot");
+        let snapshot = LiveReload::snapshot(&root);
+
+        assert_eq!(snapshot.len(), 2);
+        assert!(snapshot.contains_key(&root.join("index.html")));
+        assert!(snapshot.contains_key(&root.join("css/site.css")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test `LiveReload::changes` reports added, modified and removed files
+    #[test]
+    fn test_changes() {
+        let now = SystemTime::now();
+        let later = now + Duration::from_secs(1);
+        let previous = Snapshot::from([
+            (PathBuf::from("/site/a.html"), (now, 10)),
+            (PathBuf::from("/site/b.html"), (now, 10)),
+            (PathBuf::from("/site/c.html"), (now, 10)),
+        ]);
+        let current = Snapshot::from([
+            (PathBuf::from("/site/a.html"), (now, 10)),
+            (PathBuf::from("/site/b.html"), (later, 12)),
+            (PathBuf::from("/site/d.html"), (now, 10)),
+        ]);
+
+        assert_eq!(
+            LiveReload::changes(&previous, &current),
+            vec![
+                PathBuf::from("/site/b.html"),
+                PathBuf::from("/site/c.html"),
+                PathBuf::from("/site/d.html"),
+            ]
+        );
+        assert!(LiveReload::changes(&current, &current).is_empty());
+    }
+
+    /// Test `LiveReload::message` swaps stylesheets only when nothing else changed
+    #[test]
+    fn test_message() {
+        let root = PathBuf::from("/site");
+        let stylesheets = vec![root.join("css/site.css"), root.join("print style.CSS")];
+        assert_eq!(
+            LiveReload::message(&root, &stylesheets),
+            "css\n/css/site.css\n/print%20style.CSS"
+        );
+
+        let mixed = vec![root.join("css/site.css"), root.join("index.html")];
+        assert_eq!(LiveReload::message(&root, &mixed), "reload");
+        assert_eq!(LiveReload::message(&root, &[]), "reload");
+    }
+
+    /// Test `LiveReload::script` connects to the live reload endpoint
+    #[test]
+    fn test_script() {
+        let script = LiveReload::script();
+        assert!(script.starts_with("<script>"));
+        assert!(script.contains(LiveReload::PATH));
+        assert!(!script.contains("__PATH__"));
+    }
+}
@@ -0,0 +1,164 @@
+use katana::core::config::config::Config;
+use katana::core::server::http::{HttpMethod, HttpStatus};
+use katana::core::server::location::{Location, LocationAuth, LocationMatch, LocationRule};
+use katana::core::utils::keyval::KeyVal;
+use std::env;
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `Location::glob_matches` with stars, double stars, question marks and sets
+    #[test]
+    fn test_glob_matches() {
+        assert!(Location::glob_matches("*.pdf", "report.pdf"));
+        assert!(!Location::glob_matches("*.pdf", "report.pdf.txt"));
+        assert!(Location::glob_matches("/docs/*.md", "/docs/a.md"));
+        assert!(!Location::glob_matches("/docs/*.md", "/docs/sub/a.md"));
+        assert!(Location::glob_matches("/docs/**/*.md", "/docs/a.md"));
+        assert!(Location::glob_matches("/docs/**/*.md", "/docs/sub/deep/a.md"));
+        assert!(Location::glob_matches("/img/???.png", "/img/cat.png"));
+        assert!(!Location::glob_matches("/img/???.png", "/img/bird.png"));
+        assert!(Location::glob_matches("/v[0-9]/*", "/v2/api"));
+        assert!(!Location::glob_matches("/v[0-9]/*", "/v-/api"));
+    }
+
+    /// Test `LocationMatch::matches` compares globs without a slash to the file name
+    #[test]
+    fn test_match() {
+        assert_eq!(LocationMatch::new("/docs"), LocationMatch::Prefix("/docs".to_string()));
+        assert!(LocationMatch::new("/docs").matches("/docs/a.html"));
+        assert!(!LocationMatch::new("/docs").matches("/documents"));
+        assert!(LocationMatch::new("*.pdf").matches("/files/deep/a.pdf"));
+        assert!(!LocationMatch::new("/*.pdf").matches("/files/a.pdf"));
+    }
+
+    /// Test `Location::resolve` applies prefixes from the shortest, then globs, merging headers
+    #[test]
+    fn test_resolve_precedence() {
+        let mut glob = LocationRule::new("*.html");
+        glob.directory_listing = Some(true);
+        glob.headers = vec![("x-frame".to_string(), "glob".to_string())];
+        let mut docs = LocationRule::new("/docs");
+        docs.directory_listing = Some(false);
+        docs.index = Some(vec!["README.html".to_string()]);
+        docs.headers = vec![("X-Frame".to_string(), "docs".to_string())];
+        let mut root = LocationRule::new("/");
+        root.directory_listing = Some(true);
+        root.auth = Some(LocationAuth::Off);
+        root.headers = vec![("X-Site".to_string(), "root".to_string())];
+        let rules = vec![glob, docs, root];
+
+        let location = Location::resolve(&rules, "/docs/guide");
+        assert_eq!(location.matched, ["/", "/docs"]);
+        assert!(!location.directory_listing);
+        assert_eq!(location.index, ["README.html"]);
+        assert_eq!(location.auth, Some(LocationAuth::Off));
+        assert_eq!(
+            location.headers,
+            [("X-Site".to_string(), "root".to_string()), ("X-Frame".to_string(), "docs".to_string())]
+        );
+
+        let location = Location::resolve(&rules, "/docs/../docs/page.html");
+        assert_eq!(location.matched, ["/", "/docs", "*.html"]);
+        assert!(location.directory_listing);
+        assert_eq!(location.headers.last(), Some(&("x-frame".to_string(), "glob".to_string())));
+
+        let location = Location::resolve(&[], "/");
+        assert_eq!(location, Location::default());
+    }
+
+    /// Test `Location::allows` restricts the methods, HEAD comes with GET
+    #[test]
+    fn test_allows() {
+        let mut rule = LocationRule::new("/api");
+        rule.methods = Some(vec![HttpMethod::GET, HttpMethod::POST]);
+        let location = Location::resolve(&[rule], "/api/users");
+
+        assert!(location.allows(&HttpMethod::GET));
+        assert!(location.allows(&HttpMethod::HEAD));
+        assert!(location.allows(&HttpMethod::POST));
+        assert!(!location.allows(&HttpMethod::DELETE));
+        assert!(Location::default().allows(&HttpMethod::DELETE));
+    }
+
+    /// Test `Location::add_headers` sends `cache_control` with successful answers only
+    #[test]
+    fn test_add_headers_cache_control() {
+        let location = Location {
+            cache_control: Some("public, max-age=86400".to_string()),
+            headers: vec![("X-Robots-Tag".to_string(), "noindex".to_string())],
+            ..Location::default()
+        };
+
+        for status in [HttpStatus::Ok, HttpStatus::PartialContent, HttpStatus::NotModified] {
+            let mut headers = KeyVal::new();
+            headers.add("Expires".to_string(), "Thu, 01 Jan 1970 00:00:00 GMT".to_string());
+            location.add_headers(&status, &mut headers);
+            assert_eq!(headers.get("Cache-Control"), Some(&"public, max-age=86400".to_string()));
+            assert!(!headers.exists("Expires"));
+        }
+
+        for status in [HttpStatus::Unauthorized, HttpStatus::NotFound, HttpStatus::InternalServerError] {
+            let mut headers = KeyVal::new();
+            location.add_headers(&status, &mut headers);
+            assert!(!headers.exists("Cache-Control"), "{}", status.to_code());
+            assert_eq!(headers.get("X-Robots-Tag"), Some(&"noindex".to_string()));
+        }
+    }
+
+    /// Test `Config::from_file` reads `[[location]]` blocks and reports their mistakes
+    #[test]
+    fn test_from_file() {
+        let temp_dir = env::temp_dir().join("katana_location_file");
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let path = temp_dir.join(".katana");
+        let content = concat!(
+            "[katana]\n",
+            "document_root = \"{root}\"\n",
+            "[[location]]\n",
+            "path = \"/docs\"\n",
+            "index = [\"README.html\"]\n",
+            "methods = [\"get\", \"post\"]\n",
+            "error_pages = { 404 = \"/404.html\" }\n",
+            "headers = { \"X-Robots-Tag\" = \"noindex\" }\n",
+            "[[location]]\n",
+            "path = \"*.pdf\"\n",
+            "methods = [\"FETCH\"]\n",
+            "error_pages = { 200 = \"ok.html\" }\n",
+            "listing = false\n",
+            "[[location]]\n",
+            "index = \"index.htm\"\n",
+        );
+        let root = temp_dir.join("public");
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy())).unwrap();
+
+        let config = Config::from_file(&path);
+        assert_eq!(config.location.len(), 2);
+        let docs = &config.location[0];
+        assert_eq!(docs.matcher, LocationMatch::Prefix("/docs".to_string()));
+        assert_eq!(docs.index, Some(vec!["README.html".to_string()]));
+        assert_eq!(docs.methods, Some(vec![HttpMethod::GET, HttpMethod::POST]));
+        assert_eq!(docs.error_pages, [(404, "/404.html".to_string())]);
+        assert_eq!(docs.headers, [("X-Robots-Tag".to_string(), "noindex".to_string())]);
+        assert_eq!(config.location[1].matcher, LocationMatch::Glob("*.pdf".to_string()));
+
+        let issues: Vec<String> = config.issues.iter().map(|issue| issue.message.clone()).collect();
+        assert_eq!(
+            issues,
+            [
+                "`methods` must be HTTP methods like \"GET\", got \"FETCH\"",
+                "`200` must be an error status between 400 and 599",
+                "unknown key `listing` in [[location]]",
+                "[[location]] without a `path`",
+            ]
+        );
+        assert!(config.issues[0].location.ends_with(".katana:11:1"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use katana::core::config::config::Config;
+use katana::core::server::filetype::{MimeRegistry, MimeSettings};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the built-in table answers modern types, with the charset on text only
+    #[test]
+    fn test_builtin() {
+        let registry = MimeRegistry::builtin();
+        assert_eq!(registry.get("webp"), Some("image/webp"));
+        assert_eq!(registry.get(".WASM"), Some("application/wasm"));
+        assert_eq!(registry.content_type("css"), "text/css; charset=utf-8");
+        assert_eq!(registry.content_type("json"), "application/json; charset=utf-8");
+        assert_eq!(registry.content_type("png"), "image/png");
+        assert_eq!(registry.content_type("unknown"), MimeRegistry::DEFAULT_TYPE);
+        assert!(MimeRegistry::is_text("image/svg+xml"));
+        assert!(!MimeRegistry::is_text("application/wasm"));
+    }
+
+    /// Test `MimeRegistry::load_types` reads `mime.types` lines and skips comments
+    #[test]
+    fn test_load_types() {
+        let mut registry = MimeRegistry::builtin().as_ref().clone();
+        let content = concat!(
+            "# type         extensions\n",
+            "text/x-nim     nim nims\n",
+            "image/png      png # again\n",
+            "not-a-type     foo\n",
+            "application/x-nothing\n",
+        );
+        assert_eq!(registry.load_types(content), 3);
+        assert_eq!(registry.get("nims"), Some("text/x-nim"));
+        assert_eq!(registry.get("png"), Some("image/png"));
+        assert_eq!(registry.get("foo"), None);
+    }
+
+    /// Test `MimeSettings::build` applies files, overrides, the default type and the charset
+    #[test]
+    fn test_build() {
+        let temp_dir = env::temp_dir().join("katana_mime_build");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let file = temp_dir.join("mime.types");
+        fs::write(&file, "text/x-log log\ntext/markdown md\n").unwrap();
+
+        let settings = MimeSettings {
+            files: vec![file, PathBuf::from("/nonexistent/mime.types")],
+            types: vec![("md".to_string(), "text/plain".to_string())],
+            default_type: "text/plain".to_string(),
+            charset: None,
+            sniff: false,
+        };
+        let registry = settings.build();
+        assert_eq!(registry.get("log"), Some("text/x-log"));
+        assert_eq!(registry.get("md"), Some("text/plain"));
+        assert_eq!(registry.content_type("css"), "text/css");
+        assert_eq!(registry.content_type("unknown"), "text/plain");
+        assert_eq!(MimeSettings::default().build(), *MimeRegistry::builtin());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test `Config::from_file` reads the `[mime]` section and reports its mistakes
+    #[test]
+    fn test_from_file() {
+        let temp_dir = env::temp_dir().join("katana_mime_file");
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let path = temp_dir.join(".katana");
+        let content = concat!(
+            "[katana]\n",
+            "document_root = \"{root}\"\n",
+            "[mime]\n",
+            "files = [\"{root}/missing.types\"]\n",
+            "default = \"text/plain\"\n",
+            "charset = false\n",
+            "sniff = true\n",
+            "\".md\" = \"text/markdown\"\n",
+            "gpx = \"application/gpx+xml\"\n",
+            "\"tar.gz\" = \"application/gzip\"\n",
+            "dat = \"binary\"\n",
+        );
+        let root = temp_dir.join("public");
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy())).unwrap();
+
+        let config = Config::from_file(&path);
+        assert_eq!(config.mime.default_type, "text/plain");
+        assert_eq!(config.mime.charset, None);
+        assert!(config.mime.sniff);
+        assert_eq!(
+            config.mime.types,
+            [
+                ("md".to_string(), "text/markdown".to_string()),
+                ("gpx".to_string(), "application/gpx+xml".to_string()),
+            ]
+        );
+
+        let issues: Vec<String> = config.issues.iter().map(|issue| issue.message.clone()).collect();
+        assert_eq!(
+            issues,
+            [
+                format!("mime.types file {:?} not found", root.join("missing.types")),
+                "`tar.gz` is not a file extension".to_string(),
+                "`dat` must be a media type like \"text/plain\", got \"binary\"".to_string(),
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use katana::core::server::http::HttpStatus;
+use katana::core::server::proxy::{Proxy, ProxyRule, Upstream};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `Upstream::parse` with TCP and Unix socket upstreams
+    #[test]
+    fn test_upstream_parse() {
+        assert_eq!(
+            Upstream::parse("http://127.0.0.1:3000/v1/").unwrap(),
+            Upstream::Http { host: "127.0.0.1".to_string(), port: 3000, path: "/v1".to_string() }
+        );
+        assert_eq!(
+            Upstream::parse("http://backend").unwrap(),
+            Upstream::Http { host: "backend".to_string(), port: 80, path: String::new() }
+        );
+        assert_eq!(Upstream::parse("http://[::1]").unwrap().authority(), "[::1]");
+        assert_eq!(Upstream::parse("http://[::1]:8000").unwrap().authority(), "[::1]:8000");
+        assert_eq!(
+            Upstream::parse("unix:/run/app.sock").unwrap(),
+            Upstream::Unix(PathBuf::from("/run/app.sock"))
+        );
+
+        assert!(Upstream::parse("https://example.com").is_none());
+        assert!(Upstream::parse("http://host:port").is_none());
+        assert!(Upstream::parse("http://:80").is_none());
+        assert!(Upstream::parse("unix:").is_none());
+    }
+
+    /// Test `ProxyRule::set` with valid and invalid options
+    #[test]
+    fn test_proxy_rule_options() {
+        let mut rule = ProxyRule::new("/api", Upstream::parse("http://127.0.0.1:3000").unwrap());
+        assert!(!rule.try_files && !rule.strip_prefix);
+        assert_eq!(rule.timeout, ProxyRule::DEFAULT_TIMEOUT);
+
+        assert!(rule.set("try_files on"));
+        assert!(rule.set("strip_prefix"));
+        assert!(rule.set("timeout 5"));
+        assert!(rule.try_files && rule.strip_prefix);
+        assert_eq!(rule.timeout, Duration::from_secs(5));
+
+        assert!(rule.set("try_files off"));
+        assert!(!rule.try_files);
+        assert!(!rule.set("timeout 0"));
+        assert!(!rule.set("timeout soon"));
+        assert!(!rule.set("strip_prefix maybe"));
+        assert!(!rule.set("retries 3"));
+    }
+
+    /// Test `Proxy::rule_for` picks the longest matching prefix on segment boundaries
+    #[test]
+    fn test_proxy_rule_for() {
+        let upstream = Upstream::parse("http://127.0.0.1:3000").unwrap();
+        let rules = vec![ProxyRule::new("/api", upstream.clone()), ProxyRule::new("/api/admin", upstream)];
+
+        assert_eq!(Proxy::rule_for(&rules, "/api/users").unwrap().prefix, "/api");
+        assert_eq!(Proxy::rule_for(&rules, "/api/admin/logs").unwrap().prefix, "/api/admin");
+        assert!(Proxy::rule_for(&rules, "/apiary").is_none());
+        assert!(Proxy::rule_for(&rules, "/").is_none());
+    }
+
+    /// Test `Proxy::upstream_path` forwards the normalized path `rule_for` matched
+    #[test]
+    fn test_upstream_path() {
+        let upstream = Upstream::parse("http://127.0.0.1:3000/v1").unwrap();
+        let rule = ProxyRule::new("/api", upstream.clone());
+        let proxy = Proxy::new(&rule);
+
+        assert_eq!(proxy.upstream_path("/api//users"), "/v1/api/users");
+        assert_eq!(proxy.upstream_path("/api/./users/../teams/"), "/v1/api/teams/");
+        assert_eq!(proxy.upstream_path("/api/a b"), "/v1/api/a%20b");
+
+        let mut stripped = ProxyRule::new("/api", upstream);
+        assert!(stripped.set("strip_prefix"));
+        let proxy = Proxy::new(&stripped);
+        assert_eq!(proxy.upstream_path("/api//users"), "/v1/users");
+        assert_eq!(proxy.upstream_path("/api/./"), "/v1/");
+        assert_eq!(proxy.upstream_path("/api"), "/v1/");
+    }
+
+    /// Test `Proxy::forwarded` quotes IPv6 clients and strips quotes from the host
+    #[test]
+    fn test_forwarded() {
+        let ipv4: IpAddr = "192.0.2.60".parse().unwrap();
+        let ipv6: IpAddr = "2001:db8::17".parse().unwrap();
+
+        assert_eq!(Proxy::forwarded(Some(ipv4), "example.com"), "for=192.0.2.60;host=\"example.com\";proto=http");
+        assert_eq!(Proxy::forwarded(Some(ipv6), ""), "for=\"[2001:db8::17]\";proto=http");
+        assert_eq!(Proxy::forwarded(None, "a\"b"), "for=unknown;host=\"ab\";proto=http");
+    }
+
+    /// Test `HttpStatus::from_code` round trips known codes
+    #[test]
+    fn test_status_from_code() {
+        assert_eq!(HttpStatus::from_code(502).unwrap().to_code(), 502);
+        assert_eq!(HttpStatus::from_code(504).unwrap().to_message(), "Gateway Timeout");
+        assert!(HttpStatus::from_code(299).is_none());
+    }
+}
@@ -0,0 +1,91 @@
+use katana::core::server::ratelimit::{RateLimitRule, RateLimiter};
+use katana::core::utils::keyval::KeyVal;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    /// Test the bucket allows `burst` requests at once, then refuses until refilled
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = RateLimiter::new(vec![RateLimitRule::new("/", 2.0, 3)]);
+        let client = ip("192.0.2.1");
+        let start = Instant::now();
+
+        for expected in [2, 1, 0] {
+            let status = limiter.check_at(Some(&client), "/", start).unwrap();
+            assert!(status.allowed);
+            assert_eq!(status.remaining, expected);
+        }
+
+        let refused = limiter.check_at(Some(&client), "/", start).unwrap();
+        assert!(!refused.allowed);
+        assert_eq!(refused.retry_after, 1);
+        assert_eq!(refused.reset, 2, "Three tokens at two per second need two seconds");
+
+        let later = limiter
+            .check_at(Some(&client), "/", start + Duration::from_millis(500))
+            .unwrap();
+        assert!(later.allowed, "Half a second should bring one token back");
+    }
+
+    /// Test clients and path prefixes get separate buckets
+    #[test]
+    fn test_keyed_by_client_and_prefix() {
+        let limiter = RateLimiter::new(vec![
+            RateLimitRule::new("/", 100.0, 100),
+            RateLimitRule::new("/api", 1.0, 1),
+        ]);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(Some(&ip("192.0.2.1")), "/api/users", now).unwrap().allowed);
+        assert!(!limiter.check_at(Some(&ip("192.0.2.1")), "/api/items", now).unwrap().allowed);
+        assert!(limiter.check_at(Some(&ip("192.0.2.2")), "/api/users", now).unwrap().allowed);
+        assert!(limiter.check_at(Some(&ip("192.0.2.1")), "/index.html", now).unwrap().allowed);
+        assert!(!limiter.check_at(Some(&ip("192.0.2.1")), "/public/../api", now).unwrap().allowed);
+
+        let open = RateLimiter::new(vec![RateLimitRule::new("/api", 1.0, 1)]);
+        assert!(open.check_at(Some(&ip("192.0.2.1")), "/index.html", now).is_none());
+    }
+
+    /// Test the number of tracked clients stays bounded and idle clients expire
+    #[test]
+    fn test_memory_bounded() {
+        let limiter = RateLimiter::with_capacity(vec![RateLimitRule::new("/", 1.0, 5)], 10);
+        let now = Instant::now();
+
+        for index in 0..50 {
+            let client = ip(&format!("10.0.0.{}", index));
+            limiter.check_at(Some(&client), "/", now);
+        }
+        assert_eq!(limiter.len(), 10);
+
+        // every bucket is full again after 5 seconds, the next sweep drops them all
+        limiter.check_at(Some(&ip("10.0.1.1")), "/", now + Duration::from_secs(120));
+        assert_eq!(limiter.len(), 1);
+    }
+
+    /// Test `RateLimitStatus::add_headers`
+    #[test]
+    fn test_headers() {
+        let limiter = RateLimiter::new(vec![RateLimitRule::new("/", 1.0, 1)]);
+        let client = ip("2001:db8::1");
+        let now = Instant::now();
+
+        let mut headers = KeyVal::new();
+        limiter.check_at(Some(&client), "/", now).unwrap().add_headers(&mut headers);
+        assert_eq!(headers.get("RateLimit-Limit").unwrap(), "1");
+        assert_eq!(headers.get("RateLimit-Remaining").unwrap(), "0");
+        assert!(headers.get("Retry-After").is_none());
+
+        let mut headers = KeyVal::new();
+        limiter.check_at(Some(&client), "/", now).unwrap().add_headers(&mut headers);
+        assert_eq!(headers.get("Retry-After").unwrap(), "1");
+    }
+}
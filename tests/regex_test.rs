@@ -0,0 +1,62 @@
+use katana::core::utils::regex::Regex;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to compile a pattern known to be valid
+    fn regex(pattern: &str) -> Regex {
+        Regex::new(pattern).unwrap()
+    }
+
+    /// Test `Regex::is_match` with literals, anchors, `.` and escapes
+    #[test]
+    fn test_literals_and_anchors() {
+        assert!(regex("app").is_match("static/app.js"));
+        assert!(!regex("^app").is_match("static/app.js"));
+        assert!(regex("\\.js$").is_match("app.js"));
+        assert!(!regex("\\.js$").is_match("app.json"));
+        assert!(regex("a.c").is_match("abc"));
+        assert!(!regex("a\\.c").is_match("abc"));
+        assert!(regex("^\\d+-\\w+\\s?$").is_match("42-abc_d "));
+        assert!(regex("").is_match("anything"));
+    }
+
+    /// Test classes, quantifiers and groups on fingerprinted file names
+    #[test]
+    fn test_fingerprints() {
+        let fingerprint = regex("\\.[0-9a-f]{4,}\\.[^.]+$");
+        assert!(fingerprint.is_match("app.3f9a.js"));
+        assert!(fingerprint.is_match("vendor.0123456789abcdef.css"));
+        assert!(!fingerprint.is_match("app.js"));
+        assert!(!fingerprint.is_match("app.3f9.js"));
+        assert!(!fingerprint.is_match("app.3f9a.min.js"));
+
+        let webpack = regex("^(main|vendor)(?:-[0-9A-F]{8})?\\.(js|css)$");
+        assert!(webpack.is_match("main-0A1B2C3D.js"));
+        assert!(webpack.is_match("vendor.css"));
+        assert!(!webpack.is_match("main-0a1b2c3d.js"));
+        assert!(!webpack.is_match("other.js"));
+
+        assert!(regex("^a{2}b{1,2}c*$").is_match("aabbccc"));
+        assert!(!regex("^a{2}b{1,2}c*$").is_match("abbb"));
+        assert!(regex("^[]a-]+$").is_match("]a-"));
+        assert!(regex("^x{,2}$").is_match("x{,2}"));
+        assert!(regex("^(a*)*b$").is_match("aaab"));
+        assert!(!regex("^(a*)*b$").is_match("aaaa"));
+    }
+
+    /// Test `Regex::new` refuses malformed patterns
+    #[test]
+    fn test_invalid() {
+        assert!(Regex::new("(abc").is_err());
+        assert!(Regex::new("abc)").is_err());
+        assert!(Regex::new("[a-").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("a{3,1}").is_err());
+        assert!(Regex::new("abc\\").is_err());
+        assert!(Regex::new("\\q").is_err());
+        assert_eq!(regex("a+").to_string(), "a+");
+    }
+}
@@ -0,0 +1,120 @@
+use katana::core::config::config::Config;
+use katana::core::server::filetype::FileType;
+use katana::core::server::reload::ConfigReload;
+use katana::core::utils::logger::{LogLevel, Logger};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to load a `.katana` file next to a document root
+    fn load_temp_config(name: &str, content: &str) -> (PathBuf, Config) {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("public")).unwrap();
+        let root = temp_dir.join("public");
+        let path = temp_dir.join(".katana");
+        fs::write(&path, content.replace("{root}", &root.to_string_lossy())).unwrap();
+        let config = Config::from_file(&path);
+        (temp_dir, config)
+    }
+
+    /// Test `Config::diff` lists the changed settings and those requiring a restart
+    #[test]
+    fn test_diff() {
+        let (temp_dir, old) = load_temp_config("katana_reload_diff_old", "[katana]\nport = 9090\ndocument_root = \"{root}\"\n");
+        let (other_dir, new) = load_temp_config(
+            "katana_reload_diff_new",
+            "[katana]\nport = 9191\ndocument_root = \"{root}\"\nclean_urls = true\n",
+        );
+
+        let changes = old.diff(&new);
+        let keys: Vec<&str> = changes.iter().map(|change| change.key).collect();
+        assert_eq!(keys, vec!["port", "document_root", "clean_urls"]);
+        assert_eq!(changes[0].to_string(), "port: 9090 -> 9191 (requires restart)");
+        assert_eq!(changes[2].to_string(), "clean_urls: false -> true");
+        assert!(old.diff(&old).is_empty());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+        fs::remove_dir_all(&other_dir).unwrap();
+    }
+
+    /// Test `ConfigReload::apply` swaps the configuration, keeping the settings requiring a restart
+    #[test]
+    fn test_apply() {
+        let (temp_dir, old) = load_temp_config("katana_reload_apply", "[katana]\nport = 9090\ndocument_root = \"{root}\"\n");
+        let mut new = old.clone();
+        new.port = 9191;
+        new.clean_urls = true;
+
+        let reload = ConfigReload::new(old);
+        let changes = reload.apply(new).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        let (generation, config) = reload.current();
+        assert_eq!(generation, 1);
+        assert_eq!(config.port, 9090);
+        assert!(config.clean_urls);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Test `ConfigReload::apply` refuses an invalid configuration and keeps the active one
+    #[test]
+    fn test_apply_refused() {
+        let (temp_dir, old) = load_temp_config("katana_reload_refused", "[katana]\ndocument_root = \"{root}\"\n");
+        let (other_dir, invalid) = load_temp_config("katana_reload_invalid", "[katana]\ndocument_root = \"{root}/missing\"\n");
+        assert!(!invalid.issues.is_empty());
+
+        let reload = ConfigReload::new(old.clone());
+        assert_eq!(reload.apply(invalid).unwrap_err().len(), 1);
+
+        let (generation, config) = reload.current();
+        assert_eq!(generation, 0);
+        assert_eq!(config.document_root, old.document_root);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+        fs::remove_dir_all(&other_dir).unwrap();
+    }
+
+    /// Test a connection holding the snapshot from before a reload keeps its media types and log level
+    #[test]
+    fn test_snapshot_survives_reload() {
+        let (temp_dir, old) = load_temp_config(
+            "katana_reload_snapshot_old",
+            "[katana]\ndocument_root = \"{root}\"\nlog_level = \"INFO\"\n[mime]\nmd = \"text/plain\"\n",
+        );
+        let (other_dir, new) = load_temp_config(
+            "katana_reload_snapshot_new",
+            "[katana]\ndocument_root = \"{root}\"\nlog_level = \"ERROR\"\n[mime]\nmd = \"text/markdown\"\n",
+        );
+        let reload = ConfigReload::new(old);
+
+        let in_flight = reload.clone();
+        thread::spawn(move || {
+            // what `Server::serve` gives a connection thread
+            let (_, snapshot) = in_flight.current();
+            Logger::set_thread_log_level(snapshot.log_level.clone());
+
+            in_flight.apply(new).unwrap();
+
+            assert_eq!(FileType::for_extension(&snapshot.mime_types, "md").content_type, "text/plain; charset=utf-8");
+            assert_eq!(Logger::get_min_log_level(), LogLevel::INFO);
+        })
+        .join()
+        .unwrap();
+
+        let (_, config) = reload.current();
+        assert_eq!(FileType::for_extension(&config.mime_types, "md").content_type, "text/markdown; charset=utf-8");
+        assert_eq!(config.log_level, LogLevel::ERROR);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+        fs::remove_dir_all(&other_dir).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use katana::core::resources::templates::Templates;
+use katana::core::server::http::HttpStatus;
+use katana::core::server::location::Location;
+use katana::core::server::request::Request;
+use katana::core::server::response::Response;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to create a document root with pages and a folder
+    fn create_temp_root(name: &str) -> PathBuf {
+        let temp_dir = env::temp_dir().join(name);
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        fs::create_dir_all(temp_dir.join("docs")).unwrap();
+        fs::write(temp_dir.join("about.html"), "<p>about</p>").unwrap();
+        fs::write(temp_dir.join("docs/index.html"), "<p>docs</p>").unwrap();
+        temp_dir
+    }
+
+    /// Helper function to serve `method path` from `root`, the request read from a socket
+    fn serve(root: &Path, method: &str, path: &str, clean_urls: bool) -> Response {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, path).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        let request = Request::from_stream(&stream).unwrap();
+        let mut response = Response::new(request, Templates::load()).unwrap();
+        response.clean_urls = clean_urls;
+        response.serve(root, &Location::default());
+        response
+    }
+
+    /// Test a folder without its trailing slash is redirected for good, the query string kept
+    #[test]
+    fn test_folder_redirect() {
+        let root = create_temp_root("response_test_folder");
+
+        let response = serve(&root, "GET", "/docs", false);
+        assert_eq!(response.status_code.to_code(), HttpStatus::MovedPermanently.to_code());
+        assert_eq!(response.headers.get("Location"), Some(&"/docs/".to_string()));
+
+        let response = serve(&root, "GET", "/docs?page=2&sort=asc", false);
+        assert_eq!(response.status_code.to_code(), HttpStatus::MovedPermanently.to_code());
+        assert_eq!(response.headers.get("Location"), Some(&"/docs/?page=2&sort=asc".to_string()));
+
+        let response = serve(&root, "GET", "/docs/", false);
+        assert_eq!(response.status_code.to_code(), HttpStatus::Ok.to_code());
+        assert!(response.headers.get("Location").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test `/about` serves `about.html` with clean URLs only
+    #[test]
+    fn test_clean_url_serves_page() {
+        let root = create_temp_root("response_test_clean_page");
+
+        let response = serve(&root, "GET", "/about", true);
+        assert_eq!(response.status_code.to_code(), HttpStatus::Ok.to_code());
+        assert_eq!(response._path, root.join("about.html"));
+
+        let response = serve(&root, "GET", "/about", false);
+        assert_eq!(response.status_code.to_code(), HttpStatus::NotFound.to_code());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test `.html` pages redirect to their clean path temporarily, the query string kept
+    #[test]
+    fn test_clean_url_redirects() {
+        let root = create_temp_root("response_test_clean_redirect");
+
+        let response = serve(&root, "GET", "/about.html", true);
+        assert_eq!(response.status_code.to_code(), HttpStatus::Found.to_code());
+        assert_eq!(response.headers.get("Location"), Some(&"/about".to_string()));
+
+        let response = serve(&root, "GET", "/about.html?ref=nav", true);
+        assert_eq!(response.headers.get("Location"), Some(&"/about?ref=nav".to_string()));
+
+        let response = serve(&root, "GET", "/docs/index.html", true);
+        assert_eq!(response.status_code.to_code(), HttpStatus::Found.to_code());
+        assert_eq!(response.headers.get("Location"), Some(&"/docs/".to_string()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test pages are served as is with clean URLs off, and other methods are never redirected
+    #[test]
+    fn test_clean_url_off() {
+        let root = create_temp_root("response_test_clean_off");
+
+        for path in ["/about.html", "/docs/index.html"] {
+            let response = serve(&root, "GET", path, false);
+            assert_eq!(response.status_code.to_code(), HttpStatus::Ok.to_code(), "{} should be served", path);
+            assert!(response.headers.get("Location").is_none());
+        }
+
+        let response = serve(&root, "POST", "/about.html", true);
+        assert!(response.headers.get("Location").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test leading slashes and `..` never redirect to another host nor reach outside the root
+    #[test]
+    fn test_redirect_stays_on_host() {
+        let root = create_temp_root("response_test_host");
+
+        for path in ["//docs", "/..//docs", "///docs", "/../docs"] {
+            let response = serve(&root, "GET", path, false);
+            assert_eq!(response.status_code.to_code(), HttpStatus::MovedPermanently.to_code(), "{}", path);
+            assert_eq!(response.headers.get("Location"), Some(&"/docs/".to_string()), "{}", path);
+        }
+
+        let response = serve(&root, "GET", "//about.html", true);
+        assert_eq!(response.headers.get("Location"), Some(&"/about".to_string()));
+
+        // an absolute path of the host, `/tmp` and the files of the root by their full path
+        let outside = format!("/{}", root.parent().unwrap().to_string_lossy());
+        let inside = format!("/{}", root.join("about.html").to_string_lossy());
+        for path in [outside.as_str(), inside.as_str(), "/..//etc/hostname"] {
+            let response = serve(&root, "GET", path, false);
+            assert_eq!(response.status_code.to_code(), HttpStatus::NotFound.to_code(), "{}", path);
+            assert!(response.headers.get("Location").is_none(), "{}", path);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
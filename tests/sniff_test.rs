@@ -0,0 +1,59 @@
+use katana::core::server::filetype::{FileType, MimeRegistry, MimeSettings, Sniffer};
+use std::env;
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `Sniffer::detect` recognizes magic bytes, UTF-8 text and nothing else
+    #[test]
+    fn test_detect() {
+        assert_eq!(Sniffer::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(Sniffer::detect(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(Sniffer::detect(b"GIF89a\x01\0"), Some("image/gif"));
+        assert_eq!(Sniffer::detect(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(Sniffer::detect(b"PK\x03\x04\x14\0"), Some("application/zip"));
+        assert_eq!(Sniffer::detect(b"\x1f\x8b\x08\0"), Some("application/gzip"));
+        assert_eq!(Sniffer::detect(b"\x7fELF\x02\x01\x01"), Some("application/x-executable"));
+        assert_eq!(Sniffer::detect(b"\0asm\x01\0\0\0"), Some("application/wasm"));
+        assert_eq!(Sniffer::detect(b"MIT License\n\nCopyright"), Some("text/plain"));
+        assert_eq!(Sniffer::detect(b""), Some("text/plain"));
+        assert_eq!(Sniffer::detect(b"\x00\x01\x02\x03"), None);
+    }
+
+    /// Test `Sniffer::is_text` accepts UTF-8 cut in a character, refuses control characters
+    #[test]
+    fn test_is_text() {
+        assert!(Sniffer::is_text("héllo\tworld\r\n".as_bytes()));
+        assert!(Sniffer::is_text(&"日本".as_bytes()[..4]));
+        assert!(Sniffer::is_text(b"\x1b[31mred\x1b[0m"));
+        assert!(!Sniffer::is_text(b"caf\xe9 au lait"));
+        assert!(!Sniffer::is_text(b"text\0with a nul"));
+    }
+
+    /// Test `FileType::sniff` types files by content when enabled, and follows changes to them
+    #[test]
+    fn test_sniff_file() {
+        let temp_dir = env::temp_dir().join("katana_sniff_file");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("LICENSE");
+        fs::write(&path, "Permission is hereby granted").unwrap();
+
+        assert!(FileType::sniff(&MimeRegistry::builtin(), &path).is_none());
+        let settings = MimeSettings {
+            sniff: true,
+            ..MimeSettings::default()
+        };
+        let registry = settings.build();
+
+        assert_eq!(FileType::sniff(&registry, &path).unwrap().content_type, "text/plain; charset=utf-8");
+        fs::write(&path, b"\x89PNG\r\n\x1a\n").unwrap();
+        assert_eq!(FileType::sniff(&registry, &path).unwrap().content_type, "image/png");
+        fs::write(&path, b"\x00\x01\x02\x03\x04\x05").unwrap();
+        assert_eq!(FileType::sniff(&registry, &path).unwrap().content_type, MimeRegistry::DEFAULT_TYPE);
+        assert!(FileType::sniff(&registry, &temp_dir.join("missing")).is_none());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
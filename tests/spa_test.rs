@@ -0,0 +1,44 @@
+use katana::core::server::spa::{Spa, SpaRule};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `SpaRule::new` keeps the entry page inside the document root
+    #[test]
+    fn test_rule_new() {
+        assert_eq!(SpaRule::new("/", "index.html").unwrap().fallback, "/index.html");
+        assert_eq!(SpaRule::new("/admin", "/admin/./index.html").unwrap().fallback, "/admin/index.html");
+        assert_eq!(SpaRule::new("/", "../../index.html").unwrap().fallback, "/index.html");
+        assert!(SpaRule::new("/", ".hidden/index.html").is_none());
+        assert!(SpaRule::new("/", "/").is_none());
+    }
+
+    /// Test `Spa::rule_for` picks the longest prefix covering the path
+    #[test]
+    fn test_rule_for() {
+        let rules = vec![
+            SpaRule::new("/", "index.html").unwrap(),
+            SpaRule::new("/admin", "admin/index.html").unwrap(),
+        ];
+
+        assert_eq!(Spa::rule_for(&rules, "/settings/profile").unwrap().fallback, "/index.html");
+        assert_eq!(Spa::rule_for(&rules, "/admin/users/3").unwrap().fallback, "/admin/index.html");
+        assert_eq!(Spa::rule_for(&rules, "/administrator").unwrap().fallback, "/index.html");
+        assert!(Spa::rule_for(&rules[1..], "/settings").is_none());
+    }
+
+    /// Test `Spa::is_asset` tells files from client-side routes
+    #[test]
+    fn test_is_asset() {
+        assert!(Spa::is_asset("/static/app.js"));
+        assert!(Spa::is_asset("/logo.png"));
+        assert!(Spa::is_asset("/fonts/inter.woff2/"));
+
+        assert!(!Spa::is_asset("/"));
+        assert!(!Spa::is_asset("/settings/profile"));
+        assert!(!Spa::is_asset("/v1.2/settings"));
+        assert!(!Spa::is_asset("/.well-known"));
+        assert!(!Spa::is_asset("/trailing."));
+    }
+}
@@ -0,0 +1,78 @@
+use katana::core::server::sse::{Event, EventPublisher, EventRoutes};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test `Event` frames carry every field and split multi-line data
+    #[test]
+    fn test_event_frame() {
+        let event = Event::new("first\nsecond\r\nthird")
+            .with_id("42")
+            .with_event("build")
+            .with_retry(Duration::from_secs(3));
+        assert_eq!(
+            event.to_string(),
+            "id: 42\nevent: build\nretry: 3000\ndata: first\ndata: second\ndata: third\n\n"
+        );
+        assert_eq!(Event::new("").to_string(), "data: \n\n");
+    }
+
+    /// Test `Event` fields cannot inject other fields
+    #[test]
+    fn test_event_field_injection() {
+        let event = Event::new("ok").with_id("1\ndata: forged").with_event("log\r\nid: 9");
+        assert_eq!(event.to_string(), "id: 1data: forged\nevent: logid: 9\ndata: ok\n\n");
+    }
+
+    /// Test `EventPublisher::publish` numbers events and keeps them for reconnecting clients
+    #[test]
+    fn test_publisher_history() {
+        let publisher = EventPublisher::new();
+        assert_eq!(publisher.send("build", "started"), 0);
+        publisher.publish(Event::new("custom").with_id("deploy-7"));
+        publisher.send("build", "done");
+
+        let missed = publisher.missed("1");
+        assert_eq!(missed.len(), 2);
+        assert_eq!(missed[0].id.as_deref(), Some("deploy-7"));
+        assert_eq!(missed[1].id.as_deref(), Some("2"));
+        assert_eq!(missed[1].data, "done");
+
+        assert!(publisher.missed("2").is_empty());
+        assert!(publisher.missed("unknown").is_empty());
+        assert_eq!(publisher.subscribers(), 0);
+    }
+
+    /// Test `EventPublisher` forgets the oldest events past `HISTORY`
+    #[test]
+    fn test_publisher_history_limit() {
+        let publisher = EventPublisher::new();
+        for i in 0..EventPublisher::HISTORY + 10 {
+            publisher.send("tick", &i.to_string());
+        }
+
+        assert!(publisher.missed("10").is_empty());
+        assert_eq!(publisher.missed("11").len(), EventPublisher::HISTORY - 1);
+    }
+
+    /// Test `EventRoutes` matches mounted paths exactly, shared with the returned publisher
+    #[test]
+    fn test_routes() {
+        let mut routes = EventRoutes::new();
+        assert!(routes.is_empty());
+
+        let publisher = EventPublisher::new();
+        routes.mount("/events/", publisher.clone());
+        assert!(routes.publisher_for("/events").is_some());
+        assert!(routes.publisher_for("/events/").is_some());
+        assert!(routes.publisher_for("/events/build").is_none());
+
+        publisher.send("build", "shared");
+        assert_eq!(routes.publisher_for("/events").unwrap().missed("0").len(), 0);
+        assert_eq!(routes.publisher_for("/events").unwrap().missed("1").len(), 0);
+        publisher.send("build", "again");
+        assert_eq!(routes.publisher_for("/events").unwrap().missed("1").len(), 1);
+    }
+}
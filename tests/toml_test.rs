@@ -0,0 +1,231 @@
+use katana::core::utils::toml::{TomlDate, TomlDatetime, TomlParser, TomlTime, TomlValue};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> TomlParser {
+        let mut parser = TomlParser::new();
+        parser.parse(input).unwrap();
+        parser
+    }
+
+    fn table(value: Option<&TomlValue>) -> &HashMap<String, TomlValue> {
+        match value {
+            Some(TomlValue::Table(table)) => table,
+            other => panic!("expected a table, got {:?}", other),
+        }
+    }
+
+    /// Test `TomlParser::parse` reads sections, comments after values and quoted keys
+    #[test]
+    fn test_parse_sections() {
+        let parser = parse(
+            "# katana\n\
+             [katana]\n\
+             host = \"0.0.0.0\"   # all interfaces\n\
+             port = 8080\n\
+             \n\
+             [rate_limit]\n\
+             \"/api\" = [2, 5]    # rate and burst\n",
+        );
+
+        assert_eq!(parser.get_nested_value("katana", "host"), Some(&TomlValue::String("0.0.0.0".to_string())));
+        assert_eq!(parser.get_nested_value("katana", "port"), Some(&TomlValue::Integer(8080)));
+        assert_eq!(
+            parser.get_nested_value("rate_limit", "/api"),
+            Some(&TomlValue::Array(vec![TomlValue::Integer(2), TomlValue::Integer(5)]))
+        );
+    }
+
+    /// Test `TomlParser::parse` reads basic, literal and multi-line strings
+    #[test]
+    fn test_parse_strings() {
+        let parser = parse(concat!(
+            "basic = \"tab\\there \\\"quoted\\\" \\u00e9\\U0001F600\"\n",
+            "literal = 'C:\\Users\\katana'\n",
+            "multi = \"\"\"\nfirst\n  second\"\"\"\n",
+            "joined = \"\"\"one \\\n     two\"\"\"\n",
+            "quotes = \"\"\"\"\"say\"\"\"\"\"\n",
+            "raw = '''\n\\n stays'''\n",
+        ));
+
+        let value = |key: &str| match parser.get_value(key) {
+            Some(TomlValue::String(value)) => value.clone(),
+            other => panic!("expected a string, got {:?}", other),
+        };
+        assert_eq!(value("basic"), "tab\there \"quoted\" é😀");
+        assert_eq!(value("literal"), "C:\\Users\\katana");
+        assert_eq!(value("multi"), "first\n  second");
+        assert_eq!(value("joined"), "one two");
+        assert_eq!(value("quotes"), "\"\"say\"\"");
+        assert_eq!(value("raw"), "\\n stays");
+    }
+
+    /// Test `TomlParser::parse_value` reads integers, floats and booleans
+    #[test]
+    fn test_parse_numbers() {
+        let value = |input: &str| TomlParser::parse_value(input).unwrap();
+
+        assert_eq!(value("+99"), TomlValue::Integer(99));
+        assert_eq!(value("-17"), TomlValue::Integer(-17));
+        assert_eq!(value("1_000_000"), TomlValue::Integer(1_000_000));
+        assert_eq!(value("0xDEAD_beef"), TomlValue::Integer(0xdead_beef));
+        assert_eq!(value("0o755"), TomlValue::Integer(0o755));
+        assert_eq!(value("0b1101"), TomlValue::Integer(13));
+        assert_eq!(value("6.626e-34"), TomlValue::Float(6.626e-34));
+        assert_eq!(value("-0.5"), TomlValue::Float(-0.5));
+        assert_eq!(value("5e+22"), TomlValue::Float(5e22));
+        assert_eq!(value("-inf"), TomlValue::Float(f64::NEG_INFINITY));
+        assert!(matches!(value("nan"), TomlValue::Float(nan) if nan.is_nan()));
+        assert_eq!(value("true"), TomlValue::Boolean(true));
+
+        for invalid in ["01", "1__0", "_1", "1_", "1.", ".5", "1e", "+0x10", "9223372036854775808", "yes"] {
+            assert!(TomlParser::parse_value(invalid).is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    /// Test `TomlParser::parse_value` reads the four kinds of date and time
+    #[test]
+    fn test_parse_datetimes() {
+        let value = |input: &str| match TomlParser::parse_value(input).unwrap() {
+            TomlValue::Datetime(datetime) => datetime,
+            other => panic!("expected a datetime, got {:?}", other),
+        };
+
+        let datetime = value("1979-05-27T07:32:00.999999-07:00");
+        assert_eq!(
+            datetime,
+            TomlDatetime {
+                date: Some(TomlDate { year: 1979, month: 5, day: 27 }),
+                time: Some(TomlTime { hour: 7, minute: 32, second: 0, nanosecond: 999_999_000 }),
+                offset: Some(-420),
+            }
+        );
+        assert_eq!(datetime.to_string(), "1979-05-27T07:32:00.999999-07:00");
+        assert_eq!(value("1979-05-27 07:32:00Z").to_string(), "1979-05-27T07:32:00Z");
+        assert_eq!(value("1979-05-27T07:32:00").offset, None);
+        assert_eq!(value("2024-02-29").time, None);
+        assert_eq!(value("00:32:00.5").to_string(), "00:32:00.5");
+
+        for invalid in ["2023-02-29", "1979-13-01", "1979-05-27T25:00:00", "07:32", "07:32:00Z", "1979-05-27T07:32:00+7"] {
+            assert!(TomlParser::parse_value(invalid).is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    /// Test `TomlParser::parse` reads multi-line arrays, inline tables and dotted keys
+    #[test]
+    fn test_parse_nested() {
+        let parser = parse(concat!(
+            "hosts = [\n",
+            "  \"alpha\", # first\n",
+            "  \"omega\",\n",
+            "]\n",
+            "point = { x = 1, y.z = 2 }\n",
+            "site.\"name\".short = 'k'\n",
+            "[a.b]\n",
+            "c = 1\n",
+            "[a]\n",
+            "d = 2\n",
+        ));
+
+        assert_eq!(
+            parser.get_value("hosts"),
+            Some(&TomlValue::Array(vec![
+                TomlValue::String("alpha".to_string()),
+                TomlValue::String("omega".to_string())
+            ]))
+        );
+        let point = table(parser.get_value("point"));
+        assert_eq!(point.get("x"), Some(&TomlValue::Integer(1)));
+        assert_eq!(table(point.get("y")).get("z"), Some(&TomlValue::Integer(2)));
+        let site = table(parser.get_value("site"));
+        assert_eq!(table(site.get("name")).get("short"), Some(&TomlValue::String("k".to_string())));
+        let a = table(parser.get_value("a"));
+        assert_eq!(table(a.get("b")).get("c"), Some(&TomlValue::Integer(1)));
+        assert_eq!(a.get("d"), Some(&TomlValue::Integer(2)));
+    }
+
+    /// Test `TomlParser::parse` appends `[[array]]` tables and their sub-tables
+    #[test]
+    fn test_parse_array_of_tables() {
+        let parser = parse(concat!(
+            "[[location]]\n",
+            "path = \"/api\"\n",
+            "[location.headers]\n",
+            "x = 1\n",
+            "[[location]]\n",
+            "path = \"/static\"\n",
+        ));
+
+        let locations = match parser.get_value("location") {
+            Some(TomlValue::Array(locations)) => locations,
+            other => panic!("expected an array, got {:?}", other),
+        };
+        assert_eq!(locations.len(), 2);
+        let first = table(locations.first());
+        assert_eq!(first.get("path"), Some(&TomlValue::String("/api".to_string())));
+        assert_eq!(table(first.get("headers")).get("x"), Some(&TomlValue::Integer(1)));
+        assert_eq!(table(locations.get(1)).get("path"), Some(&TomlValue::String("/static".to_string())));
+    }
+
+    /// Test `TomlParser::position` locates keys and tables, arrays of tables by index
+    #[test]
+    fn test_position() {
+        let parser = parse("title = 'k'\n\n[katana]\n  port = 80\n[[location]]\n[[location]]\n\"/api\" = 1\n");
+
+        assert_eq!(parser.position("title"), Some((1, 1)));
+        assert_eq!(parser.position("katana"), Some((3, 1)));
+        assert_eq!(parser.position("katana.port"), Some((4, 3)));
+        assert_eq!(parser.position("location.1"), Some((6, 1)));
+        assert_eq!(parser.position("location.1./api"), Some((7, 1)));
+        assert_eq!(parser.position("katana.host"), None);
+    }
+
+    /// Test `TomlParser::parse` rejects redefinitions and reports where
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| TomlParser::new().parse(input).unwrap_err();
+
+        let duplicate = error("[katana]\nport = 80\nport = 81\n");
+        assert_eq!((duplicate.line, duplicate.column), (3, 1));
+        assert_eq!(duplicate.to_string(), "key `port` is already defined at line 3, column 1");
+
+        let unquoted = error("[katana]\nhost = localhost\n");
+        assert_eq!((unquoted.line, unquoted.column), (2, 8));
+
+        let trailing = error("port = 80 80\n");
+        assert_eq!((trailing.line, trailing.column), (1, 11));
+
+        let escape = error("a = \"\\q\"");
+        assert_eq!((escape.line, escape.column), (1, 6));
+
+        for invalid in [
+            "[a]\n[a]\n",
+            "a.b = 1\n[a.b]\n",
+            "[a.b.c]\n[a]\nb.c.d = 1\n",
+            "a = [1]\n[[a]]\n",
+            "a = { b = 1 }\n[a.c]\n",
+            "[[a]]\n[a]\n",
+            "a = 1\na.b = 2\n",
+            "a = { b = 1, }\n",
+            "a = { b = 1,\n c = 2 }\n",
+            "a = \"unterminated\n",
+            "a = [1 2]\n",
+            "[a\n",
+            "= 1\n",
+            "a = 1\rb = 2\n",
+        ] {
+            assert!(TomlParser::new().parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    /// Test `TomlParser::parse` reads the example configuration shipped with katana
+    #[test]
+    fn test_parse_example() {
+        let example = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/.katana.example")).unwrap();
+        let parser = parse(&example);
+        assert!(matches!(parser.get_value("katana"), Some(TomlValue::Table(_))));
+    }
+}
@@ -0,0 +1,199 @@
+use katana::core::resources::templates::Templates;
+use katana::core::server::cgi::{CgiRule, ScriptRules};
+use katana::core::server::http::HttpStatus;
+use katana::core::server::multipart::{Multipart, MultipartReader};
+use katana::core::server::request::Request;
+use katana::core::server::response::Response;
+use katana::core::server::upload::{OverwritePolicy, Upload, UploadRule};
+use katana::core::utils::utils::Utils;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // hands out one byte per read, like a very slow client
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            let size = self.0.len().min(buffer.len()).min(1);
+            buffer[..size].copy_from_slice(&self.0[..size]);
+            self.0 = &self.0[size..];
+            Ok(size)
+        }
+    }
+
+    /// Helper function to answer a raw request with `upload`, the body read from a socket
+    fn handle(upload: &Upload, raw: &str) -> Response {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(raw.as_bytes()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        let request = Request::from_stream(&stream).unwrap();
+        let mut response = Response::new(request, Templates::load()).unwrap();
+        upload.handle(&mut response, &stream);
+        response
+    }
+
+    /// Test `Multipart::boundary` only accepts form data with a boundary
+    #[test]
+    fn test_multipart_boundary() {
+        assert_eq!(
+            Multipart::boundary("multipart/form-data; boundary=----WebKitFormBoundary7MA4YWxk").unwrap(),
+            "----WebKitFormBoundary7MA4YWxk"
+        );
+        assert_eq!(Multipart::boundary("multipart/form-data; charset=utf-8; boundary=\"a b\"").unwrap(), "a b");
+        assert!(Multipart::boundary("multipart/form-data").is_none());
+        assert!(Multipart::boundary("application/x-www-form-urlencoded; boundary=x").is_none());
+    }
+
+    /// Test `MultipartReader` splits fields and files, even when a chunk ends inside a boundary
+    #[test]
+    fn test_multipart_reader() {
+        let body = "preamble\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"note\"\r\n\r\n\
+            hello\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".txt\"\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            line 1\r\n-XyZ not a boundary\r\n--XyZ--\r\nepilogue";
+        // one byte at a time, the worst case for boundary detection
+        let mut reader = MultipartReader::new(OneByte(body.as_bytes()), "XyZ");
+
+        let note = reader.next_part().unwrap().unwrap();
+        assert_eq!(note.name, "note");
+        assert!(note.filename.is_none());
+        let mut value = String::new();
+        reader.read_to_string(&mut value).unwrap();
+        assert_eq!(value, "hello");
+
+        let file = reader.next_part().unwrap().unwrap();
+        assert_eq!(file.filename.as_deref(), Some("a \"b\".txt"));
+        assert_eq!(file.content_type.as_deref(), Some("text/plain"));
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "line 1\r\n-XyZ not a boundary");
+
+        assert!(reader.next_part().unwrap().is_none());
+    }
+
+    /// Test a body cut before the closing boundary is an error
+    #[test]
+    fn test_multipart_reader_truncated() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a\"\r\n\r\nabc";
+        let mut reader = MultipartReader::new(&body[..], "XyZ");
+
+        assert!(reader.next_part().unwrap().is_some());
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    /// Test `Upload::file_name` drops client paths and refuses hidden names
+    #[test]
+    fn test_upload_file_name() {
+        assert_eq!(Upload::file_name("report.pdf").unwrap(), "report.pdf");
+        assert_eq!(Upload::file_name("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(Upload::file_name("C:\\Users\\me\\build.zip").unwrap(), "build.zip");
+        assert!(Upload::file_name(".htaccess").is_none());
+        assert!(Upload::file_name("folder/").is_none());
+        assert!(Upload::file_name("..").is_none());
+    }
+
+    /// Test `UploadRule::set` options and `Upload::rule_for` prefix matching
+    #[test]
+    fn test_upload_rule() {
+        let mut rule = UploadRule::new("/builds");
+        assert!(rule.set("max_size 2G"));
+        assert!(rule.set("overwrite rename"));
+        assert!(rule.set("form off"));
+        assert!(!rule.set("quota lots"));
+        assert_eq!(rule.max_size, 2 << 30);
+        assert_eq!(rule.overwrite, OverwritePolicy::Rename);
+        assert!(!rule.form);
+
+        let rules = vec![UploadRule::new("/incoming"), rule];
+        assert_eq!(Upload::rule_for(&rules, "/builds/v1/app.zip").unwrap().prefix, "/builds");
+        assert!(Upload::rule_for(&rules, "/incoming/../sub/a").is_none());
+        assert!(Upload::rule_for(&rules, "/buildsx").is_none());
+    }
+
+    /// Test `Utils::parse_size` units
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(Utils::parse_size("512"), Some(512));
+        assert_eq!(Utils::parse_size("64k"), Some(64 * 1024));
+        assert_eq!(Utils::parse_size("100MB"), Some(100 * 1024 * 1024));
+        assert_eq!(Utils::parse_size("1 GiB"), Some(1 << 30));
+        assert_eq!(Utils::parse_size("1.5G"), None);
+        assert_eq!(Utils::parse_size(""), None);
+    }
+
+    /// Test `Upload::write_atomically` enforces its limit and leaves nothing behind
+    #[test]
+    fn test_write_atomically() {
+        let folder = std::env::temp_dir().join(format!("katana-upload-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let target = folder.join("file.bin");
+
+        assert_eq!(Upload::write_atomically(&target, &mut &[1u8; 10][..], Some(10)).unwrap(), 10);
+        let error = Upload::write_atomically(&target, &mut &[2u8; 11][..], Some(10)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::FileTooLarge);
+
+        assert_eq!(std::fs::read(&target).unwrap(), vec![1u8; 10], "Refused upload must keep the old file");
+        assert_eq!(std::fs::read_dir(&folder).unwrap().count(), 1, "Temporary file should be removed");
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    /// Test uploads never store a file a CGI rule would run, by PUT or by form
+    #[test]
+    fn test_upload_refuses_scripts() {
+        let root = std::env::temp_dir().join(format!("katana-upload-scripts-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("uploads")).unwrap();
+        let rule = UploadRule::new("/uploads");
+        let cgi = vec![CgiRule::new(".py", Some(PathBuf::from("/usr/bin/python3")))];
+        let upload = Upload::new(&root, &rule).with_scripts(ScriptRules { cgi: &cgi, fastcgi: &[] });
+
+        let response = handle(&upload, "PUT /uploads/x.py HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nprint");
+        assert_eq!(response.status_code.to_code(), HttpStatus::Forbidden.to_code());
+
+        let form = "--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"x.PY\"\r\n\r\nprint\r\n--XyZ--\r\n";
+        let raw = format!(
+            "POST /uploads/ HTTP/1.1\r\nHost: localhost\r\nContent-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: {}\r\n\r\n{}",
+            form.len(),
+            form
+        );
+        let response = handle(&upload, &raw);
+        assert_eq!(response.status_code.to_code(), HttpStatus::Forbidden.to_code());
+        assert_eq!(std::fs::read_dir(root.join("uploads")).unwrap().count(), 0, "No script should be stored");
+
+        let response = handle(&upload, "PUT /uploads/x.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nprint");
+        assert_eq!(response.status_code.to_code(), HttpStatus::Created.to_code());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Test concurrent uploads of one name under the deny policy store a single file
+    #[test]
+    fn test_upload_deny_concurrent() {
+        let root = std::env::temp_dir().join(format!("katana-upload-deny-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("uploads")).unwrap();
+        let mut rule = UploadRule::new("/uploads");
+        assert!(rule.set("overwrite deny"));
+        let upload = Upload::new(&root, &rule);
+
+        let body = "x".repeat(64 * 1024);
+        let raw = format!("PUT /uploads/report.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        let codes: Vec<u16> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8).map(|_| scope.spawn(|| handle(&upload, &raw).status_code.to_code())).collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+
+        assert_eq!(codes.iter().filter(|code| **code == HttpStatus::Created.to_code()).count(), 1, "{:?}", codes);
+        assert!(codes.iter().all(|code| [HttpStatus::Created.to_code(), HttpStatus::Conflict.to_code()].contains(code)), "{:?}", codes);
+        assert_eq!(std::fs::read_to_string(root.join("uploads/report.txt")).unwrap(), body);
+        assert_eq!(std::fs::read_dir(root.join("uploads")).unwrap().count(), 1, "Temporary files should be removed");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use katana::core::resources::templates::Templates;
+use katana::core::server::cgi::{CgiRule, ScriptRules};
+use katana::core::server::chunked::ChunkedReader;
+use katana::core::server::filetype::MimeRegistry;
+use katana::core::server::http::{HttpMethod, HttpStatus};
+use katana::core::server::request::Request;
+use katana::core::server::response::Response;
+use katana::core::server::webdav::{LockManager, WebDav, WebDavMode};
+use katana::core::utils::utils::Utils;
+use katana::core::utils::xml::Xml;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper function to answer a raw request with `webdav`, the body read from a socket
+    fn handle(webdav: &WebDav, raw: &str) -> Response {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(raw.as_bytes()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        let request = Request::from_stream(&stream).unwrap();
+        let mut response = Response::new(request, Templates::load()).unwrap();
+        webdav.handle(&mut response, &stream);
+        response
+    }
+
+    /// Test `Xml::parse` resolves namespaces and entities
+    #[test]
+    fn test_xml_parse_namespaces() {
+        let body = r#"<?xml version="1.0" encoding="utf-8" ?>
+<!-- client request -->
+<D:propfind xmlns:D="DAV:" xmlns="urn:custom">
+  <D:prop><D:getetag/><color a="1 &amp; 2">red &lt;3&#x21;</color></D:prop>
+</D:propfind>"#;
+        let root = Xml::parse(body).unwrap();
+
+        assert!(root.is("DAV:", "propfind"));
+        let prop = root.child("DAV:", "prop").unwrap();
+        assert!(prop.child("DAV:", "getetag").is_some());
+        let color = prop.child("urn:custom", "color").unwrap();
+        assert_eq!(color.text, "red <3!");
+        assert_eq!(color.attributes, vec![("a".to_string(), "1 & 2".to_string())]);
+    }
+
+    /// Test `Xml::parse` rejects malformed documents and internal DTD subsets
+    #[test]
+    fn test_xml_parse_invalid() {
+        assert!(Xml::parse("<a><b></a>").is_none());
+        assert!(Xml::parse("<x:a/>").is_none(), "Undeclared prefix");
+        assert!(Xml::parse("<a/><b/>").is_none());
+        assert!(Xml::parse("<!DOCTYPE a [<!ENTITY x \"y\">]><a>&x;</a>").is_none());
+        assert_eq!(Xml::escape("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+
+    /// Test `Utils::request_path` normalizes paths and refuses hidden segments
+    #[test]
+    fn test_request_path() {
+        assert_eq!(Utils::request_path("/docs/../a.txt").unwrap(), "/a.txt");
+        assert_eq!(Utils::request_path("/../../a").unwrap(), "/a");
+        assert_eq!(Utils::request_path("/folder/").unwrap(), "/folder");
+        assert_eq!(Utils::request_path("/").unwrap(), "/");
+        assert!(Utils::request_path("/.katana").is_none());
+        assert!(Utils::request_path("/docs/.git/config").is_none());
+    }
+
+    /// Test `WebDavMode` names and the methods each mode exposes
+    #[test]
+    fn test_webdav_mode() {
+        assert_eq!(WebDavMode::from_name("read-only"), Some(WebDavMode::ReadOnly));
+        assert_eq!(WebDavMode::from_name("Read-Write"), Some(WebDavMode::ReadWrite));
+        assert_eq!(WebDavMode::from_name("maybe"), None);
+
+        assert!(WebDavMode::Off.methods().is_empty());
+        assert_eq!(WebDavMode::ReadOnly.methods(), vec![HttpMethod::PROPFIND]);
+        assert!(WebDavMode::ReadWrite.methods().contains(&HttpMethod::LOCK));
+        assert_eq!(WebDavMode::ReadWrite.compliance(), "1, 2");
+    }
+
+    /// Test `LockManager` conflicts, token checks and unlocking
+    #[test]
+    fn test_lock_manager() {
+        let locks = LockManager::new();
+        let timeout = Duration::from_secs(60);
+
+        let folder = locks.lock("/docs", true, true, "", timeout).unwrap();
+        assert!(folder.token.starts_with("opaquelocktoken:"));
+        assert!(locks.lock("/docs/a.txt", true, false, "", timeout).is_none(), "Covered by a depth infinity lock");
+        assert!(locks.lock("/other", false, false, "", timeout).is_some());
+
+        assert!(!locks.permits("/docs/a.txt", false, None));
+        let if_header = format!("(<{}>)", folder.token);
+        assert!(locks.permits("/docs/a.txt", false, Some(&if_header)));
+        assert!(!locks.permits("/", true, Some(&if_header)), "Deleting the root also needs /other's token");
+
+        assert!(!locks.unlock("/elsewhere", &folder.token));
+        assert!(locks.unlock("/docs", &folder.token));
+        assert!(locks.permits("/docs/a.txt", false, None));
+    }
+
+    /// Test shared locks can be stacked while exclusive ones cannot
+    #[test]
+    fn test_lock_manager_shared() {
+        let locks = LockManager::new();
+        let timeout = Duration::from_secs(60);
+
+        assert!(locks.lock("/a.txt", false, false, "", timeout).is_some());
+        assert!(locks.lock("/a.txt", false, false, "", timeout).is_some());
+        assert!(locks.lock("/a.txt", true, false, "", timeout).is_none());
+        assert_eq!(locks.discover("/a.txt").len(), 2);
+    }
+
+    /// Test `ChunkedReader` decodes chunks and skips extensions and trailers
+    #[test]
+    fn test_chunked_reader() {
+        let body = b"5;name=value\r\nhello\r\n7\r\n, world\r\n0\r\nExpires: never\r\n\r\nnext request";
+        let mut decoded = String::new();
+        ChunkedReader::new(&body[..]).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "hello, world");
+
+        let mut truncated = Vec::new();
+        assert!(ChunkedReader::new(&b"a\r\nshort"[..]).read_to_end(&mut truncated).is_err());
+    }
+
+    /// Test PUT, COPY, MOVE and LOCK never place a file a CGI rule would run
+    #[test]
+    fn test_webdav_refuses_scripts() {
+        let root = std::env::temp_dir().join(format!("katana-webdav-scripts-{}", std::process::id()));
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(root.join("notes/run.txt"), "print").unwrap();
+        let locks = LockManager::new();
+        let mime_types = MimeRegistry::builtin();
+        let cgi = vec![CgiRule::new("/cgi-bin", None), CgiRule::new(".py", Some(PathBuf::from("/usr/bin/python3")))];
+        let webdav = WebDav::new(&root, &locks, &mime_types).with_scripts(ScriptRules { cgi: &cgi, fastcgi: &[] });
+
+        let lockinfo = "<?xml version=\"1.0\"?><D:lockinfo xmlns:D=\"DAV:\"><D:lockscope><D:exclusive/></D:lockscope></D:lockinfo>";
+        let lock = format!("LOCK /new.py HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", lockinfo.len(), lockinfo);
+        let requests = [
+            lock.as_str(),
+            "PUT /x.py HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nprint",
+            "MOVE /notes/run.txt HTTP/1.1\r\nHost: localhost\r\nDestination: /notes/run.py\r\n\r\n",
+            "COPY /notes HTTP/1.1\r\nHost: localhost\r\nDestination: /cgi-bin\r\n\r\n",
+        ];
+        for raw in requests {
+            let response = handle(&webdav, raw);
+            assert_eq!(response.status_code.to_code(), HttpStatus::Forbidden.to_code(), "{}", raw);
+        }
+        assert!(!root.join("x.py").exists());
+        assert!(!root.join("new.py").exists());
+        assert!(root.join("notes/run.txt").exists());
+        assert!(!root.join("cgi-bin").exists());
+
+        let response = handle(&webdav, "COPY /notes HTTP/1.1\r\nHost: localhost\r\nDestination: /archive\r\n\r\n");
+        assert_eq!(response.status_code.to_code(), HttpStatus::Created.to_code());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
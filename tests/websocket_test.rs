@@ -0,0 +1,95 @@
+use katana::core::server::websocket::{CloseCode, Frame, Message, Opcode, WebSocket, WebSocketHandler, WebSocketRoutes, WebSocketSender};
+use std::io::ErrorKind;
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl WebSocketHandler for Echo {
+        fn on_message(&self, sender: &WebSocketSender, message: Message) {
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Test `WebSocket::accept_key` with the example of RFC 6455
+    #[test]
+    fn test_accept_key() {
+        assert_eq!(WebSocket::accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    /// Test `Frame::read` decodes the masked and unmasked examples of RFC 6455
+    #[test]
+    fn test_frame_read_rfc_examples() {
+        let unmasked = [0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f];
+        let frame = Frame::read(&mut &unmasked[..], false, 1024).unwrap();
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, Opcode::Text);
+        assert_eq!(frame.payload, b"Hello");
+
+        let masked = [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58];
+        let frame = Frame::read(&mut &masked[..], true, 1024).unwrap();
+        assert_eq!(frame.payload, b"Hello");
+
+        let fragment = [0x01, 0x03, 0x48, 0x65, 0x6c];
+        let frame = Frame::read(&mut &fragment[..], false, 1024).unwrap();
+        assert!(!frame.fin);
+        assert_eq!(frame.payload, b"Hel");
+    }
+
+    /// Test `Frame::to_bytes` round trips through `Frame::read` for every length encoding
+    #[test]
+    fn test_frame_round_trip() {
+        for size in [0, 125, 126, 65535, 65536] {
+            let frame = Frame::new(Opcode::Binary, (0..size).map(|i| i as u8).collect());
+            let bytes = frame.to_bytes(Some([1, 2, 3, 4]));
+            assert_eq!(Frame::read(&mut bytes.as_slice(), true, 1 << 20).unwrap(), frame, "Size {}", size);
+
+            let bytes = frame.to_bytes(None);
+            assert_eq!(Frame::read(&mut bytes.as_slice(), false, 1 << 20).unwrap(), frame, "Size {}", size);
+        }
+    }
+
+    /// Test `Frame::read` refuses protocol violations and oversized payloads
+    #[test]
+    fn test_frame_read_errors() {
+        let unmasked = Frame::new(Opcode::Text, b"hi".to_vec()).to_bytes(None);
+        assert_eq!(Frame::read(&mut unmasked.as_slice(), true, 1024).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let reserved = [0xC1, 0x80, 0, 0, 0, 0];
+        assert_eq!(Frame::read(&mut &reserved[..], true, 1024).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let long_ping = Frame::new(Opcode::Ping, vec![0; 126]).to_bytes(Some([0; 4]));
+        assert_eq!(Frame::read(&mut long_ping.as_slice(), true, 1024).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let fragmented_close = [0x08, 0x80, 0, 0, 0, 0];
+        assert_eq!(Frame::read(&mut &fragmented_close[..], true, 1024).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let large = Frame::new(Opcode::Binary, vec![0; 2048]).to_bytes(Some([0; 4]));
+        assert_eq!(Frame::read(&mut large.as_slice(), true, 1024).unwrap_err().kind(), ErrorKind::FileTooLarge);
+    }
+
+    /// Test `CloseCode::is_valid` on assigned, reserved and application codes
+    #[test]
+    fn test_close_codes() {
+        assert!(CloseCode::is_valid(CloseCode::NORMAL));
+        assert!(CloseCode::is_valid(4000));
+        assert!(!CloseCode::is_valid(1005), "1005 must never be sent");
+        assert!(!CloseCode::is_valid(999));
+        assert!(!CloseCode::is_valid(5000));
+    }
+
+    /// Test `WebSocketRoutes` matches normalized paths exactly
+    #[test]
+    fn test_routes() {
+        let mut routes = WebSocketRoutes::new();
+        routes.mount("/ws/echo/", Arc::new(Echo));
+
+        assert!(routes.handler_for("/ws/echo").is_some());
+        assert!(routes.handler_for("/ws/./echo/").is_some());
+        assert!(routes.handler_for("/ws").is_none());
+        assert!(routes.handler_for("/ws/echo/more").is_none());
+    }
+}